@@ -0,0 +1,63 @@
+#[macro_use]
+extern crate criterion;
+extern crate fits_rs;
+
+use criterion::{Criterion, Throughput, black_box};
+use fits_rs::parser::{layout, parse_header};
+use fits_rs::types::{Header, KeywordRecord, Keyword, Value};
+
+/// A header with 2000 cards, shaped like a binary table with 1000 columns.
+fn wide_table_header<'a>() -> Header<'a> {
+    let mut records = vec!();
+    for n in 1u16..1001u16 {
+        records.push(KeywordRecord::new(Keyword::TTYPEn(n), Value::CharacterString("COLUMN".into()), Option::None));
+        records.push(KeywordRecord::new(Keyword::TFORMn(n), Value::CharacterString("1E".into()), Option::None));
+    }
+    Header::new(records)
+}
+
+fn keyword_lookup(c: &mut Criterion) {
+    let header = wide_table_header();
+    let keywords: Vec<Keyword> = (1u16..1001u16).map(Keyword::TFORMn).collect();
+
+    c.bench_function("value_of every TFORMn in a 2000 card header", |b| {
+        b.iter(|| {
+            for keyword in &keywords {
+                black_box(header.value_of(keyword).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("linear scan for every TFORMn in a 2000 card header", |b| {
+        b.iter(|| {
+            for keyword in &keywords {
+                black_box(header.records().iter().find(|record| record.keyword() == keyword).unwrap());
+            }
+        })
+    });
+}
+
+/// Parsing headers card by card, the hot path of `header_record`.
+fn header_parsing(c: &mut Criterion) {
+    let data = include_bytes!("../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+    let headers: Vec<&[u8]> = layout(data).into_iter().map(|(header, _)| &data[header]).collect();
+    let wide = wide_table_header().to_fits_string();
+
+    let mut group = c.benchmark_group("header");
+    group.throughput(Throughput::Bytes(headers.iter().map(|header| header.len() as u64).sum()));
+    group.bench_function("parse the headers of the Kepler target pixel file", |b| {
+        b.iter(|| {
+            for header in &headers {
+                black_box(parse_header(black_box(header)));
+            }
+        })
+    });
+    group.throughput(Throughput::Bytes(wide.len() as u64));
+    group.bench_function("parse a 2000 card header", |b| {
+        b.iter(|| black_box(parse_header(black_box(wide.as_bytes()))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, keyword_lookup, header_parsing);
+criterion_main!(benches);
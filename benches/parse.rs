@@ -0,0 +1,120 @@
+#[macro_use]
+extern crate criterion;
+extern crate fits_rs;
+extern crate nom;
+
+use std::str::{self, FromStr};
+use criterion::{Criterion, Throughput, black_box};
+use nom::IResult;
+use fits_rs::parser::{fits, header_length, lazy_fits, layout};
+use fits_rs::table::BinTable;
+use fits_rs::types::Keyword;
+
+fn target_pixel_file(c: &mut Criterion) {
+    let data = include_bytes!("../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+
+    let cards = match fits(data) {
+        IResult::Done(_, file) => file.iter().map(|hdu| hdu.header.len()).sum::<usize>(),
+        _ => panic!("the target pixel file should parse"),
+    };
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(cards as u64));
+    group.bench_function("Kepler target pixel file", |b| {
+        b.iter(|| black_box(fits(black_box(data))))
+    });
+    group.bench_function("three keywords of every HDU of the Kepler target pixel file", |b| {
+        b.iter(|| {
+            if let IResult::Done(_, hdus) = lazy_fits(black_box(data)) {
+                for hdu in &hdus {
+                    black_box(hdu.header.value_of(&Keyword::EXTNAME).ok());
+                    black_box(hdu.header.value_of(&Keyword::OBJECT).ok());
+                    black_box(hdu.header.value_of(&Keyword::EXPOSURE).ok());
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn end_cards(c: &mut Criterion) {
+    let data = include_bytes!("../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+    let starts: Vec<usize> = layout(data).into_iter().map(|(header, _)| header.start).collect();
+
+    let mut group = c.benchmark_group("parse");
+    group.bench_function("END card of every header of the Kepler target pixel file", |b| {
+        b.iter(|| {
+            for &start in &starts {
+                black_box(header_length(black_box(&data[start..])));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn keywords(c: &mut Criterion) {
+    let data = include_bytes!("../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+    let names: Vec<&str> = layout(data).into_iter()
+        .flat_map(|(header, _)| data[header].chunks(80))
+        .map(|card| str::from_utf8(&card[..8]).unwrap())
+        .filter(|name| !name.trim().is_empty())
+        .collect();
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(names.len() as u64));
+    group.bench_function("keywords of the Kepler target pixel file", |b| {
+        b.iter(|| {
+            for name in &names {
+                let _ = black_box(Keyword::from_str(black_box(name)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn columns(c: &mut Criterion) {
+    let data = include_bytes!("../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+    let file = match fits(data) {
+        IResult::Done(_, file) => file,
+        _ => panic!("the target pixel file should parse"),
+    };
+    let table = BinTable::new(&file[1].header).unwrap();
+    let indices: Vec<usize> = (0..table.columns().len()).collect();
+
+    let mut group = c.benchmark_group("table");
+    group.throughput(Throughput::Bytes((table.rows() * table.row_width()) as u64));
+    group.bench_function("all columns of the Kepler target pixel file", |b| {
+        b.iter(|| black_box(table.read_columns(file[1].data(), black_box(&indices))))
+    });
+    group.finish();
+}
+
+/// The target pixel file with its first extension repeated to 200 extensions,
+/// parsed one HDU after the other and in parallel.
+#[cfg(feature = "rayon")]
+fn mosaic(c: &mut Criterion) {
+    use fits_rs::parser::fits_parallel;
+
+    let data = include_bytes!("../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+    let ranges = layout(data);
+    let mut mosaic = data[..ranges[1].1.end].to_vec();
+    for _ in 1..200 {
+        mosaic.extend_from_slice(&data[ranges[1].0.start..ranges[1].1.end]);
+    }
+
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(20);
+    group.bench_function("200 extensions", |b| {
+        b.iter(|| black_box(fits(black_box(&mosaic))))
+    });
+    group.bench_function("200 extensions in parallel", |b| {
+        b.iter(|| black_box(fits_parallel(black_box(&mosaic))))
+    });
+    group.finish();
+}
+
+#[cfg(feature = "rayon")]
+criterion_group!(benches, target_pixel_file, end_cards, keywords, columns, mosaic);
+#[cfg(not(feature = "rayon"))]
+criterion_group!(benches, target_pixel_file, end_cards, keywords, columns);
+criterion_main!(benches);
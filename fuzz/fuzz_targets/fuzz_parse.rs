@@ -0,0 +1,10 @@
+//! Runs the parsers over arbitrary input, see `parser::fuzz_parse`.
+//!
+//! Run it with `cargo fuzz run fuzz_parse` from the root of the repository.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    fits_rs::parser::fuzz_parse(data);
+});
@@ -0,0 +1,79 @@
+#ifndef FITS_RS_H
+#define FITS_RS_H
+
+/* Generated with cbindgen from src/capi, do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * The call succeeded.
+ */
+#define FITS_OK 0
+
+/**
+ * The HDU or the keyword is not present.
+ */
+#define FITS_NOT_PRESENT 1
+
+/**
+ * The keyword is present without a value.
+ */
+#define FITS_UNDEFINED 2
+
+/**
+ * An argument is a null pointer, or not a valid keyword.
+ */
+#define FITS_INVALID_ARGUMENT -1
+
+/**
+ * A parsed FITS file, opaque to C.
+ */
+typedef struct FitsFile FitsFile;
+
+/**
+ * Opens and parses the FITS file at `path`, a NUL terminated path. Returns
+ * null when the file could not be read or parsed.
+ */
+FitsFile *fits_open(const char *path);
+
+/**
+ * Parses the `length` bytes at `data` as a FITS file, copying them. Returns
+ * null when they could not be parsed.
+ */
+FitsFile *fits_open_memory(const uint8_t *data, size_t length);
+
+/**
+ * The number of HDUs of `file`, including the primary HDU. 0 for null.
+ */
+size_t fits_hdu_count(const FitsFile *file);
+
+/**
+ * Looks up `keyword` in the header of HDU `hdu`, 0 for the primary HDU, and
+ * stores its value as text in `value`: character strings without quotes,
+ * logicals as `T` or `F` and numbers as in the fixed format. The text is freed
+ * with `fits_free_string`.
+ *
+ * Returns `FITS_OK`, or `FITS_NOT_PRESENT`, `FITS_UNDEFINED` or
+ * `FITS_INVALID_ARGUMENT` without storing a value.
+ */
+int fits_keyword_value(const FitsFile *file, size_t hdu, const char *keyword, char **value);
+
+/**
+ * The data array of HDU `hdu`, without padding, with its size in bytes
+ * stored in `size`. The bytes live as long as `file` is open. Returns null,
+ * with a size of 0, when there is no such HDU or it has no data array.
+ */
+const uint8_t *fits_data(const FitsFile *file, size_t hdu, size_t *size);
+
+/**
+ * Frees a string returned by `fits_keyword_value`. Null is ignored.
+ */
+void fits_free_string(char *text);
+
+/**
+ * Closes `file`, freeing its headers and data arrays. Null is ignored.
+ */
+void fits_close(FitsFile *file);
+
+#endif /* FITS_RS_H */
@@ -0,0 +1,154 @@
+extern crate nom;
+extern crate fits_rs;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use nom::IResult;
+use fits_rs::image::{Image, Interval, Rebin, Stretch};
+use fits_rs::parser::fits;
+use fits_rs::types::{Fits, HDU};
+
+const USAGE: &str = "usage: fits2png [--hdu HDU] [--stretch linear|sqrt|log|asinh|zscale] [--percentile P] [--downsample N] [--output-dir DIR] FILE...
+
+Renders the first two dimensional image of each FILE, or the HDU given as an
+index or EXTNAME, to a PNG next to it or in DIR. The values between the minimum
+and maximum are stretched, or with zscale those between IRAF's zscale limits,
+or with --percentile the central P percent of the values, which zscale does not
+combine with. With --downsample every N x N block of pixels is averaged into
+one.";
+
+struct Options {
+    filenames: Vec<String>,
+    hdu: Option<String>,
+    stretch: Stretch,
+    interval: Interval,
+    downsample: usize,
+    output_dir: Option<PathBuf>,
+}
+
+fn main() {
+    let options = match options(env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    let mut failed = false;
+    for filename in &options.filenames {
+        match render(filename, &options) {
+            Ok(output) => println!("{}: {}", filename, output.display()),
+            Err(message) => {
+                eprintln!("{}: {}", filename, message);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn options(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        filenames: vec!(),
+        hdu: Option::None,
+        stretch: Stretch::Linear,
+        interval: Interval::MinMax,
+        downsample: 1,
+        output_dir: Option::None,
+    };
+    let mut zscale = false;
+    let mut percentile = Option::None;
+    let mut args = args.into_iter();
+    while let Option::Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hdu" => options.hdu = Option::Some(args.next().ok_or("--hdu needs an index or EXTNAME")?),
+            "--stretch" => {
+                let name = args.next().ok_or("--stretch needs a name")?;
+                zscale = name == "zscale";
+                options.stretch = match name.as_str() {
+                    "linear" | "zscale" => Stretch::Linear,
+                    "sqrt" => Stretch::Sqrt,
+                    "log" => Stretch::Log,
+                    "asinh" => Stretch::Asinh,
+                    _ => return Err(format!("unknown stretch {}", name)),
+                };
+            }
+            "--percentile" => {
+                let percent = args.next().ok_or("--percentile needs a percentage")?;
+                let clipped = f64::from_str(&percent)
+                    .ok()
+                    .filter(|&percent| percent > 0f64 && percent <= 100f64)
+                    .map(|percent| (100f64 - percent) / 2f64)
+                    .ok_or(format!("invalid percentage {}", percent))?;
+                percentile = Option::Some(Interval::Percentile(clipped, 100f64 - clipped));
+            }
+            "--downsample" => {
+                let factor = args.next().ok_or("--downsample needs a factor")?;
+                options.downsample = usize::from_str(&factor)
+                    .ok()
+                    .filter(|&factor| factor > 0)
+                    .ok_or(format!("invalid factor {}", factor))?;
+            }
+            "--output-dir" => options.output_dir = Option::Some(PathBuf::from(args.next().ok_or("--output-dir needs a directory")?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ => options.filenames.push(arg),
+        }
+    }
+    options.interval = match (zscale, percentile) {
+        (true, Option::Some(_)) => return Err("--stretch zscale and --percentile both choose the values to stretch".to_string()),
+        (true, Option::None) => Interval::ZScale,
+        (false, Option::Some(interval)) => interval,
+        (false, Option::None) => Interval::MinMax,
+    };
+    if options.filenames.is_empty() {
+        return Err("expected at least one file".to_string())
+    }
+    Ok(options)
+}
+
+fn render(filename: &str, options: &Options) -> Result<PathBuf, String> {
+    let mut buffer: Vec<u8> = vec!();
+    File::open(filename)
+        .and_then(|mut f| f.read_to_end(&mut buffer))
+        .map_err(|error| error.to_string())?;
+    let file = match fits(&buffer) {
+        IResult::Done(_, file) => file,
+        _ => return Err("not a FITS file".to_string()),
+    };
+    let mut image = select(&file, &options.hdu)?;
+    if options.downsample > 1 {
+        image = image.rebin(options.downsample, Rebin::Mean);
+    }
+    let preview = image.to_gray_image_with(options.stretch, options.interval).map_err(|error| format!("could not render: {:?}", error))?;
+
+    let path = Path::new(filename).with_extension("png");
+    let output = match options.output_dir {
+        Option::Some(ref directory) => directory.join(path.file_name().unwrap()),
+        Option::None => path,
+    };
+    preview.save(&output).map_err(|error| error.to_string())?;
+    Ok(output)
+}
+
+fn select(file: &Fits, hdu: &Option<String>) -> Result<Image, String> {
+    let decode = |hdu: &HDU| Image::new(&hdu.header, hdu.data());
+    match *hdu {
+        Option::Some(ref name) => {
+            let hdu = match usize::from_str(name) {
+                Ok(index) => file.get(index).ok_or(format!("no HDU {}", index))?,
+                Err(_) => file.by_name(name).ok_or(format!("no HDU named {}", name))?,
+            };
+            decode(hdu).map_err(|error| format!("not an image: {:?}", error))
+        },
+        Option::None => file.iter()
+            .filter_map(|hdu| decode(hdu).ok())
+            .find(|image| image.axes.len() >= 2)
+            .ok_or("no two dimensional image".to_string()),
+    }
+}
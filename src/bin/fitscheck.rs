@@ -0,0 +1,61 @@
+extern crate nom;
+extern crate fits_rs;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use nom::IResult;
+use fits_rs::lint::{fixed_format, Profile, Severity, Validator};
+use fits_rs::parser::fits;
+
+const USAGE: &str = "usage: fitscheck [--strict] FILE...
+
+Checks FITS files for conformance with the standard. Exits with 1 when any file
+has errors, or warnings with --strict. --strict also checks that the mandatory
+keywords are in fixed format, and validates with the pure FITS 4.0 profile:
+standard units and dates, and warnings about the keywords of conventions.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let strict = args.iter().any(|arg| arg == "--strict");
+    let filenames: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    if filenames.is_empty() || args.iter().any(|arg| arg == "--help") {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let validator = Validator::for_profile(if strict { Profile::Standard } else { Profile::Conventions });
+    let mut failed = false;
+    for filename in filenames {
+        let mut buffer: Vec<u8> = vec!();
+        if let Err(error) = File::open(filename).and_then(|mut f| f.read_to_end(&mut buffer)) {
+            println!("{}: {}", filename, error);
+            failed = true;
+            continue;
+        }
+        match fits(&buffer) {
+            IResult::Done(_, file) => {
+                let mut findings = validator.validate(&file);
+                if strict {
+                    findings.extend(fixed_format(&buffer));
+                    findings.sort_by_key(|finding| (finding.hdu, finding.card.unwrap_or(0)));
+                }
+                for finding in &findings {
+                    println!("{}: {}", filename, finding);
+                }
+                let errors = findings.iter().filter(|finding| finding.severity == Severity::Error).count();
+                let warnings = findings.len() - errors;
+                println!("{}: {} errors, {} warnings", filename, errors, warnings);
+                failed |= errors > 0 || (strict && warnings > 0);
+            },
+            _ => {
+                println!("{}: error: the file could not be parsed", filename);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
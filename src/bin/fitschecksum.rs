@@ -0,0 +1,147 @@
+extern crate nom;
+extern crate fits_rs;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::process;
+use nom::IResult;
+use fits_rs::checksum::{self, Status};
+use fits_rs::parser::fits;
+use fits_rs::types::{HDU, Header, Keyword, Value};
+use fits_rs::writer::write_hdu;
+
+const USAGE: &str = "usage: fitschecksum [--update] FILE...
+
+Verifies CHECKSUM and DATASUM of every HDU, reporting the HDUs that fail. Exits
+with 1 when any of them does. With --update both are written instead.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let update = args.iter().any(|arg| arg == "--update");
+    let filenames: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    if filenames.is_empty() || args.iter().any(|arg| arg == "--help") {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut failed = false;
+    for filename in filenames {
+        let mut buffer: Vec<u8> = vec!();
+        if let Err(error) = File::open(filename).and_then(|mut f| f.read_to_end(&mut buffer)) {
+            eprintln!("{}: {}", filename, error);
+            failed = true;
+            continue;
+        }
+        let result = if update { rewrite(filename, &buffer) } else { verify(filename, &buffer) };
+        match result {
+            Ok(ok) => failed |= !ok,
+            Err(message) => {
+                eprintln!("{}: {}", filename, message);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Reports the state of the checksums of every HDU, returning whether none of
+/// them failed.
+fn verify(filename: &str, buffer: &[u8]) -> Result<bool, String> {
+    if let IResult::Error(_) | IResult::Incomplete(_) = fits(buffer) {
+        return Err("not a FITS file".to_string())
+    }
+    let mut ok = true;
+    for (index, verification) in checksum::verify_file(buffer).iter().enumerate() {
+        ok &= verification.is_ok();
+        println!("{}: HDU {}: CHECKSUM {}, DATASUM {}", filename, index, state(verification.checksum), state(verification.datasum));
+    }
+    Ok(ok)
+}
+
+fn state(status: Status) -> &'static str {
+    match status {
+        Status::Ok => "ok",
+        Status::Failed => "FAILED",
+        Status::Missing => "missing",
+    }
+}
+
+/// Writes `CHECKSUM` and `DATASUM` into every HDU, rewriting the file.
+fn rewrite(filename: &str, buffer: &[u8]) -> Result<bool, String> {
+    let (output, count) = updated(buffer)?;
+    for index in 0..count {
+        println!("{}: HDU {}: updated", filename, index);
+    }
+
+    let temporary = format!("{}.fitschecksum", filename);
+    File::create(&temporary)
+        .and_then(|mut f| f.write_all(&output))
+        .and_then(|_| fs::rename(&temporary, filename))
+        .map_err(|error| error.to_string())?;
+    Ok(true)
+}
+
+/// The file `buffer` with `CHECKSUM` and `DATASUM` in every HDU, and the
+/// number of HDUs. The writer computes both, as it pads the data arrays.
+fn updated(buffer: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let file = match fits(buffer) {
+        IResult::Done(_, file) => file,
+        _ => return Err("not a FITS file".to_string()),
+    };
+
+    let mut output = vec!();
+    let mut count = 0;
+    for (index, mut hdu) in file.into_iter().enumerate() {
+        hdu.header.set(Keyword::CHECKSUM, Value::CharacterString(checksum::ZEROS.into()), Option::Some("HDU checksum".into()))
+            .and_then(|_| hdu.header.set(Keyword::DATASUM, Value::CharacterString("0".into()), Option::Some("data unit checksum".into())))
+            .map_err(|error| format!("could not update HDU {}: {:?}", index, error))?;
+        // Without the bytes it was parsed from, the HDU is never copied as is.
+        let rebuilt = HDU::with_data(Header::new(hdu.header.records().to_vec()), hdu.data());
+        write_hdu(&mut output, &rebuilt).map_err(|error| error.to_string())?;
+        count += 1;
+    }
+    Ok((output, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use fits_rs::checksum::{verify_file, Status};
+    use super::updated;
+
+    #[test]
+    fn updated_checksums_should_verify_for_ascii_tables_with_placeholders() {
+        let card = |text: &str| format!("{:<80}", text);
+        let mut input = vec!();
+        for text in &["SIMPLE  =                    T", "BITPIX  =                    8", "NAXIS   =                    0", "EXTEND  =                    T", "END"] {
+            input.extend_from_slice(card(text).as_bytes());
+        }
+        input.resize(2880, b' ');
+        for text in &["XTENSION= 'TABLE   '", "BITPIX  =                    8", "NAXIS   =                    2",
+                      "NAXIS1  =                   10", "NAXIS2  =                    3", "PCOUNT  =                    0",
+                      "GCOUNT  =                    1", "TFIELDS =                    1", "TFORM1  = 'A10     '",
+                      "TBCOL1  =                    1", "CHECKSUM= '0000000000000000'", "DATASUM = '0       '", "END"] {
+            input.extend_from_slice(card(text).as_bytes());
+        }
+        input.resize(2 * 2880, b' ');
+        input.extend_from_slice(b"first row second rowthird row ");
+        input.resize(3 * 2880, b' ');
+
+        let (output, count) = updated(&input).unwrap();
+
+        assert_eq!(count, 2);
+        for verification in verify_file(&output) {
+            assert_eq!((verification.checksum, verification.datasum), (Status::Ok, Status::Ok));
+        }
+    }
+
+    #[test]
+    fn updated_checksums_should_verify_for_every_hdu() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let (once, _) = updated(data).unwrap();
+
+        assert!(verify_file(&once).iter().all(|verification| verification.is_ok()));
+    }
+}
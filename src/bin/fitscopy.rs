@@ -0,0 +1,105 @@
+extern crate fits_rs;
+
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process;
+use std::str::FromStr;
+use fits_rs::parser::{template, HeaderParser};
+use fits_rs::types::{HDU, Header, Keyword};
+use fits_rs::writer::write_hdu;
+
+const USAGE: &str = "usage: fitscopy INPUT[HDU,...] OUTPUT
+
+Copies the HDUs of INPUT, given as indices or EXTNAMEs between brackets, or all
+of them, to a new file OUTPUT. An empty primary HDU is written first when the
+first HDU copied is an extension.";
+
+const EMPTY_PRIMARY: &str = "SIMPLE = T / conforms to the FITS standard
+BITPIX = 8
+NAXIS = 0
+EXTEND = T";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let (input, selection) = split(&args[0]);
+    if let Err(message) = run(input, &selection, &args[1]) {
+        eprintln!("{}: {}", input, message);
+        process::exit(1);
+    }
+}
+
+/// Splits `file.fits[A,B]` into the filename and the selected HDUs.
+fn split(argument: &str) -> (&str, Vec<&str>) {
+    match argument.find('[') {
+        Option::Some(start) if argument.ends_with(']') => {
+            let selection = argument[start + 1..argument.len() - 1]
+                .split(',')
+                .map(str::trim)
+                .filter(|hdu| !hdu.is_empty())
+                .collect();
+            (&argument[..start], selection)
+        }
+        _ => (argument, vec!()),
+    }
+}
+
+fn run(input: &str, selection: &[&str], output: &str) -> Result<(), String> {
+    let mut parser = HeaderParser::new(File::open(input).map_err(|error| error.to_string())?);
+    let mut read: Vec<(usize, HDU<'static>)> = vec!();
+    let mut index = 0;
+    while let Option::Some(header) = parser.next_header().map_err(|error| error.to_string())? {
+        if selection.is_empty() || selection.iter().any(|hdu| selects(hdu, index, &header, &read)) {
+            read.push((index, parser.read_hdu().map_err(|error| error.to_string())?));
+        }
+        index += 1;
+    }
+    if index == 0 {
+        return Err("not a FITS file".to_string())
+    }
+    let hdus: Vec<&HDU> = if selection.is_empty() {
+        read.iter().map(|(_, hdu)| hdu).collect()
+    } else {
+        selection.iter().map(|hdu| select(&read, hdu)).collect::<Result<Vec<&HDU>, String>>()?
+    };
+    let is_primary = |hdu: &HDU| hdu.header.values_of(&Keyword::SIMPLE).next().is_some();
+    if hdus.iter().skip(1).any(|hdu| is_primary(hdu)) {
+        return Err("the primary HDU can only be copied first".to_string())
+    }
+
+    let mut out = BufWriter::new(File::create(output).map_err(|error| format!("{}: {}", output, error))?);
+    if !hdus.first().is_some_and(|hdu| is_primary(hdu)) {
+        let primary = HDU::new(template(EMPTY_PRIMARY).expect("the empty primary header is a valid template"));
+        write_hdu(&mut out, &primary).map_err(|error| format!("{}: {}", output, error))?;
+    }
+    for hdu in hdus {
+        write_hdu(&mut out, hdu).map_err(|error| format!("{}: {}", output, error))?;
+    }
+    out.flush().map_err(|error| format!("{}: {}", output, error))
+}
+
+/// Whether `hdu`, an index or an `EXTNAME`, selects the HDU at `index` with
+/// `header`. A name selects the first HDU with that name only.
+fn selects(hdu: &str, index: usize, header: &Header, read: &[(usize, HDU)]) -> bool {
+    match usize::from_str(hdu) {
+        Ok(selected) => selected == index,
+        Err(_) => has_name(header, hdu) && !read.iter().any(|(_, other)| has_name(&other.header, hdu)),
+    }
+}
+
+fn select<'r, 'a>(read: &'r [(usize, HDU<'a>)], hdu: &str) -> Result<&'r HDU<'a>, String> {
+    match usize::from_str(hdu) {
+        Ok(index) => read.iter().find(|&&(i, _)| i == index).map(|(_, hdu)| hdu).ok_or(format!("no HDU {}", index)),
+        Err(_) => read.iter().map(|(_, other)| other).find(|other| has_name(&other.header, hdu)).ok_or(format!("no HDU named {}", hdu)),
+    }
+}
+
+/// Whether `header` has `EXTNAME` equal to `name`, compared like `Fits::by_name`.
+fn has_name(header: &Header, name: &str) -> bool {
+    header.character_string_value_of(&Keyword::EXTNAME)
+        .is_ok_and(|extname| extname.trim_end().eq_ignore_ascii_case(name.trim_end()))
+}
@@ -0,0 +1,116 @@
+extern crate nom;
+extern crate fits_rs;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+use std::str::FromStr;
+use nom::IResult;
+use fits_rs::checksum;
+use fits_rs::diff::{diff, CardDiff};
+use fits_rs::parser::fits;
+use fits_rs::table::BinTable;
+use fits_rs::types::{Fits, HDU, Keyword, KeywordRecord};
+
+const USAGE: &str = "usage: fitsdiff [--ignore KEYWORD,...] FIRST SECOND
+
+Compares two FITS files HDU by HDU: header records, the sums of the data arrays
+and the rows of binary tables. Exits with 0 when the files are equivalent, 1
+when they differ and 2 on trouble.";
+
+fn main() {
+    let mut filenames = vec!();
+    let mut ignored = vec!();
+    let mut args = env::args().skip(1);
+    while let Option::Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ignore" => match args.next() {
+                Option::Some(names) => for name in names.split(',') {
+                    match Keyword::from_str(&name.trim().to_uppercase()) {
+                        Ok(keyword) => ignored.push(keyword),
+                        Err(_) => usage(&format!("invalid keyword {}", name)),
+                    }
+                },
+                Option::None => usage("--ignore needs a list of keywords"),
+            },
+            "-h" | "--help" => usage(""),
+            _ => filenames.push(arg),
+        }
+    }
+    if filenames.len() != 2 {
+        usage("expected two files");
+    }
+
+    let first = read(&filenames[0]);
+    let second = read(&filenames[1]);
+    let differences = match (fits(&first), fits(&second)) {
+        (IResult::Done(_, a), IResult::Done(_, b)) => compare(&a, &b, &ignored),
+        (IResult::Done(_, _), _) => trouble(&filenames[1], "not a FITS file"),
+        _ => trouble(&filenames[0], "not a FITS file"),
+    };
+    for difference in &differences {
+        println!("{}", difference);
+    }
+    if !differences.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn trouble(filename: &str, message: &str) -> ! {
+    eprintln!("{}: {}", filename, message);
+    process::exit(2);
+}
+
+fn read(filename: &str) -> Vec<u8> {
+    let mut buffer: Vec<u8> = vec!();
+    if let Err(error) = File::open(filename).and_then(|mut f| f.read_to_end(&mut buffer)) {
+        trouble(filename, &error.to_string());
+    }
+    buffer
+}
+
+fn compare(a: &Fits, b: &Fits, ignored: &[Keyword]) -> Vec<String> {
+    let mut differences = vec!();
+    if a.len() != b.len() {
+        differences.push(format!("number of HDUs: {} versus {}", a.len(), b.len()));
+    }
+    for (index, (first, second)) in a.iter().zip(b.iter()).enumerate() {
+        compare_hdu(index, first, second, ignored, &mut differences);
+    }
+    differences
+}
+
+fn compare_hdu(index: usize, a: &HDU, b: &HDU, ignored: &[Keyword], differences: &mut Vec<String>) {
+    for difference in diff(&a.header, &b.header) {
+        let line = match difference {
+            CardDiff::Removed(ref record) if !ignored.contains(record.keyword()) => format!("- {}", card(record)),
+            CardDiff::Added(ref record) if !ignored.contains(record.keyword()) => format!("+ {}", card(record)),
+            CardDiff::Changed(ref first, ref second) if !ignored.contains(first.keyword()) =>
+                format!("- {}\nHDU {}: + {}", card(first), index, card(second)),
+            _ => continue,
+        };
+        differences.push(format!("HDU {}: {}", index, line));
+    }
+
+    if let (Ok(first), Ok(second)) = (BinTable::new(&a.header), BinTable::new(&b.header)) {
+        if first.rows() != second.rows() {
+            differences.push(format!("HDU {}: rows: {} versus {}", index, first.rows(), second.rows()));
+        }
+    }
+    let (first, second) = (a.data(), b.data());
+    if first.len() != second.len() {
+        differences.push(format!("HDU {}: data size: {} versus {} bytes", index, first.len(), second.len()));
+    } else if first != second {
+        differences.push(format!("HDU {}: data sum: {} versus {}", index, checksum::sum(first), checksum::sum(second)));
+    }
+}
+
+fn card(record: &KeywordRecord) -> String {
+    record.to_string().trim_end().to_string()
+}
@@ -0,0 +1,170 @@
+extern crate nom;
+extern crate fits_rs;
+
+use std::borrow::Cow;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::process;
+use std::ptr;
+use std::str::FromStr;
+use nom::IResult;
+use fits_rs::parser::{fits, layout, template, TemplateError};
+use fits_rs::types::{HDU, Header, Keyword};
+use fits_rs::writer::write_hdu;
+
+const USAGE: &str = "usage: fitsedit FILE [--hdu HDU] [--set KEYWORD=VALUE[/COMMENT]]... [--delete KEYWORD]... [--rename OLD=NEW]...
+
+Edits the header of the primary HDU, or the HDU given as an index or EXTNAME,
+in place. Edits are applied in order. Strings need not be quoted, a / starts
+the comment of unquoted strings. CHECKSUM and DATASUM are updated when the
+header has them.";
+
+enum Edit {
+    Set(String),
+    Delete(String),
+    Rename(String, String),
+}
+
+struct Options {
+    filename: String,
+    hdu: Option<String>,
+    edits: Vec<Edit>,
+}
+
+fn main() {
+    let options = match options(env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(message) = run(&options) {
+        eprintln!("{}: {}", options.filename, message);
+        process::exit(1);
+    }
+}
+
+fn options(args: Vec<String>) -> Result<Options, String> {
+    let mut positional = vec!();
+    let mut hdu = Option::None;
+    let mut edits = vec!();
+    let mut args = args.into_iter();
+    while let Option::Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hdu" => hdu = Option::Some(args.next().ok_or("--hdu needs an index or EXTNAME")?),
+            "--set" => edits.push(Edit::Set(args.next().ok_or("--set needs KEYWORD=VALUE")?)),
+            "--delete" => edits.push(Edit::Delete(args.next().ok_or("--delete needs a keyword")?)),
+            "--rename" => {
+                let names = args.next().ok_or("--rename needs OLD=NEW")?;
+                let mut names = names.splitn(2, '=');
+                match (names.next(), names.next()) {
+                    (Option::Some(old), Option::Some(new)) => edits.push(Edit::Rename(old.to_string(), new.to_string())),
+                    _ => return Err("--rename needs OLD=NEW".to_string()),
+                }
+            }
+            "-h" | "--help" => return Err(String::new()),
+            _ => positional.push(arg),
+        }
+    }
+    if positional.len() != 1 {
+        return Err("expected a file".to_string())
+    }
+    if edits.is_empty() {
+        return Err("expected at least one edit".to_string())
+    }
+    Ok(Options { filename: positional.remove(0), hdu, edits })
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut buffer: Vec<u8> = vec!();
+    File::open(&options.filename)
+        .and_then(|mut f| f.read_to_end(&mut buffer))
+        .map_err(|error| error.to_string())?;
+    let file = match fits(&buffer) {
+        IResult::Done(_, file) => file,
+        _ => return Err("not a FITS file".to_string()),
+    };
+    let index = match options.hdu {
+        Option::None => 0,
+        Option::Some(ref hdu) => match usize::from_str(hdu) {
+            Ok(index) if index < file.len() => index,
+            Ok(index) => return Err(format!("no HDU {}", index)),
+            Err(_) => {
+                let named = file.by_name(hdu).ok_or(format!("no HDU named {}", hdu))?;
+                file.iter().position(|candidate| ptr::eq(candidate, named)).unwrap()
+            }
+        },
+    };
+    let ranges = layout(&buffer);
+    let values: Vec<String> = options.edits.iter()
+        .filter_map(|edit| match *edit {
+            Edit::Set(ref assignment) => Option::Some(quoted(assignment)),
+            _ => Option::None,
+        })
+        .collect();
+    let templates = values.iter()
+        .map(|assignment| match template(assignment) {
+            Ok(ref header) if header.records().is_empty() => Err(format!("nothing to set in {}", assignment)),
+            Ok(header) => Ok(header),
+            Err(_) => Err(format!("invalid assignment {}", assignment)),
+        })
+        .collect::<Result<Vec<Header>, String>>()?;
+
+    let mut hdus: Vec<HDU> = file.into_iter().collect();
+    let hdu = &mut hdus[index];
+    let mut templates = templates.iter();
+    for edit in &options.edits {
+        let result = match *edit {
+            Edit::Set(ref assignment) => {
+                let record = &templates.next().unwrap().records()[0];
+                hdu.header.set(record.keyword().clone(), record.value().clone(), record.comment().map(Cow::Borrowed))
+                    .map_err(|error| format!("could not set {}: {:?}", assignment, error))
+            }
+            Edit::Delete(ref name) => hdu.header.remove(&keyword(name)?)
+                .map(|_| ())
+                .map_err(|error| format!("could not delete {}: {:?}", name, error)),
+            Edit::Rename(ref old, ref new) => hdu.header.rename(&keyword(old)?, keyword(new)?)
+                .map_err(|error| format!("could not rename {}: {:?}", old, error)),
+        };
+        result?;
+    }
+
+    let mut written = vec!();
+    write_hdu(&mut written, hdu).map_err(|error| error.to_string())?;
+
+    let (ref header_range, ref data_range) = ranges[index];
+    let mut output = Vec::with_capacity(buffer.len() + written.len());
+    output.extend_from_slice(&buffer[..header_range.start]);
+    output.extend_from_slice(&written);
+    output.extend_from_slice(&buffer[data_range.end..]);
+
+    let temporary = format!("{}.fitsedit", options.filename);
+    File::create(&temporary)
+        .and_then(|mut f| f.write_all(&output))
+        .and_then(|_| fs::rename(&temporary, &options.filename))
+        .map_err(|error| error.to_string())
+}
+
+/// Quotes the value of `KEYWORD=VALUE[/COMMENT]` when it is not a valid value by
+/// itself, so that strings can be given without quotes.
+fn quoted(assignment: &str) -> String {
+    match template(assignment) {
+        Err(TemplateError::InvalidValue(_)) => {
+            let mut parts = assignment.splitn(2, '=');
+            let name = parts.next().unwrap_or("");
+            let rest = parts.next().unwrap_or("");
+            let (value, comment) = match rest.find('/') {
+                Option::Some(slash) => (&rest[..slash], &rest[slash..]),
+                Option::None => (rest, ""),
+            };
+            format!("{}= '{}' {}", name, value.trim().replace('\'', "''"), comment)
+        }
+        _ => assignment.to_string(),
+    }
+}
+
+fn keyword(name: &str) -> Result<Keyword, String> {
+    Keyword::from_str(&name.to_uppercase()).map_err(|_| format!("invalid keyword {}", name))
+}
@@ -0,0 +1,87 @@
+extern crate fits_rs;
+
+use std::env;
+use std::fs::File;
+use std::io;
+use std::process;
+use fits_rs::parser::HeaderParser;
+use fits_rs::image::ZImageInfo;
+use fits_rs::table::BinTable;
+use fits_rs::types::{Header, Keyword};
+
+fn main() {
+    let filenames: Vec<String> = env::args().skip(1).collect();
+    if filenames.is_empty() {
+        eprintln!("usage: fitsinfo FILE...");
+        process::exit(2);
+    }
+
+    let mut failed = false;
+    for filename in &filenames {
+        if let Err(error) = File::open(filename).and_then(|file| info(filename, file)) {
+            eprintln!("{}: {}", filename, error);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Prints a line per HDU of `file`, parsing only the headers. The data
+/// arrays are skipped.
+fn info(filename: &str, file: File) -> io::Result<()> {
+    let mut parser = HeaderParser::new(file);
+    let first = match parser.next_header() {
+        Ok(Option::Some(header)) => header,
+        Ok(Option::None) | Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "not a FITS file")),
+    };
+    println!("Filename: {}", filename);
+    println!("{:<4} {:<16} {:>4} {:<10} {:>6} {:>6}  {:<20} {:>10}", "No.", "Name", "Ver", "Type", "Cards", "BITPIX", "Dimensions", "Size");
+    println!("{}", summary(0, &first));
+    let mut index = 1;
+    while let Option::Some(header) = parser.next_header()? {
+        println!("{}", summary(index, &header));
+        index += 1;
+    }
+    Ok(())
+}
+
+fn summary(index: usize, header: &Header) -> String {
+    if let Ok(info) = ZImageInfo::new(header) {
+        return compressed_summary(index, header, &info);
+    }
+    let kind = header.character_string_value_of(&Keyword::XTENSION)
+        .map(|xtension| xtension.trim_end().to_string())
+        .unwrap_or_else(|_| "PRIMARY".to_string());
+    let (name, version) = name_and_version(index, header);
+    let bitpix = header.integer_value_of(&Keyword::BITPIX)
+        .map(|bitpix| bitpix.to_string())
+        .unwrap_or_else(|_| "?".to_string());
+    let dimensions = match BinTable::new(header) {
+        Ok(table) => format!("{}R x {}C", table.rows(), table.columns().len()),
+        Err(_) => match header.naxes() {
+            Ok(ref axes) if axes.is_empty() => "()".to_string(),
+            Ok(axes) => axes.iter().map(|length| length.to_string()).collect::<Vec<String>>().join(" x "),
+            Err(_) => "?".to_string(),
+        },
+    };
+    format!("{:<4} {:<16} {:>4} {:<10} {:>6} {:>6}  {:<20} {:>10}",
+            index, name, version, kind, header.len(), bitpix, dimensions, header.data_size())
+}
+
+/// Compressed images are reported with the type, `BITPIX` and axes of the
+/// uncompressed image and their compression algorithm.
+fn compressed_summary(index: usize, header: &Header, info: &ZImageInfo) -> String {
+    let (name, version) = name_and_version(index, header);
+    let dimensions = info.axes.iter().map(|length| length.to_string()).collect::<Vec<String>>().join(" x ");
+    format!("{:<4} {:<16} {:>4} {:<10} {:>6} {:>6}  {:<20} {:>10}  {}",
+            index, name, version, "COMPRESSED", header.len(), info.bitpix, dimensions, header.data_size(), info.compression)
+}
+
+fn name_and_version(index: usize, header: &Header) -> (String, i64) {
+    let name = header.character_string_value_of(&Keyword::EXTNAME)
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_else(|_| if index == 0 { "PRIMARY".to_string() } else { String::new() });
+    (name, header.integer_value_of(&Keyword::EXTVER).unwrap_or(1i64))
+}
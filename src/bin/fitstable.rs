@@ -0,0 +1,268 @@
+extern crate nom;
+extern crate fits_rs;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::process;
+use std::str::FromStr;
+use nom::IResult;
+use fits_rs::parser::fits;
+use fits_rs::table::{BinTable, Column, ColumnData, ColumnValues};
+use fits_rs::types::{Fits, HDU};
+
+const USAGE: &str = "usage: fitstable FILE [HDU] [--columns NAME,...] [--rows START:END] [--format csv|jsonl]
+
+Lists the columns of a binary table, the first one unless HDU is given as an
+index or EXTNAME. With --format the selected columns and rows are dumped.";
+
+struct Options {
+    filename: String,
+    hdu: Option<String>,
+    columns: Option<Vec<String>>,
+    rows: Option<(usize, Option<usize>)>,
+    format: Option<String>,
+}
+
+fn main() {
+    let options = match options(env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(message) = run(&options) {
+        eprintln!("{}: {}", options.filename, message);
+        process::exit(1);
+    }
+}
+
+fn options(args: Vec<String>) -> Result<Options, String> {
+    let mut positional = vec!();
+    let mut columns = Option::None;
+    let mut rows = Option::None;
+    let mut format = Option::None;
+    let mut args = args.into_iter();
+    while let Option::Some(arg) = args.next() {
+        match arg.as_str() {
+            "--columns" => {
+                let names = args.next().ok_or("--columns needs a list of names")?;
+                columns = Option::Some(names.split(',').map(|name| name.trim().to_string()).collect());
+            }
+            "--rows" => {
+                let range = args.next().ok_or("--rows needs a range")?;
+                let mut bounds = range.splitn(2, ':');
+                let start = bounds.next().filter(|start| !start.is_empty()).map_or(Ok(0), usize::from_str)
+                    .map_err(|_| format!("invalid range {}", range))?;
+                let end = match bounds.next().filter(|end| !end.is_empty()) {
+                    Option::Some(end) => Option::Some(usize::from_str(end).map_err(|_| format!("invalid range {}", range))?),
+                    Option::None => Option::None,
+                };
+                rows = Option::Some((start, end));
+            }
+            "--format" => {
+                let name = args.next().ok_or("--format needs csv or jsonl")?;
+                if name != "csv" && name != "jsonl" {
+                    return Err(format!("unknown format {}", name))
+                }
+                format = Option::Some(name);
+            }
+            "-h" | "--help" => return Err(String::new()),
+            _ => positional.push(arg),
+        }
+    }
+    if positional.is_empty() || positional.len() > 2 {
+        return Err("expected a file and optionally an HDU".to_string())
+    }
+    let mut positional = positional.into_iter();
+    Ok(Options {
+        filename: positional.next().unwrap(),
+        hdu: positional.next(),
+        columns,
+        rows,
+        format,
+    })
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut buffer: Vec<u8> = vec!();
+    File::open(&options.filename)
+        .and_then(|mut f| f.read_to_end(&mut buffer))
+        .map_err(|error| error.to_string())?;
+    let file = match fits(&buffer) {
+        IResult::Done(_, file) => file,
+        _ => return Err("not a FITS file".to_string()),
+    };
+    let hdu = select(&file, &options.hdu)?;
+    let table = BinTable::new(&hdu.header).map_err(|error| format!("not a binary table: {:?}", error))?;
+
+    let indices: Vec<usize> = match options.columns {
+        Option::Some(ref names) => names.iter()
+            .map(|name| table.column_index(name).ok_or(format!("no column {}", name)))
+            .collect::<Result<Vec<usize>, String>>()?,
+        Option::None => (0..table.columns().len()).collect(),
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    match options.format {
+        Option::None => list(&mut out, &table, &indices),
+        Option::Some(ref format) => {
+            let (start, end) = options.rows.unwrap_or((0, Option::None));
+            let end = end.unwrap_or(table.rows()).min(table.rows());
+            let values = table.read_columns(hdu.data(), &indices)
+                .map_err(|error| format!("could not read the table: {:?}", error))?;
+            let columns: Vec<(&Column, ColumnValues)> = indices.iter().map(|&index| &table.columns()[index]).zip(values).collect();
+            if format == "csv" {
+                csv(&mut out, &columns, start..end)
+            } else {
+                jsonl(&mut out, &columns, start..end)
+            }
+        }
+    }.map_err(|error| error.to_string())
+}
+
+fn select<'f, 'a>(file: &'f Fits<'a>, hdu: &Option<String>) -> Result<&'f HDU<'a>, String> {
+    match *hdu {
+        Option::Some(ref hdu) => match usize::from_str(hdu) {
+            Ok(index) => file.get(index).ok_or(format!("no HDU {}", index)),
+            Err(_) => file.by_name(hdu).ok_or(format!("no HDU named {}", hdu)),
+        },
+        Option::None => file.iter()
+            .find(|hdu| BinTable::new(&hdu.header).is_ok())
+            .ok_or("no binary table".to_string()),
+    }
+}
+
+fn list<W: Write>(out: &mut W, table: &BinTable, indices: &[usize]) -> io::Result<()> {
+    writeln!(out, "{} rows", table.rows())?;
+    writeln!(out, "{:<4} {:<20} {:<12} Unit", "No.", "Name", "Format")?;
+    for &index in indices {
+        let column = &table.columns()[index];
+        writeln!(out, "{:<4} {:<20} {:<12} {}",
+                 index + 1,
+                 column.name.clone().unwrap_or_default(),
+                 column.form.to_string(),
+                 column.unit.clone().unwrap_or_default())?;
+    }
+    Ok(())
+}
+
+fn name(column: &Column, position: usize) -> String {
+    column.name.clone().unwrap_or_else(|| format!("COL{}", position + 1))
+}
+
+fn csv<W: Write>(out: &mut W, columns: &[(&Column, ColumnValues)], rows: Range<usize>) -> io::Result<()> {
+    let names: Vec<String> = columns.iter().enumerate().map(|(position, &(column, _))| csv_field(&name(column, position))).collect();
+    writeln!(out, "{}", names.join(","))?;
+    for row in rows {
+        let fields: Vec<String> = columns.iter()
+            .map(|&(column, ref values)| {
+                let elements: Vec<String> = cell(column, values, row).into_iter().map(|element| match element {
+                    Element::Text(text) => text,
+                    Element::Number(number) => number,
+                    Element::Logical(b) => if b { "T" } else { "F" }.to_string(),
+                    Element::Null => String::new(),
+                }).collect();
+                csv_field(&elements.join(" "))
+            })
+            .collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn jsonl<W: Write>(out: &mut W, columns: &[(&Column, ColumnValues)], rows: Range<usize>) -> io::Result<()> {
+    for row in rows {
+        let members: Vec<String> = columns.iter().enumerate()
+            .map(|(position, &(column, ref values))| {
+                let elements: Vec<String> = cell(column, values, row).into_iter().map(|element| match element {
+                    Element::Text(text) => json_string(&text),
+                    Element::Number(number) => number,
+                    Element::Logical(b) => b.to_string(),
+                    Element::Null => "null".to_string(),
+                }).collect();
+                let value = if is_scalar(column) && elements.len() == 1 {
+                    elements[0].clone()
+                } else {
+                    format!("[{}]", elements.join(","))
+                };
+                format!("{}:{}", json_string(&name(column, position)), value)
+            })
+            .collect();
+        writeln!(out, "{{{}}}", members.join(","))?;
+    }
+    Ok(())
+}
+
+fn json_string(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+enum Element {
+    Text(String),
+    Number(String),
+    Logical(bool),
+    Null,
+}
+
+fn is_scalar(column: &Column) -> bool {
+    column.form.descriptor.is_none() && column.form.repeat == 1
+}
+
+fn cell(column: &Column, values: &ColumnValues, row: usize) -> Vec<Element> {
+    let range = values.offsets[row]..values.offsets[row + 1];
+    let scaled = column.scale != 1f64 || column.zero != 0f64;
+    let integer = |raw: i64| {
+        if Option::Some(raw) == column.null {
+            Element::Null
+        } else if scaled {
+            real(raw as f64 * column.scale + column.zero)
+        } else {
+            Element::Number(raw.to_string())
+        }
+    };
+    match values.data {
+        ColumnData::Character(_) => vec!(Element::Text(values.text(row).unwrap_or("").to_string())),
+        ColumnData::Logical(ref v) => v[range].iter().map(|b| b.map_or(Element::Null, Element::Logical)).collect(),
+        ColumnData::Bit(ref v) => v[range].iter().map(|&b| Element::Number(if b { "1" } else { "0" }.to_string())).collect(),
+        ColumnData::UnsignedByte(ref v) => v[range].iter().map(|&x| integer(x as i64)).collect(),
+        ColumnData::Short(ref v) => v[range].iter().map(|&x| integer(x as i64)).collect(),
+        ColumnData::Int(ref v) => v[range].iter().map(|&x| integer(x as i64)).collect(),
+        ColumnData::Long(ref v) => v[range].iter().map(|&x| integer(x)).collect(),
+        ColumnData::Float(ref v) => v[range].iter().map(|&x| {
+            if scaled { real(x as f64 * column.scale + column.zero) } else { real_f32(x) }
+        }).collect(),
+        ColumnData::Double(ref v) => v[range].iter().map(|&x| real(x * column.scale + column.zero)).collect(),
+        ColumnData::ComplexFloat(ref v) => v[range].iter().flat_map(|&(re, im)| vec!(real_f32(re), real_f32(im))).collect(),
+        ColumnData::ComplexDouble(ref v) => v[range].iter().flat_map(|&(re, im)| vec!(real(re), real(im))).collect(),
+    }
+}
+
+fn real(x: f64) -> Element {
+    if x.is_finite() { Element::Number(x.to_string()) } else { Element::Null }
+}
+
+fn real_f32(x: f32) -> Element {
+    if x.is_finite() { Element::Number(x.to_string()) } else { Element::Null }
+}
@@ -12,7 +12,7 @@ use fits_rs::types::Header;
 fn main() {
     let args: Vec<String> = env::args().collect();
     let filename = &args[1];
-    let header_index = u64::from_str(&args[2]).expect("second argument should be a non-negative number");
+    let header_index = usize::from_str(&args[2]).expect("second argument should be a non-negative number");
 
     let mut f = File::open(filename).expect("file not found");
     let mut buffer: Vec<u8> = vec!();
@@ -22,13 +22,9 @@ fn main() {
 
     match result {
         IResult::Done(_, trappist1) => {
-            let header: &Header = if header_index == 0 {
-                &trappist1.primary_hdu.header
-            } else {
-                &trappist1.extensions[0].header
-            };
+            let header: &Header = &trappist1[header_index].header;
 
-            for ref record in &header.keyword_records {
+            for record in header.records() {
                 println!("{}", record);
             }
         },
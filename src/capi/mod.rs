@@ -0,0 +1,207 @@
+//! The capi module is a small C interface, behind the `capi` feature, so that
+//! C and C++ pipelines can parse files with this crate. `include/fits_rs.h`
+//! declares it, `cbindgen` regenerates it from this module.
+//!
+//! A file is opened into a `FitsFile` handle that owns its headers and data
+//! arrays until it is closed with `fits_close`. Strings returned by the
+//! interface are freed with `fits_free_string`.
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::slice;
+use std::str::FromStr;
+use nom::IResult;
+use super::parser::fits;
+use super::types::{Fits, Keyword, Value};
+
+/// The call succeeded.
+pub const FITS_OK: c_int = 0;
+/// The HDU or the keyword is not present.
+pub const FITS_NOT_PRESENT: c_int = 1;
+/// The keyword is present without a value.
+pub const FITS_UNDEFINED: c_int = 2;
+/// An argument is a null pointer, or not a valid keyword.
+pub const FITS_INVALID_ARGUMENT: c_int = -1;
+
+/// A parsed FITS file, opaque to C.
+pub struct FitsFile {
+    fits: Fits<'static>,
+}
+
+/// Opens and parses the FITS file at `path`, a NUL terminated path. Returns
+/// null when the file could not be read or parsed.
+///
+/// # Safety
+/// `path` is null or points to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fits_open(path: *const c_char) -> *mut FitsFile {
+    if path.is_null() {
+        return ptr::null_mut()
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+    match fs::read(path) {
+        Ok(bytes) => open(&bytes),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Parses the `length` bytes at `data` as a FITS file, copying them. Returns
+/// null when they could not be parsed.
+///
+/// # Safety
+/// `data` is null or points to `length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fits_open_memory(data: *const u8, length: usize) -> *mut FitsFile {
+    if data.is_null() {
+        return ptr::null_mut()
+    }
+    open(slice::from_raw_parts(data, length))
+}
+
+fn open(bytes: &[u8]) -> *mut FitsFile {
+    match fits(bytes) {
+        IResult::Done(_, parsed) => Box::into_raw(Box::new(FitsFile { fits: parsed.into_owned() })),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// The number of HDUs of `file`, including the primary HDU. 0 for null.
+///
+/// # Safety
+/// `file` is null or a handle returned by `fits_open` that is not closed.
+#[no_mangle]
+pub unsafe extern "C" fn fits_hdu_count(file: *const FitsFile) -> usize {
+    match file.as_ref() {
+        Option::Some(file) => file.fits.len(),
+        Option::None => 0,
+    }
+}
+
+/// Looks up `keyword` in the header of HDU `hdu`, 0 for the primary HDU, and
+/// stores its value as text in `value`: character strings without quotes,
+/// logicals as `T` or `F` and numbers as in the fixed format. The text is freed
+/// with `fits_free_string`.
+///
+/// Returns `FITS_OK`, or `FITS_NOT_PRESENT`, `FITS_UNDEFINED` or
+/// `FITS_INVALID_ARGUMENT` without storing a value.
+///
+/// # Safety
+/// `file` is null or an open handle, `keyword` is null or a NUL terminated
+/// string and `value` is null or points to writable storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn fits_keyword_value(file: *const FitsFile, hdu: usize, keyword: *const c_char, value: *mut *mut c_char) -> c_int {
+    let (file, value) = match (file.as_ref(), value.as_mut()) {
+        (Option::Some(file), Option::Some(value)) if !keyword.is_null() => (file, value),
+        _ => return FITS_INVALID_ARGUMENT,
+    };
+    let keyword = match CStr::from_ptr(keyword).to_str().ok().and_then(|name| Keyword::from_str(&name.to_uppercase()).ok()) {
+        Option::Some(keyword) => keyword,
+        Option::None => return FITS_INVALID_ARGUMENT,
+    };
+    let found = match file.fits.get(hdu).map(|hdu| hdu.header.value_opt(&keyword)) {
+        Option::Some(Option::Some(found)) => found,
+        _ => return FITS_NOT_PRESENT,
+    };
+    let text = match found {
+        Option::Some(Value::CharacterString(text)) => text.trim_end().to_string(),
+        Option::Some(other) => other.to_string().trim().to_string(),
+        Option::None => return FITS_UNDEFINED,
+    };
+    match CString::new(text) {
+        Ok(text) => {
+            *value = text.into_raw();
+            FITS_OK
+        }
+        Err(_) => FITS_INVALID_ARGUMENT,
+    }
+}
+
+/// The data array of HDU `hdu`, without padding, with its size in bytes
+/// stored in `size`. The bytes live as long as `file` is open. Returns null,
+/// with a size of 0, when there is no such HDU or it has no data array.
+///
+/// # Safety
+/// `file` is null or an open handle and `size` is null or points to writable
+/// storage for a `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn fits_data(file: *const FitsFile, hdu: usize, size: *mut usize) -> *const u8 {
+    let data = file.as_ref()
+        .and_then(|file| file.fits.get(hdu))
+        .map(|hdu| hdu.data())
+        .filter(|data| !data.is_empty());
+    if let Option::Some(size) = size.as_mut() {
+        *size = data.map(|data| data.len()).unwrap_or(0);
+    }
+    data.map(|data| data.as_ptr()).unwrap_or(ptr::null())
+}
+
+/// Frees a string returned by `fits_keyword_value`. Null is ignored.
+///
+/// # Safety
+/// `text` is null or a string returned by this interface that is not freed.
+#[no_mangle]
+pub unsafe extern "C" fn fits_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Closes `file`, freeing its headers and data arrays. Null is ignored.
+///
+/// # Safety
+/// `file` is null or an open handle, which is no longer used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn fits_close(file: *mut FitsFile) {
+    if !file.is_null() {
+        drop(Box::from_raw(file));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+    use super::*;
+
+    #[test]
+    fn c_interface_should_read_keywords_and_data() {
+        let bytes = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        unsafe {
+            let file = fits_open_memory(bytes.as_ptr(), bytes.len());
+            assert!(!file.is_null());
+            assert_eq!(fits_hdu_count(file), 3);
+
+            let mut value: *mut c_char = ptr::null_mut();
+            let keyword = CString::new("extname").unwrap();
+            assert_eq!(fits_keyword_value(file, 2, keyword.as_ptr(), &mut value), FITS_OK);
+            assert_eq!(CStr::from_ptr(value).to_str(), Ok("APERTURE"));
+            fits_free_string(value);
+            let keyword = CString::new("NAXIS1").unwrap();
+            assert_eq!(fits_keyword_value(file, 2, keyword.as_ptr(), &mut value), FITS_OK);
+            assert_eq!(CStr::from_ptr(value).to_str(), Ok("11"));
+            fits_free_string(value);
+            let keyword = CString::new("PMRA").unwrap();
+            assert_eq!(fits_keyword_value(file, 0, keyword.as_ptr(), &mut value), FITS_UNDEFINED);
+            assert_eq!(fits_keyword_value(file, 5, keyword.as_ptr(), &mut value), FITS_NOT_PRESENT);
+            assert_eq!(fits_keyword_value(file, 0, ptr::null(), &mut value), FITS_INVALID_ARGUMENT);
+
+            let mut size = 1usize;
+            let data = fits_data(file, 2, &mut size);
+            assert_eq!(size, 484);
+            assert_eq!(*data, bytes[bytes.len() - 2880]);
+            assert!(fits_data(file, 0, &mut size).is_null());
+            assert_eq!(size, 0);
+
+            fits_close(file);
+        }
+        unsafe {
+            assert!(fits_open_memory(bytes.as_ptr(), 100).is_null());
+            assert!(fits_open(ptr::null()).is_null());
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! The checksum module computes the `CHECKSUM` and `DATASUM` values of the
+//! checksum convention, part of the standard since version 4.0.
+//!
+//! Sums are 32 bit ones' complement sums of the bytes of an HDU, taken as big
+//! endian words. `DATASUM` holds the sum of the data array as an unsigned
+//! decimal string, `CHECKSUM` an ASCII encoding chosen so that the sum of the
+//! whole HDU is negative zero, i.e. all ones.
+//!
+//! `verify` and `verify_file` check the sums of HDUs as they appear in a file.
+//! `HDU::verify_checksum` and `Fits::verify_all` check parsed HDUs against the
+//! bytes they were parsed from.
+
+use std::str;
+use std::str::FromStr;
+use super::parser::layout;
+use super::types::{Fits, HDU};
+use super::writer::{check_header, fill, padding, HeaderWriteError};
+
+/// The value of `CHECKSUM` while the sum of a header is computed.
+pub const ZEROS: &str = "0000000000000000";
+
+const EXCLUDED: &[u8] = b":;<=>?@[\\]^_`";
+
+/// The 32 bit ones' complement sum of `bytes`. A trailing partial word is
+/// padded with zeros, as the padding of a data array would be.
+pub fn sum(bytes: &[u8]) -> u32 {
+    let mut total: u64 = 0;
+    for word in bytes.chunks(4) {
+        let mut padded = [0u8; 4];
+        padded[..word.len()].copy_from_slice(word);
+        total += u64::from(u32::from_be_bytes(padded));
+    }
+    fold(total)
+}
+
+/// The ones' complement sum of two sums, e.g. of a header and its data array.
+pub fn add(a: u32, b: u32) -> u32 {
+    fold(u64::from(a) + u64::from(b))
+}
+
+fn fold(mut total: u64) -> u32 {
+    while total >> 32 != 0 {
+        total = (total & 0xffff_ffff) + (total >> 32);
+    }
+    total as u32
+}
+
+/// The 16 characters of `CHECKSUM` for an HDU that sums to `sum` with the
+/// value of `CHECKSUM` set to `ZEROS`.
+pub fn encode(sum: u32) -> String {
+    let value = !sum;
+    let mut ascii = [0u8; 16];
+    for (i, byte) in value.to_be_bytes().iter().enumerate() {
+        let quotient = byte / 4 + b'0';
+        let mut characters = [quotient; 4];
+        characters[0] += byte % 4;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for j in [0, 2] {
+                if EXCLUDED.contains(&characters[j]) || EXCLUDED.contains(&characters[j + 1]) {
+                    characters[j] += 1;
+                    characters[j + 1] -= 1;
+                    changed = true;
+                }
+            }
+        }
+        for (j, &character) in characters.iter().enumerate() {
+            ascii[4 * j + i] = character;
+        }
+    }
+    (0..16).map(|i| ascii[(i + 15) % 16] as char).collect()
+}
+
+/// Fill in the `CHECKSUM` record of `header`, the bytes of a header as they
+/// appear in a file, for a data array that sums to `datasum`.
+///
+/// The `CHECKSUM` record has to be present with a value of 16 characters,
+/// e.g. `ZEROS`, starting in column 11 as in the fixed format.
+pub fn update(header: &mut [u8], datasum: u32) -> Result<(), ChecksumError> {
+    let start = header.chunks(80)
+        .position(|card| card.starts_with(b"CHECKSUM= '") && card.len() == 80 && card[27] == b'\'')
+        .map(|index| index * 80 + 11)
+        .ok_or(ChecksumError::NoChecksumRecord)?;
+    header[start..start + 16].copy_from_slice(ZEROS.as_bytes());
+    let checksum = encode(add(sum(header), datasum));
+    header[start..start + 16].copy_from_slice(checksum.as_bytes());
+    Ok(())
+}
+
+/// The state of a `CHECKSUM` or `DATASUM` record of an HDU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Status {
+    /// The record is present and matches the HDU.
+    Ok,
+    /// The record is present but does not match the HDU.
+    Failed,
+    /// The header has no such record.
+    Missing,
+}
+
+/// The result of verifying the checksums of an HDU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Verification {
+    /// Whether the whole HDU sums to negative zero, by `CHECKSUM`.
+    pub checksum: Status,
+    /// Whether `DATASUM` is the sum of the data array.
+    pub datasum: Status,
+}
+
+impl Verification {
+    /// Determines if neither `CHECKSUM` nor `DATASUM` failed. Missing records
+    /// pass.
+    pub fn is_ok(&self) -> bool {
+        self.checksum != Status::Failed && self.datasum != Status::Failed
+    }
+}
+
+/// Verify the `CHECKSUM` and `DATASUM` records of the HDU with `header` and
+/// `data`, the bytes of its header and data array as they appear in a file,
+/// padding included.
+pub fn verify(header: &[u8], data: &[u8]) -> Verification {
+    verify_sum(header, sum(data))
+}
+
+/// Verify like `verify`, for a data array that sums to `datasum`.
+fn verify_sum(header: &[u8], datasum: u32) -> Verification {
+    let card = |name: &[u8]| header.chunks(80)
+        .take_while(|card| !card.starts_with(b"END     "))
+        .find(|card| card.starts_with(name));
+    let checksum = match card(b"CHECKSUM= ") {
+        Option::Some(_) if add(sum(header), datasum) == 0xffff_ffff => Status::Ok,
+        Option::Some(_) => Status::Failed,
+        Option::None => Status::Missing,
+    };
+    let datasum = match card(b"DATASUM = ") {
+        Option::Some(card) => {
+            let value = str::from_utf8(&card[10..]).ok()
+                .and_then(|field| field.trim_start().strip_prefix('\''))
+                .and_then(|field| field.split('\'').next())
+                .and_then(|value| u32::from_str(value.trim()).ok());
+            if value == Option::Some(datasum) { Status::Ok } else { Status::Failed }
+        }
+        Option::None => Status::Missing,
+    };
+    Verification { checksum, datasum }
+}
+
+/// Verify the checksums of every HDU of `input`, a FITS file, in order. HDUs
+/// after the first one that could not be parsed are not verified.
+pub fn verify_file(input: &[u8]) -> Vec<Verification> {
+    layout(input)
+        .into_iter()
+        .map(|(header, data)| verify(&input[header], &input[data]))
+        .collect()
+}
+
+impl<'a> HDU<'a> {
+    /// Verify the `CHECKSUM` and `DATASUM` records of this HDU.
+    ///
+    /// A parsed HDU is verified against the bytes it was parsed from, as long
+    /// as its header and data array are unchanged. Other HDUs are summed as
+    /// their header and padded data array are laid out, with the checksums
+    /// they hold, which fails when the header can not be written.
+    pub fn verify_checksum(&self) -> Result<Verification, HeaderWriteError> {
+        if let Option::Some((header, padding)) = self.unchanged_source() {
+            return Ok(verify_sum(header, sum_of(&[self.data(), padding])))
+        }
+        check_header(&self.header)?;
+        let data = self.data();
+        let datasum = sum_of(&[data, &vec!(fill(&self.header); padding(data.len()))]);
+        Ok(verify_sum(self.header.to_fits_string().as_bytes(), datasum))
+    }
+}
+
+impl<'a> Fits<'a> {
+    /// Verify the checksums of every HDU of this file, in order, see
+    /// `HDU::verify_checksum`.
+    pub fn verify_all(&self) -> Vec<Result<Verification, HeaderWriteError>> {
+        self.iter().map(HDU::verify_checksum).collect()
+    }
+}
+
+/// The sum of `parts` one after the other, as if they were one slice.
+pub(crate) fn sum_of(parts: &[&[u8]]) -> u32 {
+    let mut total: u64 = 0;
+    let mut word = [0u8; 4];
+    let mut length = 0;
+    for &byte in parts.iter().flat_map(|part| part.iter()) {
+        word[length] = byte;
+        length += 1;
+        if length == 4 {
+            total += u64::from(u32::from_be_bytes(word));
+            length = 0;
+        }
+    }
+    if length > 0 {
+        total += u64::from(sum(&word[..length]));
+    }
+    fold(total)
+}
+
+/// Problems that could occur when updating checksums.
+#[derive(Debug, PartialEq)]
+pub enum ChecksumError {
+    /// The header has no `CHECKSUM` record with a 16 character value.
+    NoChecksumRecord,
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::parser::{fits, template};
+    use super::super::types::{HDU, Header, Keyword, KeywordRecord, Value};
+    use super::super::writer::{write_hdu, HeaderWriteError};
+    use super::{add, encode, sum, update, verify_file, ChecksumError, Status, Verification, ZEROS};
+
+    #[test]
+    fn sum_should_wrap_carries_around() {
+        assert_eq!(sum(&[0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x02]), 2);
+        assert_eq!(sum(&[0x01, 0x02]), 0x0102_0000);
+        assert_eq!(add(0xffff_fffe, 3), 2);
+    }
+
+    #[test]
+    fn encoded_checksum_should_complement_the_sum() {
+        let mut header = format!("{:80}{:80}", format!("CHECKSUM= '{}'", ZEROS), "END").into_bytes();
+        let data = b"some data".to_vec();
+
+        update(&mut header, sum(&data)).unwrap();
+
+        assert_eq!(add(sum(&header), sum(&data)), 0xffff_ffff);
+    }
+
+    #[test]
+    fn encoding_should_match_the_convention() {
+        assert_eq!(encode(868229149), "hcHjjc9ghcEghc9g");
+    }
+
+    #[test]
+    fn update_should_need_a_checksum_record() {
+        let mut header = format!("{:80}", "END").into_bytes();
+
+        assert_eq!(update(&mut header, 0), Err(ChecksumError::NoChecksumRecord));
+    }
+
+    #[test]
+    fn verify_should_check_both_sums() {
+        let header = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 1\nNAXIS1 = 4\nCHECKSUM = '0000000000000000'\nDATASUM = '0'").unwrap();
+        let data = [1u8, 2, 3, 4];
+        let mut hdu = HDU::with_data(header, &data);
+        let datasum = sum(&data);
+        hdu.header.set(Keyword::DATASUM, Value::CharacterString(datasum.to_string().into()), Option::None).unwrap();
+        let mut bytes = vec!();
+        write_hdu(&mut bytes, &hdu).unwrap();
+        update(&mut bytes[..2880], datasum).unwrap();
+        let ok = Verification { checksum: Status::Ok, datasum: Status::Ok };
+
+        let file = match fits(&bytes) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        assert_eq!(verify_file(&bytes), vec!(ok));
+        assert_eq!(file.verify_all(), vec!(Ok(ok)));
+        bytes[2880] = 9;
+        let corrupted = verify_file(&bytes);
+        assert_eq!(corrupted, vec!(Verification { checksum: Status::Failed, datasum: Status::Failed }));
+        assert!(!corrupted[0].is_ok());
+        assert_eq!(HDU::new(template("SIMPLE = T").unwrap()).verify_checksum(), Ok(Verification { checksum: Status::Missing, datasum: Status::Missing }));
+    }
+
+    #[test]
+    fn verify_all_should_check_the_bytes_of_files_written_by_other_software() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let file = match fits(data) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let ok = Verification { checksum: Status::Ok, datasum: Status::Ok };
+
+        assert_eq!(verify_file(data), vec!(ok; 3));
+        assert_eq!(file.verify_all(), vec!(Ok(ok), Ok(ok), Ok(ok)));
+        let mut edited = file.into_iter().nth(1).unwrap();
+        edited.header.set(Keyword::OBJECT, Value::from("TRAPPIST-1"), Option::None).unwrap();
+        assert_eq!(edited.verify_checksum().map(|verification| verification.checksum), Ok(Status::Failed));
+    }
+
+    #[test]
+    fn verify_checksum_should_fail_for_headers_that_can_not_be_written() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::OBSERVER, Value::from("Müller"), Option::None),
+        ));
+
+        assert_eq!(HDU::new(header).verify_checksum(), Err(HeaderWriteError::NonAscii(Keyword::OBSERVER)));
+    }
+}
@@ -0,0 +1,335 @@
+//! The diff module compares headers record by record, and files HDU by HDU.
+
+use std::collections::HashMap;
+use super::types::{Fits, Header, KeywordRecord, Keyword, Value};
+
+/// A difference between two headers.
+#[derive(Debug, PartialEq)]
+pub enum CardDiff<'a> {
+    /// A record that is only present in the second header.
+    Added(KeywordRecord<'a>),
+    /// A record that is only present in the first header.
+    Removed(KeywordRecord<'a>),
+    /// A record whose value or comment differs, as `(first, second)`.
+    Changed(KeywordRecord<'a>, KeywordRecord<'a>),
+}
+
+/// Reports the records that were added, removed or changed going from header
+/// `a` to header `b`.
+///
+/// Records are matched by keyword and occurrence, so the second `HISTORY` of `a`
+/// is compared with the second `HISTORY` of `b`. Formatting-only differences are
+/// ignored: trailing spaces in strings and comments, and integers that equal
+/// reals. Removed and changed records are reported in the order of `a`, followed
+/// by the added records in the order of `b`.
+pub fn diff<'a>(a: &Header<'a>, b: &Header<'a>) -> Vec<CardDiff<'a>> {
+    let first = occurrences(a);
+    let second = occurrences(b);
+    let first_index: HashMap<&(Keyword, usize), &KeywordRecord> = first.iter().map(|(key, record)| (key, *record)).collect();
+    let second_index: HashMap<&(Keyword, usize), &KeywordRecord> = second.iter().map(|(key, record)| (key, *record)).collect();
+    let mut differences = vec!();
+
+    for (key, record) in &first {
+        match second_index.get(key) {
+            Option::Some(other) => {
+                if !equivalent(record, other) {
+                    differences.push(CardDiff::Changed((*record).clone(), (*other).clone()));
+                }
+            }
+            Option::None => differences.push(CardDiff::Removed((*record).clone())),
+        }
+    }
+    for (key, record) in &second {
+        if !first_index.contains_key(key) {
+            differences.push(CardDiff::Added((*record).clone()));
+        }
+    }
+
+    differences
+}
+
+/// The options of `semantic_eq`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompareOptions {
+    /// Keywords whose records are not compared, by default the volatile
+    /// `DATE`, `CHECKSUM` and `DATASUM`.
+    pub ignored: Vec<Keyword>,
+    /// Whether the order of the records is ignored, `true` by default.
+    pub ignore_order: bool,
+    /// Whether comments are ignored, `false` by default.
+    pub ignore_comments: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> CompareOptions {
+        CompareOptions {
+            ignored: vec!(Keyword::DATE, Keyword::CHECKSUM, Keyword::DATASUM),
+            ignore_order: true,
+            ignore_comments: false,
+        }
+    }
+}
+
+impl CompareOptions {
+    /// The default options.
+    pub fn new() -> CompareOptions {
+        CompareOptions::default()
+    }
+
+    /// Do not compare the records of `keyword` either.
+    pub fn ignore(mut self, keyword: Keyword) -> CompareOptions {
+        self.ignored.push(keyword);
+        self
+    }
+
+    /// Report records in a different order when `ignore_order` is `false`.
+    pub fn with_ignore_order(mut self, ignore_order: bool) -> CompareOptions {
+        self.ignore_order = ignore_order;
+        self
+    }
+
+    /// Ignore comments when `ignore_comments` is `true`.
+    pub fn with_ignore_comments(mut self, ignore_comments: bool) -> CompareOptions {
+        self.ignore_comments = ignore_comments;
+        self
+    }
+}
+
+/// The differences between two files, see `semantic_eq`.
+#[derive(Debug, PartialEq)]
+pub struct FitsDiff<'a> {
+    /// The number of HDUs of both files, when they differ.
+    pub hdu_count: Option<(usize, usize)>,
+    /// The HDUs that differ, of the HDUs both files have.
+    pub hdus: Vec<HduDiff<'a>>,
+}
+
+impl<'a> FitsDiff<'a> {
+    /// Determines if the files are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.hdu_count.is_none() && self.hdus.is_empty()
+    }
+}
+
+/// The differences between two HDUs at the same index.
+#[derive(Debug, PartialEq)]
+pub struct HduDiff<'a> {
+    /// The index of the HDU, 0 for the primary HDU.
+    pub hdu: usize,
+    /// The records that differ, as reported by `diff`.
+    pub cards: Vec<CardDiff<'a>>,
+    /// Whether the records both headers have are in a different order. Only
+    /// reported when the order is not ignored.
+    pub reordered: bool,
+    /// How the data arrays differ, if they do.
+    pub data: Option<DataDiff>,
+}
+
+/// A difference between two data arrays.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DataDiff {
+    /// The data arrays have different lengths in bytes, as `(first, second)`.
+    Length(usize, usize),
+    /// The data arrays have the same length but `count` bytes differ, the
+    /// first at offset `first`.
+    Bytes {
+        /// The offset of the first byte that differs.
+        first: usize,
+        /// The number of bytes that differ.
+        count: usize,
+    },
+}
+
+/// Compares files `a` and `b` HDU by HDU, ignoring how the cards are formatted
+/// and, following `options`, the order of the records, comments and volatile
+/// keywords. The data arrays are compared byte by byte.
+///
+/// The files are equivalent when the returned report `is_empty`.
+pub fn semantic_eq<'a>(a: &Fits<'a>, b: &Fits<'a>, options: &CompareOptions) -> FitsDiff<'a> {
+    let hdus = a.iter().zip(b.iter()).enumerate()
+        .map(|(index, (first, second))| {
+            let cards: Vec<CardDiff<'a>> = diff(&first.header, &second.header)
+                .into_iter()
+                .filter(|difference| !ignored(difference, options))
+                .collect();
+            let reordered = !options.ignore_order && reordered(&first.header, &second.header, options);
+            HduDiff { hdu: index, cards, reordered, data: data_diff(first.data(), second.data()) }
+        })
+        .filter(|difference| !difference.cards.is_empty() || difference.reordered || difference.data.is_some())
+        .collect();
+    let hdu_count = if a.len() == b.len() { Option::None } else { Option::Some((a.len(), b.len())) };
+    FitsDiff { hdu_count, hdus }
+}
+
+/// Whether `difference` is about an ignored keyword, or only about comments
+/// when they are ignored.
+fn ignored(difference: &CardDiff, options: &CompareOptions) -> bool {
+    match *difference {
+        CardDiff::Added(ref record) | CardDiff::Removed(ref record) => options.ignored.contains(record.keyword()),
+        CardDiff::Changed(ref first, ref second) => {
+            options.ignored.contains(first.keyword()) ||
+                (options.ignore_comments && equivalent_values(first.value(), second.value()))
+        }
+    }
+}
+
+/// Whether the records that `a` and `b` both have, and that are compared, are
+/// in a different order.
+fn reordered(a: &Header, b: &Header, options: &CompareOptions) -> bool {
+    let first = order(a, options);
+    let second = order(b, options);
+    let common = |key: &&(Keyword, usize), other: &[(Keyword, usize)]| other.contains(key);
+    !first.iter().filter(|key| common(key, &second)).eq(second.iter().filter(|key| common(key, &first)))
+}
+
+/// The keywords and occurrences of the records of `header` that are compared,
+/// in header order.
+fn order(header: &Header, options: &CompareOptions) -> Vec<(Keyword, usize)> {
+    occurrences(header).into_iter()
+        .map(|(key, _)| key)
+        .filter(|(keyword, _)| !options.ignored.contains(keyword))
+        .collect()
+}
+
+fn data_diff(a: &[u8], b: &[u8]) -> Option<DataDiff> {
+    if a.len() != b.len() {
+        return Option::Some(DataDiff::Length(a.len(), b.len()))
+    }
+    let mut differing = a.iter().zip(b.iter()).enumerate().filter(|(_, (x, y))| x != y).map(|(offset, _)| offset);
+    differing.next().map(|first| DataDiff::Bytes { first, count: 1 + differing.count() })
+}
+
+/// Pairs each record with its keyword and how often that keyword occurred before.
+fn occurrences<'h, 'a>(header: &'h Header<'a>) -> Vec<((Keyword, usize), &'h KeywordRecord<'a>)> {
+    let mut seen: HashMap<&Keyword, usize> = HashMap::new();
+    header.records()
+        .iter()
+        .filter(|record| *record.keyword() != Keyword::END)
+        .map(|record| {
+            let count = seen.entry(record.keyword()).or_insert(0);
+            *count += 1;
+            ((record.keyword().clone(), *count), record)
+        })
+        .collect()
+}
+
+fn equivalent(a: &KeywordRecord, b: &KeywordRecord) -> bool {
+    equivalent_values(a.value(), b.value()) &&
+        a.comment().map(str::trim_end) == b.comment().map(str::trim_end)
+}
+
+fn equivalent_values(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::CharacterString(left), Value::CharacterString(right)) => left.trim_end() == right.trim_end(),
+        (&Value::Integer(left), &Value::Real(right)) => left as f64 == right,
+        (&Value::Real(left), &Value::Integer(right)) => left == right as f64,
+        (left, right) => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::parser::fits;
+    use super::super::testkit::FitsSpec;
+    use super::super::types::{Fits, HDU, Header, KeywordRecord, Keyword, Value};
+    use super::{diff, semantic_eq, CardDiff, CompareOptions, DataDiff, FitsDiff, HduDiff};
+
+    #[test]
+    fn diff_should_be_empty_for_formatting_only_differences() {
+        let a = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC    ".into()), Option::Some("target ".into())),
+            KeywordRecord::new(Keyword::EQUINOX, Value::Integer(2000i64), Option::None),
+        ));
+        let b = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::Some("target".into())),
+            KeywordRecord::new(Keyword::EQUINOX, Value::Real(2000.0f64), Option::None),
+            KeywordRecord::new(Keyword::END, Value::Undefined, Option::None),
+        ));
+
+        assert_eq!(diff(&a, &b), vec!());
+    }
+
+    #[test]
+    fn diff_should_report_added_removed_and_changed_records() {
+        let a = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::None),
+            KeywordRecord::new(Keyword::EXPTIME, Value::Real(1.0f64), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+        ));
+        let b = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("TRAPPIST-1".into()), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("calibrated".into())),
+        ));
+
+        assert_eq!(diff(&a, &b), vec!(
+            CardDiff::Changed(
+                KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::None),
+                KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("TRAPPIST-1".into()), Option::None)),
+            CardDiff::Removed(KeywordRecord::new(Keyword::EXPTIME, Value::Real(1.0f64), Option::None)),
+            CardDiff::Added(KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("calibrated".into()))),
+        ));
+    }
+
+    /// An HDU with a data array of four bytes and `records` after its shape.
+    fn hdu<'a>(records: Vec<KeywordRecord<'a>>, data: &'a [u8]) -> Fits<'a> {
+        let mut all = vec!(
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1), Value::Integer(4i64), Option::None),
+        );
+        all.extend(records);
+        Fits::new(HDU::with_data(Header::new(all), data), vec!())
+    }
+
+    #[test]
+    fn semantic_eq_should_ignore_volatile_keywords_and_order() {
+        let data = [1u8, 2, 3, 4];
+        let other = [1u8, 9, 3, 9];
+        let a = hdu(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::Some("target".into())),
+            KeywordRecord::new(Keyword::EXPTIME, Value::Real(1.0f64), Option::None),
+            KeywordRecord::new(Keyword::DATE, Value::CharacterString("2024-01-01".into()), Option::None),
+            KeywordRecord::new(Keyword::CHECKSUM, Value::CharacterString("AAAA".into()), Option::None),
+        ), &data);
+        let b = hdu(vec!(
+            KeywordRecord::new(Keyword::EXPTIME, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::Some("the target".into())),
+            KeywordRecord::new(Keyword::DATE, Value::CharacterString("2025-06-30".into()), Option::None),
+        ), &data);
+        let c = hdu(vec!(), &other);
+
+        assert!(semantic_eq(&a, &b, &CompareOptions::new().with_ignore_comments(true)).is_empty());
+        assert_eq!(semantic_eq(&a, &b, &CompareOptions::new().with_ignore_order(false)), FitsDiff {
+            hdu_count: Option::None,
+            hdus: vec!(HduDiff {
+                hdu: 0,
+                cards: vec!(CardDiff::Changed(
+                    KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::Some("target".into())),
+                    KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::Some("the target".into())))),
+                reordered: true,
+                data: Option::None,
+            }),
+        });
+        assert_eq!(semantic_eq(&c, &c, &CompareOptions::new()), FitsDiff { hdu_count: Option::None, hdus: vec!() });
+        assert_eq!(semantic_eq(&b, &c, &CompareOptions::new().ignore(Keyword::EXPTIME).ignore(Keyword::OBJECT)).hdus[0].data,
+                   Option::Some(DataDiff::Bytes { first: 1, count: 2 }));
+    }
+
+    #[test]
+    fn semantic_eq_should_report_different_numbers_of_hdus() {
+        let one = FitsSpec::new().image(8, &[3]).generate().unwrap();
+        let two = FitsSpec::new().image(8, &[3]).table(&["1J"], 1).generate().unwrap();
+        let (a, b) = match (fits(&one), fits(&two)) {
+            (IResult::Done(_, a), IResult::Done(_, b)) => (a, b),
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let report = semantic_eq(&a, &b, &CompareOptions::new());
+
+        assert_eq!(report.hdu_count, Option::Some((1, 2)));
+        assert_eq!(report.hdus.len(), 1);
+        assert_eq!(report.hdus[0].cards, vec!(CardDiff::Added(KeywordRecord::new(Keyword::EXTEND, Value::Logical(true), Option::None))));
+    }
+}
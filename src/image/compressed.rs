@@ -0,0 +1,155 @@
+//! The metadata of tile compressed images, which are stored in a `BINTABLE`
+//! with `ZIMAGE = T` and describe the uncompressed image with `Z` keywords.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+use super::super::types::{HDU, Header, Keyword, Value, ValueRetrievalError};
+
+/// The geometry and compression settings of a tile compressed image.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ZImageInfo {
+    /// The compression algorithm, `ZCMPTYPE` without trailing spaces, e.g.
+    /// `RICE_1`.
+    pub compression: String,
+    /// The `BITPIX` of the uncompressed image, `ZBITPIX`.
+    pub bitpix: i64,
+    /// The lengths of the axes of the uncompressed image, `ZNAXIS1` first.
+    pub axes: Vec<u64>,
+    /// The lengths of the axes of the tiles, `ZTILE1` first. Without `ZTILEn`
+    /// each row of the image is a tile.
+    pub tiles: Vec<u64>,
+    /// The parameters of the algorithm, `ZNAMEi` with its `ZVALi`.
+    pub parameters: Vec<(String, Value<'static>)>,
+    /// The method used to quantize floating point pixels, `ZQUANTIZ` without
+    /// trailing spaces.
+    pub quantize: Option<String>,
+}
+
+impl ZImageInfo {
+    /// Read the compressed image metadata from `header`.
+    pub fn new(header: &Header) -> Result<ZImageInfo, ZImageError> {
+        match header.logical_value_of(&z("ZIMAGE")) {
+            Ok(true) => (),
+            _ => return Err(ZImageError::NotCompressed),
+        }
+        let required = |keyword: Keyword| header.integer_value_of(&keyword)
+            .map_err(|error| ZImageError::MissingKeyword(keyword, error));
+        let length = |keyword: Keyword, value: i64| u64::try_from(value)
+            .map_err(|_| ZImageError::OutOfRange(keyword, value));
+        let compression = header.character_string_value_of(&z("ZCMPTYPE"))
+            .map_err(|error| ZImageError::MissingKeyword(z("ZCMPTYPE"), error))?
+            .trim_end()
+            .to_string();
+        let bitpix = required(z("ZBITPIX"))?;
+        let naxis = required(z("ZNAXIS"))?;
+        let naxis = u16::try_from(naxis).map_err(|_| ZImageError::OutOfRange(z("ZNAXIS"), naxis))?;
+        let mut axes = vec!();
+        let mut tiles = vec!();
+        for n in 1..=naxis {
+            let keyword = z(&format!("ZNAXIS{}", n));
+            let axis = length(keyword.clone(), required(keyword)?)?;
+            let keyword = z(&format!("ZTILE{}", n));
+            let tile = match header.integer_value_of(&keyword) {
+                Ok(tile) => length(keyword, tile)?,
+                Err(ValueRetrievalError::KeywordNotPresent) => if n == 1 { axis } else { 1 },
+                Err(error) => return Err(ZImageError::MissingKeyword(keyword, error)),
+            };
+            axes.push(axis);
+            tiles.push(tile);
+        }
+        let mut parameters = vec!();
+        for i in 1.. {
+            let name = match header.character_string_value_of(&z(&format!("ZNAME{}", i))) {
+                Ok(name) => name.trim_end().to_string(),
+                Err(_) => break,
+            };
+            let keyword = z(&format!("ZVAL{}", i));
+            let value = header.value_of(&keyword)
+                .map_err(|error| ZImageError::MissingKeyword(keyword, error))?;
+            parameters.push((name, value.into_owned()));
+        }
+        let quantize = header.character_string_value_of(&z("ZQUANTIZ"))
+            .map(|quantize| quantize.trim_end().to_string())
+            .ok();
+        Ok(ZImageInfo { compression, bitpix, axes, tiles, parameters, quantize })
+    }
+
+    /// The value of the parameter of the algorithm called `name`, e.g. `BLOCKSIZE`.
+    pub fn parameter(&self, name: &str) -> Option<&Value<'static>> {
+        self.parameters.iter()
+            .find(|&(parameter, _)| parameter.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// The number of tiles, rounding partial tiles at the edges up.
+    pub fn tile_count(&self) -> u64 {
+        self.axes.iter().zip(self.tiles.iter())
+            .map(|(&axis, &tile)| if tile == 0 { 0 } else { axis.div_ceil(tile) })
+            .product()
+    }
+}
+
+impl<'a> HDU<'a> {
+    /// The compressed image metadata of this HDU, when it holds a tile
+    /// compressed image.
+    pub fn compressed_image_info(&self) -> Result<ZImageInfo, ZImageError> {
+        ZImageInfo::new(&self.header)
+    }
+}
+
+/// Problems that could occur when reading compressed image metadata.
+#[derive(Debug, PartialEq)]
+pub enum ZImageError {
+    /// The header does not have `ZIMAGE = T`.
+    NotCompressed,
+    /// A keyword the metadata needs could not be retrieved.
+    MissingKeyword(Keyword, ValueRetrievalError),
+    /// A length or count is negative or too large.
+    OutOfRange(Keyword, i64),
+}
+
+/// The keyword called `name`, which this crate does not recognize by itself.
+fn z(name: &str) -> Keyword {
+    Keyword::from_str(name).expect("compressed image keywords are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::super::types::{HDU, Value, ValueRetrievalError};
+    use super::{z, ZImageError, ZImageInfo};
+
+    #[test]
+    fn compressed_image_info_should_report_the_uncompressed_geometry() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 8\nNAXIS2 = 100\nZIMAGE = T\nZCMPTYPE = 'RICE_1  '\nZBITPIX = -32\nZNAXIS = 2\nZNAXIS1 = 1000\nZNAXIS2 = 998\nZTILE1 = 1000\nZTILE2 = 10\nZNAME1 = 'BLOCKSIZE'\nZVAL1 = 32\nZNAME2 = 'BYTEPIX'\nZVAL2 = 4\nZQUANTIZ = 'SUBTRACTIVE_DITHER_1'").unwrap();
+
+        let info = HDU::new(header).compressed_image_info().unwrap();
+
+        assert_eq!(info.compression, "RICE_1");
+        assert_eq!(info.bitpix, -32);
+        assert_eq!(info.axes, vec!(1000, 998));
+        assert_eq!(info.tiles, vec!(1000, 10));
+        assert_eq!(info.tile_count(), 100);
+        assert_eq!(info.parameter("blocksize"), Option::Some(&Value::Integer(32)));
+        assert_eq!(info.parameters.len(), 2);
+        assert_eq!(info.quantize, Option::Some("SUBTRACTIVE_DITHER_1".to_string()));
+    }
+
+    #[test]
+    fn compressed_image_info_should_default_to_tiles_of_rows() {
+        let info = ZImageInfo::new(&template("ZIMAGE = T\nZCMPTYPE = 'GZIP_1'\nZBITPIX = 16\nZNAXIS = 3\nZNAXIS1 = 5\nZNAXIS2 = 4\nZNAXIS3 = 2").unwrap()).unwrap();
+
+        assert_eq!(info.tiles, vec!(5, 1, 1));
+        assert_eq!(info.tile_count(), 8);
+        assert_eq!(info.quantize, Option::None);
+    }
+
+    #[test]
+    fn compressed_image_info_should_reject_other_headers() {
+        assert_eq!(ZImageInfo::new(&template("XTENSION = 'BINTABLE'").unwrap()), Err(ZImageError::NotCompressed));
+        assert_eq!(ZImageInfo::new(&template("ZIMAGE = T\nZCMPTYPE = 'RICE_1'\nZBITPIX = 16").unwrap()),
+                   Err(ZImageError::MissingKeyword(z("ZNAXIS"), ValueRetrievalError::KeywordNotPresent)));
+        assert_eq!(ZImageInfo::new(&template("ZIMAGE = T\nZCMPTYPE = 'RICE_1'\nZBITPIX = 16\nZNAXIS = 1\nZNAXIS1 = -4").unwrap()),
+                   Err(ZImageError::OutOfRange(z("ZNAXIS1"), -4)));
+    }
+}
@@ -0,0 +1,213 @@
+//! Cutouts of rectangular parts of images, by pixel or by sky coordinates.
+
+use std::convert::TryFrom;
+use std::iter;
+use std::ops::Range;
+use super::super::types::{Header, HDU, Keyword, Value, ValueRetrievalError};
+use super::super::wcs::WcsError;
+use super::ImageError;
+
+/// A rectangular part of an image, with a header that describes it.
+#[derive(Debug, PartialEq)]
+pub struct Cutout<'a> {
+    /// The header of the image with the axes of the cutout and its reference
+    /// pixels moved along, so that the world coordinates of the pixels stay
+    /// the same.
+    pub header: Header<'a>,
+    /// The raw pixels of the cutout, in the encoding of the image.
+    pub data: Vec<u8>,
+}
+
+impl<'a> Cutout<'a> {
+    /// The cutout as an HDU, ready to be written. Its data array counts as
+    /// edited, so the writer recomputes `CHECKSUM` and `DATASUM`.
+    pub fn hdu<'b>(&'b self) -> HDU<'b> {
+        let mut hdu = HDU::with_data(Header::new(self.header.records().to_vec()), &self.data);
+        hdu.mark_data_edited();
+        hdu
+    }
+}
+
+impl<'a> HDU<'a> {
+    /// The part of this image in the zero based `columns` and `rows`. Every
+    /// plane of further axes is cut out alike.
+    ///
+    /// `NAXIS1`, `NAXIS2` and the `CRPIX1a` and `CRPIX2a` of every world
+    /// coordinate system are updated. `CHECKSUM` and `DATASUM` are kept, to be
+    /// recomputed when the HDU of the cutout is written.
+    pub fn cutout(&self, columns: Range<usize>, rows: Range<usize>) -> Result<Cutout<'a>, CutoutError> {
+        let header = &self.header;
+        match header.character_string_value_of(&Keyword::XTENSION) {
+            Ok(xtension) if xtension.trim_end() != "IMAGE" => return Err(CutoutError::Image(ImageError::NotAnImage)),
+            _ => (),
+        }
+        let bitpix = header.integer_value_of(&Keyword::BITPIX)
+            .map_err(|error| CutoutError::Image(ImageError::MissingKeyword(Keyword::BITPIX, error)))?;
+        let size = match bitpix {
+            8 | 16 | 32 | 64 | -32 | -64 => (bitpix.abs() / 8) as usize,
+            _ => return Err(CutoutError::Image(ImageError::InvalidBitpix(bitpix))),
+        };
+        let axes: Vec<usize> = header.naxes()
+            .map_err(|error| CutoutError::Image(ImageError::Axes(error)))?
+            .into_iter()
+            .map(|length| usize::try_from(length).map_err(|_| CutoutError::Image(ImageError::DataTooShort)))
+            .collect::<Result<_, _>>()?;
+        if axes.len() < 2 {
+            return Err(CutoutError::OutsideImage)
+        }
+        let (width, height) = (axes[0], axes[1]);
+        if columns.start >= columns.end || rows.start >= rows.end || columns.end > width || rows.end > height {
+            return Err(CutoutError::OutsideImage)
+        }
+        let planes: usize = axes[2..].iter().product();
+        let length = axes.iter().try_fold(size, |product, &axis| product.checked_mul(axis));
+        let data = self.data();
+        match length {
+            Option::Some(length) if length <= data.len() => (),
+            _ => return Err(CutoutError::Image(ImageError::DataTooShort)),
+        }
+
+        let mut pixels = Vec::with_capacity(columns.len() * rows.len() * planes * size);
+        for plane in 0..planes {
+            for row in rows.clone() {
+                let start = ((plane * height + row) * width + columns.start) * size;
+                pixels.extend_from_slice(&data[start..start + columns.len() * size]);
+            }
+        }
+
+        let mut header = Header::new(header.records().to_vec());
+        header.set(Keyword::NAXISn(1), Value::Integer(columns.len() as i64), Option::None)
+            .and_then(|_| header.set(Keyword::NAXISn(2), Value::Integer(rows.len() as i64), Option::None))
+            .expect("NAXISn can be set");
+        let alternates: Vec<Option<char>> = iter::once(Option::None)
+            .chain(header.wcs_alternates().into_iter().map(Option::Some))
+            .collect();
+        for alternate in alternates {
+            for &(axis, offset) in [(1u16, columns.start), (2u16, rows.start)].iter() {
+                let crpix = Keyword::CRPIXja(axis, alternate);
+                let reference = match header.real_value_of(&crpix) {
+                    Ok(reference) => reference,
+                    Err(ValueRetrievalError::KeywordNotPresent) if header.values_of(&Keyword::CTYPEia(axis, alternate)).next().is_some() => 0f64,
+                    _ => continue,
+                };
+                header.set(crpix, Value::Real(reference - offset as f64), Option::None).expect("CRPIXja can be set");
+            }
+        }
+        Ok(Cutout { header, data: pixels })
+    }
+
+    /// The smallest cutout of this image that holds the circle of `radius`
+    /// degrees around `(ra, dec)`, in degrees, found with the primary world
+    /// coordinate system. The circle is clipped to the image.
+    pub fn cutout_sky(&self, ra: f64, dec: f64, radius: f64) -> Result<Cutout<'a>, CutoutError> {
+        let wcs = self.header.wcs().map_err(CutoutError::Wcs)?;
+        let axes = self.header.naxes().map_err(|error| CutoutError::Image(ImageError::Axes(error)))?;
+        if axes.len() < 2 {
+            return Err(CutoutError::OutsideImage)
+        }
+
+        let (sin_dec, cos_dec) = dec.to_radians().sin_cos();
+        let (sin_radius, cos_radius) = radius.to_radians().sin_cos();
+        let mut points = vec!((ra, dec));
+        for step in 0..32 {
+            let (sin_bearing, cos_bearing) = (step as f64 * 360f64 / 32f64).to_radians().sin_cos();
+            let latitude = (sin_dec * cos_radius + cos_dec * sin_radius * cos_bearing).clamp(-1f64, 1f64).asin();
+            let longitude = ra.to_radians() + (sin_bearing * sin_radius * cos_dec).atan2(cos_radius - sin_dec * latitude.sin());
+            points.push((longitude.to_degrees(), latitude.to_degrees()));
+        }
+        let mut pixels = vec!();
+        for (longitude, latitude) in points {
+            pixels.push(wcs.sky_to_pixel(longitude, latitude).map_err(CutoutError::Wcs)?);
+        }
+
+        let range = |low: f64, high: f64, length: u64| {
+            let start = (low.round() - 1f64).max(0f64);
+            let end = high.round().min(length as f64);
+            if start < end { start as usize..end as usize } else { 0..0 }
+        };
+        let (low_x, high_x) = pixels.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &(x, _)| (low.min(x), high.max(x)));
+        let (low_y, high_y) = pixels.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &(_, y)| (low.min(y), high.max(y)));
+        self.cutout(range(low_x, high_x, axes[0]), range(low_y, high_y, axes[1]))
+    }
+}
+
+/// Problems that could occur when cutting out part of an image.
+#[derive(Debug, PartialEq)]
+pub enum CutoutError {
+    /// The HDU is not an image that can be cut.
+    Image(ImageError),
+    /// The cutout lies outside of the image, or is empty.
+    OutsideImage,
+    /// The world coordinate system could not locate the cutout.
+    Wcs(WcsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::checksum::{verify, Status};
+    use super::super::super::parser::template;
+    use super::super::super::types::{HDU, Keyword};
+    use super::super::super::writer::write_hdu;
+    use super::CutoutError;
+
+    const IMAGE: &str = "SIMPLE = T
+BITPIX = 8
+NAXIS = 2
+NAXIS1 = 100
+NAXIS2 = 80
+CTYPE1 = 'RA---TAN'
+CTYPE2 = 'DEC--TAN'
+CRPIX1 = 50.5
+CRPIX2 = 40.5
+CRVAL1 = 150.0
+CRVAL2 = 20.0
+CDELT1 = -2.777777777777778E-4
+CDELT2 = 2.777777777777778E-4
+CTYPE1A = 'X'
+CTYPE2A = 'Y'
+CHECKSUM = '0000000000000000'";
+
+    fn pixels() -> Vec<u8> {
+        (0..80).flat_map(|row| (0..100).map(move |column| ((row + column) % 256) as u8)).collect()
+    }
+
+    #[test]
+    fn cutout_should_copy_the_pixels_and_move_the_reference_pixel() {
+        let header = template(IMAGE).unwrap();
+        let data = pixels();
+        let hdu = HDU::with_data(header, &data);
+
+        let cutout = hdu.cutout(10..20, 30..35).unwrap();
+
+        assert_eq!(cutout.data.len(), 50);
+        assert_eq!(cutout.data[0], 40);
+        assert_eq!(cutout.data[11], 42);
+        assert_eq!(cutout.header.integer_value_of(&Keyword::NAXISn(1)), Ok(10));
+        assert_eq!(cutout.header.real_value_of(&Keyword::CRPIXja(1, Option::None)), Ok(40.5f64));
+        assert_eq!(cutout.header.real_value_of(&Keyword::CRPIXja(2, Option::Some('A'))), Ok(-30f64));
+        let mut written = vec!();
+        write_hdu(&mut written, &cutout.hdu()).unwrap();
+        let (header, data) = written.split_at(2880);
+        assert_eq!(verify(header, data).checksum, Status::Ok);
+        assert_eq!(hdu.cutout(90..110, 0..5), Err(CutoutError::OutsideImage));
+    }
+
+    #[test]
+    fn cutout_sky_should_keep_the_sky_at_its_pixels() {
+        let header = template(IMAGE).unwrap();
+        let data = pixels();
+        let hdu = HDU::with_data(header, &data);
+        let wcs = hdu.header.wcs().unwrap();
+        let (ra, dec) = wcs.pixel_to_sky(30f64, 60f64).unwrap();
+
+        let cutout = hdu.cutout_sky(ra, dec, 5f64 / 3600f64).unwrap();
+
+        assert_eq!(cutout.header.integer_value_of(&Keyword::NAXISn(1)), Ok(11));
+        assert_eq!(cutout.header.integer_value_of(&Keyword::NAXISn(2)), Ok(11));
+        let (x, y) = cutout.header.wcs().unwrap().sky_to_pixel(ra, dec).unwrap();
+        assert!((x - 6f64).abs() < 1e-6 && (y - 6f64).abs() < 1e-6);
+        assert_eq!(cutout.data[5 * 11 + 5], data[59 * 100 + 29]);
+        let (ra, dec) = wcs.pixel_to_sky(-500f64, 40f64).unwrap();
+        assert_eq!(hdu.cutout_sky(ra, dec, 5f64 / 3600f64).map(|_| ()), Err(CutoutError::OutsideImage));
+    }
+}
@@ -0,0 +1,135 @@
+//! Display limits of images, the range of physical values that a stretch
+//! maps to gray levels.
+
+use std::cmp::Ordering;
+use super::Image;
+
+/// How the limits of the values to display are chosen.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Interval {
+    /// The minimum and maximum of the defined values.
+    MinMax,
+    /// The lower and upper percentile of the defined values, e.g. 0.5 and
+    /// 99.5, clipping outliers like hot pixels and cosmic rays.
+    Percentile(f64, f64),
+    /// IRAF's zscale, which DS9 offers to show the faint detail around the sky
+    /// level.
+    ZScale,
+}
+
+impl Interval {
+    /// The limits of the defined values in `values`, or `None` when there are
+    /// none.
+    pub fn limits(&self, values: &[f64]) -> Option<(f64, f64)> {
+        let mut finite: Vec<f64> = values.iter().cloned().filter(|value| value.is_finite()).collect();
+        if finite.is_empty() {
+            return Option::None
+        }
+        match *self {
+            Interval::MinMax => Option::Some(finite.iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &value| (lo.min(value), hi.max(value)))),
+            Interval::Percentile(lower, upper) => {
+                finite.sort_by(compare);
+                Option::Some((percentile(&finite, lower), percentile(&finite, upper)))
+            }
+            Interval::ZScale => zscale(finite),
+        }
+    }
+}
+
+impl Image {
+    /// The limits of the first plane of this image.
+    pub fn limits(&self, interval: Interval) -> Option<(f64, f64)> {
+        let size = (self.width() * self.height()).min(self.pixels.len());
+        interval.limits(&self.pixels[..size])
+    }
+}
+
+fn compare(a: &f64, b: &f64) -> Ordering {
+    a.partial_cmp(b).expect("finite values are ordered")
+}
+
+/// The `p`th percentile of `sorted`, interpolating between neighbours.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100f64).clamp(0f64, 1f64) * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+/// The display limits of IRAF's zscale: a line is fitted to a sorted sample of
+/// the pixels, rejecting outliers, and its slope reduced by the contrast sets the
+/// range around the median.
+fn zscale(finite: Vec<f64>) -> Option<(f64, f64)> {
+    const SAMPLES: usize = 1000;
+    const CONTRAST: f64 = 0.25;
+    const REJECTION: f64 = 2.5;
+    const ITERATIONS: usize = 5;
+
+    let stride = (finite.len() / SAMPLES).max(1);
+    let mut sample: Vec<f64> = finite.into_iter().step_by(stride).collect();
+    sample.sort_by(compare);
+    let n = sample.len();
+    let (minimum, maximum) = (sample[0], sample[n - 1]);
+    let median = if n.is_multiple_of(2) { (sample[n / 2 - 1] + sample[n / 2]) / 2f64 } else { sample[n / 2] };
+
+    let mut used = vec!(true; n);
+    let mut slope = 0f64;
+    for _ in 0..ITERATIONS {
+        let points: Vec<(f64, f64)> = (0..n).filter(|&i| used[i]).map(|i| (i as f64, sample[i])).collect();
+        let count = points.len() as f64;
+        let mean_x = points.iter().map(|p| p.0).sum::<f64>() / count;
+        let mean_y = points.iter().map(|p| p.1).sum::<f64>() / count;
+        let sxx: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.0 - mean_x)).sum();
+        let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+        slope = if sxx > 0f64 { sxy / sxx } else { 0f64 };
+        let intercept = mean_y - slope * mean_x;
+        let residual = |i: usize| sample[i] - (intercept + slope * i as f64);
+        let sigma = (points.iter().map(|p| residual(p.0 as usize).powi(2)).sum::<f64>() / count).sqrt();
+        let mut rejected = 0;
+        for (i, use_point) in used.iter_mut().enumerate() {
+            if *use_point && residual(i).abs() > REJECTION * sigma {
+                *use_point = false;
+                rejected += 1;
+            }
+        }
+        if rejected == 0 || used.iter().filter(|&&u| u).count() < n / 2 {
+            break
+        }
+    }
+    if used.iter().filter(|&&u| u).count() < n / 2 {
+        return Option::Some((minimum, maximum))
+    }
+    let slope = slope / CONTRAST;
+    let center = (n / 2) as f64;
+    Option::Some(((median - center * slope).max(minimum), (median + (n as f64 - 1f64 - center) * slope).min(maximum)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Image;
+    use super::Interval;
+
+    #[test]
+    fn percentile_should_interpolate_and_ignore_undefined_values() {
+        let values: Vec<f64> = (0..=100).map(|i| i as f64).chain(vec!(f64::NAN)).collect();
+
+        assert_eq!(Interval::MinMax.limits(&values), Option::Some((0f64, 100f64)));
+        assert_eq!(Interval::Percentile(0.5, 99.5).limits(&values), Option::Some((0.5, 99.5)));
+        assert_eq!(Interval::Percentile(25f64, 75f64).limits(&[4f64, 1f64, 3f64, 2f64, 5f64]), Option::Some((2f64, 4f64)));
+        assert_eq!(Interval::ZScale.limits(&[f64::NAN]), Option::None);
+    }
+
+    #[test]
+    fn zscale_should_ignore_outliers() {
+        let mut pixels: Vec<f64> = (0..400).map(|i| 100f64 + (i % 20) as f64 * 0.1).collect();
+        pixels[7] = 60000f64;
+        pixels[300] = -5000f64;
+        let image = Image { axes: vec!(20, 20), pixels };
+
+        let (low, high) = image.limits(Interval::ZScale).unwrap();
+
+        assert!(low > 90f64 && high < 110f64);
+        assert!(low < high);
+        assert_eq!(image.limits(Interval::MinMax), Option::Some((-5000f64, 60000f64)));
+    }
+}
@@ -0,0 +1,160 @@
+//! The image module decodes the data arrays of primary HDUs and `IMAGE`
+//! extensions, and reads the metadata of tile compressed images.
+
+use std::convert::TryFrom;
+use super::types::{Header, Keyword, NaxesError, ValueRetrievalError};
+
+mod compressed;
+mod cutout;
+mod interval;
+mod rebin;
+#[cfg(feature = "image")]
+mod preview;
+
+pub use self::compressed::{ZImageError, ZImageInfo};
+pub use self::cutout::{Cutout, CutoutError};
+pub use self::interval::Interval;
+pub use self::rebin::{Rebin, Rebinned};
+
+#[cfg(feature = "image")]
+pub use self::preview::{PreviewError, Stretch};
+
+/// The physical values of an image, with `NAXIS1` varying fastest.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Image {
+    /// The lengths of the axes, `NAXIS1` first.
+    pub axes: Vec<usize>,
+    /// The physical values of the pixels, `BSCALE * raw + BZERO`. Pixels equal to
+    /// `BLANK` are `NaN`.
+    pub pixels: Vec<f64>,
+}
+
+impl Image {
+    /// Decode the image described by `header` from `data`, its data array.
+    pub fn new(header: &Header, data: &[u8]) -> Result<Image, ImageError> {
+        match header.character_string_value_of(&Keyword::XTENSION) {
+            Ok(xtension) if xtension.trim_end() != "IMAGE" => return Err(ImageError::NotAnImage),
+            _ => (),
+        }
+        let bitpix = header.integer_value_of(&Keyword::BITPIX)
+            .map_err(|error| ImageError::MissingKeyword(Keyword::BITPIX, error))?;
+        let axes: Vec<usize> = header.naxes()
+            .map_err(ImageError::Axes)?
+            .into_iter()
+            .map(|length| usize::try_from(length).map_err(|_| ImageError::DataTooShort))
+            .collect::<Result<_, _>>()?;
+        let size = (bitpix.abs() / 8) as usize;
+        let length = if axes.is_empty() {
+            Option::Some(0)
+        } else {
+            axes.iter().try_fold(size, |product, &axis| product.checked_mul(axis))
+        };
+        let length = match length {
+            Option::Some(length) if length <= data.len() => length,
+            _ => return Err(ImageError::DataTooShort),
+        };
+        let scale = header.real_value_of(&Keyword::BSCALE).unwrap_or(1f64);
+        let zero = header.real_value_of(&Keyword::BZERO).unwrap_or(0f64);
+        let blank = header.integer_value_of(&Keyword::BLANK).ok();
+        let raw = &data[..length];
+        let physical = |value: i64| {
+            if Option::Some(value) == blank {
+                f64::NAN
+            } else {
+                value as f64 * scale + zero
+            }
+        };
+        let pixels: Vec<f64> = match bitpix {
+            8 => raw.iter().map(|&b| physical(b as i64)).collect(),
+            16 => raw.chunks(2).map(|c| physical(i16::from_be_bytes([c[0], c[1]]) as i64)).collect(),
+            32 => raw.chunks(4).map(|c| physical(i32::from_be_bytes([c[0], c[1], c[2], c[3]]) as i64)).collect(),
+            64 => raw.chunks(8).map(|c| physical(i64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))).collect(),
+            -32 => raw.chunks(4).map(|c| f32::from_be_bytes([c[0], c[1], c[2], c[3]]) as f64 * scale + zero).collect(),
+            -64 => raw.chunks(8).map(|c| f64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]) * scale + zero).collect(),
+            _ => return Err(ImageError::InvalidBitpix(bitpix)),
+        };
+        Ok(Image { axes, pixels })
+    }
+
+    /// The length of the first axis, or 0 for an image without axes.
+    pub fn width(&self) -> usize {
+        self.axes.first().cloned().unwrap_or(0)
+    }
+
+    /// The length of the second axis, or 1 for an image with fewer axes.
+    pub fn height(&self) -> usize {
+        self.axes.get(1).cloned().unwrap_or(1)
+    }
+
+    /// The value of the pixel at zero based column `x` and row `y` of the first
+    /// plane.
+    pub fn get(&self, x: usize, y: usize) -> Option<f64> {
+        if x < self.width() && y < self.height() {
+            self.pixels.get(y * self.width() + x).cloned()
+        } else {
+            Option::None
+        }
+    }
+}
+
+/// Problems that could occur when decoding an image.
+#[derive(Debug, PartialEq)]
+pub enum ImageError {
+    /// The header is the header of an extension other than `IMAGE`.
+    NotAnImage,
+    /// A keyword the image needs could not be retrieved.
+    MissingKeyword(Keyword, ValueRetrievalError),
+    /// The axes of the image could not be determined.
+    Axes(NaxesError),
+    /// `BITPIX` is not one of 8, 16, 32, 64, -32 or -64.
+    InvalidBitpix(i64),
+    /// The data is shorter than the pixels of the image.
+    DataTooShort,
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::parser::fits;
+    use super::super::types::{Header, KeywordRecord, Keyword, Value};
+    use super::{Image, ImageError};
+
+    #[test]
+    fn image_should_apply_scaling_and_blank() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(16i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::BSCALE, Value::Real(0.5f64), Option::None),
+            KeywordRecord::new(Keyword::BZERO, Value::Integer(10i64), Option::None),
+            KeywordRecord::new(Keyword::BLANK, Value::Integer(-1i64), Option::None),
+        ));
+        let data = [0, 2, 0xff, 0xfe, 0xff, 0xff, 0, 0];
+
+        let image = Image::new(&header, &data).unwrap();
+
+        assert_eq!(image.axes, vec!(2, 2));
+        assert_eq!(image.get(0, 0), Option::Some(11f64));
+        assert_eq!(image.get(1, 0), Option::Some(9f64));
+        assert!(image.get(0, 1).unwrap().is_nan());
+        assert_eq!(image.get(1, 1), Option::Some(10f64));
+        assert_eq!(image.get(2, 0), Option::None);
+    }
+
+    #[test]
+    fn image_should_decode_the_aperture_of_the_target_pixel_file() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let image = Image::new(&fits[2].header, fits[2].data()).unwrap();
+
+        assert_eq!((image.width(), image.height()), (11, 11));
+        assert_eq!(image.pixels.len(), 121);
+        assert_eq!(Image::new(&fits[1].header, fits[1].data()), Err(ImageError::NotAnImage));
+    }
+}
@@ -0,0 +1,148 @@
+//! Quick-look previews of images.
+
+extern crate image;
+
+use self::image::{GrayImage, Luma};
+use super::super::types::HDU;
+use super::{Image, ImageError, Interval};
+
+/// The scaling from physical values between the limits of an image to gray
+/// levels.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Stretch {
+    /// Gray levels proportional to the value.
+    Linear,
+    /// Gray levels proportional to the square root of the value, brightening
+    /// faint features.
+    Sqrt,
+    /// Logarithmic scaling, for a high dynamic range.
+    Log,
+    /// Inverse hyperbolic sine scaling, linear for faint and logarithmic for
+    /// bright values.
+    Asinh,
+}
+
+impl Stretch {
+    /// Maps `t`, between 0 and 1, to a level between 0 and 1.
+    fn apply(&self, t: f64) -> f64 {
+        match *self {
+            Stretch::Linear => t,
+            Stretch::Sqrt => t.sqrt(),
+            Stretch::Log => (1000f64 * t + 1f64).ln() / 1001f64.ln(),
+            Stretch::Asinh => (10f64 * t).asinh() / 10f64.asinh(),
+        }
+    }
+}
+
+/// Problems that could occur when rendering a preview.
+#[derive(Debug, PartialEq)]
+pub enum PreviewError {
+    /// The image could not be decoded.
+    Image(ImageError),
+    /// The image has fewer than two axes.
+    NotTwoDimensional,
+}
+
+impl From<ImageError> for PreviewError {
+    fn from(error: ImageError) -> PreviewError {
+        PreviewError::Image(error)
+    }
+}
+
+impl Image {
+    /// Render the first plane of this image as 8 bit gray levels, scaling the
+    /// values between the minimum and maximum with `stretch`.
+    ///
+    /// The first row of the image is the bottom row of the preview, as FITS
+    /// viewers show it. Undefined values are black.
+    pub fn to_gray_image(&self, stretch: Stretch) -> Result<GrayImage, PreviewError> {
+        self.to_gray_image_with(stretch, Interval::MinMax)
+    }
+
+    /// Render the first plane of this image as `to_gray_image` does, scaling
+    /// the values between the limits chosen by `interval` with `stretch`.
+    /// Values outside the limits are clipped. `Interval::ZScale` with
+    /// `Stretch::Linear` looks like the previews of DS9.
+    pub fn to_gray_image_with(&self, stretch: Stretch, interval: Interval) -> Result<GrayImage, PreviewError> {
+        if self.axes.len() < 2 {
+            return Err(PreviewError::NotTwoDimensional)
+        }
+        let (width, height) = (self.width(), self.height());
+        let plane = &self.pixels[..width * height];
+        let (minimum, maximum) = self.limits(interval).unwrap_or((0f64, 0f64));
+        let range = maximum - minimum;
+        Ok(GrayImage::from_fn(width as u32, height as u32, |x, y| {
+            let value = plane[(height - 1 - y as usize) * width + x as usize];
+            if !value.is_finite() || range <= 0f64 {
+                return Luma([0u8])
+            }
+            let level = stretch.apply(((value - minimum) / range).clamp(0f64, 1f64));
+            Luma([(level * 255f64).round() as u8])
+        }))
+    }
+}
+
+impl<'a> HDU<'a> {
+    /// Render a preview of the image in this HDU, as `Image::to_gray_image`.
+    pub fn to_image(&self, stretch: Stretch) -> Result<GrayImage, PreviewError> {
+        Image::new(&self.header, self.data())?.to_gray_image(stretch)
+    }
+
+    /// Render a preview of the image in this HDU, as `Image::to_gray_image_with`.
+    pub fn to_image_with(&self, stretch: Stretch, interval: Interval) -> Result<GrayImage, PreviewError> {
+        Image::new(&self.header, self.data())?.to_gray_image_with(stretch, interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::fits;
+    use super::super::{Image, Interval};
+    use super::{PreviewError, Stretch};
+
+    #[test]
+    fn gray_image_should_be_flipped_and_stretched() {
+        let image = Image { axes: vec!(2, 2), pixels: vec!(0f64, 1f64, f64::NAN, 4f64) };
+
+        let linear = image.to_gray_image(Stretch::Linear).unwrap();
+        let sqrt = image.to_gray_image(Stretch::Sqrt).unwrap();
+
+        assert_eq!(linear.get_pixel(0, 1)[0], 0);
+        assert_eq!(linear.get_pixel(1, 1)[0], 64);
+        assert_eq!(linear.get_pixel(0, 0)[0], 0);
+        assert_eq!(linear.get_pixel(1, 0)[0], 255);
+        assert_eq!(sqrt.get_pixel(1, 1)[0], 128);
+    }
+
+    #[test]
+    fn gray_image_should_clip_to_the_interval() {
+        let image = Image { axes: vec!(2, 2), pixels: vec!(0f64, 1f64, 2f64, 100f64) };
+
+        let clipped = image.to_gray_image_with(Stretch::Linear, Interval::Percentile(0f64, 50f64)).unwrap();
+
+        assert_eq!(clipped.get_pixel(0, 1)[0], 0);
+        assert_eq!(clipped.get_pixel(1, 1)[0], 170);
+        assert_eq!(clipped.get_pixel(1, 0)[0], 255);
+    }
+
+    #[test]
+    fn preview_should_need_two_axes() {
+        let image = Image { axes: vec!(3), pixels: vec!(0f64, 1f64, 2f64) };
+
+        assert_eq!(image.to_gray_image(Stretch::Linear), Err(PreviewError::NotTwoDimensional));
+    }
+
+    #[test]
+    fn aperture_of_the_target_pixel_file_should_render() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let preview = fits[2].to_image(Stretch::Log).unwrap();
+
+        assert_eq!(preview.dimensions(), (11, 11));
+    }
+}
@@ -0,0 +1,183 @@
+//! Binning of images into smaller images, for overviews of large mosaics.
+
+use std::iter;
+use super::super::types::{Header, HDU, Keyword, Value};
+use super::{Image, ImageError};
+
+/// How the pixels of a block are combined into one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Rebin {
+    /// The sum of the defined values, which preserves the total flux.
+    Sum,
+    /// The mean of the defined values, which preserves surface brightness.
+    Mean,
+    /// The median of the defined values, which ignores hot pixels and cosmic
+    /// rays.
+    Median,
+}
+
+impl Rebin {
+    fn combine(&self, values: &mut [f64]) -> f64 {
+        if values.is_empty() {
+            return f64::NAN
+        }
+        match *self {
+            Rebin::Sum => values.iter().sum(),
+            Rebin::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Rebin::Median => {
+                values.sort_by(|a, b| a.partial_cmp(b).expect("defined values are ordered"));
+                let n = values.len();
+                if n.is_multiple_of(2) { (values[n / 2 - 1] + values[n / 2]) / 2f64 } else { values[n / 2] }
+            }
+        }
+    }
+}
+
+impl Image {
+    /// Combine every `factor` x `factor` block of pixels of every plane into
+    /// one with `method`, ignoring undefined values. Blocks at the right and
+    /// top edges may be partial. A block without defined values is `NaN`.
+    ///
+    /// Panics when `factor` is 0.
+    pub fn rebin(&self, factor: usize, method: Rebin) -> Image {
+        assert!(factor > 0, "rebin by a factor of 0");
+        let (width, height) = (self.width(), self.height());
+        let (columns, rows) = (width.div_ceil(factor), height.div_ceil(factor));
+        let planes = self.pixels.len().checked_div(width * height).unwrap_or(0);
+        let mut pixels = Vec::with_capacity(columns * rows * planes);
+        let mut block = Vec::with_capacity(factor * factor);
+        for plane in self.pixels.chunks((width * height).max(1)).take(planes) {
+            for row in 0..rows {
+                for column in 0..columns {
+                    block.clear();
+                    for y in row * factor..((row + 1) * factor).min(height) {
+                        let start = y * width;
+                        block.extend(plane[start + column * factor..start + ((column + 1) * factor).min(width)]
+                            .iter()
+                            .filter(|value| !value.is_nan()));
+                    }
+                    pixels.push(method.combine(&mut block));
+                }
+            }
+        }
+        let mut axes = self.axes.clone();
+        if let Option::Some(axis) = axes.get_mut(0) {
+            *axis = columns;
+        }
+        if let Option::Some(axis) = axes.get_mut(1) {
+            *axis = rows;
+        }
+        Image { axes, pixels }
+    }
+}
+
+/// A binned image, with a header that describes it.
+#[derive(Debug, PartialEq)]
+pub struct Rebinned<'a> {
+    /// The header of the image with the axes of the binned image, `BITPIX`
+    /// -64 and its world coordinate systems scaled along, so that the world
+    /// coordinates of the pixels stay the same.
+    pub header: Header<'a>,
+    /// The binned image.
+    pub image: Image,
+    /// The pixels of the binned image, as big endian 64 bit floating point.
+    pub data: Vec<u8>,
+}
+
+impl<'a> Rebinned<'a> {
+    /// The binned image as an HDU, ready to be written.
+    pub fn hdu<'b>(&'b self) -> HDU<'b> {
+        HDU::with_data(Header::new(self.header.records().to_vec()), &self.data)
+    }
+}
+
+impl<'a> HDU<'a> {
+    /// Bin the image in this HDU as `Image::rebin` does.
+    ///
+    /// `NAXIS1` and `NAXIS2` are updated, and for every world coordinate
+    /// system `CRPIX1a` and `CRPIX2a` are moved and `CDELT1a`, `CDELT2a` and
+    /// the `CDi_ja` of the first two axes scaled. Distortions like SIP are not
+    /// adjusted. The pixels are physical values, so `BITPIX` becomes -64 and
+    /// `BSCALE`, `BZERO`, `BLANK`, `CHECKSUM` and `DATASUM` are removed.
+    ///
+    /// Panics when `factor` is 0.
+    pub fn rebin(&self, factor: usize, method: Rebin) -> Result<Rebinned<'a>, ImageError> {
+        let image = Image::new(&self.header, self.data())?.rebin(factor, method);
+        let mut header = Header::new(self.header.records().to_vec());
+        header.set(Keyword::BITPIX, Value::Integer(-64), Option::None).expect("BITPIX can be set");
+        for (index, &length) in image.axes.iter().enumerate().take(2) {
+            header.set(Keyword::NAXISn(index as u16 + 1), Value::Integer(length as i64), Option::None).expect("NAXISn can be set");
+        }
+        for keyword in [Keyword::BSCALE, Keyword::BZERO, Keyword::BLANK, Keyword::CHECKSUM, Keyword::DATASUM].iter() {
+            let _ = header.remove(keyword);
+        }
+        let alternates: Vec<Option<char>> = iter::once(Option::None)
+            .chain(header.wcs_alternates().into_iter().map(Option::Some))
+            .collect();
+        let factor = factor as f64;
+        for alternate in alternates {
+            for axis in 1u16..=2 {
+                scale(&mut header, Keyword::CRPIXja(axis, alternate), |reference| (reference - 0.5) / factor + 0.5);
+                scale(&mut header, Keyword::CDELTia(axis, alternate), |delta| delta * factor);
+                for i in 1u16..=2 {
+                    scale(&mut header, Keyword::CDi_ja(i, axis, alternate), |element| element * factor);
+                }
+            }
+        }
+        let data = image.pixels.iter().flat_map(|value| value.to_be_bytes()).collect();
+        Ok(Rebinned { header, image, data })
+    }
+}
+
+/// Replace the value of `keyword`, when it is present, by `f` of it.
+fn scale<F: Fn(f64) -> f64>(header: &mut Header, keyword: Keyword, f: F) {
+    if let Ok(value) = header.real_value_of(&keyword) {
+        header.set(keyword, Value::Real(f(value)), Option::None).expect("WCS keywords can be set");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::super::types::{HDU, Keyword};
+    use super::super::Image;
+    use super::Rebin;
+
+    #[test]
+    fn rebin_should_combine_blocks_of_every_plane() {
+        let image = Image {
+            axes: vec!(3, 2, 2),
+            pixels: vec!(1f64, 2f64, 3f64, 4f64, 100f64, f64::NAN, 0f64, 0f64, 0f64, 0f64, 0f64, 0f64),
+        };
+
+        let sum = image.rebin(2, Rebin::Sum);
+        let mean = image.rebin(2, Rebin::Mean);
+        let median = image.rebin(2, Rebin::Median);
+
+        assert_eq!(sum.axes, vec!(2, 1, 2));
+        assert_eq!(sum.pixels, vec!(107f64, 3f64, 0f64, 0f64));
+        assert_eq!(mean.pixels, vec!(26.75, 3f64, 0f64, 0f64));
+        assert_eq!(median.pixels, vec!(3f64, 3f64, 0f64, 0f64));
+        assert!(Image { axes: vec!(1, 1), pixels: vec!(f64::NAN) }.rebin(4, Rebin::Mean).pixels[0].is_nan());
+    }
+
+    #[test]
+    fn rebin_should_scale_the_world_coordinates() {
+        let header = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 4\nNAXIS2 = 4\nBZERO = 10.0\nCRPIX1 = 2.5\nCRPIX2 = 0.5\nCDELT1 = -0.1\nCDELT2 = 0.1\nCTYPE1A = 'X'\nCD1_1A = 2.0\nCD2_2A = 3.0\nCHECKSUM = '0000000000000000'").unwrap();
+        let data: Vec<u8> = (0..16).collect();
+
+        let rebinned = HDU::with_data(header, &data).rebin(2, Rebin::Mean).unwrap();
+        let header = &rebinned.header;
+
+        assert_eq!(rebinned.image.pixels, vec!(12.5, 14.5, 20.5, 22.5));
+        assert_eq!(header.integer_value_of(&Keyword::BITPIX), Ok(-64));
+        assert_eq!(header.naxes(), Ok(vec!(2, 2)));
+        assert_eq!(header.real_value_of(&Keyword::CRPIXja(1, Option::None)), Ok(1.5));
+        assert_eq!(header.real_value_of(&Keyword::CRPIXja(2, Option::None)), Ok(0.5));
+        assert_eq!(header.real_value_of(&Keyword::CDELTia(1, Option::None)), Ok(-0.2));
+        assert_eq!(header.real_value_of(&Keyword::CDi_ja(2, 2, Option::Some('A'))), Ok(6.0));
+        assert!(header.value_of(&Keyword::BZERO).is_err() && header.value_of(&Keyword::CHECKSUM).is_err());
+        assert_eq!(rebinned.hdu().data(), &rebinned.data[..]);
+        assert_eq!(rebinned.data.len(), 32);
+    }
+}
@@ -4,16 +4,93 @@
 //! The *Flexible Image Transport System* ([FITS](https://en.wikipedia.org/wiki/FITS)) is
 //! > an open standard defining a digital file format useful for storage,
 //! > transmission and processing of scientific and other images.
+//!
+//! # Threads
+//!
+//! All public types are `Send` and `Sync`. The parsed types borrow from their
+//! input, so to hand a file to other threads either share the input with
+//! scoped threads, copy it with `Fits::into_owned` or `types::OwnedFits`, or
+//! share the buffer itself with `types::DataRef` or, behind the `bytes`
+//! feature, `types::SharedFits`.
 
 #[macro_use]
 extern crate nom;
+extern crate memchr;
+extern crate phf;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod checksum;
+pub mod diff;
+pub mod image;
+pub mod lightcurve;
+pub mod lint;
 pub mod parser;
+pub mod prelude;
+pub mod region;
+pub mod spectrum;
+pub mod table;
+pub mod testkit;
 pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wcs;
+pub mod writer;
 
 #[cfg(test)]
 mod tests {
     #[test]
     fn it_works() {
     }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn public_types_should_be_send_and_sync() {
+        use super::{checksum, diff, image, lint, parser, table, types, wcs};
+
+        assert_send_sync::<types::Fits<'static>>();
+        assert_send_sync::<types::HDU<'static>>();
+        assert_send_sync::<types::Header<'static>>();
+        assert_send_sync::<types::InheritedHeader<'static, 'static>>();
+        assert_send_sync::<types::KeywordRecord<'static>>();
+        assert_send_sync::<types::Value<'static>>();
+        assert_send_sync::<types::Keyword>();
+        assert_send_sync::<types::DataArray<'static>>();
+        assert_send_sync::<types::DataRef>();
+        assert_send_sync::<types::OwnedFits>();
+        assert_send_sync::<types::ValueRetrievalError>();
+        assert_send_sync::<types::HeaderEditError>();
+        assert_send_sync::<types::FitsEditError>();
+        #[cfg(feature = "bytes")]
+        assert_send_sync::<types::SharedFits>();
+        assert_send_sync::<parser::LazyHeader<'static>>();
+        assert_send_sync::<parser::LazyHDU<'static>>();
+        assert_send_sync::<parser::TemplateError>();
+        assert_send_sync::<table::BinTable>();
+        assert_send_sync::<table::ColumnValues>();
+        assert_send_sync::<table::TableHdu>();
+        assert_send_sync::<table::TableError>();
+        assert_send_sync::<image::Image>();
+        assert_send_sync::<image::Cutout<'static>>();
+        assert_send_sync::<wcs::Wcs>();
+        assert_send_sync::<lint::Validator>();
+        assert_send_sync::<lint::Finding>();
+        assert_send_sync::<diff::CardDiff<'static>>();
+        assert_send_sync::<checksum::ChecksumError>();
+        assert_send_sync::<checksum::Verification>();
+    }
 }
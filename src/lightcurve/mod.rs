@@ -0,0 +1,165 @@
+//! The lightcurve module extracts light curves, flux against time, from the
+//! binary tables of Kepler, K2 and TESS light curve and target pixel files.
+
+use super::table::{BinTable, TableError};
+use super::types::Fits;
+
+/// The columns that hold the flux of light curve and target pixel files, in
+/// order of preference.
+pub const FLUX_COLUMNS: [&str; 3] = ["PDCSAP_FLUX", "SAP_FLUX", "FLUX"];
+
+/// The flux of a target against time, with the cadences of which the time or
+/// flux is undefined left out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LightCurve {
+    /// The times of the cadences, from `TIME`.
+    pub time: Vec<f64>,
+    /// The flux at each time.
+    pub flux: Vec<f64>,
+    /// The unit of the times, `TUNITn` of `TIME`, e.g. `BJD - 2454833`.
+    pub time_unit: Option<String>,
+    /// The unit of the flux, e.g. `e-/s`.
+    pub flux_unit: Option<String>,
+}
+
+impl LightCurve {
+    /// Extract the light curve from the first binary table of `fits` that has
+    /// a `TIME` column and one of the `FLUX_COLUMNS`.
+    pub fn new(fits: &Fits) -> Result<LightCurve, LightCurveError> {
+        for flux in FLUX_COLUMNS.iter() {
+            match LightCurve::with_columns(fits, "TIME", flux) {
+                Err(LightCurveError::NoTable) => continue,
+                result => return result,
+            }
+        }
+        Err(LightCurveError::NoTable)
+    }
+
+    /// Extract the light curve from columns `time` and `flux` of the first
+    /// binary table of `fits` that has both.
+    ///
+    /// When the flux column holds an image per cadence, as in target pixel
+    /// files, the flux of a cadence is the sum of the defined pixels.
+    pub fn with_columns(fits: &Fits, time: &str, flux: &str) -> Result<LightCurve, LightCurveError> {
+        for hdu in fits.iter() {
+            let table = match BinTable::new(&hdu.header) {
+                Ok(table) => table,
+                Err(_) => continue,
+            };
+            let (time_index, flux_index) = match (table.column_index(time), table.column_index(flux)) {
+                (Option::Some(time_index), Option::Some(flux_index)) => (time_index, flux_index),
+                _ => continue,
+            };
+            let values = table.read_columns(hdu.data(), &[time_index, flux_index])
+                .map_err(LightCurveError::Table)?;
+            let time_column = &table.columns()[time_index];
+            let flux_column = &table.columns()[flux_index];
+            let times = values[0].physical(time_column)
+                .ok_or_else(|| LightCurveError::NotNumeric(time.to_string()))?;
+            let fluxes = values[1].physical(flux_column)
+                .ok_or_else(|| LightCurveError::NotNumeric(flux.to_string()))?;
+            let mut curve = LightCurve {
+                time: vec!(),
+                flux: vec!(),
+                time_unit: time_column.unit.clone(),
+                flux_unit: flux_column.unit.clone(),
+            };
+            for row in 0..table.rows() {
+                let t = times[values[0].offsets[row]..values[0].offsets[row + 1]].first().cloned().unwrap_or(f64::NAN);
+                let f = sum(&fluxes[values[1].offsets[row]..values[1].offsets[row + 1]]);
+                if t.is_finite() && f.is_finite() {
+                    curve.time.push(t);
+                    curve.flux.push(f);
+                }
+            }
+            return Ok(curve)
+        }
+        Err(LightCurveError::NoTable)
+    }
+
+    /// The number of cadences.
+    pub fn len(&self) -> usize {
+        self.time.len()
+    }
+
+    /// Determines if there are no cadences.
+    pub fn is_empty(&self) -> bool {
+        self.time.is_empty()
+    }
+}
+
+/// The sum of the defined values, or `NaN` when none is.
+fn sum(values: &[f64]) -> f64 {
+    values.iter()
+        .filter(|value| !value.is_nan())
+        .fold(Option::None, |total: Option<f64>, value| Option::Some(total.unwrap_or(0f64) + value))
+        .unwrap_or(f64::NAN)
+}
+
+/// Extract the light curve of `fits`, see `LightCurve::new`.
+pub fn lightcurve(fits: &Fits) -> Result<LightCurve, LightCurveError> {
+    LightCurve::new(fits)
+}
+
+/// Problems that could occur when extracting a light curve.
+#[derive(Debug, PartialEq)]
+pub enum LightCurveError {
+    /// No binary table has the time and flux columns.
+    NoTable,
+    /// The table could not be decoded.
+    Table(TableError),
+    /// The column with this name is not numeric.
+    NotNumeric(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::parser::{fits, template};
+    use super::super::table::BinTable;
+    use super::super::types::{Fits, HDU};
+    use super::{lightcurve, LightCurve, LightCurveError};
+
+    #[test]
+    fn lightcurve_should_sum_the_pixels_of_target_pixel_files() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let file = match fits(data) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let curve = lightcurve(&file).unwrap();
+
+        let table = BinTable::new(&file[1].header).unwrap();
+        let index = table.column_index("FLUX").unwrap();
+        let flux = table.read_column(file[1].data(), index).unwrap();
+        let pixels = flux.physical(&table.columns()[index]).unwrap();
+        let first = (0..table.rows())
+            .map(|row| &pixels[flux.offsets[row]..flux.offsets[row + 1]])
+            .find(|row| row.iter().any(|value| !value.is_nan()))
+            .unwrap();
+        assert!(!curve.is_empty());
+        assert_eq!(curve.time.len(), curve.flux.len());
+        assert!(curve.time.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(curve.flux[0], first.iter().filter(|value| !value.is_nan()).sum::<f64>());
+        assert_eq!(curve.time_unit, table.columns()[table.column_index("TIME").unwrap()].unit);
+    }
+
+    #[test]
+    fn lightcurve_should_scale_and_drop_null_fluxes() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 12\nNAXIS2 = 3\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 2\nTTYPE1 = 'TIME'\nTFORM1 = 'D'\nTTYPE2 = 'SAP_FLUX'\nTFORM2 = 'J'\nTSCAL2 = 0.5\nTZERO2 = 100.0\nTNULL2 = -1\nTUNIT2 = 'e-/s'").unwrap();
+        let mut data = vec!();
+        for &(time, flux) in &[(1.0f64, 10i32), (2.0, -1), (3.0, 30)] {
+            data.extend_from_slice(&time.to_bits().to_be_bytes());
+            data.extend_from_slice(&flux.to_be_bytes());
+        }
+        let file = Fits::new(HDU::new(template("SIMPLE = T").unwrap()), vec!(HDU::with_data(header, &data)));
+
+        let curve = LightCurve::new(&file).unwrap();
+
+        assert_eq!(curve.time, vec!(1.0, 3.0));
+        assert_eq!(curve.flux, vec!(105.0, 115.0));
+        assert_eq!(curve.flux_unit, Option::Some("e-/s".to_string()));
+        assert_eq!(LightCurve::with_columns(&file, "TIME", "PDCSAP_FLUX"), Err(LightCurveError::NoTable));
+    }
+}
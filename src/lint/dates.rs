@@ -0,0 +1,181 @@
+//! Checks of the values of date keywords against the ISO-8601 subset of the
+//! standard, section 9.1.1: `CCYY-MM-DD`, optionally followed by
+//! `Thh:mm:ss[.s...]`, with a sign and more digits for years beyond 0000 to
+//! 9999.
+
+use std::fmt::{Display, Error, Formatter};
+use super::{Finding, Lint};
+use super::super::types::{Fits, HDU, Value};
+
+/// Check that `date` is a date in the format of the standard.
+pub fn check_date(date: &str) -> Result<(), DateError> {
+    let date = date.trim_end();
+    if date.is_empty() {
+        return Err(DateError::Empty)
+    }
+    let fields: Vec<&str> = date.split('/').collect();
+    if fields.len() == 3 && fields.iter().all(|field| field.len() == 2 && is_number(field)) {
+        return match (fields[1].parse::<u32>(), fields[0].parse::<u32>()) {
+            (Ok(month), Ok(day)) if valid_day(1900 + fields[2].parse::<i64>().unwrap_or(0), month, day) => Err(DateError::Legacy),
+            _ => Err(DateError::Invalid),
+        }
+    }
+    let (day, time) = match date.find('T') {
+        Option::Some(position) => (&date[..position], Option::Some(&date[position + 1..])),
+        Option::None => (date, Option::None),
+    };
+    let (sign, unsigned) = match day.as_bytes().first() {
+        Option::Some(b'+') | Option::Some(b'-') => (true, &day[1..]),
+        _ => (false, day),
+    };
+    let fields: Vec<&str> = unsigned.splitn(3, '-').collect();
+    if fields.len() != 3 || !fields.iter().all(|field| is_number(field)) || fields[1].len() != 2 || fields[2].len() != 2 {
+        return Err(DateError::Invalid)
+    }
+    match (sign, fields[0].len()) {
+        (false, 2) => return Err(DateError::TwoDigitYear),
+        (false, 4) => (),
+        (true, length) if length >= 5 => (),
+        _ => return Err(DateError::Invalid),
+    }
+    let year = fields[0].parse::<i64>().map_err(|_| DateError::Invalid)?;
+    let year = if day.starts_with('-') { -year } else { year };
+    if !valid_day(year, fields[1].parse().unwrap_or(0), fields[2].parse().unwrap_or(0)) {
+        return Err(DateError::Invalid)
+    }
+    match time {
+        Option::Some(time) if !valid_time(time) => Err(DateError::Invalid),
+        _ => Ok(()),
+    }
+}
+
+fn is_number(field: &str) -> bool {
+    !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn valid_day(year: i64, month: u32, day: u32) -> bool {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// Whether `time` is `hh:mm:ss` with an optional decimal fraction of seconds.
+fn valid_time(time: &str) -> bool {
+    let (time, fraction) = match time.find('.') {
+        Option::Some(position) => (&time[..position], Option::Some(&time[position + 1..])),
+        Option::None => (time, Option::None),
+    };
+    let fields: Vec<&str> = time.split(':').collect();
+    if fields.len() != 3 || !fields.iter().all(|field| field.len() == 2 && is_number(field)) {
+        return false
+    }
+    let limits = [23, 59, 60];
+    fields.iter().zip(limits.iter()).all(|(field, &limit)| field.parse::<u32>().is_ok_and(|value| value <= limit))
+        && fraction.is_none_or(is_number)
+}
+
+/// Why the value of a date keyword is not a date of the standard.
+#[derive(Debug, PartialEq)]
+pub enum DateError {
+    /// The value is empty.
+    Empty,
+    /// The value is in the form `DD/MM/YY` of files written before 1999. The
+    /// standard reads the year as `19YY`, but writers did not always mean it.
+    Legacy,
+    /// The year has two digits, e.g. `98-05-12`, which is ambiguous.
+    TwoDigitYear,
+    /// The value is not of the form `CCYY-MM-DD[Thh:mm:ss[.s...]]`, or a field
+    /// is out of range.
+    Invalid,
+}
+
+impl Display for DateError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            DateError::Empty => write!(f, "is empty"),
+            DateError::Legacy => write!(f, "is in the deprecated DD/MM/YY format, of which the two digit year is ambiguous"),
+            DateError::TwoDigitYear => write!(f, "has an ambiguous two digit year"),
+            DateError::Invalid => write!(f, "is not of the form CCYY-MM-DD[Thh:mm:ss[.s...]]"),
+        }
+    }
+}
+
+/// `DATE`, `DATEREF` and the `DATE-` keywords, like `DATE-OBS`, should hold
+/// dates of the standard. Dates in the legacy `DD/MM/YY` format and empty
+/// dates are warnings, other malformed dates errors. Many files in archives
+/// write dates in other forms, so the lint is not one of the lints of
+/// `Validator::new`.
+pub struct Dates;
+
+impl Lint for Dates {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        for (position, record) in hdu.header.records().iter().enumerate() {
+            let name = record.keyword().to_string();
+            if name != "DATE" && name != "DATEREF" && !name.starts_with("DATE-") {
+                continue;
+            }
+            let card = Option::Some(position + 1);
+            let error = match *record.value() {
+                Value::CharacterString(ref date) => match check_date(date) {
+                    Ok(()) => continue,
+                    Err(error) => error,
+                },
+                _ => {
+                    findings.push(Finding::error(index, card, format!("{} must be a character string", name)));
+                    continue;
+                }
+            };
+            let message = format!("{} {}", name, error);
+            findings.push(match error {
+                DateError::Empty | DateError::Legacy => Finding::warning(index, card, message),
+                DateError::TwoDigitYear | DateError::Invalid => Finding::error(index, card, message),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::fits;
+    use super::super::{Finding, Validator};
+    use super::{check_date, DateError, Dates};
+
+    #[test]
+    fn dates_should_follow_the_standard() {
+        for date in &["2017-03-08", "2016-02-29T23:59:60", "2016-12-15T21:10:14.721", "+12016-01-01", "-04713-11-24"] {
+            assert_eq!(check_date(date), Ok(()), "{}", date);
+        }
+        assert_eq!(check_date(""), Err(DateError::Empty));
+        assert_eq!(check_date("15/12/96"), Err(DateError::Legacy));
+        assert_eq!(check_date("31/02/96"), Err(DateError::Invalid));
+        assert_eq!(check_date("96-12-15"), Err(DateError::TwoDigitYear));
+        assert_eq!(check_date("2015-02-29"), Err(DateError::Invalid));
+        assert_eq!(check_date("2016-12-15T24:00:00"), Err(DateError::Invalid));
+        assert_eq!(check_date("2016-12-15T21:10"), Err(DateError::Invalid));
+        assert_eq!(check_date("2016-12-15:21:10:14.721Z"), Err(DateError::Invalid));
+    }
+
+    #[test]
+    fn dates_lint_should_report_the_dates_of_target_pixel_files() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let file = match fits(data) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let card = |index: usize, name: &str| file[index].header.records().iter().position(|record| record.keyword().to_string() == name).map(|position| position + 1);
+
+        let findings = Validator::empty().with_lint(Dates).validate(&file);
+
+        assert_eq!(findings, vec!(
+            Finding::error(1, card(1, "DATE-OBS"), "DATE-OBS is not of the form CCYY-MM-DD[Thh:mm:ss[.s...]]".to_string()),
+            Finding::warning(1, card(1, "DATE-END"), "DATE-END is empty".to_string()),
+        ));
+    }
+}
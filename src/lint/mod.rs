@@ -0,0 +1,623 @@
+//! The lint module checks FITS files for conformance with the standard.
+//!
+//! A `Validator` runs a set of `Lint`s over every HDU of a file and collects
+//! their `Finding`s. `validate` uses the lints of the standard. `fixed_format`
+//! checks the layout of the cards of the mandatory keywords in the bytes of a
+//! file, for strict validation.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Error};
+use std::str;
+use std::str::FromStr;
+use super::parser::layout;
+use super::table::{BinTable, TForm, TableLint};
+use super::types::{Fits, HDU, KeywordRecord, Keyword, Value};
+
+mod dates;
+mod profile;
+mod units;
+
+pub use self::dates::{check_date, DateError, Dates};
+pub use self::profile::{ConventionKeywords, Profile};
+pub use self::units::{check_unit, UnitError, Units};
+
+/// How severe a finding is.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Severity {
+    /// The file does not conform to the standard.
+    Error,
+    /// The file conforms, but might confuse readers.
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a FITS file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finding {
+    /// How severe the problem is.
+    pub severity: Severity,
+    /// The index of the HDU, 0 for the primary HDU.
+    pub hdu: usize,
+    /// The number of the card in the header, starting at 1, when the problem
+    /// concerns a single card.
+    pub card: Option<usize>,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Finding {
+    /// Create an error about card `card`, starting at 1, of HDU `hdu`.
+    pub fn error(hdu: usize, card: Option<usize>, message: String) -> Finding {
+        Finding { severity: Severity::Error, hdu, card, message }
+    }
+
+    /// Create a warning about card `card`, starting at 1, of HDU `hdu`.
+    pub fn warning(hdu: usize, card: Option<usize>, message: String) -> Finding {
+        Finding { severity: Severity::Warning, hdu, card, message }
+    }
+}
+
+impl Display for Finding {
+    /// Formats this finding as `HDU 1, card 3: error: message`.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "HDU {}", self.hdu)?;
+        if let Option::Some(card) = self.card {
+            write!(f, ", card {}", card)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// A check of a single HDU. Lints are `Send` and `Sync`, so that a `Validator`
+/// can be shared between threads.
+pub trait Lint: Send + Sync {
+    /// Check `hdu`, the HDU at `index` of `fits`, adding the problems to `findings`.
+    fn check(&self, fits: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>);
+}
+
+/// Runs lints over all HDUs of a file.
+pub struct Validator {
+    lints: Vec<Box<dyn Lint>>,
+}
+
+impl Validator {
+    /// A validator with the lints of the standard, which accept the keywords
+    /// of common conventions, see `Profile::Conventions`.
+    pub fn new() -> Validator {
+        Validator::empty()
+            .with_lint(MandatoryKeywords)
+            .with_lint(DuplicateKeywords)
+            .with_lint(WcsKeywords)
+            .with_lint(TableStructure)
+    }
+
+    /// A validator without any lints.
+    pub fn empty() -> Validator {
+        Validator { lints: vec!() }
+    }
+
+    /// Add `lint` to the lints of this validator.
+    pub fn with_lint<L: Lint + 'static>(mut self, lint: L) -> Validator {
+        self.lints.push(Box::new(lint));
+        self
+    }
+
+    /// Check all HDUs of `fits`, returning the findings by HDU and card.
+    pub fn validate(&self, fits: &Fits) -> Vec<Finding> {
+        let mut findings = vec!();
+        for (index, hdu) in fits.iter().enumerate() {
+            for lint in &self.lints {
+                lint.check(fits, index, hdu, &mut findings);
+            }
+        }
+        findings.sort_by_key(|finding| (finding.hdu, finding.card.unwrap_or(0)));
+        findings
+    }
+}
+
+impl Default for Validator {
+    fn default() -> Validator {
+        Validator::new()
+    }
+}
+
+/// Check `fits` with the lints of the standard.
+pub fn validate(fits: &Fits) -> Vec<Finding> {
+    Validator::new().validate(fits)
+}
+
+/// Check that the cards of the mandatory keywords of every header of `input`,
+/// a FITS file, are in fixed format: the value indicator in bytes 9 and 10,
+/// integers right-justified and logicals in byte 30, and character strings
+/// starting with a quote in byte 11.
+///
+/// Free format is valid for other keywords, but some readers only understand
+/// mandatory keywords in fixed format, so these are warnings.
+pub fn fixed_format(input: &[u8]) -> Vec<Finding> {
+    let mut findings = vec!();
+    for (index, (header, _)) in layout(input).into_iter().enumerate() {
+        for (position, card) in input[header].chunks(80).enumerate() {
+            let name = match str::from_utf8(&card[..8]) {
+                Ok(name) => name.trim_end(),
+                Err(_) => continue,
+            };
+            if name == "END" {
+                break
+            }
+            let format = match Keyword::from_str(name) {
+                Ok(Keyword::SIMPLE) | Ok(Keyword::GROUPS) => FixedFormat::Logical,
+                Ok(Keyword::XTENSION) | Ok(Keyword::TFORMn(_)) => FixedFormat::CharacterString,
+                Ok(Keyword::BITPIX) | Ok(Keyword::NAXIS) | Ok(Keyword::NAXISn(_)) | Ok(Keyword::PCOUNT)
+                    | Ok(Keyword::GCOUNT) | Ok(Keyword::TFIELDS) => FixedFormat::Integer,
+                _ => continue,
+            };
+            if let Option::Some(problem) = format.check(card) {
+                findings.push(Finding::warning(index, Option::Some(position + 1), format!("{} {}", name, problem)));
+            }
+        }
+    }
+    findings
+}
+
+/// The fixed format of the value of a mandatory keyword.
+enum FixedFormat {
+    Logical,
+    Integer,
+    CharacterString,
+}
+
+impl FixedFormat {
+    /// What is wrong with the layout of `card`, `None` when it is fixed format.
+    fn check(&self, card: &[u8]) -> Option<&'static str> {
+        if &card[8..10] != b"= " {
+            return Option::Some("must have its value indicator in bytes 9 and 10")
+        }
+        match *self {
+            FixedFormat::CharacterString => {
+                if card[10] != b'\'' {
+                    return Option::Some("must start its value with a quote in byte 11")
+                }
+            }
+            FixedFormat::Logical | FixedFormat::Integer => {
+                let start = card[10..].iter().position(|&b| b != b' ').map_or(card.len(), |offset| offset + 10);
+                let end = card[start..].iter().position(|&b| b == b' ' || b == b'/').map_or(card.len(), |offset| offset + start);
+                if end != 30 {
+                    return Option::Some(match *self {
+                        FixedFormat::Logical => "must have its value in byte 30",
+                        _ => "must have its value right-justified to byte 30",
+                    })
+                }
+            }
+        }
+        Option::None
+    }
+}
+
+/// The mandatory keywords of primary headers and extensions must be present,
+/// in order, with valid values.
+pub struct MandatoryKeywords;
+
+impl Lint for MandatoryKeywords {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        let records = hdu.header.records();
+        let mut cards = Cards { hdu: index, records, findings };
+
+        let xtension = if index == 0 {
+            match cards.expect(0, Keyword::SIMPLE) {
+                Option::Some((_, &Value::Logical(true))) => (),
+                Option::Some((card, &Value::Logical(false))) => cards.warning(card, "SIMPLE = F, the file does not conform to the standard".to_string()),
+                Option::Some((card, _)) => cards.error(card, "SIMPLE must be a logical".to_string()),
+                Option::None => (),
+            }
+            Option::None
+        } else {
+            match cards.expect(0, Keyword::XTENSION) {
+                Option::Some((card, Value::CharacterString(xtension))) => {
+                    let xtension = xtension.trim_end();
+                    if !["IMAGE", "TABLE", "BINTABLE"].contains(&xtension) {
+                        cards.warning(card, format!("XTENSION '{}' is not a standard extension", xtension));
+                    }
+                    Option::Some(xtension)
+                }
+                Option::Some((card, _)) => {
+                    cards.error(card, "XTENSION must be a character string".to_string());
+                    Option::None
+                }
+                Option::None => Option::None,
+            }
+        };
+
+        let bitpix = cards.integer(1, Keyword::BITPIX);
+        if let Option::Some((card, value)) = bitpix {
+            if ![8, 16, 32, 64, -32, -64].contains(&value) {
+                cards.error(card, format!("BITPIX must be one of 8, 16, 32, 64, -32 or -64, not {}", value));
+            }
+        }
+        let (naxis_card, naxis) = cards.integer(2, Keyword::NAXIS).unwrap_or((2, 0));
+        if !(0..=999).contains(&naxis) {
+            cards.error(naxis_card, format!("NAXIS must be between 0 and 999, not {}", naxis));
+        }
+        let naxis = naxis.clamp(0, 999) as usize;
+        for n in 1..(naxis + 1) {
+            if let Option::Some((card, length)) = cards.integer(2 + n, Keyword::NAXISn(n as u16)) {
+                if length < 0 {
+                    cards.error(card, format!("NAXIS{} must not be negative", n));
+                }
+            }
+        }
+
+        if let Option::Some(xtension) = xtension {
+            let pcount = cards.integer(3 + naxis, Keyword::PCOUNT);
+            let gcount = cards.integer(4 + naxis, Keyword::GCOUNT);
+            match xtension {
+                "IMAGE" => {
+                    if let Option::Some((card, pcount)) = pcount.filter(|&(_, pcount)| pcount != 0) {
+                        cards.error(card, format!("PCOUNT must be 0 for an IMAGE extension, not {}", pcount));
+                    }
+                    if let Option::Some((card, gcount)) = gcount.filter(|&(_, gcount)| gcount != 1) {
+                        cards.error(card, format!("GCOUNT must be 1 for an IMAGE extension, not {}", gcount));
+                    }
+                }
+                "TABLE" | "BINTABLE" => {
+                    if let Option::Some((card, bitpix)) = bitpix.filter(|&(_, bitpix)| bitpix != 8) {
+                        cards.error(card, format!("BITPIX must be 8 for a {} extension, not {}", xtension, bitpix));
+                    }
+                    if naxis != 2 {
+                        cards.error(naxis_card, format!("NAXIS must be 2 for a {} extension, not {}", xtension, naxis));
+                    }
+                    if let Option::Some((card, pcount)) = pcount.filter(|&(_, pcount)| pcount != 0 && xtension == "TABLE") {
+                        cards.error(card, format!("PCOUNT must be 0 for a TABLE extension, not {}", pcount));
+                    }
+                    if let Option::Some((card, gcount)) = gcount.filter(|&(_, gcount)| gcount != 1) {
+                        cards.error(card, format!("GCOUNT must be 1 for a {} extension, not {}", xtension, gcount));
+                    }
+                    cards.fields(xtension == "BINTABLE");
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Keywords other than commentary keywords should appear only once.
+pub struct DuplicateKeywords;
+
+impl Lint for DuplicateKeywords {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        let mut first: HashMap<&Keyword, usize> = HashMap::new();
+        for (position, record) in hdu.header.records().iter().enumerate() {
+            let keyword = record.keyword();
+            if keyword.is_commentary() || *keyword == Keyword::END {
+                continue;
+            }
+            match first.get(keyword) {
+                Option::Some(card) => findings.push(Finding::warning(index, Option::Some(position + 1),
+                                                                     format!("{} already appears in card {}", keyword, card))),
+                Option::None => {
+                    first.insert(keyword, position + 1);
+                }
+            }
+        }
+    }
+}
+
+/// The keywords of every world coordinate system must be consistent: `CDi_j`
+/// and `PCi_j` exclude each other, `WCSAXES` precedes the keywords indexed by
+/// axis and bounds their axes, and every declared axis has a `CRVALi`.
+pub struct WcsKeywords;
+
+/// The cards of one world coordinate system of a header.
+#[derive(Default)]
+struct WcsCards<'r> {
+    wcsaxes: Option<(usize, i64)>,
+    first: Option<usize>,
+    axes: Vec<(usize, &'r Keyword, u16)>,
+    cd: Option<(usize, &'r Keyword)>,
+    pc: Option<(usize, &'r Keyword)>,
+    ctype: BTreeMap<u16, usize>,
+    crval: Vec<u16>,
+}
+
+impl Lint for WcsKeywords {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        let mut descriptions: BTreeMap<Option<char>, WcsCards> = BTreeMap::new();
+        for (position, record) in hdu.header.records().iter().enumerate() {
+            let keyword = record.keyword();
+            let (alternate, axes): (Option<char>, Vec<u16>) = match *keyword {
+                Keyword::WCSAXESa(alternate) => {
+                    let cards = descriptions.entry(alternate).or_default();
+                    cards.wcsaxes = match *record.value() {
+                        Value::Integer(axes) => Option::Some((position, axes)),
+                        _ => {
+                            findings.push(Finding::error(index, Option::Some(position + 1), format!("{} must be an integer", keyword)));
+                            Option::None
+                        }
+                    };
+                    continue
+                }
+                Keyword::CTYPEia(i, alternate) => {
+                    descriptions.entry(alternate).or_default().ctype.entry(i).or_insert(position);
+                    (alternate, vec!(i))
+                }
+                Keyword::CRVALia(i, alternate) => {
+                    descriptions.entry(alternate).or_default().crval.push(i);
+                    (alternate, vec!(i))
+                }
+                Keyword::CDi_ja(i, j, alternate) => {
+                    let cards = descriptions.entry(alternate).or_default();
+                    cards.cd = cards.cd.or(Option::Some((position, keyword)));
+                    (alternate, vec!(i, j))
+                }
+                Keyword::PCi_ja(i, j, alternate) => {
+                    let cards = descriptions.entry(alternate).or_default();
+                    cards.pc = cards.pc.or(Option::Some((position, keyword)));
+                    (alternate, vec!(i, j))
+                }
+                Keyword::CRPIXja(i, alternate) | Keyword::CDELTia(i, alternate) | Keyword::CUNITia(i, alternate)
+                    | Keyword::PVi_ma(i, _, alternate) | Keyword::PSi_ma(i, _, alternate) => (alternate, vec!(i)),
+                Keyword::CROTAi(i) => (Option::None, vec!(i)),
+                Keyword::LONPOLEa(alternate) | Keyword::LATPOLEa(alternate) | Keyword::WCSNAMEa(alternate)
+                    | Keyword::RESTFRQa(alternate) | Keyword::RESTWAVa(alternate) => (alternate, vec!()),
+                _ => continue,
+            };
+            let cards = descriptions.entry(alternate).or_default();
+            if !axes.is_empty() {
+                cards.first = cards.first.or(Option::Some(position));
+            }
+            cards.axes.extend(axes.into_iter().map(|axis| (position, keyword, axis)));
+        }
+
+        let naxis = hdu.header.integer_value_of(&Keyword::NAXIS).unwrap_or(0);
+        let suffix = |alternate: Option<char>| alternate.map(String::from).unwrap_or_default();
+        for (alternate, cards) in descriptions {
+            if let (Option::Some((cd, cd_keyword)), Option::Some((pc, pc_keyword))) = (cards.cd, cards.pc) {
+                findings.push(Finding::error(index, Option::Some(pc + 1),
+                                             format!("{} can not be combined with {} in card {}", pc_keyword, cd_keyword, cd + 1)));
+            }
+            match cards.wcsaxes {
+                Option::Some((card, wcsaxes)) => {
+                    if let Option::Some(first) = cards.first.filter(|&first| first < card) {
+                        findings.push(Finding::error(index, Option::Some(card + 1),
+                                                     format!("WCSAXES{} must precede the WCS keywords indexed by axis, like card {}", suffix(alternate), first + 1)));
+                    }
+                    for &(position, keyword, axis) in cards.axes.iter().filter(|&&(_, _, axis)| axis as i64 > wcsaxes) {
+                        findings.push(Finding::error(index, Option::Some(position + 1),
+                                                     format!("{} refers to axis {} beyond WCSAXES{} = {}", keyword, axis, suffix(alternate), wcsaxes)));
+                    }
+                }
+                Option::None => {
+                    if let Option::Some(&(position, keyword, axis)) = cards.axes.iter().find(|&&(_, _, axis)| axis as i64 > naxis) {
+                        findings.push(Finding::warning(index, Option::Some(position + 1),
+                                                       format!("{} refers to axis {} beyond NAXIS = {} without WCSAXES{}", keyword, axis, naxis, suffix(alternate))));
+                    }
+                }
+            }
+            for (axis, position) in cards.ctype {
+                if !cards.crval.contains(&axis) {
+                    findings.push(Finding::warning(index, Option::Some(position + 1),
+                                                   format!("CTYPE{}{} declares axis {} without CRVAL{}{}", axis, suffix(alternate), axis, axis, suffix(alternate))));
+                }
+            }
+        }
+    }
+}
+
+/// The columns of binary tables must fill their rows, and their heaps must
+/// follow the main table within `PCOUNT`, see `BinTable::lint`. Columns
+/// without a name are warned about.
+pub struct TableStructure;
+
+impl Lint for TableStructure {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        let heap = BinTable::new(&hdu.header).map(|table| table.lint_heap(hdu.data())).unwrap_or_default();
+        for lint in BinTable::lint(&hdu.header).into_iter().chain(heap) {
+            match lint {
+                TableLint::Invalid(_) => (),
+                TableLint::MissingName(_) => findings.push(Finding::warning(index, Option::None, lint.to_string())),
+                _ => findings.push(Finding::error(index, Option::None, lint.to_string())),
+            }
+        }
+    }
+}
+
+/// The cards of a header, reporting problems with them.
+struct Cards<'f, 'r, 'a: 'r> {
+    hdu: usize,
+    records: &'r [KeywordRecord<'a>],
+    findings: &'f mut Vec<Finding>,
+}
+
+impl<'f, 'r, 'a> Cards<'f, 'r, 'a> {
+    fn error(&mut self, position: usize, message: String) {
+        self.findings.push(Finding::error(self.hdu, Option::Some(position + 1), message));
+    }
+
+    fn warning(&mut self, position: usize, message: String) {
+        self.findings.push(Finding::warning(self.hdu, Option::Some(position + 1), message));
+    }
+
+    /// The position and value of `keyword`, which must be at `position`.
+    /// Reports when it is elsewhere or missing.
+    fn expect(&mut self, position: usize, keyword: Keyword) -> Option<(usize, &'r Value<'a>)> {
+        let records = self.records;
+        match records.get(position) {
+            Option::Some(record) if *record.keyword() == keyword => Option::Some((position, record.value())),
+            _ => match records.iter().position(|record| *record.keyword() == keyword) {
+                Option::Some(actual) => {
+                    self.error(actual, format!("{} must be card {}", keyword, position + 1));
+                    Option::Some((actual, records[actual].value()))
+                }
+                Option::None => {
+                    self.findings.push(Finding::error(self.hdu, Option::None, format!("{} is missing", keyword)));
+                    Option::None
+                }
+            },
+        }
+    }
+
+    /// The position and integer value of `keyword`, which must be at `position`.
+    fn integer(&mut self, position: usize, keyword: Keyword) -> Option<(usize, i64)> {
+        match self.expect(position, keyword.clone()) {
+            Option::Some((actual, &Value::Integer(n))) => Option::Some((actual, n)),
+            Option::Some((actual, _)) => {
+                self.error(actual, format!("{} must be an integer", keyword));
+                Option::None
+            }
+            Option::None => Option::None,
+        }
+    }
+
+    /// Checks `TFIELDS` and the `TFORMn` of each field of a table.
+    fn fields(&mut self, binary: bool) {
+        let records = self.records;
+        let position = |keyword: &Keyword| records.iter().position(|record| record.keyword() == keyword);
+        let fields = match position(&Keyword::TFIELDS) {
+            Option::Some(card) => match *records[card].value() {
+                Value::Integer(fields) if (0..=999).contains(&fields) => fields as u16,
+                _ => {
+                    self.error(card, "TFIELDS must be an integer between 0 and 999".to_string());
+                    return
+                }
+            },
+            Option::None => {
+                self.findings.push(Finding::error(self.hdu, Option::None, "TFIELDS is missing".to_string()));
+                return
+            }
+        };
+        for n in 1..(fields + 1) {
+            let keyword = Keyword::TFORMn(n);
+            match position(&keyword) {
+                Option::Some(card) => match *records[card].value() {
+                    Value::CharacterString(ref form) if !binary || TForm::from_str(form).is_ok() => (),
+                    _ => self.error(card, format!("{} is not a valid format", keyword)),
+                },
+                Option::None => self.findings.push(Finding::error(self.hdu, Option::None, format!("{} is missing", keyword))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::parser::{fits, template};
+    use super::super::types::{Fits, HDU, Header, KeywordRecord, Keyword, Value};
+    use super::{fixed_format, validate, Finding};
+
+    #[test]
+    fn target_pixel_file_should_conform() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        assert_eq!(validate(&fits), vec!());
+    }
+
+    #[test]
+    fn mandatory_keywords_should_be_in_order() {
+        let primary = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(12i64), Option::None),
+        ));
+        let extension = Header::new(vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(4i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::PCOUNT, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::GCOUNT, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString("3Z".into()), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(1i64), Option::None),
+        ));
+        let fits = Fits::new(HDU::new(primary), vec!(HDU::new(extension)));
+
+        assert_eq!(validate(&fits), vec!(
+            Finding::error(0, Option::None, "NAXIS1 is missing".to_string()),
+            Finding::error(0, Option::Some(2), "NAXIS must be card 3".to_string()),
+            Finding::error(0, Option::Some(3), "BITPIX must be card 2".to_string()),
+            Finding::error(0, Option::Some(3), "BITPIX must be one of 8, 16, 32, 64, -32 or -64, not 12".to_string()),
+            Finding::error(1, Option::Some(9), "TFORM1 is not a valid format".to_string()),
+            Finding::warning(1, Option::Some(10), "TFIELDS already appears in card 8".to_string()),
+        ));
+    }
+
+    #[test]
+    fn wcs_keywords_should_be_consistent() {
+        let header = template("SIMPLE = T
+BITPIX = 8
+NAXIS = 2
+NAXIS1 = 10
+NAXIS2 = 10
+CTYPE1 = 'RA---TAN'
+CTYPE2 = 'DEC--TAN'
+CRVAL1 = 10.0
+CD1_1 = 1.0
+PC1_1 = 1.0
+CTYPE3A = 'FREQ'
+WCSAXESA = 2
+CRVAL3A = 1.0E9").unwrap();
+        let fits = Fits::new(HDU::new(header), vec!());
+
+        assert_eq!(validate(&fits), vec!(
+            Finding::warning(0, Option::Some(7), "CTYPE2 declares axis 2 without CRVAL2".to_string()),
+            Finding::error(0, Option::Some(10), "PC1_1 can not be combined with CD1_1 in card 9".to_string()),
+            Finding::error(0, Option::Some(11), "CTYPE3A refers to axis 3 beyond WCSAXESA = 2".to_string()),
+            Finding::error(0, Option::Some(12), "WCSAXESA must precede the WCS keywords indexed by axis, like card 11".to_string()),
+            Finding::error(0, Option::Some(13), "CRVAL3A refers to axis 3 beyond WCSAXESA = 2".to_string()),
+        ));
+    }
+
+    #[test]
+    fn mandatory_keywords_should_be_in_fixed_format() {
+        let cards = [
+            "SIMPLE  =                    T",
+            "BITPIX  = 8",
+            "NAXIS   =                    0",
+            "EXTEND  = T",
+            "END",
+            "XTENSION= 'BINTABLE'",
+            "BITPIX  =                    8",
+            "NAXIS   =                    2",
+            "NAXIS1  =                    0 / free comment",
+            "NAXIS2  =                    0",
+            "PCOUNT  =                    0",
+            "GCOUNT  =                    1",
+            "TFIELDS =                    1",
+            "TFORM1  =  '1J      '",
+            "END",
+        ];
+        let mut input = vec!();
+        for card in cards.iter() {
+            input.extend_from_slice(format!("{:<80}", card).as_bytes());
+            if *card == "END" {
+                input.resize(input.len().div_ceil(2880) * 2880, b' ');
+            }
+        }
+
+        assert_eq!(fixed_format(&input), vec!(
+            Finding::warning(0, Option::Some(2), "BITPIX must have its value right-justified to byte 30".to_string()),
+            Finding::warning(1, Option::Some(9), "TFORM1 must start its value with a quote in byte 11".to_string()),
+        ));
+    }
+
+    #[test]
+    fn findings_should_display_with_card_numbers() {
+        assert_eq!(Finding::error(1, Option::Some(3), "BITPIX must be card 2".to_string()).to_string(),
+                   "HDU 1, card 3: error: BITPIX must be card 2");
+        assert_eq!(Finding::warning(0, Option::None, "no data".to_string()).to_string(),
+                   "HDU 0: warning: no data");
+    }
+}
@@ -0,0 +1,92 @@
+//! Conformance profiles, the sets of lints archives with different tolerances
+//! validate with.
+
+use super::{Dates, Finding, Lint, Units, Validator};
+use super::super::types::{Fits, HDU, Keyword};
+
+/// How strictly files are validated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Profile {
+    /// Pure FITS 4.0: the lints of `Validator::new`, standard units and dates,
+    /// and warnings about keywords of conventions, see `ConventionKeywords`.
+    Standard,
+    /// The lints of `Validator::new`, which accept the keywords of common
+    /// conventions, like `HIERARCH`, `CONTINUE`, `INHERIT` and those of tile
+    /// compression, without warnings.
+    Conventions,
+}
+
+impl Validator {
+    /// A validator with the lints of `profile`.
+    pub fn for_profile(profile: Profile) -> Validator {
+        match profile {
+            Profile::Standard => Validator::new()
+                .with_lint(Units)
+                .with_lint(Dates)
+                .with_lint(ConventionKeywords),
+            Profile::Conventions => Validator::new(),
+        }
+    }
+}
+
+/// The conventions `ConventionKeywords` recognizes, by the name of their
+/// keywords. A name ending in `*` also matches the name followed by digits.
+const CONVENTIONS: &[(&str, &[&str])] = &[
+    ("the HIERARCH convention", &["HIERARCH"]),
+    ("the long string convention", &["CONTINUE", "LONGSTRN"]),
+    ("the inheritance convention", &["INHERIT"]),
+    ("the tile compression convention", &[
+        "ZIMAGE", "ZCMPTYPE", "ZBITPIX", "ZNAXIS*", "ZTILE*", "ZNAME*", "ZVAL*", "ZQUANTIZ", "ZDITHER0",
+        "ZMASKCMP", "ZSIMPLE", "ZTENSION", "ZEXTEND", "ZBLOCKED", "ZPCOUNT", "ZGCOUNT", "ZHECKSUM",
+        "ZDATASUM", "ZBLANK", "ZSCALE", "ZZERO",
+    ]),
+];
+
+/// Keywords of conventions outside of the standard are warned about, once per
+/// convention and HDU, at their first card.
+pub struct ConventionKeywords;
+
+impl Lint for ConventionKeywords {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        for &(convention, names) in CONVENTIONS {
+            let first = hdu.header.records().iter().enumerate().find(|&(_, record)| matches(record.keyword(), names));
+            if let Option::Some((position, record)) = first {
+                findings.push(Finding::warning(index, Option::Some(position + 1),
+                                               format!("{} belongs to {}, which is not part of the standard", record.keyword(), convention)));
+            }
+        }
+    }
+}
+
+fn matches(keyword: &Keyword, names: &[&str]) -> bool {
+    let name = keyword.to_string();
+    names.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Option::Some(prefix) => name.strip_prefix(prefix).is_some_and(|digits| digits.bytes().all(|b| b.is_ascii_digit())),
+        Option::None => name == *pattern,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::super::types::{Fits, HDU};
+    use super::super::{Finding, Validator};
+    use super::Profile;
+
+    #[test]
+    fn profiles_should_differ_in_their_tolerance_of_conventions() {
+        let primary = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 0\nEXTEND = T").unwrap();
+        let extension = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 0\nNAXIS2 = 0\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 0\nINHERIT = T\nZIMAGE = T\nZNAXIS1 = 10\nZTILE1 = 10\nBUNIT = 'ADU'").unwrap();
+        let fits = Fits::new(HDU::new(primary), vec!(HDU::new(extension)));
+
+        let standard = Validator::for_profile(Profile::Standard).validate(&fits);
+        let conventions = Validator::for_profile(Profile::Conventions).validate(&fits);
+
+        assert_eq!(conventions, vec!());
+        assert_eq!(standard, vec!(
+            Finding::warning(1, Option::Some(9), "INHERIT belongs to the inheritance convention, which is not part of the standard".to_string()),
+            Finding::warning(1, Option::Some(10), "ZIMAGE belongs to the tile compression convention, which is not part of the standard".to_string()),
+            Finding::warning(1, Option::Some(13), "BUNIT 'ADU' is not a standard unit: unknown unit ADU".to_string()),
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+//! Checks of unit strings against the grammar of units of the standard,
+//! section 4.3: units with optional prefixes, separated by spaces, `.`, `*`
+//! or `/`, raised to powers like `m2`, `s-1`, `s**-1` or `m^(1/2)`, grouped
+//! by parentheses, the functions `log`, `ln`, `exp` and `sqrt`, and a leading
+//! scale factor like `10**-7`.
+
+use std::fmt::{Display, Error, Formatter};
+use super::{Finding, Lint};
+use super::super::types::{Fits, HDU, Keyword, Value};
+
+/// The units that may not have a prefix.
+const UNITS: &[&str] = &[
+    "deg", "arcmin", "arcsec", "mas", "min", "h", "d", "solMass", "u", "solLum", "Angstrom", "solRad",
+    "AU", "lyr", "count", "ct", "photon", "ph", "Sun", "chan", "bin", "voxel", "adu", "beam", "pixel", "pix",
+];
+
+/// The units that may have a prefix.
+const PREFIXED_UNITS: &[&str] = &[
+    "m", "g", "s", "rad", "sr", "K", "A", "mol", "cd", "Hz", "J", "W", "V", "N", "Pa", "C", "Ohm", "S",
+    "F", "Wb", "T", "H", "lm", "lx", "a", "yr", "eV", "erg", "Ry", "Jy", "mag", "R", "G", "pc", "barn",
+    "D", "bit", "byte",
+];
+
+/// The prefixes for decimal multiples, `da` before `d`.
+const PREFIXES: &[&str] = &[
+    "y", "z", "a", "f", "p", "n", "u", "m", "c", "da", "d", "h", "k", "M", "G", "T", "P", "E", "Z", "Y",
+];
+
+const FUNCTIONS: &[&str] = &["log", "ln", "exp", "sqrt"];
+
+/// Check that `unit` is a unit string of the standard. The empty string is
+/// dimensionless and valid.
+pub fn check_unit(unit: &str) -> Result<(), UnitError> {
+    let mut parser = UnitParser { input: unit.trim().as_bytes(), position: 0 };
+    if parser.input.is_empty() {
+        return Ok(())
+    }
+    parser.scale()?;
+    parser.product()?;
+    match parser.peek() {
+        Option::None => Ok(()),
+        Option::Some(_) => Err(UnitError::Syntax(parser.position)),
+    }
+}
+
+struct UnitParser<'u> {
+    input: &'u [u8],
+    position: usize,
+}
+
+impl<'u> UnitParser<'u> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).cloned()
+    }
+
+    fn eat(&mut self, token: &[u8]) -> bool {
+        if self.input[self.position..].starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// An optional leading factor `10**k`, `10^k` or `10+k`, followed by a
+    /// space.
+    fn scale(&mut self) -> Result<(), UnitError> {
+        if !self.eat(b"10") {
+            return Ok(())
+        }
+        if !(self.eat(b"**") || self.eat(b"^") || matches!(self.peek(), Option::Some(b'+') | Option::Some(b'-'))) {
+            return Err(UnitError::Syntax(self.position))
+        }
+        self.exponent()?;
+        self.eat(b" ");
+        Ok(())
+    }
+
+    /// Terms separated by spaces, `.`, `*` or `/`.
+    fn product(&mut self) -> Result<(), UnitError> {
+        self.term()?;
+        while let Option::Some(b' ') | Option::Some(b'.') | Option::Some(b'*') | Option::Some(b'/') = self.peek() {
+            self.position += 1;
+            self.term()?;
+        }
+        Ok(())
+    }
+
+    /// A unit, a function or a parenthesized product, with an optional power.
+    fn term(&mut self) -> Result<(), UnitError> {
+        if self.eat(b"(") {
+            self.product()?;
+            if !self.eat(b")") {
+                return Err(UnitError::Syntax(self.position))
+            }
+        } else {
+            let start = self.position;
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                self.position += 1;
+            }
+            let name = String::from_utf8_lossy(&self.input[start..self.position]).into_owned();
+            if name.is_empty() {
+                return Err(UnitError::Syntax(self.position))
+            }
+            if FUNCTIONS.contains(&name.as_str()) && self.peek() == Option::Some(b'(') {
+                return self.term()
+            }
+            if !is_unit(&name) {
+                return Err(UnitError::UnknownUnit(name))
+            }
+        }
+        if self.eat(b"**") || self.eat(b"^") || self.peek().is_some_and(|c| c.is_ascii_digit() || c == b'+' || c == b'-') {
+            self.exponent()?;
+        }
+        Ok(())
+    }
+
+    /// A signed integer, or a signed number or fraction in parentheses.
+    fn exponent(&mut self) -> Result<(), UnitError> {
+        let parenthesized = self.eat(b"(");
+        if !(self.eat(b"+") || self.eat(b"-") || self.peek().is_some_and(|c| c.is_ascii_digit())) {
+            return Err(UnitError::Syntax(self.position))
+        }
+        self.digits()?;
+        if parenthesized {
+            if self.eat(b".") || self.eat(b"/") {
+                self.digits()?;
+            }
+            if !self.eat(b")") {
+                return Err(UnitError::Syntax(self.position))
+            }
+        }
+        Ok(())
+    }
+
+    fn digits(&mut self) -> Result<(), UnitError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if self.position == start {
+            return Err(UnitError::Syntax(self.position))
+        }
+        Ok(())
+    }
+}
+
+fn is_unit(name: &str) -> bool {
+    UNITS.contains(&name) || PREFIXED_UNITS.contains(&name) || PREFIXES.iter().any(|prefix| {
+        name.strip_prefix(prefix).is_some_and(|unit| PREFIXED_UNITS.contains(&unit))
+    })
+}
+
+/// Why a unit string is not a unit of the standard.
+#[derive(Debug, PartialEq)]
+pub enum UnitError {
+    /// The unit with this name, after any prefix, is not known.
+    UnknownUnit(String),
+    /// The string does not follow the grammar at this byte offset.
+    Syntax(usize),
+}
+
+impl Display for UnitError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            UnitError::UnknownUnit(ref name) => write!(f, "unknown unit {}", name),
+            UnitError::Syntax(position) => write!(f, "unexpected character at offset {}", position),
+        }
+    }
+}
+
+/// `BUNIT` and `TUNITn` should be units of the standard, so that readers can
+/// interpret them. Many files use other units, like `ADU/s` or `e-/s`, so
+/// these are warnings, and the lint is not one of the lints of `Validator::new`.
+pub struct Units;
+
+impl Lint for Units {
+    fn check(&self, _: &Fits, index: usize, hdu: &HDU, findings: &mut Vec<Finding>) {
+        for (position, record) in hdu.header.records().iter().enumerate() {
+            let unit = match (record.keyword(), record.value()) {
+                (&Keyword::BUNIT, &Value::CharacterString(ref unit)) | (&Keyword::TUNITn(_), &Value::CharacterString(ref unit)) => unit.trim(),
+                _ => continue,
+            };
+            if let Err(error) = check_unit(unit) {
+                findings.push(Finding::warning(index, Option::Some(position + 1),
+                                               format!("{} '{}' is not a standard unit: {}", record.keyword(), unit, error)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::super::types::{Fits, HDU};
+    use super::super::{Finding, Validator};
+    use super::{check_unit, UnitError, Units};
+
+    #[test]
+    fn units_should_follow_the_grammar() {
+        for unit in &["", "adu s-1", "erg/s/cm2/Angstrom", "km s**-1", "10**-7 J", "m^(1/2)", "log(Hz)", "(m.s)2", "mJy/beam", "daN"] {
+            assert_eq!(check_unit(unit), Ok(()), "{}", unit);
+        }
+        assert_eq!(check_unit("ADU/s"), Err(UnitError::UnknownUnit("ADU".to_string())));
+        assert_eq!(check_unit("e-/s"), Err(UnitError::UnknownUnit("e".to_string())));
+        assert_eq!(check_unit("kdeg"), Err(UnitError::UnknownUnit("kdeg".to_string())));
+        assert_eq!(check_unit("m**"), Err(UnitError::Syntax(3)));
+        assert_eq!(check_unit("(m s"), Err(UnitError::Syntax(4)));
+    }
+
+    #[test]
+    fn units_lint_should_warn_about_non_standard_units() {
+        let header = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 0\nBUNIT = 'ADU/s'\nTUNIT1 = 'adu s-1'").unwrap();
+        let fits = Fits::new(HDU::new(header), vec!());
+
+        assert_eq!(Validator::empty().with_lint(Units).validate(&fits), vec!(
+            Finding::warning(0, Option::Some(4), "BUNIT 'ADU/s' is not a standard unit: unknown unit ADU".to_string()),
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+//! Deferred parsing of headers, for readers that only look at a few keywords.
+//!
+//! A `LazyHeader` splits every card into its keyword and the raw bytes of its
+//! value and comment. The value is parsed when it is first asked for.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use std::str;
+use nom::{ErrorKind, IResult, Needed};
+use super::super::types::{self, Header, Keyword, KeywordRecord, Value, ValueRetrievalError};
+use super::{blank_record, end_record, header_length, join_continued, keyword, padded_data_size, valuecomment};
+
+/// A card of which only the keyword is parsed.
+#[derive(Debug)]
+pub struct LazyCard<'a> {
+    keyword: Keyword,
+    field: &'a [u8],
+    parsed: OnceLock<(Value<'a>, Option<&'a str>)>,
+}
+
+impl<'a> LazyCard<'a> {
+    /// The keyword of this card.
+    pub fn keyword(&self) -> &Keyword {
+        &self.keyword
+    }
+
+    /// The value of this card, parsed on first use.
+    pub fn value(&self) -> &Value<'a> {
+        &self.parse().0
+    }
+
+    /// The comment of this card, or the text of a commentary card.
+    pub fn comment(&self) -> Option<&'a str> {
+        self.parse().1
+    }
+
+    /// Whether the value of this card has been parsed.
+    pub fn is_parsed(&self) -> bool {
+        self.parsed.get().is_some()
+    }
+
+    /// This card as a fully parsed record.
+    pub fn record(&self) -> KeywordRecord<'a> {
+        KeywordRecord::new(self.keyword.clone(), self.value().clone(), self.comment().map(Cow::Borrowed))
+    }
+
+    fn parse(&self) -> &(Value<'a>, Option<&'a str>) {
+        self.parsed.get_or_init(|| {
+            match valuecomment(self.field) {
+                IResult::Done(_, (value, comment)) => (value, comment.map(|c| c.trim())),
+                _ => unreachable!("every field of 70 bytes has a value"),
+            }
+        })
+    }
+}
+
+/// A header of which the values are parsed when they are first asked for.
+#[derive(Debug)]
+pub struct LazyHeader<'a> {
+    cards: Vec<LazyCard<'a>>,
+}
+
+impl<'a> LazyHeader<'a> {
+    /// The cards of this header, in header order, without the `END` card.
+    pub fn cards(&self) -> &[LazyCard<'a>] {
+        &self.cards
+    }
+
+    /// Retrieve the value associated with the first occurrence of `keyword`.
+    pub fn value_of(&self, keyword: &Keyword) -> Result<Value<'a>, ValueRetrievalError> {
+        self.first_value_of(keyword).cloned()
+    }
+
+    fn first_value_of(&self, keyword: &Keyword) -> Result<&Value<'a>, ValueRetrievalError> {
+        self.cards.iter()
+            .find(|card| card.keyword == *keyword)
+            .map(LazyCard::value)
+            .ok_or(ValueRetrievalError::KeywordNotPresent)
+    }
+
+    /// Retrieve the integer value associated with `keyword`.
+    pub fn integer_value_of(&self, keyword: &Keyword) -> Result<i64, ValueRetrievalError> {
+        self.value_of(keyword).and_then(types::integer)
+    }
+
+    /// Retrieve the real value associated with `keyword`, promoting integers.
+    pub fn real_value_of(&self, keyword: &Keyword) -> Result<f64, ValueRetrievalError> {
+        self.value_of(keyword).and_then(types::real)
+    }
+
+    /// Retrieve the logical value associated with `keyword`.
+    pub fn logical_value_of(&self, keyword: &Keyword) -> Result<bool, ValueRetrievalError> {
+        self.value_of(keyword).and_then(types::logical)
+    }
+
+    /// Retrieve the complex value associated with `keyword`.
+    pub fn complex_value_of(&self, keyword: &Keyword) -> Result<(f64, f64), ValueRetrievalError> {
+        self.value_of(keyword).and_then(types::complex)
+    }
+
+    /// Retrieve the character string value associated with `keyword`.
+    pub fn character_string_value_of(&self, keyword: &Keyword) -> Result<&str, ValueRetrievalError> {
+        self.first_value_of(keyword).and_then(types::character_string)
+    }
+
+    /// Determines the size in bytes of the data array following this header,
+    /// without the padding to whole blocks. Only the cards that size the data
+    /// array are parsed.
+    pub fn data_size(&self) -> usize {
+        self.sizing().data_size()
+    }
+
+    /// Determines the size in bits of the data array following this header,
+    /// including the padding to whole blocks.
+    pub fn data_array_size(&self) -> usize {
+        self.sizing().data_array_size()
+    }
+
+    /// The size in bytes of the data array following this header, see
+    /// `Header::data_length`.
+    pub fn data_length(&self) -> Option<u64> {
+        self.sizing().data_length()
+    }
+
+    /// The size in bytes of the data array following this header, including
+    /// the padding, see `Header::padded_data_length`.
+    pub fn padded_data_length(&self) -> Option<u64> {
+        self.sizing().padded_data_length()
+    }
+
+    /// Parse every card into a `Header`, joining long strings continued over
+    /// `CONTINUE` cards.
+    pub fn to_header(&self) -> Header<'a> {
+        Header::new(join_continued(self.cards.iter().map(LazyCard::record).collect()))
+    }
+
+    fn sizing(&self) -> Header<'a> {
+        Header::new(self.cards.iter()
+            .filter(|card| matches!(card.keyword,
+                Keyword::SIMPLE | Keyword::BITPIX | Keyword::NAXIS | Keyword::NAXISn(_) |
+                Keyword::PCOUNT | Keyword::GCOUNT))
+            .map(LazyCard::record)
+            .collect())
+    }
+}
+
+/// An HDU with a `LazyHeader`.
+#[derive(Debug)]
+pub struct LazyHDU<'a> {
+    /// The header of this HDU.
+    pub header: LazyHeader<'a>,
+    data: &'a [u8],
+}
+
+impl<'a> LazyHDU<'a> {
+    /// The bytes of the data array of this HDU, without the padding.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// Will parse a header like `header`, but defers parsing the values of its
+/// cards until they are asked for.
+pub fn lazy_header<'a>(input: &'a [u8]) -> IResult<&'a [u8], LazyHeader<'a>> {
+    let mut cards = Vec::with_capacity(header_length(input).unwrap_or(0) / 80);
+    let mut position = 0;
+    loop {
+        let card = match input.get(position..position + 80) {
+            Option::Some(card) => card,
+            Option::None => return IResult::Incomplete(Needed::Size(position + 80)),
+        };
+        position += 80;
+        if let IResult::Done(_, _) = end_record(card) {
+            break
+        }
+        let (_, key) = try_parse!(card, keyword);
+        if key.is_commentary() {
+            let text = match str::from_utf8(&card[8..]) {
+                Ok(text) => text.trim_end(),
+                Err(_) => return IResult::Error(error_position!(ErrorKind::MapRes, card)),
+            };
+            cards.push(LazyCard { keyword: key, field: &card[10..], parsed: OnceLock::from((Value::Undefined, Option::Some(text))) });
+        } else {
+            try_parse!(&card[8..10], tag!(if key == Keyword::CONTINUE { "  " } else { "= " }));
+            cards.push(LazyCard { keyword: key, field: &card[10..], parsed: OnceLock::new() });
+        }
+    }
+    while let Option::Some(IResult::Done(_, _)) = input.get(position..).map(blank_record) {
+        position += 80;
+    }
+    IResult::Done(&input[position..], LazyHeader { cards })
+}
+
+/// Will parse the HDUs of a FITS file with `lazy_header`. The HDUs before the
+/// first one that could not be parsed are returned.
+pub fn lazy_fits<'a>(input: &'a [u8]) -> IResult<&'a [u8], Vec<LazyHDU<'a>>> {
+    let mut hdus = vec!();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (after_header, header) = match lazy_header(rest) {
+            IResult::Done(after_header, header) => (after_header, header),
+            _ if !hdus.is_empty() => break,
+            IResult::Error(error) => return IResult::Error(error),
+            IResult::Incomplete(needed) => return IResult::Incomplete(needed),
+        };
+        let sizing = header.sizing();
+        let size = padded_data_size(&sizing);
+        if after_header.len() < size {
+            break
+        }
+        let data = &after_header[..sizing.data_size()];
+        hdus.push(LazyHDU { header, data });
+        rest = &after_header[size..];
+    }
+    IResult::Done(rest, hdus)
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::types::{Keyword, ValueRetrievalError};
+    use super::super::fits;
+    use super::{lazy_fits, lazy_header};
+
+    #[test]
+    fn lazy_fits_should_parse_like_fits() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let (lazy, eager) = match (lazy_fits(data), fits(data)) {
+            (IResult::Done(_, lazy), IResult::Done(_, eager)) => (lazy, eager),
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        assert_eq!(lazy.len(), eager.len());
+        for (lazy, eager) in lazy.iter().zip(eager.iter()) {
+            assert_eq!(lazy.header.to_header(), eager.header);
+            assert_eq!(lazy.data(), eager.data());
+        }
+    }
+
+    #[test]
+    fn values_should_only_be_parsed_when_asked_for() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let header = match lazy_header(data) {
+            IResult::Done(_, header) => header,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let header = &header;
+        let parsed = |header: &super::LazyHeader| header.cards().iter().filter(|card| card.is_parsed() && !card.keyword().is_commentary()).count();
+        assert_eq!(parsed(header), 0);
+
+        assert_eq!(header.character_string_value_of(&Keyword::TELESCOP), Ok("Kepler  "));
+        assert_eq!(header.integer_value_of(&Keyword::TELESCOP), Err(ValueRetrievalError::NotAnInteger));
+        assert_eq!(header.value_of(&Keyword::THEAP), Err(ValueRetrievalError::KeywordNotPresent));
+        assert_eq!(parsed(header), 1);
+    }
+}
@@ -1,9 +1,29 @@
 //! The parser module is responsible for parsing FITS files.
 
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::str;
 use std::str::FromStr;
-use nom::{is_space, is_digit};
-use super::types::{Fits, HDU, Header, KeywordRecord, Keyword, Value, BlankRecord};
+use memchr::memmem;
+use nom::{is_space, is_digit, ErrorKind, IResult, Needed};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+#[cfg(feature = "bytes")]
+use super::types::SharedFits;
+use super::types::{Fits, OwnedFits, HDU, Header, KeywordRecord, Keyword, Value, BlankRecord};
+
+mod lazy;
+mod options;
+mod stream;
+
+pub use self::lazy::{lazy_fits, lazy_header, LazyCard, LazyHDU, LazyHeader};
+pub use self::options::{fits_with_options, DuplicateKeyword, DuplicatePolicy, FitsParser, ParseError, ParseOptions, Parsed};
+pub use self::stream::{HeaderParser, READ_BLOCKS};
 
 named!(#[doc = "Will parse data from a FITS file into a `Fits` structure"], pub fits<&[u8], Fits>,
        do_parse!(
@@ -12,28 +32,95 @@ named!(#[doc = "Will parse data from a FITS file into a `Fits` structure"], pub
                (Fits::new(hdu, extensions))
        ));
 
-named!(hdu<&[u8], HDU>,
-       do_parse!(
-           h: header >>
-               take!(h.data_array_size()/8) >>
-               (HDU::new(h))
-       ));
+fn hdu(input: &[u8]) -> IResult<&[u8], HDU<'_>> {
+    let (rest, h) = try_parse!(input, header);
+    let header_bytes = &input[..input.len() - rest.len()];
+    let (rest, data) = try_parse!(rest, take!(padded_data_size(&h)));
+    IResult::Done(rest, HDU::parsed(h, header_bytes, data))
+}
 
 named!(header<&[u8], Header>,
        do_parse!(
-           records: many0!(keyword_record) >>
+           records: many0!(record) >>
                end_record >>
                many0!(blank_record) >>
-               (Header::new(records))
+               (Header::new(join_continued(records)))
        ));
 
-named!(keyword_record<&[u8], KeywordRecord>,
-       do_parse!(
-           key: keyword  >>
-               tag!("= ") >>
-           vc: valuecomment >>
-               (KeywordRecord::new(key, vc.0, vc.1.map(|c| c.trim() )))
-       ));
+/// Will parse a single header from `input`, up to and including the padding
+/// after its `END` card. The remainder starts with the data array of the
+/// header, which is `Header::padded_data_length` bytes long, so that callers can
+/// walk a file header by header.
+pub fn parse_header<'a>(input: &'a [u8]) -> IResult<&'a [u8], Header<'a>> {
+    header(input)
+}
+
+/// Joins long string values continued over `CONTINUE` records, as in section
+/// 4.2.1.2 of the standard: a string ending in `&` continues with the string
+/// of the following `CONTINUE` record. The comments are joined by spaces.
+/// `CONTINUE` records that do not continue a string are kept.
+fn join_continued(records: Vec<KeywordRecord>) -> Vec<KeywordRecord> {
+    if !records.iter().any(|record| *record.keyword() == Keyword::CONTINUE) {
+        return records
+    }
+    let mut joined: Vec<KeywordRecord> = Vec::with_capacity(records.len());
+    for record in records {
+        if *record.keyword() == Keyword::CONTINUE {
+            if let Option::Some(previous) = joined.last_mut().filter(|previous| continues(previous.value())) {
+                if let Value::CharacterString(ref text) = *record.value() {
+                    let value = match *previous.value() {
+                        Value::CharacterString(ref head) => {
+                            let head = head.trim_end();
+                            format!("{}{}", &head[..head.len() - 1], text)
+                        }
+                        _ => unreachable!("only character strings continue"),
+                    };
+                    let comment = match (previous.comment(), record.comment()) {
+                        (Option::Some(head), Option::Some(tail)) if !head.is_empty() && !tail.is_empty() => Option::Some(Cow::Owned(format!("{} {}", head, tail))),
+                        (Option::Some(head), _) if !head.is_empty() => Option::Some(Cow::Owned(head.to_string())),
+                        (_, tail) => tail.map(|tail| Cow::Owned(tail.to_string())),
+                    };
+                    *previous = KeywordRecord::new(previous.keyword().clone(), Value::CharacterString(Cow::Owned(value)), comment);
+                    continue;
+                }
+            }
+        }
+        joined.push(record);
+    }
+    joined
+}
+
+/// Whether `value` is a character string continued by a `CONTINUE` record.
+fn continues(value: &Value) -> bool {
+    match *value {
+        Value::CharacterString(ref text) => text.trim_end().ends_with('&'),
+        _ => false,
+    }
+}
+
+/// Parses a card by its fixed regions: the keyword in bytes 1 to 8, the value
+/// indicator in bytes 9 and 10 and the value and comment in the remaining 70
+/// bytes. The keyword is parsed once and decides how the rest is read.
+fn record<'a>(input: &'a [u8]) -> IResult<&'a [u8], KeywordRecord<'a>> {
+    if input.len() < 80 {
+        return IResult::Incomplete(Needed::Size(80))
+    }
+    let (card, rest) = input.split_at(80);
+    let (_, key) = try_parse!(&card[..8], keyword);
+    if key.is_commentary() {
+        return match str::from_utf8(&card[8..]) {
+            Ok(text) => IResult::Done(rest, KeywordRecord::new(key, Value::Undefined, Option::Some(Cow::Borrowed(text.trim_end())))),
+            Err(_) => IResult::Error(error_position!(ErrorKind::MapRes, input)),
+        }
+    }
+    if key == Keyword::CONTINUE {
+        try_parse!(&card[8..10], tag!("  "));
+    } else {
+        try_parse!(&card[8..10], tag!("= "));
+    }
+    let (_, (value, comment)) = try_parse!(&card[10..], valuecomment);
+    IResult::Done(rest, KeywordRecord::new(key, value, comment.map(|c| Cow::Borrowed(c.trim()))))
+}
 
 named!(keyword<&[u8], Keyword>,
        map_res!(
@@ -43,29 +130,92 @@ named!(keyword<&[u8], Keyword>,
            Keyword::from_str
        ));
 
-named!(valuecomment<&[u8], (Value, Option<&str>)>,
-       flat_map!(
-           take!(70),
-           pair!(
-               value,
-               opt!(complete!(comment))
-           )));
+/// Parses the 70 bytes of value and optional comment of a card.
+fn valuecomment<'a>(input: &'a [u8]) -> IResult<&'a [u8], (Value<'a>, Option<&'a str>)> {
+    if input.len() < 70 {
+        return IResult::Incomplete(Needed::Size(70))
+    }
+    let (field, rest) = input.split_at(70);
+    let (after_value, value) = try_parse!(field, value);
+    let comment = match comment(after_value) {
+        IResult::Done(_, comment) => Option::Some(comment),
+        _ => Option::None,
+    };
+    IResult::Done(rest, (value, comment))
+}
+
+/// Parses a value with the parser its first byte calls for, falling back to
+/// trying every parser in turn.
+fn value<'a>(input: &'a [u8]) -> IResult<&'a [u8], Value<'a>> {
+    let parsed = match input.iter().find(|&&b| b != b' ') {
+        Option::Some(&b'\'') => character_string(input),
+        Option::Some(&b'T') | Option::Some(&b'F') => logical_constant(input),
+        Option::Some(&b'/') | Option::None => undefined(input),
+        Option::Some(_) => number(input),
+    };
+    match parsed {
+        IResult::Done(_, _) => parsed,
+        _ => any_value(input),
+    }
+}
+
+/// Parses a real when the number has a decimal point or an exponent, an
+/// integer otherwise.
+fn number<'a>(input: &'a [u8]) -> IResult<&'a [u8], Value<'a>> {
+    let is_real = input.iter()
+        .skip_while(|&&b| b == b' ')
+        .take_while(|&&b| b != b' ' && b != b'/')
+        .any(|&b| b == b'.' || b == b'E' || b == b'D');
+    if is_real {
+        real(input)
+    } else {
+        integer(input)
+    }
+}
 
-named!(value<&[u8], Value>,
+named!(any_value<&[u8], Value>,
        alt_complete!(character_string | logical_constant | real | integer | undefined));
 
-named!(character_string<&[u8], Value>,
-       map!(
-           map_res!(
-               ws!(delimited!(
-                   tag!("'"),
-                   take_while!(is_allowed_in_character_string),
-                   tag!("'")
-               )),
-               str::from_utf8
-           ),
-           Value::CharacterString
-       ));
+/// Parses a character string between single quotes. Like the other values,
+/// white space around the quotes is skipped, including directly inside them.
+///
+/// A quote inside the string is written as two quotes. The string is borrowed
+/// from `input` unless it contains such quotes, which are unescaped.
+fn character_string<'a>(input: &'a [u8]) -> IResult<&'a [u8], Value<'a>> {
+    let open = white_space(input, 0);
+    if input.get(open) != Option::Some(&b'\'') {
+        return IResult::Error(error_position!(ErrorKind::Tag, input))
+    }
+    let start = white_space(input, open + 1);
+    let mut end = start;
+    let mut escaped = false;
+    loop {
+        end += input[end..].iter().take_while(|&&b| is_allowed_in_character_string(b)).count();
+        if input.get(end) == Option::Some(&b'\'') && input.get(end + 1) == Option::Some(&b'\'') {
+            end += 2;
+            escaped = true;
+        } else {
+            break
+        }
+    }
+    let close = white_space(input, end);
+    if input.get(close) != Option::Some(&b'\'') {
+        return IResult::Error(error_position!(ErrorKind::Tag, input))
+    }
+    match str::from_utf8(&input[start..end]) {
+        Ok(text) => {
+            let text = if escaped { Cow::Owned(text.replace("''", "'")) } else { Cow::Borrowed(text) };
+            IResult::Done(&input[white_space(input, close + 1)..], Value::CharacterString(text))
+        }
+        Err(_) => IResult::Error(error_position!(ErrorKind::MapRes, input)),
+    }
+}
+
+/// The position of the first byte from `start` on in `input` that is not
+/// white space, the separators `ws!` skips.
+fn white_space(input: &[u8], start: usize) -> usize {
+    start + input[start..].iter().take_while(|&&b| b == b' ' || b == b'\t' || b == b'\r' || b == b'\n').count()
+}
 
 fn is_allowed_in_character_string(chr: u8) -> bool {
     is_restricted_ascii(chr) && chr != 39
@@ -94,56 +244,88 @@ fn logical_constant_from_str(constant: &str) -> Result<Value, ParseLogicalConsta
     }
 }
 
-named!(integer<&[u8], Value>,
-       map!(
-           map_res!(
-               map_res!(
-                   ws!(take_while!(is_digit)), // TODO negative numbers, prefix zeroes
-                   str::from_utf8
-               ),
-               i64::from_str
-           ),
-           Value::Integer
-       ));
+/// Parses an integer: an optional sign and digits, surrounded by spaces.
+fn integer<'a>(input: &'a [u8]) -> IResult<&'a [u8], Value<'a>> {
+    let start = spaces(input, 0);
+    let integral = signed(input, start);
+    let end = digits(input, integral);
+    if end == integral {
+        return IResult::Error(error_position!(ErrorKind::TakeWhile1, input))
+    }
+    match str::from_utf8(&input[start..end]).ok().and_then(|integer| i64::from_str(integer).ok()) {
+        Option::Some(integer) => IResult::Done(&input[spaces(input, end)..], Value::Integer(integer)),
+        Option::None => IResult::Error(error_position!(ErrorKind::MapRes, input)),
+    }
+}
 
-named!(real<&[u8], Value>,
-       map!(
-           map_res!(
-               ws!(tuple!(take_while!(is_digit), tag!("."), take_while!(is_digit))),
-               tuple_to_f64
-           ),
-           Value::Real
-       ));
+/// The position of the first byte from `start` on in `input` that is not a
+/// space or a tab.
+fn spaces(input: &[u8], start: usize) -> usize {
+    start + input[start..].iter().take_while(|&&b| is_space(b)).count()
+}
 
-/// Reasons for converting to a f64 from a parse triple (left, _, right) to fail.
+/// Parses a real: an optional sign and digits, followed by a decimal point
+/// and digits, an exponent, or both, surrounded by spaces. The exponent is
+/// introduced by `E` or `D`.
+fn real<'a>(input: &'a [u8]) -> IResult<&'a [u8], Value<'a>> {
+    let start = spaces(input, 0);
+    let integral = signed(input, start);
+    let mut end = digits(input, integral);
+    let fraction = input.get(end) == Option::Some(&b'.');
+    if fraction {
+        end = digits(input, end + 1);
+    }
+    end = match input.get(end) {
+        Option::Some(&b'E') | Option::Some(&b'D') => {
+            let exponent = signed(input, end + 1);
+            match digits(input, exponent) {
+                exponent_end if exponent_end > exponent => exponent_end,
+                _ if fraction => end,
+                _ => return IResult::Error(error_position!(ErrorKind::TakeWhile1, input)),
+            }
+        }
+        _ if fraction => end,
+        _ => return IResult::Error(error_position!(ErrorKind::Alt, input)),
+    };
+    match real_from_bytes(&input[start..end]) {
+        Ok(real) => IResult::Done(&input[spaces(input, end)..], Value::Real(real)),
+        Err(_) => IResult::Error(error_position!(ErrorKind::MapRes, input)),
+    }
+}
+
+/// The position in `input` after an optional sign at `start`.
+fn signed(input: &[u8], start: usize) -> usize {
+    match input.get(start) {
+        Option::Some(&b'+') | Option::Some(&b'-') => start + 1,
+        _ => start,
+    }
+}
+
+/// The position of the first byte from `start` on in `input` that is not a
+/// digit.
+fn digits(input: &[u8], start: usize) -> usize {
+    start + input[start..].iter().take_while(|&&b| is_digit(b)).count()
+}
+
+/// Reasons for converting to a f64 from the bytes of a real to fail.
 pub enum RealParseError {
-    /// When left is not parse-able as `str`.
-    IntegerPartUnparseable,
-    /// When right is not parse-able as `str`.
-    FractionalPartUnparseable,
-    /// When the combination is not a `f64`.
+    /// When the bytes are not parse-able as `str`.
+    Unparseable,
+    /// When the `str` is not a `f64`.
     NotARealNumber,
 }
 
-fn tuple_to_f64((left, _, right): (&[u8], &[u8], &[u8])) -> Result<f64, RealParseError> {
-    match str::from_utf8(left) {
-        Ok(integer_part) => {
-            match str::from_utf8(right) {
-                Ok(fractional_part) => {
-                    let mut number = String::from("");
-                    number.push_str(integer_part);
-                    number.push_str(".");
-                    number.push_str(fractional_part);
-
-                    match f64::from_str(&number) {
-                        Ok(result) => Ok(result),
-                        Err(_) => Err(RealParseError::NotARealNumber)
-                    }
-                }
-                Err(_) => Err(RealParseError::FractionalPartUnparseable)
-            }
+fn real_from_bytes(input: &[u8]) -> Result<f64, RealParseError> {
+    match str::from_utf8(input) {
+        Ok(representation) => {
+            let real = if representation.contains('D') {
+                f64::from_str(&representation.replace('D', "E"))
+            } else {
+                f64::from_str(representation)
+            };
+            real.map_err(|_| RealParseError::NotARealNumber)
         }
-        Err(_) => Err(RealParseError::IntegerPartUnparseable)
+        Err(_) => Err(RealParseError::Unparseable)
     }
 }
 
@@ -153,43 +335,427 @@ named!(undefined<&[u8], Value>,
            |_| { Value::Undefined}
        ));
 
-named!(comment<&[u8], &str>,
-       map_res!(
-           do_parse!(
-               tag!("/") >>
-                   comment: take_while!(is_restricted_ascii) >>
-                   (comment)
-           ),
-           str::from_utf8
-       ));
+/// Parses a comment, the restricted ASCII text following a `/`.
+fn comment(input: &[u8]) -> IResult<&[u8], &str> {
+    if !input.starts_with(b"/") {
+        return IResult::Error(error_position!(ErrorKind::Tag, input))
+    }
+    let end = input[1..].iter().position(|&b| !is_restricted_ascii(b)).map_or(input.len(), |length| length + 1);
+    match str::from_utf8(&input[1..end]) {
+        Ok(comment) => IResult::Done(&input[end..], comment),
+        Err(_) => IResult::Error(error_position!(ErrorKind::MapRes, input)),
+    }
+}
 
 fn is_restricted_ascii(chr: u8) -> bool {
     32u8 <= chr && chr <= 126u8
 }
 
-named!(end_record<&[u8], Keyword>,
-       map!(
-           flat_map!(
-               take!(80),
-               pair!(tag!("END"), many0!(tag!(" ")))
-           ),
-           |_| { Keyword::END }
-       ));
+/// Parses the `END` card, the rest of which is not looked at.
+fn end_record(input: &[u8]) -> IResult<&[u8], Keyword> {
+    if input.len() < 80 {
+        return IResult::Incomplete(Needed::Size(80))
+    }
+    if input.starts_with(b"END") {
+        IResult::Done(&input[80..], Keyword::END)
+    } else {
+        IResult::Error(error_position!(ErrorKind::Tag, input))
+    }
+}
 
-named!(blank_record<&[u8], BlankRecord>,
-       map!(
-           count!(tag!(" "), 80),
-           |_| { BlankRecord }
-       ));
+/// Parses a card of 80 spaces.
+fn blank_record(input: &[u8]) -> IResult<&[u8], BlankRecord> {
+    match input.iter().take(80).position(|&b| b != b' ') {
+        Option::Some(_) => IResult::Error(error_position!(ErrorKind::Tag, input)),
+        Option::None if input.len() < 80 => IResult::Incomplete(Needed::Size(80)),
+        Option::None => IResult::Done(&input[80..], BlankRecord),
+    }
+}
 
 named!(extensions<&[u8], Vec<HDU> >,
        many0!(hdu));
 
+/// The size in bytes of the data array following `header`, including the
+/// padding to whole blocks. Sizes that do not fit in memory saturate.
+fn padded_data_size(header: &Header) -> usize {
+    header.padded_data_length()
+        .and_then(|length| usize::try_from(length).ok())
+        .unwrap_or(usize::MAX)
+}
+
+/// The byte ranges of the HDUs in `input`, a FITS file: of each header and of
+/// the data array following it, both including their padding.
+///
+/// The ranges of the HDUs before the first one that could not be parsed are
+/// returned. The data array of the last HDU is cut short at the end of `input`.
+pub fn layout(input: &[u8]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut ranges = vec!();
+    let mut offset = 0;
+    while let IResult::Done(rest, h) = header(&input[offset..]) {
+        let data_start = input.len() - rest.len();
+        let data_end = data_start.saturating_add(padded_data_size(&h)).min(input.len());
+        ranges.push((offset..data_start, data_start..data_end));
+        offset = data_end;
+    }
+    ranges
+}
+
+/// The byte ranges of the HDUs in `file` like `layout`, as `u64` offsets from
+/// the start of the file.
+///
+/// Only the headers are read, the data arrays are skipped by seeking, so files
+/// larger than the address space, like files over 4 GiB on 32-bit targets, can
+/// be laid out. Reading stops at the end of the file or at the first header
+/// that could not be parsed.
+pub fn file_layout<R: Read + Seek>(file: &mut R) -> io::Result<Vec<(Range<u64>, Range<u64>)>> {
+    let length = file.seek(SeekFrom::End(0))?;
+    let mut ranges = vec!();
+    let mut offset = 0u64;
+    let mut block = [0u8; 2880];
+    while offset < length {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut bytes = vec!();
+        let header_end = loop {
+            match file.read_exact(&mut block) {
+                Ok(()) => bytes.extend_from_slice(&block),
+                Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(ranges),
+                Err(error) => return Err(error),
+            }
+            if let Option::Some(header_end) = header_length(&bytes) {
+                break header_end as u64
+            }
+        };
+        let padded_data_length = match header(&bytes) {
+            IResult::Done(_, h) => h.padded_data_length().unwrap_or(u64::MAX),
+            _ => return Ok(ranges),
+        };
+        let data_start = offset + header_end;
+        let data_end = data_start.saturating_add(padded_data_length).min(length);
+        ranges.push((offset..data_start, data_start..data_end));
+        offset = data_end;
+    }
+    Ok(ranges)
+}
+
+/// The entry point for fuzzers: runs the parsers of this module over `data`.
+///
+/// Whatever `data` holds, the parsers must return an error rather than panic,
+/// so a fuzz target only has to call this function. The `fuzz` directory holds
+/// a `cargo fuzz` target, `fuzz_parse`, that does.
+pub fn fuzz_parse(data: &[u8]) {
+    if let IResult::Done(_, parsed) = fits(data) {
+        for hdu in &parsed {
+            let _ = hdu.header.naxes();
+            let _ = hdu.header.to_string();
+        }
+    }
+    let _ = fits_with_options(data, &ParseOptions::new().with_lenient(true).with_keywords_case_insensitive(true));
+    let _ = lazy_fits(data);
+    let _ = layout(data);
+    let _ = header_length(data);
+    if let Ok(text) = str::from_utf8(data) {
+        let _ = template(text);
+    }
+}
+
+/// Will parse data from a FITS file into an `OwnedFits`, which copies the
+/// headers and data arrays so that it does not borrow from `input`.
+pub fn parse_owned(input: &[u8]) -> IResult<&[u8], OwnedFits> {
+    fits(input).map(|fits| OwnedFits::from(&fits))
+}
+
+/// Will parse data from a FITS file into a `SharedFits`, of which the data
+/// arrays are slices of `input` rather than copies.
+#[cfg(feature = "bytes")]
+pub fn parse_shared(input: &Bytes) -> IResult<&[u8], SharedFits> {
+    fits(input).map(|fits| SharedFits::from_fits(input, &fits))
+}
+
+/// Will parse data from a FITS file into a `Fits` structure like `fits`, but
+/// parses the headers of the HDUs in parallel on the rayon thread pool.
+///
+/// The HDUs are located by parsing only the cards that determine the size of
+/// their data arrays, which pays off for files with many extensions. Input
+/// that is not a sequence of whole HDUs is left to `fits`, so that both agree.
+#[cfg(feature = "rayon")]
+pub fn fits_parallel<'a>(input: &'a [u8]) -> IResult<&'a [u8], Fits<'a>> {
+    let boundaries = boundaries(input);
+    if boundaries.last().map(|(_, data_range)| data_range.end) != Option::Some(input.len()) {
+        return fits(input)
+    }
+    let hdus: Option<Vec<HDU>> = boundaries.par_iter()
+        .map(|(header_range, data_range)| {
+            match header(&input[header_range.clone()]) {
+                IResult::Done([], h) => Option::Some(HDU::parsed(h, &input[header_range.clone()], &input[data_range.clone()])),
+                _ => Option::None,
+            }
+        })
+        .collect();
+    match hdus {
+        Option::Some(hdus) => {
+            let mut hdus = hdus.into_iter();
+            let primary_hdu = hdus.next().expect("a primary HDU is located");
+            IResult::Done(&input[input.len()..], Fits::new(primary_hdu, hdus.collect()))
+        }
+        Option::None => fits(input),
+    }
+}
+
+/// The byte ranges of the header and of the data array of the HDUs in
+/// `input`, found by parsing only the cards that determine the size of the
+/// data arrays. Locating stops at the first HDU that does not fit in `input`.
+#[cfg(feature = "rayon")]
+fn boundaries(input: &[u8]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut ranges = vec!();
+    let mut offset = 0;
+    'hdus: while offset < input.len() {
+        let mut sizing = vec!();
+        let mut position = offset;
+        loop {
+            let card = match input.get(position..position + 80) {
+                Option::Some(card) => card,
+                Option::None => break 'hdus,
+            };
+            position += 80;
+            if let IResult::Done(_, _) = end_record(card) {
+                break
+            }
+            match keyword(card) {
+                IResult::Done(_, Keyword::SIMPLE) | IResult::Done(_, Keyword::BITPIX) |
+                IResult::Done(_, Keyword::NAXIS) | IResult::Done(_, Keyword::NAXISn(_)) |
+                IResult::Done(_, Keyword::PCOUNT) | IResult::Done(_, Keyword::GCOUNT) => {
+                    if let IResult::Done(_, record) = record(card) {
+                        sizing.push(record)
+                    }
+                }
+                _ => (),
+            }
+        }
+        while let Option::Some(IResult::Done(_, _)) = input.get(position..position + 80).map(blank_record) {
+            position += 80;
+        }
+        let size = padded_data_size(&Header::new(sizing));
+        if size > input.len() - position {
+            break
+        }
+        ranges.push((offset..position, position..position + size));
+        offset = position + size;
+    }
+    ranges
+}
+
+/// The length in bytes of the header at the start of `input`, up to the end of
+/// the block that holds its `END` card, without parsing the cards. `None` when
+/// `input` holds no `END` card.
+///
+/// The `END` card is found by searching for its keyword and only considering
+/// matches that start a card, which is a lot faster than parsing the cards up
+/// to it. The length may be past the end of `input` when the last block is
+/// cut short.
+pub fn header_length(input: &[u8]) -> Option<usize> {
+    memmem::find_iter(input, b"END     ")
+        .find(|position| position % 80 == 0)
+        .map(|position| (position / 2880 + 1) * 2880)
+}
+
+/// Constructs a `Header` from a header template.
+///
+/// A template has a record per line, in the free format of cfitsio header
+/// templates: `KEYWORD = value / comment`, or a commentary keyword followed by
+/// its text. Keywords are converted to uppercase, blank lines and the `END`
+/// record are ignored. `Header::to_template` produces this format.
+pub fn template<'a>(text: &'a str) -> Result<Header<'a>, TemplateError> {
+    let mut records = vec!();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let length = line.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(line.len());
+        let (name, rest) = line.split_at(length);
+        let key = Keyword::from_str(&name.to_uppercase())
+            .map_err(|_| TemplateError::InvalidKeyword(line_number))?;
+        if key == Keyword::END {
+            continue;
+        }
+        if key.is_commentary() {
+            records.push(KeywordRecord::new(key, Value::Undefined, Option::Some(Cow::Borrowed(rest.strip_prefix(' ').unwrap_or(rest)))));
+            continue;
+        }
+        let rest = rest.trim_start();
+        if !rest.starts_with('=') {
+            return Err(TemplateError::MissingValueIndicator(line_number))
+        }
+        match template_valuecomment(&rest.as_bytes()[1..]) {
+            IResult::Done(tail, (value, comment)) if tail.iter().all(|&c| is_space(c)) => {
+                records.push(KeywordRecord::new(key, value, comment.map(|c| Cow::Borrowed(c.trim()))));
+            }
+            _ => return Err(TemplateError::InvalidValue(line_number)),
+        }
+    }
+    Ok(Header::new(records))
+}
+
+/// Problems that could occur when constructing a `Header` from a template. Each
+/// variant holds the line number, starting at 1.
+#[derive(Debug, PartialEq)]
+pub enum TemplateError {
+    /// When a line does not start with a valid keyword.
+    InvalidKeyword(usize),
+    /// When a keyword that is not commentary is not followed by `=`.
+    MissingValueIndicator(usize),
+    /// When the value or comment could not be parsed.
+    InvalidValue(usize),
+}
+
+fn template_valuecomment<'a>(input: &'a [u8]) -> IResult<&'a [u8], (Value<'a>, Option<&'a str>)> {
+    pair!(input, value, opt!(complete!(comment)))
+}
+
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+    use std::env;
+    use std::fs;
+    use std::fs::File;
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+    use std::process;
     use nom::{IResult};
     use super::super::types::{HDU, Header, KeywordRecord, Keyword, Value, BlankRecord};
-    use super::{fits, header, keyword_record, keyword, valuecomment, character_string, logical_constant, real, integer, undefined, end_record, blank_record};
+    use super::{fits, file_layout, fuzz_parse, header_length, layout, parse_header, template, TemplateError, header, record, keyword, valuecomment, value, character_string, logical_constant, real, integer, undefined, end_record, blank_record};
+
+    #[test]
+    fn fuzz_parse_should_not_panic_on_malformed_input() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let mut garbled = data[..5760].to_vec();
+        for position in (10..5760).step_by(97) {
+            garbled[position] = b"'&=/E-T"[position % 7];
+        }
+
+        for length in [0, 1, 80, 2879, 2880, 5760, 9000] {
+            fuzz_parse(&data[..length]);
+            fuzz_parse(&garbled[..length.min(garbled.len())]);
+        }
+        fuzz_parse(&[0xff; 2880]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn fits_parallel_should_parse_like_fits() {
+        use super::fits_parallel;
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let ranges = layout(data);
+        let mut mosaic = data[..ranges[1].1.end].to_vec();
+        for _ in 0..50 {
+            mosaic.extend_from_slice(&data[ranges[1].0.start..ranges[1].1.end]);
+        }
+
+        for input in [&data[..], &mosaic[..], &data[..data.len() - 100], &data[..2880]].iter() {
+            match (fits_parallel(input), fits(input)) {
+                (IResult::Done(parallel_rest, parallel), IResult::Done(rest, sequential)) => {
+                    assert_eq!(parallel, sequential);
+                    assert_eq!(parallel_rest.len(), rest.len());
+                }
+                (IResult::Done(_, _), _) | (_, IResult::Done(_, _)) => panic!("Expected both parses to agree"),
+                _ => (),
+            }
+        }
+        match fits_parallel(&mosaic) {
+            IResult::Done(_, fits) => assert_eq!(fits.extensions.len(), 51),
+            _ => panic!("Did not expect the parse to fail"),
+        }
+    }
+
+    #[test]
+    fn header_length_should_find_the_end_card() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let ranges = layout(data);
+
+        for (header, _) in ranges.iter() {
+            assert_eq!(header_length(&data[header.start..]), Option::Some(header.len()));
+        }
+        let card = |text: &str| format!("{:80}", text);
+        let ends_late = [card("SIMPLE  =                    T"), card("ENDTIME = 'later'"), card("COMMENT END"), card("END")].concat();
+        assert_eq!(header_length(ends_late.as_bytes()), Option::Some(2880));
+        assert_eq!(header_length(card("SIMPLE  =                    T").as_bytes()), Option::None);
+    }
+
+    #[test]
+    fn parse_header_should_walk_a_file_header_by_header() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let file = match fits(data) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let mut rest = &data[..];
+
+        for (hdu, (header_range, _)) in file.iter().zip(layout(data)) {
+            let header = match parse_header(rest) {
+                IResult::Done(remainder, header) => {
+                    assert_eq!(data.len() - remainder.len(), header_range.end);
+                    rest = &remainder[header.padded_data_length().unwrap() as usize..];
+                    header
+                }
+                _ => panic!("Did not expect the parse to fail"),
+            };
+            assert_eq!(header, hdu.header);
+        }
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn file_layout_should_agree_with_layout() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+
+        for input in [&data[..], &data[..data.len() - 100], &data[..2880]].iter() {
+            let expected: Vec<_> = layout(input).into_iter()
+                .map(|(h, d)| (h.start as u64..h.end as u64, d.start as u64..d.end as u64))
+                .collect();
+            assert_eq!(file_layout(&mut Cursor::new(input)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn file_layout_should_find_headers_past_4_gib() {
+        let primary = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 65536\nNAXIS2 = 81920").unwrap();
+        let extension = template("XTENSION = 'IMAGE'\nBITPIX = 16\nNAXIS = 1\nNAXIS1 = 10\nPCOUNT = 0\nGCOUNT = 1").unwrap();
+        assert_eq!(primary.data_length(), Option::Some(5 * 1024 * 1024 * 1024));
+        let second = 2880 + primary.padded_data_length().unwrap();
+        let path = env::temp_dir().join(format!("fits-rs-sparse-{}.fits", process::id()));
+
+        let ranges = {
+            let mut file = File::create(&path).unwrap();
+            file.write_all(primary.to_fits_string().as_bytes()).unwrap();
+            file.set_len(second).unwrap();
+            file.seek(SeekFrom::Start(second)).unwrap();
+            file.write_all(extension.to_fits_string().as_bytes()).unwrap();
+            file.write_all(&[0u8; 2880]).unwrap();
+            drop(file);
+            file_layout(&mut File::open(&path).unwrap())
+        };
+        fs::remove_file(&path).unwrap();
+
+        let ranges = ranges.unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0], (0..2880, 2880..second));
+        assert_eq!(ranges[1], (second..second + 2880, second + 2880..second + 2880 * 2));
+        assert!(ranges[1].0.start > u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn layout_should_cover_the_file() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+
+        let ranges = layout(data);
+
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0], (0..2880*2, 2880*2..2880*2));
+        assert_eq!(ranges[1].0.start, 2880*2);
+        assert_eq!(ranges[1].0.end, ranges[1].1.start);
+        assert_eq!(ranges[2].0.start, ranges[1].1.end);
+        assert_eq!(ranges[2].1.end, data.len());
+    }
 
     #[test]
     fn it_should_parse_a_fits_file(){
@@ -208,6 +774,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn template_should_construct_a_header(){
+        let text = "simple  = T / conforms\n\nBITPIX = -32\nNAXIS=0\nOBJECT = 'EPIC' / target\nEQUINOX = 2000.0\nHISTORY   indented\nEND\n";
+
+        let result = template(text);
+
+        assert_eq!(result, Ok(Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::Some("conforms".into())),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(-32i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::Some("target".into())),
+            KeywordRecord::new(Keyword::EQUINOX, Value::Real(2000.0f64), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("  indented".into())),
+        ))));
+    }
+
+    #[test]
+    fn template_should_report_the_offending_line(){
+        assert_eq!(template("SIMPLE = T\nNAXIS 0"), Err(TemplateError::MissingValueIndicator(2)));
+        assert_eq!(template("NAXIS = zero"), Err(TemplateError::InvalidValue(1)));
+        assert_eq!(template("LONGKEYWORD = 1"), Err(TemplateError::InvalidKeyword(1)));
+    }
+
+    #[test]
+    fn template_should_round_trip_a_primary_header(){
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let original = match header(&data[0..(2*2880)]) {
+            IResult::Done(_, h) => h,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let text = original.to_template();
+
+        let result = template(&text).unwrap();
+
+        let records: Vec<&KeywordRecord> = original.records().iter().filter(|r| *r.keyword() != Keyword::END).collect();
+        assert_eq!(result.records().iter().collect::<Vec<_>>(), records);
+    }
+
     #[test]
     fn header_should_parse_a_primary_header(){
         let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
@@ -228,8 +832,8 @@ mod tests {
         let result = header(&data[(2*2880)..(10*2880)]);
 
         match result {
-            IResult::Done(_, h) => assert_eq!(h.keyword_records.len(), 284),
-            IResult::Error(e) => panic!(format!("Did not expect an error: {:?}", e)),
+            IResult::Done(_, h) => assert_eq!(h.len(), 284),
+            IResult::Error(e) => panic!("Did not expect an error: {:?}", e),
             IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
         }
     }
@@ -238,185 +842,185 @@ mod tests {
         Header::new(vec!(
             KeywordRecord::new(Keyword::SIMPLE,
                                Value::Logical(true),
-                               Option::Some("conforms to FITS standards")),
+                               Option::Some("conforms to FITS standards".into())),
             KeywordRecord::new(Keyword::BITPIX,
                                Value::Integer(8i64),
-                               Option::Some("array data type")),
+                               Option::Some("array data type".into())),
             KeywordRecord::new(Keyword::NAXIS,
                                Value::Integer(0i64),
-                               Option::Some("number of array dimensions")),
+                               Option::Some("number of array dimensions".into())),
             KeywordRecord::new(Keyword::EXTEND,
                                Value::Logical(true),
-                               Option::Some("file contains extensions")),
+                               Option::Some("file contains extensions".into())),
             KeywordRecord::new(Keyword::NEXTEND,
                                Value::Integer(2i64),
-                               Option::Some("number of standard extensions")),
+                               Option::Some("number of standard extensions".into())),
             KeywordRecord::new(Keyword::EXTNAME,
-                               Value::CharacterString("PRIMARY "),
-                               Option::Some("name of extension")),
+                               Value::CharacterString("PRIMARY ".into()),
+                               Option::Some("name of extension".into())),
             KeywordRecord::new(Keyword::EXTVER,
                                Value::Integer(1i64),
-                               Option::Some("extension version number (not format version)")),
+                               Option::Some("extension version number (not format version)".into())),
             KeywordRecord::new(Keyword::ORIGIN,
-                               Value::CharacterString("Unofficial data product"),
-                               Option::Some("institution responsible for creating this")),
+                               Value::CharacterString("Unofficial data product".into()),
+                               Option::Some("institution responsible for creating this".into())),
             KeywordRecord::new(Keyword::DATE,
-                               Value::CharacterString("2017-03-08"),
-                               Option::Some("file creation date.")),
+                               Value::CharacterString("2017-03-08".into()),
+                               Option::Some("file creation date.".into())),
             KeywordRecord::new(Keyword::CREATOR,
-                               Value::CharacterString("kadenza "),
-                               Option::Some("pipeline job and program u")),
+                               Value::CharacterString("kadenza ".into()),
+                               Option::Some("pipeline job and program u".into())),
             KeywordRecord::new(Keyword::PROCVER,
-                               Value::CharacterString("2.1.dev "),
-                               Option::Some("SW version")),
+                               Value::CharacterString("2.1.dev ".into()),
+                               Option::Some("SW version".into())),
             KeywordRecord::new(Keyword::FILEVER,
-                               Value::CharacterString("0.0     "),
-                               Option::Some("file format version")),
+                               Value::CharacterString("0.0     ".into()),
+                               Option::Some("file format version".into())),
             KeywordRecord::new(Keyword::TIMVERSN,
-                               Value::CharacterString(""),
-                               Option::Some("OGIP memo number for file format")),
+                               Value::CharacterString("".into()),
+                               Option::Some("OGIP memo number for file format".into())),
             KeywordRecord::new(Keyword::TELESCOP,
-                               Value::CharacterString("Kepler  "),
-                               Option::Some("telescope")),
+                               Value::CharacterString("Kepler  ".into()),
+                               Option::Some("telescope".into())),
             KeywordRecord::new(Keyword::INSTRUME,
-                               Value::CharacterString("Kepler Photometer"),
-                               Option::Some("detector type")),
+                               Value::CharacterString("Kepler Photometer".into()),
+                               Option::Some("detector type".into())),
             KeywordRecord::new(Keyword::OBJECT,
-                               Value::CharacterString("EPIC 200164267"),
-                               Option::Some("string version of target id")),
+                               Value::CharacterString("EPIC 200164267".into()),
+                               Option::Some("string version of target id".into())),
             KeywordRecord::new(Keyword::KEPLERID,
                                Value::Integer(200164267i64),
-                               Option::Some("unique Kepler target identifier")),
+                               Option::Some("unique Kepler target identifier".into())),
             KeywordRecord::new(Keyword::CHANNEL,
                                Value::Integer(68i64),
-                               Option::Some("CCD channel")),
+                               Option::Some("CCD channel".into())),
             KeywordRecord::new(Keyword::MODULE,
                                Value::Integer(19i64),
-                               Option::Some("CCD module")),
+                               Option::Some("CCD module".into())),
             KeywordRecord::new(Keyword::OUTPUT,
                                Value::Integer(4i64),
-                               Option::Some("CCD output")),
+                               Option::Some("CCD output".into())),
             KeywordRecord::new(Keyword::CAMPAIGN,
-                               Value::CharacterString(""),
-                               Option::Some("Observing campaign number")),
+                               Value::CharacterString("".into()),
+                               Option::Some("Observing campaign number".into())),
             KeywordRecord::new(Keyword::DATA_REL,
-                               Value::CharacterString(""),
-                               Option::Some("data release version number")),
+                               Value::CharacterString("".into()),
+                               Option::Some("data release version number".into())),
             KeywordRecord::new(Keyword::OBSMODE,
-                               Value::CharacterString("long cadence"),
-                               Option::Some("observing mode")),
+                               Value::CharacterString("long cadence".into()),
+                               Option::Some("observing mode".into())),
             KeywordRecord::new(Keyword::MISSION,
-                               Value::CharacterString("K2      "),
-                               Option::Some("Mission name")),
+                               Value::CharacterString("K2      ".into()),
+                               Option::Some("Mission name".into())),
             KeywordRecord::new(Keyword::TTABLEID,
-                               Value::CharacterString(""),
-                               Option::Some("target table id")),
+                               Value::CharacterString("".into()),
+                               Option::Some("target table id".into())),
             KeywordRecord::new(Keyword::RADESYS,
-                               Value::CharacterString("ICRS    "),
-                               Option::Some("reference frame of celestial coordinates")),
+                               Value::CharacterString("ICRS    ".into()),
+                               Option::Some("reference frame of celestial coordinates".into())),
             KeywordRecord::new(Keyword::RA_OBJ,
-                               Value::CharacterString(""),
-                               Option::Some("[deg] right ascension")),
+                               Value::CharacterString("".into()),
+                               Option::Some("[deg] right ascension".into())),
             KeywordRecord::new(Keyword::DEC_OBJ,
-                               Value::CharacterString(""),
-                               Option::Some("[deg] declination")),
+                               Value::CharacterString("".into()),
+                               Option::Some("[deg] declination".into())),
             KeywordRecord::new(Keyword::EQUINOX,
                                Value::Real(2000.0f64),
-                               Option::Some("equinox of celestial coordinate system")),
+                               Option::Some("equinox of celestial coordinate system".into())),
             KeywordRecord::new(Keyword::PMRA,
                                Value::Undefined,
-                               Option::Some("[arcsec/yr] RA proper motion")),
+                               Option::Some("[arcsec/yr] RA proper motion".into())),
             KeywordRecord::new(Keyword::PMDEC,
                                Value::Undefined,
-                               Option::Some("[arcsec/yr] Dec proper motion")),
+                               Option::Some("[arcsec/yr] Dec proper motion".into())),
             KeywordRecord::new(Keyword::PMTOTAL,
                                Value::Undefined,
-                               Option::Some("[arcsec/yr] total proper motion")),
+                               Option::Some("[arcsec/yr] total proper motion".into())),
             KeywordRecord::new(Keyword::PARALLAX,
                                Value::Undefined,
-                               Option::Some("[arcsec] parallax")),
+                               Option::Some("[arcsec] parallax".into())),
             KeywordRecord::new(Keyword::GLON,
                                Value::Undefined,
-                               Option::Some("[deg] galactic longitude")),
+                               Option::Some("[deg] galactic longitude".into())),
             KeywordRecord::new(Keyword::GLAT,
                                Value::Undefined,
-                               Option::Some("[deg] galactic latitude")),
+                               Option::Some("[deg] galactic latitude".into())),
             KeywordRecord::new(Keyword::GMAG,
                                Value::Undefined,
-                               Option::Some("[mag] SDSS g band magnitude")),
+                               Option::Some("[mag] SDSS g band magnitude".into())),
             KeywordRecord::new(Keyword::RMAG,
                                Value::Undefined,
-                               Option::Some("[mag] SDSS r band magnitude")),
+                               Option::Some("[mag] SDSS r band magnitude".into())),
             KeywordRecord::new(Keyword::IMAG,
                                Value::Undefined,
-                               Option::Some("[mag] SDSS i band magnitude")),
+                               Option::Some("[mag] SDSS i band magnitude".into())),
             KeywordRecord::new(Keyword::ZMAG,
                                Value::Undefined,
-                               Option::Some("[mag] SDSS z band magnitude")),
+                               Option::Some("[mag] SDSS z band magnitude".into())),
             KeywordRecord::new(Keyword::JMAG,
                                Value::Undefined,
-                               Option::Some("[mag] J band magnitude from 2MASS")),
+                               Option::Some("[mag] J band magnitude from 2MASS".into())),
             KeywordRecord::new(Keyword::HMAG,
                                Value::Undefined,
-                               Option::Some("[mag] H band magnitude from 2MASS")),
+                               Option::Some("[mag] H band magnitude from 2MASS".into())),
             KeywordRecord::new(Keyword::KMAG,
                                Value::Undefined,
-                               Option::Some("[mag] K band magnitude from 2MASS")),
+                               Option::Some("[mag] K band magnitude from 2MASS".into())),
             KeywordRecord::new(Keyword::KEPMAG,
                                Value::Undefined,
-                               Option::Some("[mag] Kepler magnitude (Kp)")),
+                               Option::Some("[mag] Kepler magnitude (Kp)".into())),
             KeywordRecord::new(Keyword::GRCOLOR,
                                Value::Undefined,
-                               Option::Some("[mag] (g-r) color, SDSS bands")),
+                               Option::Some("[mag] (g-r) color, SDSS bands".into())),
             KeywordRecord::new(Keyword::JKCOLOR,
                                Value::Undefined,
-                               Option::Some("[mag] (J-K) color, 2MASS bands")),
+                               Option::Some("[mag] (J-K) color, 2MASS bands".into())),
             KeywordRecord::new(Keyword::GKCOLOR,
                                Value::Undefined,
-                               Option::Some("[mag] (g-K) color, SDSS g - 2MASS K")),
+                               Option::Some("[mag] (g-K) color, SDSS g - 2MASS K".into())),
             KeywordRecord::new(Keyword::TEFF,
                                Value::Undefined,
-                               Option::Some("[K] Effective temperature")),
+                               Option::Some("[K] Effective temperature".into())),
             KeywordRecord::new(Keyword::LOGG,
                                Value::Undefined,
-                               Option::Some("[cm/s2] log10 surface gravity")),
+                               Option::Some("[cm/s2] log10 surface gravity".into())),
             KeywordRecord::new(Keyword::FEH,
                                Value::Undefined,
-                               Option::Some("[log10([Fe/H])]  metallicity")),
+                               Option::Some("[log10([Fe/H])]  metallicity".into())),
             KeywordRecord::new(Keyword::EBMINUSV,
                                Value::Undefined,
-                               Option::Some("[mag] E(B-V) reddening")),
+                               Option::Some("[mag] E(B-V) reddening".into())),
             KeywordRecord::new(Keyword::AV,
                                Value::Undefined,
-                               Option::Some("[mag] A_v extinction")),
+                               Option::Some("[mag] A_v extinction".into())),
             KeywordRecord::new(Keyword::RADIUS,
                                Value::Undefined,
-                               Option::Some("[solar radii] stellar radius")),
+                               Option::Some("[solar radii] stellar radius".into())),
             KeywordRecord::new(Keyword::TMINDEX,
                                Value::Undefined,
-                               Option::Some("unique 2MASS catalog ID")),
+                               Option::Some("unique 2MASS catalog ID".into())),
             KeywordRecord::new(Keyword::CHECKSUM,
-                               Value::CharacterString("7k7A7h637h697h69"),
-                               Option::Some("HDU checksum updated 2017-03-08T02:47:56")),
+                               Value::CharacterString("7k7A7h637h697h69".into()),
+                               Option::Some("HDU checksum updated 2017-03-08T02:47:56".into())),
             KeywordRecord::new(Keyword::DATASUM,
-                               Value::CharacterString("0       "),
-                               Option::Some("data unit checksum updated 2017-03-08T02:47:56")),
+                               Value::CharacterString("0       ".into()),
+                               Option::Some("data unit checksum updated 2017-03-08T02:47:56".into())),
         ))
     }
 
     #[test]
-    fn keyword_record_should_parse_a_keyword_record(){
+    fn record_should_parse_a_keyword_record(){
         let data = "OBJECT  = 'EPIC 200164267'     / string version of target id                    "
             .as_bytes();
 
-        let result = keyword_record(data);
+        let result = record(data);
 
         match result {
             IResult::Done(_,k) => {
                 assert_eq!(k, KeywordRecord::new(
                     Keyword::OBJECT,
-                    Value::CharacterString("EPIC 200164267"),
-                    Option::Some("string version of target id")
+                    Value::CharacterString("EPIC 200164267".into()),
+                    Option::Some("string version of target id".into())
                 ))
             },
             IResult::Error(_) => panic!("Did not expect an error"),
@@ -425,11 +1029,11 @@ mod tests {
     }
 
     #[test]
-    fn keyword_record_should_parse_a_keyword_record_without_a_comment(){
+    fn record_should_parse_a_keyword_record_without_a_comment(){
         let data = "KEPLERID=            200164267                                                  "
             .as_bytes();
 
-        let result = keyword_record(data);
+        let result = record(data);
 
         match result {
             IResult::Done(_,k) => {
@@ -444,6 +1048,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn record_should_parse_a_history_record(){
+        let data = "HISTORY Converted from the original TPF by kadenza                               "
+            .as_bytes();
+
+        let result = record(data);
+
+        match result {
+            IResult::Done(_,k) => {
+                assert_eq!(k, KeywordRecord::new(
+                    Keyword::HISTORY,
+                    Value::Undefined,
+                    Option::Some("Converted from the original TPF by kadenza".into())
+                ))
+            },
+            IResult::Error(_) => panic!("Did not expect an error"),
+            IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+        }
+    }
+
+    #[test]
+    fn record_should_not_parse_a_keyword_record_without_a_value_indicator(){
+        let data = "KEPLERID             200164267                                                  "
+            .as_bytes();
+
+        let result = record(data);
+
+        match result {
+            IResult::Error(_) => (),
+            _ => panic!("Expected an error")
+        }
+    }
+
+    #[test]
+    fn header_should_parse_commentary_records(){
+        let data = format!("{:80}{:80}{:80}",
+                           "SIMPLE  =                    T",
+                           "COMMENT   FITS (Flexible Image Transport System) format",
+                           "END");
+
+        let result = header(data.as_bytes());
+
+        match result {
+            IResult::Done(_, h) => {
+                assert_eq!(h.comments().collect::<Vec<&str>>(),
+                           vec!("  FITS (Flexible Image Transport System) format"));
+            },
+            IResult::Error(e) => panic!("Did not expect an error: {:?}", e),
+            IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+        }
+    }
+
     #[test]
     fn valuecomment_should_parse_a_valuecomment(){
         let data = "'EPIC 200164267'     / string version of target id                    "
@@ -453,7 +1109,7 @@ mod tests {
 
         match result {
             IResult::Done(_, (value, comment)) => {
-                assert_eq!(value, Value::CharacterString("EPIC 200164267"));
+                assert_eq!(value, Value::CharacterString("EPIC 200164267".into()));
                 assert_eq!(comment, Option::Some(" string version of target id                    "));
             },
             IResult::Error(_) => panic!("Did not expect an error"),
@@ -461,6 +1117,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn valuecomment_should_parse_a_negative_value_and_a_comment(){
+        let data = "  -6.600882744792873 / [deg] declination at reference pixel           "
+            .as_bytes();
+
+        let result = valuecomment(data);
+
+        match result {
+            IResult::Done(_, (value, comment)) => {
+                assert_eq!(value, Value::Real(-6.600882744792873f64));
+                assert_eq!(comment, Option::Some(" [deg] declination at reference pixel           "));
+            },
+            IResult::Error(_) => panic!("Did not expect an error"),
+            IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+        }
+    }
+
     #[test]
     fn valuecomment_should_parse_a_valuecomment_without_a_comment(){
         let data = "200164267                                                                                         "
@@ -474,7 +1147,28 @@ mod tests {
                 assert_eq!(comment, Option::None);
             },
             IResult::Error(_) => panic!("Did not expect an error"),
-            IResult::Incomplete(e) => panic!(format!("Did not expect to be incomplete: {:?}", e))
+            IResult::Incomplete(e) => panic!("Did not expect to be incomplete: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn character_string_should_unescape_quotes() {
+        match character_string(b"'O''HARA'  ") {
+            IResult::Done(_, Value::CharacterString(Cow::Owned(s))) => assert_eq!(s, "O'HARA"),
+            result => panic!("Did not expect {:?}", result),
+        }
+        match character_string(b"''''") {
+            IResult::Done(_, Value::CharacterString(s)) => assert_eq!(s, "'"),
+            result => panic!("Did not expect {:?}", result),
+        }
+        match character_string(b"'EPIC'") {
+            IResult::Done(_, Value::CharacterString(Cow::Borrowed(s))) => assert_eq!(s, "EPIC"),
+            result => panic!("Did not expect {:?}", result),
+        }
+        let card = KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("Barnard's star".into()), Option::None).to_string();
+        match record(card.as_bytes()) {
+            IResult::Done(_, parsed) => assert_eq!(parsed.value(), &Value::CharacterString("Barnard's star".into())),
+            result => panic!("Did not expect {:?}", result),
         }
     }
 
@@ -487,7 +1181,7 @@ mod tests {
 
         match result {
             IResult::Done(_, value) => {
-                assert_eq!(value, Value::CharacterString("EPIC 200164267"));
+                assert_eq!(value, Value::CharacterString("EPIC 200164267".into()));
             },
             IResult::Error(_) => panic!("Did not expect an error"),
             IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
@@ -526,9 +1220,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn real_should_parse_signs_and_exponents() {
+        let data = vec!(
+            ("-6.600882744792873", -6.600882744792873f64),
+            ("+1.5", 1.5f64),
+            ("1.0E-20", 1e-20f64),
+            ("-2.5D3", -2500f64),
+            ("3E2", 300f64),
+            (".5", 0.5f64),
+        );
+        for (input, f) in data {
+            let result = real(input.as_bytes());
+
+            match result {
+                IResult::Done(_, value) => assert_eq!(value, Value::Real(f)),
+                IResult::Error(_) => panic!("Did not expect an error"),
+                IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+            }
+        }
+    }
+
+    #[test]
+    fn real_should_not_parse_an_integer() {
+        match real("   37 / comment".as_bytes()) {
+            IResult::Error(_) => (),
+            _ => panic!("Expected an error")
+        }
+    }
+
     #[test]
     fn integer_should_parse_an_integer() {
-        for (input, n) in vec!(("1", 1i64), ("37", 37i64), ("51", 51i64)) {
+        for (input, n) in vec!(("1", 1i64), ("37", 37i64), ("51", 51i64), ("-1", -1i64), ("+7", 7i64)) {
             let data = input.as_bytes();
 
             let result = integer(data);
@@ -541,6 +1264,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn value_should_parse_every_kind_of_value() {
+        let cases = vec!(
+            ("  'EPIC'  ", Value::CharacterString("EPIC".into())),
+            ("         T", Value::Logical(true)),
+            ("       -42", Value::Integer(-42)),
+            ("  1.5D3   ", Value::Real(1500f64)),
+            ("  .5      ", Value::Real(0.5f64)),
+            ("          ", Value::Undefined),
+            ("  (1, 2)  ", Value::Undefined),
+        );
+        for (input, expected) in cases {
+            match value(input.as_bytes()) {
+                IResult::Done(_, value) => assert_eq!(value, expected),
+                IResult::Error(_) => panic!("Did not expect an error"),
+                IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+            }
+        }
+    }
+
     #[test]
     fn undefined_should_parse_any_amount_of_whitespace() {
         for input in vec!(" ", "\t", "    \t   ") {
@@ -613,6 +1356,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fits_should_keep_the_data_of_each_hdu(){
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+
+        let result = fits(data);
+
+        match result {
+            IResult::Done(_, f) => {
+                assert_eq!(f[0].data().len(), 0);
+                assert_eq!(f[1].data().len(), 2932*3599);
+                assert_eq!(f[2].data().len(), 4*11*11);
+            },
+            IResult::Error(_) => panic!("Did not expect an error"),
+            IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+        }
+    }
+
+    #[test]
+    fn fits_should_parse_groups_without_axes_as_an_empty_primary_array(){
+        let card = |text: &str| format!("{:<80}", text);
+        let mut input = vec!();
+        for text in &["SIMPLE  =                    T", "BITPIX  =                    8", "NAXIS   =                    0",
+                      "NAXIS1  =                    0", "GROUPS  =                    T", "PCOUNT  =                    1",
+                      "GCOUNT  =                    1", "END"] {
+            input.extend_from_slice(card(text).as_bytes());
+        }
+        input.resize(2880, b' ');
+
+        match fits(&input) {
+            IResult::Done(rest, f) => {
+                assert!(rest.is_empty());
+                assert!(!f[0].header.is_random_groups());
+                assert_eq!(f[0].data().len(), 0);
+            },
+            IResult::Error(_) => panic!("Did not expect an error"),
+            IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
+        }
+    }
+
     #[test]
     fn first_extension_header_should_have_a_correct_data_array_size(){
         let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
@@ -625,4 +1407,52 @@ mod tests {
             IResult::Incomplete(_) => panic!("Did not expect to be incomplete")
         }
     }
+
+    #[test]
+    fn fits_4_0_additions_should_be_supported() {
+        use super::super::image::Image;
+        use super::super::table::{BinTable, ColumnData};
+
+        let card = |text: &str| format!("{:<80}", text);
+        let mut input = vec!();
+        for text in &["SIMPLE  =                    T", "BITPIX  =                   64", "NAXIS   =                    1",
+                      "NAXIS1  =                    2", "EXTEND  =                    T", "LONGSTRN= 'OGIP 1.0'",
+                      "OBJECT  = 'a long&'           / first", "CONTINUE  'er name'         / second", "", "END"] {
+            input.extend_from_slice(card(text).as_bytes());
+        }
+        input.resize(2880, b' ');
+        input.extend_from_slice(&(-1i64).to_be_bytes());
+        input.extend_from_slice(&(1i64 << 40).to_be_bytes());
+        input.resize(2 * 2880, 0);
+        for text in &["XTENSION= 'BINTABLE'", "BITPIX  =                    8", "NAXIS   =                    2",
+                      "NAXIS1  =                   16", "NAXIS2  =                    1", "PCOUNT  =                    8",
+                      "GCOUNT  =                    1", "TFIELDS =                    1", "TFORM1  = '1QJ     '", "END"] {
+            input.extend_from_slice(card(text).as_bytes());
+        }
+        input.resize(3 * 2880, b' ');
+        for word in &[2i64, 0i64] {
+            input.extend_from_slice(&word.to_be_bytes());
+        }
+        input.extend_from_slice(&7i32.to_be_bytes());
+        input.extend_from_slice(&(-7i32).to_be_bytes());
+        input.resize(4 * 2880, 0);
+
+        let file = match fits(&input) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let primary = &file.primary_hdu.header;
+        let table = BinTable::new(&file[1].header).unwrap();
+
+        assert_eq!(Image::new(primary, file[0].data()).unwrap().pixels, vec!(-1f64, (1i64 << 40) as f64));
+        assert_eq!(primary.character_string_value_of(&Keyword::OBJECT), Ok("a longer name"));
+        assert_eq!(primary.records()[6].comment(), Option::Some("first second"));
+        assert!(primary.records()[7].keyword().is_commentary());
+        assert_eq!(primary.records().len(), 8);
+        assert_eq!(table.read_column(file[1].data(), 0).unwrap().data, ColumnData::Int(vec!(7, -7)));
+        match super::lazy_fits(&input) {
+            IResult::Done(_, hdus) => assert_eq!(&hdus[0].header.to_header(), primary),
+            _ => panic!("Did not expect the lazy parse to fail"),
+        }
+    }
 }
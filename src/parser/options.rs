@@ -0,0 +1,335 @@
+//! Parsing with options, for callers that need to know about, or reject,
+//! headers that the plain `fits` parser accepts silently.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use nom::IResult;
+use super::super::types::{Fits, HDU, Header, Keyword, KeywordRecord};
+use super::{header, padded_data_size};
+
+/// How keywords, other than commentary keywords, that appear more than once in
+/// a header are treated.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DuplicatePolicy {
+    /// The parse fails with `ParseError::Duplicate`.
+    Error,
+    /// The first occurrence is the value of the keyword, as with `fits`. The
+    /// other occurrences stay in the header.
+    #[default]
+    FirstWins,
+    /// The last occurrence is the value of the keyword. The other occurrences
+    /// are removed from the header.
+    LastWins,
+}
+
+/// The options of `fits_with_options`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ParseOptions {
+    /// How duplicated keywords are treated, `FirstWins` by default.
+    pub duplicates: DuplicatePolicy,
+    /// Keep an HDU whose data array is cut short, with the data that is
+    /// there, instead of leaving it out. `false` by default.
+    pub lenient: bool,
+    /// The most HDUs to parse, including the primary HDU, the rest of the
+    /// input is ignored. No limit by default.
+    pub max_hdus: Option<usize>,
+    /// Recognize keywords written in lower case, like `naxis`, as their upper
+    /// case equivalents. `false` by default.
+    pub keywords_case_insensitive: bool,
+}
+
+impl ParseOptions {
+    /// The default options, which parse like `fits`.
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Treat duplicated keywords according to `policy`.
+    pub fn with_duplicates(mut self, policy: DuplicatePolicy) -> ParseOptions {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Keep HDUs whose data array is cut short when `lenient` is `true`.
+    pub fn with_lenient(mut self, lenient: bool) -> ParseOptions {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Parse at most `max` HDUs, including the primary HDU.
+    pub fn with_max_hdus(mut self, max: usize) -> ParseOptions {
+        self.max_hdus = Option::Some(max);
+        self
+    }
+
+    /// Recognize lower case keywords when `case_insensitive` is `true`.
+    pub fn with_keywords_case_insensitive(mut self, case_insensitive: bool) -> ParseOptions {
+        self.keywords_case_insensitive = case_insensitive;
+        self
+    }
+}
+
+/// Configures and runs a parse, as a builder over `ParseOptions`, e.g.
+/// `FitsParser::new().lenient(true).max_hdus(64).parse(&buffer)`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FitsParser {
+    options: ParseOptions,
+}
+
+impl FitsParser {
+    /// A parser with the default options, which parses like `fits`.
+    pub fn new() -> FitsParser {
+        FitsParser::default()
+    }
+
+    /// Treat duplicated keywords according to `policy`.
+    pub fn duplicates(self, policy: DuplicatePolicy) -> FitsParser {
+        FitsParser { options: self.options.with_duplicates(policy) }
+    }
+
+    /// Keep HDUs whose data array is cut short when `lenient` is `true`.
+    pub fn lenient(self, lenient: bool) -> FitsParser {
+        FitsParser { options: self.options.with_lenient(lenient) }
+    }
+
+    /// Parse at most `max` HDUs, including the primary HDU.
+    pub fn max_hdus(self, max: usize) -> FitsParser {
+        FitsParser { options: self.options.with_max_hdus(max) }
+    }
+
+    /// Recognize lower case keywords when `case_insensitive` is `true`.
+    pub fn keywords_case_insensitive(self, case_insensitive: bool) -> FitsParser {
+        FitsParser { options: self.options.with_keywords_case_insensitive(case_insensitive) }
+    }
+
+    /// The options this parser parses with.
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Will parse data from a FITS file, see `fits_with_options`.
+    pub fn parse<'a>(&self, input: &'a [u8]) -> Result<Parsed<'a>, ParseError> {
+        fits_with_options(input, &self.options)
+    }
+}
+
+impl From<ParseOptions> for FitsParser {
+    fn from(options: ParseOptions) -> FitsParser {
+        FitsParser { options }
+    }
+}
+
+/// A keyword that appears more than once in a header.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DuplicateKeyword {
+    /// The index of the HDU, 0 for the primary HDU.
+    pub hdu: usize,
+    /// The duplicated keyword.
+    pub keyword: Keyword,
+    /// The numbers of the cards with the keyword, starting at 1.
+    pub cards: Vec<usize>,
+}
+
+/// A FITS file parsed with `fits_with_options`.
+#[derive(Debug, PartialEq)]
+pub struct Parsed<'a> {
+    /// The parsed file.
+    pub fits: Fits<'a>,
+    /// The keywords that appear more than once, by HDU and in order of their
+    /// first occurrence.
+    pub duplicates: Vec<DuplicateKeyword>,
+}
+
+/// Will parse data from a FITS file into a `Fits` structure like `fits`,
+/// reporting the keywords that appear more than once in a header and treating
+/// them according to `options`.
+///
+/// The headers are settled before the size of their data arrays is read from
+/// them, so with `DuplicatePolicy::LastWins` the last `NAXISn` of a header
+/// locates its data array.
+pub fn fits_with_options<'a>(input: &'a [u8], options: &ParseOptions) -> Result<Parsed<'a>, ParseError> {
+    let mut duplicates = vec!();
+    let (mut rest, primary_hdu) = match hdu(input, 0, options, &mut duplicates)? {
+        Option::Some(parsed) => parsed,
+        Option::None => return Err(ParseError::Incomplete),
+    };
+    let mut extensions = vec!();
+    let max_hdus = options.max_hdus.unwrap_or(usize::MAX);
+    while !rest.is_empty() && extensions.len() + 1 < max_hdus {
+        match hdu(rest, extensions.len() + 1, options, &mut duplicates) {
+            Ok(Option::Some((tail, extension))) => {
+                rest = tail;
+                extensions.push(extension);
+            }
+            Ok(Option::None) | Err(ParseError::Incomplete) | Err(ParseError::Invalid) => break,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(Parsed { fits: Fits::new(primary_hdu, extensions), duplicates })
+}
+
+/// Parses the HDU at index `index` from `input`. `None` when the data array is
+/// cut short.
+fn hdu<'a>(input: &'a [u8], index: usize, options: &ParseOptions, duplicates: &mut Vec<DuplicateKeyword>) -> Result<Option<(&'a [u8], HDU<'a>)>, ParseError> {
+    let (rest, h) = match header(input) {
+        IResult::Done(rest, h) => (rest, h),
+        IResult::Incomplete(_) => return Err(ParseError::Incomplete),
+        IResult::Error(_) => return Err(ParseError::Invalid),
+    };
+    let header_bytes = &input[..input.len() - rest.len()];
+    let h = if options.keywords_case_insensitive { upper_case_keywords(h) } else { h };
+    let found = duplicates_of(&h, index);
+    let h = match options.duplicates {
+        DuplicatePolicy::Error if !found.is_empty() => return Err(ParseError::Duplicate(found[0].clone())),
+        DuplicatePolicy::LastWins if !found.is_empty() => {
+            let mut records = h.records().to_vec();
+            let mut removed: Vec<usize> = found.iter()
+                .flat_map(|duplicate| duplicate.cards[..duplicate.cards.len() - 1].iter().map(|card| card - 1))
+                .collect();
+            removed.sort_unstable();
+            for position in removed.into_iter().rev() {
+                records.remove(position);
+            }
+            Header::new(records)
+        }
+        _ => h,
+    };
+    duplicates.extend(found);
+    let size = padded_data_size(&h);
+    if rest.len() < size {
+        if options.lenient {
+            return Ok(Option::Some((&rest[rest.len()..], HDU::parsed(h, header_bytes, rest))))
+        }
+        return Ok(Option::None)
+    }
+    let (data, rest) = rest.split_at(size);
+    Ok(Option::Some((rest, HDU::parsed(h, header_bytes, data))))
+}
+
+/// Recognizes the unrecognized keywords of `header` that contain lower case
+/// letters by their upper case text.
+fn upper_case_keywords(header: Header) -> Header {
+    let lower_case = |record: &KeywordRecord| match *record.keyword() {
+        Keyword::Unrecognized(ref text) => text.as_str().bytes().any(|b| b.is_ascii_lowercase()),
+        _ => false,
+    };
+    if !header.records().iter().any(lower_case) {
+        return header
+    }
+    let records = header.records().iter()
+        .map(|record| {
+            if !lower_case(record) {
+                return record.clone()
+            }
+            match Keyword::from_str(&record.keyword().to_string().to_ascii_uppercase()) {
+                Ok(keyword) => KeywordRecord::new(keyword, record.value().clone(), record.comment().map(|comment| Cow::Owned(comment.to_string()))),
+                Err(_) => record.clone(),
+            }
+        })
+        .collect();
+    Header::new(records)
+}
+
+/// The keywords of `header`, the header of the HDU at `index`, that appear
+/// more than once, ignoring commentary keywords.
+fn duplicates_of(header: &Header, index: usize) -> Vec<DuplicateKeyword> {
+    let mut cards: Vec<(&Keyword, Vec<usize>)> = vec!();
+    let mut first: HashMap<&Keyword, usize> = HashMap::new();
+    for (position, record) in header.records().iter().enumerate() {
+        let keyword = record.keyword();
+        if keyword.is_commentary() || *keyword == Keyword::END {
+            continue;
+        }
+        match first.get(keyword) {
+            Option::Some(&entry) => cards[entry].1.push(position + 1),
+            Option::None => {
+                first.insert(keyword, cards.len());
+                cards.push((keyword, vec!(position + 1)));
+            }
+        }
+    }
+    cards.into_iter()
+        .filter(|(_, positions)| positions.len() > 1)
+        .map(|(keyword, positions)| DuplicateKeyword { hdu: index, keyword: keyword.clone(), cards: positions })
+        .collect()
+}
+
+/// Problems that could occur when parsing with `fits_with_options`.
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    /// The input ends before the primary HDU does.
+    Incomplete,
+    /// The primary header could not be parsed.
+    Invalid,
+    /// A keyword appears more than once, with `DuplicatePolicy::Error`.
+    Duplicate(DuplicateKeyword),
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::fits;
+    use super::super::super::types::Keyword;
+    use super::{fits_with_options, DuplicateKeyword, DuplicatePolicy, FitsParser, ParseError, ParseOptions};
+
+    fn file() -> Vec<u8> {
+        let cards = ["SIMPLE  =                    T", "BITPIX  =                    8", "NAXIS   =                    0",
+                     "OBJECT  = 'first'", "COMMENT one", "COMMENT two", "OBJECT  = 'last'", "END"];
+        let mut input: Vec<u8> = cards.iter().flat_map(|card| format!("{:<80}", card).into_bytes()).collect();
+        input.resize(2880, b' ');
+        input
+    }
+
+    #[test]
+    fn duplicates_should_be_reported_and_resolved_by_policy() {
+        let input = file();
+        let duplicate = DuplicateKeyword { hdu: 0, keyword: Keyword::OBJECT, cards: vec!(4, 7) };
+
+        let first = fits_with_options(&input, &ParseOptions::new()).unwrap();
+        let last = fits_with_options(&input, &ParseOptions::new().with_duplicates(DuplicatePolicy::LastWins)).unwrap();
+        let error = fits_with_options(&input, &ParseOptions::new().with_duplicates(DuplicatePolicy::Error));
+
+        assert_eq!(first.duplicates, vec!(duplicate.clone()));
+        assert_eq!(first.fits.primary_hdu.header.character_string_value_of(&Keyword::OBJECT), Ok("first"));
+        match fits(&input) {
+            IResult::Done(_, plain) => assert_eq!(first.fits, plain),
+            _ => panic!("Did not expect the parse to fail"),
+        }
+        assert_eq!(last.fits.primary_hdu.header.character_string_value_of(&Keyword::OBJECT), Ok("last"));
+        assert_eq!(last.fits.primary_hdu.header.records().len(), 6);
+        assert_eq!(error, Err(ParseError::Duplicate(duplicate)));
+        assert_eq!(fits_with_options(&input[..80], &ParseOptions::new()), Err(ParseError::Incomplete));
+    }
+
+    fn image(naxis1: &str) -> Vec<u8> {
+        let cards = ["XTENSION= 'IMAGE   '", "BITPIX  =                    8", "NAXIS   =                    1",
+                     naxis1, "PCOUNT  =                    0", "GCOUNT  =                    1", "END"];
+        let mut input: Vec<u8> = cards.iter().flat_map(|card| format!("{:<80}", card).into_bytes()).collect();
+        input.resize(2880, b' ');
+        input
+    }
+
+    #[test]
+    fn fits_parser_should_apply_its_options() {
+        let mut truncated = file();
+        truncated.extend(image("NAXIS1  =                   10"));
+        truncated.extend(vec!(2; 5));
+        let mut lower_case = file();
+        lower_case.extend(image("naxis1  =                   10"));
+        lower_case.extend(vec!(1; 2880));
+
+        let lenient = FitsParser::new().lenient(true).parse(&truncated).unwrap();
+        let insensitive = FitsParser::new().keywords_case_insensitive(true).parse(&lower_case).unwrap();
+
+        assert_eq!(FitsParser::new().parse(&truncated).unwrap().fits.len(), 1);
+        assert_eq!(lenient.fits.len(), 2);
+        assert_eq!(lenient.fits[1].data(), &[2u8; 5][..]);
+        assert_eq!(FitsParser::new().lenient(true).max_hdus(1).parse(&truncated).unwrap().fits.len(), 1);
+        assert_eq!(insensitive.fits[1].header.integer_value_of(&Keyword::NAXISn(1)), Ok(10));
+        assert_eq!(insensitive.fits[1].data().len(), 10);
+        assert!(FitsParser::new().parse(&lower_case).unwrap().fits[1].header.integer_value_of(&Keyword::NAXISn(1)).is_err());
+        assert_eq!(FitsParser::new().duplicates(DuplicatePolicy::Error).parse(&truncated).map(|parsed| parsed.fits.len()),
+                   Err(ParseError::Duplicate(DuplicateKeyword { hdu: 0, keyword: Keyword::OBJECT, cards: vec!(4, 7) })));
+    }
+}
@@ -0,0 +1,282 @@
+//! Parsing the headers of a FITS file from a reader, a few blocks at a time,
+//! for files that are not in memory.
+
+use std::io;
+use std::io::Read;
+use std::mem;
+use std::ops::Range;
+use nom::IResult;
+use super::super::types::{HDU, Header};
+use super::{header, header_length};
+
+/// The number of 2880 byte blocks `HeaderParser` reads at a time by default.
+pub const READ_BLOCKS: usize = 16;
+
+const BLOCK_SIZE: usize = 2880;
+
+/// Parses the HDUs of a FITS file from `reader` one header at a time, keeping
+/// track of the offsets of the headers and data arrays in the file.
+///
+/// The reader is read `READ_BLOCKS` blocks at a time into a buffer that is
+/// reused for every header, and for every file when the parser is `reset`,
+/// so parsing many small files takes few reads and allocations. The data
+/// array of a header can be read with `read_data`, otherwise it is skipped by
+/// the next call to `next_header`. A slice of bytes is a reader too, so the
+/// offsets of the HDUs of a file in memory can be followed the same way.
+pub struct HeaderParser<R> {
+    reader: R,
+    offset: u64,
+    data: Range<u64>,
+    buffer: Vec<u8>,
+    buffered: Range<usize>,
+    header: Range<usize>,
+    read_blocks: usize,
+}
+
+impl<R: Read> HeaderParser<R> {
+    /// A parser of the FITS file that `reader` reads from its start.
+    pub fn new(reader: R) -> HeaderParser<R> {
+        HeaderParser { reader, offset: 0, data: 0..0, buffer: vec!(), buffered: 0..0, header: 0..0, read_blocks: READ_BLOCKS }
+    }
+
+    /// Read `blocks` blocks at a time, at least one.
+    pub fn with_read_blocks(mut self, blocks: usize) -> HeaderParser<R> {
+        self.read_blocks = blocks.max(1);
+        self
+    }
+
+    /// Start parsing the FITS file that `reader` reads from its start, keeping
+    /// the buffer of this parser. Returns the reader of the previous file,
+    /// which may have been read beyond the last header or data array parsed.
+    pub fn reset(&mut self, reader: R) -> R {
+        self.offset = 0;
+        self.data = 0..0;
+        self.buffered = 0..0;
+        self.header = 0..0;
+        mem::replace(&mut self.reader, reader)
+    }
+
+    /// The offset from the start of the file of the next byte to parse.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The byte range in the file of the data array of the last header,
+    /// including its padding.
+    pub fn data_range(&self) -> Range<u64> {
+        self.data.clone()
+    }
+
+    /// Parses the next header, skipping what is left of the data array of the
+    /// previous one. `None` at the end of the file.
+    ///
+    /// Input that ends inside a header is an error of kind `UnexpectedEof`, a
+    /// header that could not be parsed one of kind `InvalidData`.
+    pub fn next_header(&mut self) -> io::Result<Option<Header<'static>>> {
+        self.header = 0..0;
+        self.skip_data()?;
+        let mut searched = 0;
+        let length = loop {
+            self.fill(searched + BLOCK_SIZE)?;
+            let available = &self.buffer[self.buffered.clone()];
+            if available.is_empty() {
+                return Ok(Option::None)
+            }
+            if let Option::Some(length) = header_length(&available[searched..]) {
+                break searched + length
+            }
+            if available.len() < searched + BLOCK_SIZE {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the input ends inside a header"))
+            }
+            searched = available.len() / BLOCK_SIZE * BLOCK_SIZE;
+        };
+        self.fill(length)?;
+        if self.buffered.len() < length {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the input ends inside a header"))
+        }
+        let parsed = match header(&self.buffer[self.buffered.start..self.buffered.start + length]) {
+            IResult::Done(_, h) => h.padded_data_length()
+                .map(|data_length| (h.into_owned(), data_length))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the data array is too large")),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "the header could not be parsed")),
+        };
+        let (h, data_length) = parsed?;
+        self.header = self.buffered.start..self.buffered.start + length;
+        self.consume(length);
+        self.data = self.offset..self.offset.saturating_add(data_length);
+        Ok(Option::Some(h))
+    }
+
+    /// Reads the data array of the last header, including its padding. Empty
+    /// when it has been read or skipped already.
+    pub fn read_data(&mut self) -> io::Result<Vec<u8>> {
+        let remaining = self.data.end - self.offset.max(self.data.start);
+        let buffered = (self.buffered.len() as u64).min(remaining) as usize;
+        let mut data = self.buffer[self.buffered.start..self.buffered.start + buffered].to_vec();
+        self.consume(buffered);
+        let read = (&mut self.reader).take(remaining - buffered as u64).read_to_end(&mut data)?;
+        self.offset += read as u64;
+        if (data.len() as u64) < remaining {
+            self.data = self.offset..self.offset;
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the input ends inside a data array"))
+        }
+        Ok(data)
+    }
+
+    /// Reads the data array of the last header like `read_data`, into an HDU
+    /// that keeps the bytes of the header as they are in the file, so that
+    /// writing it unchanged copies the HDU as it is.
+    pub fn read_hdu(&mut self) -> io::Result<HDU<'static>> {
+        let data = self.read_data()?;
+        let bytes = &self.buffer[self.header.clone()];
+        match header(bytes) {
+            IResult::Done(_, h) if data.len() as u64 == self.data.end - self.data.start => Ok(HDU::parsed(h, bytes, &data).into_owned()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "the data array of the last header has been read or skipped already")),
+        }
+    }
+
+    fn skip_data(&mut self) -> io::Result<()> {
+        let remaining = self.data.end - self.offset.max(self.data.start);
+        let buffered = (self.buffered.len() as u64).min(remaining);
+        self.consume(buffered as usize);
+        let skipped = io::copy(&mut (&mut self.reader).take(remaining - buffered), &mut io::sink())?;
+        self.offset += skipped;
+        if buffered + skipped < remaining {
+            self.data = self.offset..self.offset;
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the input ends inside a data array"))
+        }
+        Ok(())
+    }
+
+    /// Reads until `needed` bytes are buffered or the input ends, a whole
+    /// number of `read_blocks` blocks at a time. The buffer only grows for
+    /// headers that do not fit in it.
+    fn fill(&mut self, needed: usize) -> io::Result<()> {
+        if self.buffered.len() >= needed {
+            return Ok(())
+        }
+        self.buffer.copy_within(self.buffered.clone(), 0);
+        self.buffered = 0..self.buffered.len();
+        let chunk = self.read_blocks * BLOCK_SIZE;
+        let size = needed.div_ceil(chunk) * chunk;
+        if self.buffer.len() < size {
+            self.buffer.resize(size, 0u8);
+        }
+        while self.buffered.end < needed {
+            match self.reader.read(&mut self.buffer[self.buffered.end..]) {
+                Ok(0) => break,
+                Ok(read) => self.buffered.end += read,
+                Err(ref error) if error.kind() == io::ErrorKind::Interrupted => (),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    fn consume(&mut self, length: usize) {
+        self.buffered.start += length;
+        self.offset += length as u64;
+    }
+}
+
+impl<R: Read> Iterator for HeaderParser<R> {
+    type Item = io::Result<Header<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_header().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use nom::IResult;
+    use super::super::{fits, layout};
+    use super::super::super::writer::write_hdu;
+    use super::{HeaderParser, BLOCK_SIZE, READ_BLOCKS};
+
+    /// Reads at most `limit` bytes per call and counts the calls.
+    struct Counting<'a> {
+        data: &'a [u8],
+        limit: usize,
+        reads: usize,
+    }
+
+    impl<'a> io::Read for Counting<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            let length = buf.len().min(self.limit).min(self.data.len());
+            buf[..length].copy_from_slice(&self.data[..length]);
+            self.data = &self.data[length..];
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn header_parser_should_follow_the_layout_of_a_file() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let file = match fits(data) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let mut parser = HeaderParser::new(&data[..]);
+
+        for (index, (header_range, data_range)) in layout(data).into_iter().enumerate() {
+            assert_eq!(parser.offset(), header_range.start as u64);
+            assert_eq!(parser.next_header().unwrap().as_ref(), Option::Some(&file[index].header));
+            assert_eq!(parser.data_range(), data_range.start as u64..data_range.end as u64);
+            if index == 1 {
+                assert_eq!(parser.read_data().unwrap(), &data[data_range]);
+            }
+        }
+        assert!(parser.next_header().unwrap().is_none());
+        assert_eq!(HeaderParser::new(&data[..]).count(), 3);
+    }
+
+    #[test]
+    fn header_parser_should_report_input_cut_short() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let mut parser = HeaderParser::new(&data[..data.len() - 100]);
+
+        let errors: Vec<io::ErrorKind> = parser.by_ref().filter_map(|header| header.err()).map(|error| error.kind()).collect();
+
+        assert_eq!(errors, vec!(io::ErrorKind::UnexpectedEof));
+        assert_eq!(HeaderParser::new(&data[..1000]).next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn header_parser_should_read_many_blocks_at_a_time_into_one_buffer() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let headers: Vec<_> = HeaderParser::new(&data[..]).map(Result::unwrap).collect();
+        let mut parser = HeaderParser::new(Counting { data: &data[..], limit: usize::MAX, reads: 0 });
+
+        assert_eq!(parser.by_ref().map(Result::unwrap).collect::<Vec<_>>(), headers);
+        let blocks = data.len() / BLOCK_SIZE;
+        let previous = parser.reset(Counting { data: &data[..], limit: usize::MAX, reads: 0 });
+        assert!(previous.reads < blocks / 2, "{} reads for {} blocks", previous.reads, blocks);
+        assert_eq!(parser.buffer.len(), READ_BLOCKS * BLOCK_SIZE);
+        assert_eq!(parser.by_ref().map(Result::unwrap).collect::<Vec<_>>(), headers);
+        assert_eq!(parser.buffer.len(), READ_BLOCKS * BLOCK_SIZE);
+
+        let mut trickle = HeaderParser::new(Counting { data: &data[..], limit: 7, reads: 0 }).with_read_blocks(1);
+        assert_eq!(trickle.next_header().unwrap().as_ref(), Option::Some(&headers[0]));
+        assert_eq!(trickle.read_data().unwrap().len() as u64, trickle.data_range().end - trickle.data_range().start);
+        assert_eq!(trickle.map(Result::unwrap).collect::<Vec<_>>(), &headers[1..]);
+    }
+
+    #[test]
+    fn read_hdu_should_keep_the_bytes_of_the_header() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let ranges = layout(data);
+        let mut parser = HeaderParser::new(&data[..]).with_read_blocks(1);
+        parser.next_header().unwrap();
+        parser.next_header().unwrap();
+
+        let hdu = parser.read_hdu().unwrap();
+        let mut written = vec!();
+        write_hdu(&mut written, &hdu).unwrap();
+
+        assert_eq!(&written[..], &data[ranges[1].0.start..ranges[1].1.end]);
+        assert_eq!(parser.read_hdu().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,26 @@
+//! The prelude re-exports the most used items of this crate, so that
+//! `use fits_rs::prelude::*;` is enough to parse a file and read its headers
+//! and tables.
+//!
+//! There is no single parse function or error type; `fits` parses a file with
+//! `nom`, `fits_with_options` parses it into a `Result` with a `ParseError`.
+
+pub use super::image::Image;
+pub use super::parser::{fits, fits_with_options, FitsParser, ParseError, ParseOptions};
+pub use super::table::{BinTable, TableError};
+pub use super::types::{Fits, HDU, Header, Keyword, KeywordRecord, Value, ValueRetrievalError};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prelude_should_be_enough_to_read_a_table() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let parsed = fits_with_options(data, &ParseOptions::new()).expect("Did not expect the parse to fail");
+        let header: &Header = &parsed.fits[1].header;
+
+        assert_eq!(header.character_string_value_of(&Keyword::EXTNAME), Ok("TARGETTABLES"));
+        assert!(BinTable::new(header).is_ok());
+    }
+}
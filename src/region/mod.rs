@@ -0,0 +1,380 @@
+//! The region module reads DS9 region files and rasterizes them to masks of
+//! the pixels of an image.
+//!
+//! A common subset of the DS9 syntax is supported: `circle`, `box`,
+//! `ellipse` and `polygon`, in `image` or celestial coordinates (`fk5`,
+//! `icrs`, `fk4`, `j2000`, `b1950`, `galactic`, `ecliptic` and `wcs`). Regions
+//! prefixed with `-` are excluded. Properties after `#` and `global` lines are
+//! ignored, and `physical` coordinates are taken to be image coordinates.
+//!
+//! Celestial coordinates are in degrees, or sexagesimal with hours of right
+//! ascension for the equatorial systems, and are assumed to be in the system
+//! of the world coordinate system of the image. Sizes are in degrees unless
+//! marked with `"`, `'` or `r`.
+
+use std::str::FromStr;
+use super::types::{Header, HDU};
+use super::wcs::{Wcs, WcsError};
+
+/// The coordinate system of a region.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum System {
+    /// Image pixel coordinates: the center of the first pixel is at `(1, 1)`.
+    Image,
+    /// Celestial coordinates, in degrees.
+    Sky,
+}
+
+/// The shape of a region. Positions and sizes are in the units of its system,
+/// angles in degrees counterclockwise from the first axis.
+#[derive(Debug, PartialEq, Clone)]
+#[allow(missing_docs)]
+pub enum Shape {
+    /// A circle around `(x, y)`.
+    Circle { x: f64, y: f64, radius: f64 },
+    /// A rectangle centered on `(x, y)`.
+    Box { x: f64, y: f64, width: f64, height: f64, angle: f64 },
+    /// An ellipse around `(x, y)` with semi-axes `rx` and `ry`.
+    Ellipse { x: f64, y: f64, rx: f64, ry: f64, angle: f64 },
+    /// A polygon through its vertices.
+    Polygon(Vec<(f64, f64)>),
+}
+
+impl Shape {
+    /// Determines if the point `(px, py)` lies in this shape.
+    pub fn contains(&self, px: f64, py: f64) -> bool {
+        let rotated = |x: f64, y: f64, angle: f64| {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let (dx, dy) = (px - x, py - y);
+            (dx * cos + dy * sin, -dx * sin + dy * cos)
+        };
+        match *self {
+            Shape::Circle { x, y, radius } => (px - x).powi(2) + (py - y).powi(2) <= radius * radius,
+            Shape::Box { x, y, width, height, angle } => {
+                let (u, v) = rotated(x, y, angle);
+                u.abs() <= width / 2f64 && v.abs() <= height / 2f64
+            }
+            Shape::Ellipse { x, y, rx, ry, angle } => {
+                let (u, v) = rotated(x, y, angle);
+                (u / rx).powi(2) + (v / ry).powi(2) <= 1f64
+            }
+            Shape::Polygon(ref vertices) => {
+                let mut inside = false;
+                let mut previous = vertices.len().wrapping_sub(1);
+                for (i, &(xi, yi)) in vertices.iter().enumerate() {
+                    let (xj, yj) = vertices[previous];
+                    if (yi > py) != (yj > py) && px < (xj - xi) * (py - yi) / (yj - yi) + xi {
+                        inside = !inside;
+                    }
+                    previous = i;
+                }
+                inside
+            }
+        }
+    }
+}
+
+/// A region of a region file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Region {
+    /// The shape of the region.
+    pub shape: Shape,
+    /// The coordinate system of the shape.
+    pub system: System,
+    /// Whether the region is excluded, marked with `-`.
+    pub exclude: bool,
+}
+
+impl Region {
+    /// The shape of this region in image coordinates, converting celestial
+    /// coordinates with `wcs`. Sizes are converted with the pixel scale at
+    /// the center and angles are turned along with the direction of north.
+    pub fn to_image(&self, wcs: Option<&Wcs>) -> Result<Shape, RegionError> {
+        if self.system == System::Image {
+            return Ok(self.shape.clone())
+        }
+        let wcs = wcs.ok_or(RegionError::NoWcs)?;
+        let pixel = |longitude: f64, latitude: f64| wcs.sky_to_pixel(longitude, latitude).map_err(RegionError::Wcs);
+        // The pixel scale and the direction of north, one arcsecond from the center.
+        let local = |longitude: f64, latitude: f64| -> Result<((f64, f64), f64, f64), RegionError> {
+            let center = pixel(longitude, latitude)?;
+            let north = pixel(longitude, latitude + 1f64 / 3600f64)?;
+            let (dx, dy) = (north.0 - center.0, north.1 - center.1);
+            Ok((center, dx.hypot(dy) * 3600f64, dy.atan2(dx).to_degrees() - 90f64))
+        };
+        Ok(match self.shape {
+            Shape::Circle { x, y, radius } => {
+                let ((cx, cy), scale, _) = local(x, y)?;
+                Shape::Circle { x: cx, y: cy, radius: radius * scale }
+            }
+            Shape::Box { x, y, width, height, angle } => {
+                let ((cx, cy), scale, rotation) = local(x, y)?;
+                Shape::Box { x: cx, y: cy, width: width * scale, height: height * scale, angle: angle + rotation }
+            }
+            Shape::Ellipse { x, y, rx, ry, angle } => {
+                let ((cx, cy), scale, rotation) = local(x, y)?;
+                Shape::Ellipse { x: cx, y: cy, rx: rx * scale, ry: ry * scale, angle: angle + rotation }
+            }
+            Shape::Polygon(ref vertices) => Shape::Polygon(vertices.iter()
+                .map(|&(longitude, latitude)| pixel(longitude, latitude))
+                .collect::<Result<_, _>>()?),
+        })
+    }
+}
+
+/// Read the regions of the DS9 region file `text`.
+pub fn parse_regions(text: &str) -> Result<Vec<Region>, RegionError> {
+    let mut regions = vec!();
+    let mut system = System::Image;
+    let mut hours = false;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = match line.find('#') {
+            Option::Some(start) => &line[..start],
+            Option::None => line,
+        };
+        for part in line.split(';').map(str::trim).filter(|part| !part.is_empty()) {
+            let lower = part.to_ascii_lowercase();
+            match lower.as_str() {
+                "image" | "physical" => {
+                    system = System::Image;
+                    continue
+                }
+                "fk5" | "fk4" | "icrs" | "j2000" | "b1950" => {
+                    system = System::Sky;
+                    hours = true;
+                    continue
+                }
+                "galactic" | "ecliptic" | "wcs" => {
+                    system = System::Sky;
+                    hours = false;
+                    continue
+                }
+                _ if lower.starts_with("global") => continue,
+                _ => (),
+            }
+            regions.push(region(&lower, system, hours).ok_or(RegionError::Syntax(line_number))??);
+        }
+    }
+    Ok(regions)
+}
+
+/// The region described by `text`, `None` when it is not a shape. The shape
+/// may not be supported.
+fn region(text: &str, system: System, hours: bool) -> Option<Result<Region, RegionError>> {
+    let (exclude, text) = match text.chars().next() {
+        Option::Some('-') => (true, &text[1..]),
+        Option::Some('+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let open = text.find('(')?;
+    let close = text.rfind(')')?;
+    let name = text[..open].trim();
+    let arguments: Vec<&str> = text[open + 1..close]
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|argument| !argument.is_empty())
+        .collect();
+    let position = |i: usize| -> Option<(f64, f64)> {
+        Option::Some((coordinate(arguments.get(i)?, system, hours)?, coordinate(arguments.get(i + 1)?, system, false)?))
+    };
+    let size = |i: usize| arguments.get(i).and_then(|argument| size(argument, system));
+    let angle = |i: usize| match arguments.get(i) {
+        Option::Some(argument) => f64::from_str(argument.trim_end_matches('d')).ok(),
+        Option::None => Option::Some(0f64),
+    };
+    let shape = match name {
+        "circle" if arguments.len() == 3 => {
+            let (x, y) = position(0)?;
+            Shape::Circle { x, y, radius: size(2)? }
+        }
+        "box" if arguments.len() == 4 || arguments.len() == 5 => {
+            let (x, y) = position(0)?;
+            Shape::Box { x, y, width: size(2)?, height: size(3)?, angle: angle(4)? }
+        }
+        "ellipse" if arguments.len() == 4 || arguments.len() == 5 => {
+            let (x, y) = position(0)?;
+            Shape::Ellipse { x, y, rx: size(2)?, ry: size(3)?, angle: angle(4)? }
+        }
+        "polygon" if arguments.len() >= 6 && arguments.len().is_multiple_of(2) => {
+            Shape::Polygon((0..arguments.len()).step_by(2).map(position).collect::<Option<_>>()?)
+        }
+        "circle" | "box" | "ellipse" | "polygon" => return Option::None,
+        _ if name.chars().all(|c| c.is_ascii_alphabetic()) && !name.is_empty() => {
+            return Option::Some(Err(RegionError::UnsupportedShape(name.to_string())))
+        }
+        _ => return Option::None,
+    };
+    Option::Some(Ok(Region { shape, system, exclude }))
+}
+
+/// A position in `system`: pixels, or degrees for celestial coordinates,
+/// which may also be sexagesimal, in hours when `hours` is set.
+fn coordinate(text: &str, system: System, hours: bool) -> Option<f64> {
+    if system == System::Sky && text.contains(':') {
+        let negative = text.starts_with('-');
+        let mut value = 0f64;
+        for (i, part) in text.trim_start_matches(['-', '+']).split(':').enumerate() {
+            value += f64::from_str(part).ok()? / 60f64.powi(i as i32);
+        }
+        let value = if negative { -value } else { value };
+        return Option::Some(if hours { value * 15f64 } else { value })
+    }
+    f64::from_str(text.trim_end_matches('d')).ok()
+}
+
+/// A size in `system`: pixels, or degrees for celestial coordinates.
+fn size(text: &str, system: System) -> Option<f64> {
+    if system == System::Image {
+        return f64::from_str(text.trim_end_matches(['i', 'p'])).ok()
+    }
+    let (number, factor) = match text.chars().last()? {
+        '"' => (&text[..text.len() - 1], 1f64 / 3600f64),
+        '\'' => (&text[..text.len() - 1], 1f64 / 60f64),
+        'd' => (&text[..text.len() - 1], 1f64),
+        'r' => (&text[..text.len() - 1], 180f64 / std::f64::consts::PI),
+        _ => (text, 1f64),
+    };
+    f64::from_str(number).ok().map(|value| value * factor)
+}
+
+/// Which pixels of an image lie in a set of regions.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mask {
+    /// The number of columns, `NAXIS1`.
+    pub width: usize,
+    /// The number of rows, `NAXIS2`.
+    pub height: usize,
+    /// Whether each pixel is in the regions, row by row from the first row.
+    pub pixels: Vec<bool>,
+}
+
+impl Mask {
+    /// A mask of `width` by `height` pixels of the pixels in `regions`: those
+    /// in an included region, or in none when all regions are excluded, and
+    /// in no excluded region. Pixels are in a region when their center is.
+    pub fn new(regions: &[Region], width: usize, height: usize, wcs: Option<&Wcs>) -> Result<Mask, RegionError> {
+        let shapes = regions.iter()
+            .map(|region| region.to_image(wcs).map(|shape| (shape, region.exclude)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let any_included = shapes.iter().any(|&(_, exclude)| !exclude);
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in 0..height {
+            for column in 0..width {
+                let (x, y) = ((column + 1) as f64, (row + 1) as f64);
+                let included = !any_included || shapes.iter().any(|(shape, exclude)| !exclude && shape.contains(x, y));
+                pixels.push(included && !shapes.iter().any(|(shape, exclude)| *exclude && shape.contains(x, y)));
+            }
+        }
+        Ok(Mask { width, height, pixels })
+    }
+
+    /// Whether the pixel at zero based column `x` and row `y` is in the
+    /// regions. `false` outside the mask.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
+
+    /// The number of pixels in the regions.
+    pub fn count(&self) -> usize {
+        self.pixels.iter().filter(|&&pixel| pixel).count()
+    }
+}
+
+impl<'a> HDU<'a> {
+    /// The mask of the pixels of the image in this HDU that lie in `regions`,
+    /// see `Mask::new`. Celestial regions are located with the primary world
+    /// coordinate system of the header.
+    pub fn region_mask(&self, regions: &[Region]) -> Result<Mask, RegionError> {
+        mask(&self.header, regions)
+    }
+}
+
+/// The mask of the pixels of the image described by `header` that lie in
+/// `regions`, see `Mask::new`.
+pub fn mask(header: &Header, regions: &[Region]) -> Result<Mask, RegionError> {
+    let axes = header.naxes().map_err(|_| RegionError::NotAnImage)?;
+    if axes.len() < 2 {
+        return Err(RegionError::NotAnImage)
+    }
+    let wcs = if regions.iter().any(|region| region.system == System::Sky) {
+        Option::Some(header.wcs().map_err(RegionError::Wcs)?)
+    } else {
+        Option::None
+    };
+    Mask::new(regions, axes[0] as usize, axes[1] as usize, wcs.as_ref())
+}
+
+/// Problems that could occur when reading or rasterizing regions.
+#[derive(Debug, PartialEq)]
+pub enum RegionError {
+    /// The line with this number, counting from 1, could not be read.
+    Syntax(usize),
+    /// The shape with this name is not supported.
+    UnsupportedShape(String),
+    /// The header does not describe an image with two axes.
+    NotAnImage,
+    /// A celestial region needs a world coordinate system.
+    NoWcs,
+    /// The world coordinate system could not locate a region.
+    Wcs(WcsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::template;
+    use super::super::types::HDU;
+    use super::{parse_regions, Mask, Region, RegionError, Shape, System};
+
+    #[test]
+    fn regions_should_be_parsed_from_ds9_syntax() {
+        let text = "# Region file format: DS9 version 4.1
+global color=green dashlist=8 3 width=1
+image
+circle(10,20,5) # color=red
+-box(10 20 4 2 30)
+fk5;ellipse(10:00:00,-20:30:00,3\",1',45) # text={star}
+polygon(1,2,3,4,5,6)";
+
+        let regions = parse_regions(text).unwrap();
+
+        assert_eq!(regions, vec!(
+            Region { shape: Shape::Circle { x: 10f64, y: 20f64, radius: 5f64 }, system: System::Image, exclude: false },
+            Region { shape: Shape::Box { x: 10f64, y: 20f64, width: 4f64, height: 2f64, angle: 30f64 }, system: System::Image, exclude: true },
+            Region { shape: Shape::Ellipse { x: 150f64, y: -20.5, rx: 3f64 * (1f64 / 3600f64), ry: 1f64 / 60f64, angle: 45f64 }, system: System::Sky, exclude: false },
+            Region { shape: Shape::Polygon(vec!((1f64, 2f64), (3f64, 4f64), (5f64, 6f64))), system: System::Sky, exclude: false },
+        ));
+        assert_eq!(parse_regions("image\ncircle(1,2)"), Err(RegionError::Syntax(2)));
+        assert_eq!(parse_regions("annulus(1,2,3,4)"), Err(RegionError::UnsupportedShape("annulus".to_string())));
+    }
+
+    #[test]
+    fn mask_should_include_and_exclude_pixel_centers() {
+        let regions = parse_regions("box(3,3,5,5)\n-circle(3,3,1)\npolygon(5.4,0.5,8.5,0.5,8.5,3.6)").unwrap();
+
+        let mask = Mask::new(&regions, 8, 5, Option::None).unwrap();
+
+        assert!(mask.get(0, 0) && mask.get(4, 4));
+        assert!(!mask.get(2, 2) && !mask.get(1, 2) && !mask.get(5, 4));
+        assert!(mask.get(5, 0) && mask.get(7, 2) && !mask.get(5, 1));
+        assert_eq!(mask.count(), 25 - 5 + 6);
+        assert_eq!(Mask::new(&parse_regions("-circle(1,1,0.5)").unwrap(), 2, 2, Option::None).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn celestial_regions_should_be_located_with_the_wcs() {
+        let header = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 100\nNAXIS2 = 100\nCTYPE1 = 'RA---TAN'\nCTYPE2 = 'DEC--TAN'\nCRPIX1 = 50.0\nCRPIX2 = 50.0\nCRVAL1 = 150.0\nCRVAL2 = 20.0\nCDELT1 = -2.777777777777778E-4\nCDELT2 = 2.777777777777778E-4").unwrap();
+        let hdu = HDU::new(header);
+        let regions = parse_regions("fk5\ncircle(150.0,20.0,3.5\")").unwrap();
+
+        let mask = hdu.region_mask(&regions).unwrap();
+        let shape = regions[0].to_image(Option::Some(&hdu.header.wcs().unwrap())).unwrap();
+
+        match shape {
+            Shape::Circle { x, y, radius } => assert!((x - 50f64).abs() < 1e-6 && (y - 50f64).abs() < 1e-6 && (radius - 3.5).abs() < 1e-3),
+            _ => panic!("Expected a circle"),
+        }
+        assert_eq!(mask.count(), 37);
+        assert!(mask.get(49, 49));
+        assert_eq!(regions[0].to_image(Option::None), Err(RegionError::NoWcs));
+    }
+}
@@ -0,0 +1,221 @@
+//! The spectrum module extracts one dimensional spectra, flux against
+//! wavelength, from images with a spectral world coordinate system and from
+//! binary tables with wavelength and flux columns.
+
+use super::image::{Image, ImageError};
+use super::table::{BinTable, TableError};
+use super::types::{Fits, HDU, HduKind, Keyword};
+use super::wcs::WcsError;
+
+/// The columns that hold the wavelengths of spectra in binary tables, in
+/// order of preference. `LOGLAM`, as in SDSS spectra, holds the logarithm of
+/// the wavelength in Angstrom.
+pub const WAVELENGTH_COLUMNS: [&str; 4] = ["WAVELENGTH", "WAVE", "LAMBDA", "LOGLAM"];
+
+/// The columns that hold the flux of spectra in binary tables, in order of
+/// preference.
+pub const FLUX_COLUMNS: [&str; 2] = ["FLUX", "FLUX_DENSITY"];
+
+/// The flux of a source against wavelength, with the samples of which the
+/// wavelength or flux is undefined left out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spectrum {
+    /// The wavelengths of the samples, in m.
+    pub wavelength: Vec<f64>,
+    /// The flux at each wavelength.
+    pub flux: Vec<f64>,
+    /// The unit of the flux, `BUNIT` of an image or `TUNITn` of the flux
+    /// column, e.g. `erg/s/cm2/Angstrom`.
+    pub unit: Option<String>,
+}
+
+impl Spectrum {
+    /// Extract the spectrum from the first HDU of `fits` that holds one, see
+    /// `Spectrum::from_hdu`.
+    pub fn new(fits: &Fits) -> Result<Spectrum, SpectrumError> {
+        for hdu in fits.iter() {
+            match Spectrum::from_hdu(hdu) {
+                Err(SpectrumError::NotASpectrum) => continue,
+                result => return result,
+            }
+        }
+        Err(SpectrumError::NotASpectrum)
+    }
+
+    /// Extract the spectrum from `hdu`, which is either an image whose only
+    /// axis longer than one pixel is the spectral axis of its world
+    /// coordinate system, or a binary table with one of the
+    /// `WAVELENGTH_COLUMNS` and one of the `FLUX_COLUMNS`.
+    ///
+    /// Wavelengths of images are vacuum wavelengths. When the columns of a
+    /// table hold an array per row, as in spectra of a single row, the rows
+    /// are concatenated. A wavelength column without `TUNITn` is taken to be
+    /// in Angstrom.
+    pub fn from_hdu(hdu: &HDU) -> Result<Spectrum, SpectrumError> {
+        match hdu.kind() {
+            HduKind::Primary | HduKind::Image => from_image(hdu),
+            HduKind::BinaryTable => from_table(hdu),
+            _ => Err(SpectrumError::NotASpectrum),
+        }
+    }
+
+    /// The number of samples.
+    pub fn len(&self) -> usize {
+        self.wavelength.len()
+    }
+
+    /// Determines if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.wavelength.is_empty()
+    }
+
+    fn push(&mut self, wavelength: f64, flux: f64) {
+        if wavelength.is_finite() && flux.is_finite() {
+            self.wavelength.push(wavelength);
+            self.flux.push(flux);
+        }
+    }
+}
+
+fn from_image(hdu: &HDU) -> Result<Spectrum, SpectrumError> {
+    let axes = match hdu.header.naxes() {
+        Ok(axes) => axes,
+        Err(_) => return Err(SpectrumError::NotASpectrum),
+    };
+    let wcs = hdu.header.wcs().map_err(SpectrumError::Wcs)?;
+    let axis = match wcs.spectral() {
+        Option::Some(spectral) if spectral.axis < axes.len() => spectral.axis,
+        _ => return Err(SpectrumError::NotASpectrum),
+    };
+    if axes.iter().enumerate().any(|(index, &length)| index != axis && length != 1) {
+        return Err(SpectrumError::NotASpectrum)
+    }
+    let image = Image::new(&hdu.header, hdu.data()).map_err(SpectrumError::Image)?;
+    let mut spectrum = Spectrum {
+        wavelength: vec!(),
+        flux: vec!(),
+        unit: hdu.header.character_string_value_of(&Keyword::BUNIT).ok().map(|unit| unit.trim_end().to_string()),
+    };
+    for (channel, &flux) in image.pixels.iter().enumerate() {
+        let wavelength = wcs.channel_to_wavelength(channel as f64 + 1f64).map_err(SpectrumError::Wcs)?;
+        spectrum.push(wavelength, flux);
+    }
+    Ok(spectrum)
+}
+
+fn from_table(hdu: &HDU) -> Result<Spectrum, SpectrumError> {
+    let table = BinTable::new(&hdu.header).map_err(SpectrumError::Table)?;
+    let find = |names: &[&'static str]| names.iter().filter_map(|name| table.column_index(name).map(|index| (*name, index))).next();
+    let ((wavelength_name, wavelength_index), (flux_name, flux_index)) = match (find(&WAVELENGTH_COLUMNS), find(&FLUX_COLUMNS)) {
+        (Option::Some(wavelength), Option::Some(flux)) => (wavelength, flux),
+        _ => return Err(SpectrumError::NotASpectrum),
+    };
+    let values = table.read_columns(hdu.data(), &[wavelength_index, flux_index])
+        .map_err(SpectrumError::Table)?;
+    let wavelength_column = &table.columns()[wavelength_index];
+    let flux_column = &table.columns()[flux_index];
+    let wavelengths = values[0].physical(wavelength_column)
+        .ok_or_else(|| SpectrumError::NotNumeric(wavelength_name.to_string()))?;
+    let fluxes = values[1].physical(flux_column)
+        .ok_or_else(|| SpectrumError::NotNumeric(flux_name.to_string()))?;
+    if wavelengths.len() != fluxes.len() {
+        return Err(SpectrumError::LengthMismatch(wavelengths.len(), fluxes.len()))
+    }
+    let scale = if wavelength_name == "LOGLAM" {
+        Option::None
+    } else {
+        let unit = wavelength_column.unit.as_ref().map(|unit| unit.trim()).unwrap_or("");
+        Option::Some(length_unit(unit).ok_or_else(|| SpectrumError::UnsupportedUnit(unit.to_string()))?)
+    };
+    let mut spectrum = Spectrum { wavelength: vec!(), flux: vec!(), unit: flux_column.unit.clone() };
+    for (&wavelength, &flux) in wavelengths.iter().zip(fluxes.iter()) {
+        let wavelength = match scale {
+            Option::Some(scale) => wavelength * scale,
+            Option::None => 10f64.powf(wavelength) * 1e-10,
+        };
+        spectrum.push(wavelength, flux);
+    }
+    Ok(spectrum)
+}
+
+/// The value in m of the unit of length `name`, `None` when it is not known.
+/// An empty unit is Angstrom.
+fn length_unit(name: &str) -> Option<f64> {
+    let scale = match name {
+        "" | "Angstrom" | "angstrom" | "Angstroms" | "angstroms" | "AA" | "A" | "0.1 nm" => 1e-10,
+        "nm" => 1e-9,
+        "um" | "micron" | "microns" => 1e-6,
+        "mm" => 1e-3,
+        "cm" => 1e-2,
+        "m" => 1f64,
+        _ => return Option::None,
+    };
+    Option::Some(scale)
+}
+
+/// Extract the spectrum of `fits`, see `Spectrum::new`.
+pub fn spectrum(fits: &Fits) -> Result<Spectrum, SpectrumError> {
+    Spectrum::new(fits)
+}
+
+/// Problems that could occur when extracting a spectrum.
+#[derive(Debug, PartialEq)]
+pub enum SpectrumError {
+    /// No HDU is a one dimensional image with a spectral axis or a binary
+    /// table with wavelength and flux columns.
+    NotASpectrum,
+    /// The image could not be decoded.
+    Image(ImageError),
+    /// The table could not be decoded.
+    Table(TableError),
+    /// The world coordinate system could not be read or applied.
+    Wcs(WcsError),
+    /// The column with this name is not numeric.
+    NotNumeric(String),
+    /// The unit of the wavelength column is not a known unit of length.
+    UnsupportedUnit(String),
+    /// The wavelength and flux columns hold a different number of values.
+    LengthMismatch(usize, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::template;
+    use super::super::types::{Fits, HDU};
+    use super::{spectrum, Spectrum, SpectrumError};
+
+    #[test]
+    fn spectrum_should_follow_the_spectral_axis_of_images() {
+        let header = template("SIMPLE = T\nBITPIX = -32\nNAXIS = 2\nNAXIS1 = 1\nNAXIS2 = 4\nCTYPE2 = 'WAVE'\nCUNIT2 = 'Angstrom'\nCRPIX2 = 1.0\nCRVAL2 = 5000.0\nCDELT2 = 2.0\nBUNIT = 'erg/s/cm2/Angstrom'").unwrap();
+        let data: Vec<u8> = [1f32, f32::NAN, 3f32, 4f32].iter().flat_map(|value| value.to_be_bytes()).collect();
+        let file = Fits::new(HDU::with_data(header, &data), vec!());
+
+        let spectrum = spectrum(&file).unwrap();
+
+        assert_eq!(spectrum.len(), 3);
+        assert_eq!(spectrum.flux, vec!(1f64, 3f64, 4f64));
+        assert!((spectrum.wavelength[0] - 5000e-10).abs() < 1e-18);
+        assert!((spectrum.wavelength[2] - 5006e-10).abs() < 1e-18);
+        assert_eq!(spectrum.unit, Option::Some("erg/s/cm2/Angstrom".to_string()));
+    }
+
+    #[test]
+    fn spectrum_should_read_wavelength_and_flux_columns() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 24\nNAXIS2 = 1\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 2\nTTYPE1 = 'WAVE'\nTFORM1 = '3E'\nTUNIT1 = 'nm'\nTTYPE2 = 'FLUX'\nTFORM2 = '3E'\nTUNIT2 = 'Jy'").unwrap();
+        let data: Vec<u8> = [400f32, 500f32, 600f32, 1f32, 2f32, 3f32].iter().flat_map(|value| value.to_be_bytes()).collect();
+        let loglam = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 8\nNAXIS2 = 1\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 2\nTTYPE1 = 'loglam'\nTFORM1 = 'E'\nTTYPE2 = 'flux'\nTFORM2 = 'E'").unwrap();
+        let sdss: Vec<u8> = [4f32, 5f32].iter().flat_map(|value| value.to_be_bytes()).collect();
+        let unknown = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 8\nNAXIS2 = 0\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 2\nTTYPE1 = 'LAMBDA'\nTFORM1 = 'E'\nTUNIT1 = 'furlong'\nTTYPE2 = 'FLUX'\nTFORM2 = 'E'").unwrap();
+        let primary = || HDU::new(template("SIMPLE = T\nBITPIX = 8\nNAXIS = 0").unwrap());
+
+        let spectrum = Spectrum::new(&Fits::new(primary(), vec!(HDU::with_data(header, &data)))).unwrap();
+        let sdss = Spectrum::new(&Fits::new(primary(), vec!(HDU::with_data(loglam, &sdss)))).unwrap();
+
+        assert_eq!(spectrum.flux, vec!(1f64, 2f64, 3f64));
+        assert!((spectrum.wavelength[1] - 500e-9).abs() < 1e-18);
+        assert_eq!(spectrum.unit, Option::Some("Jy".to_string()));
+        assert!((sdss.wavelength[0] - 1e-6).abs() < 1e-15);
+        assert_eq!(Spectrum::new(&Fits::new(primary(), vec!(HDU::with_data(unknown, &[])))), Err(SpectrumError::UnsupportedUnit("furlong".to_string())));
+        assert_eq!(Spectrum::new(&Fits::new(primary(), vec!())), Err(SpectrumError::NotASpectrum));
+    }
+}
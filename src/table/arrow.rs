@@ -0,0 +1,237 @@
+//! Conversion of binary tables into Arrow record batches.
+
+extern crate arrow_array;
+extern crate arrow_buffer;
+extern crate arrow_schema;
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use self::arrow_array::{ArrayRef, ArrowPrimitiveType, BooleanArray, FixedSizeListArray, ListArray, PrimitiveArray, RecordBatch, StringArray};
+use self::arrow_array::types::{Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type};
+use self::arrow_buffer::{OffsetBuffer, ScalarBuffer};
+use self::arrow_schema::{ArrowError, DataType, Field, Schema};
+use super::{BinTable, BinType, Column, ColumnData, ColumnValues, TableError};
+
+/// Problems that could occur when converting a binary table into Arrow.
+#[derive(Debug)]
+pub enum ArrowConversionError {
+    /// The table could not be read.
+    Table(TableError),
+    /// The decoded columns could not be assembled into Arrow arrays.
+    Arrow(ArrowError),
+}
+
+impl From<TableError> for ArrowConversionError {
+    fn from(error: TableError) -> ArrowConversionError {
+        ArrowConversionError::Table(error)
+    }
+}
+
+impl From<ArrowError> for ArrowConversionError {
+    fn from(error: ArrowError) -> ArrowConversionError {
+        ArrowConversionError::Arrow(error)
+    }
+}
+
+impl BinTable {
+    /// Convert this table, with data array `data`, into an Arrow `RecordBatch`.
+    ///
+    /// Every column becomes a field named after `TTYPEn`, with `TUNITn` as the
+    /// `unit` metadata. Fields with a repeat count become fixed size lists,
+    /// variable length arrays become lists and character columns become strings.
+    /// Integers equal to `TNULLn` become nulls. The unsigned integer convention
+    /// of `TZEROn` maps to unsigned Arrow types, other scaled columns are
+    /// converted to their physical `Float64` values.
+    pub fn to_record_batch(&self, data: &[u8]) -> Result<RecordBatch, ArrowConversionError> {
+        self.record_batch(data, 0..self.rows)
+    }
+
+    /// Convert the `rows` of this table into a `RecordBatch`.
+    pub(super) fn record_batch(&self, data: &[u8], rows: Range<usize>) -> Result<RecordBatch, ArrowConversionError> {
+        let mut fields = vec!();
+        let mut arrays = vec!();
+        for (index, column) in self.columns.iter().enumerate() {
+            let values = self.read_column_rows(data, index, rows.clone())?;
+            let array = column_array(column, &values)?;
+            let name = column.name.clone().unwrap_or_else(|| format!("COL{}", index + 1));
+            let mut metadata = HashMap::new();
+            if let Option::Some(ref unit) = column.unit {
+                metadata.insert("unit".to_string(), unit.clone());
+            }
+            fields.push(Field::new(name, array.data_type().clone(), is_nullable(column)).with_metadata(metadata));
+            arrays.push(array);
+        }
+        Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+    }
+}
+
+fn is_nullable(column: &Column) -> bool {
+    column.null.is_some() || column.form.kind == BinType::Logical
+}
+
+fn column_array(column: &Column, values: &ColumnValues) -> Result<ArrayRef, ArrowError> {
+    if column.form.kind == BinType::Character {
+        let rows: Vec<Option<&str>> = (0..values.rows()).map(|row| values.text(row)).collect();
+        return Ok(Arc::new(StringArray::from(rows)))
+    }
+    let elements = element_array(column, &values.data)?;
+    let item = Arc::new(Field::new("item", elements.data_type().clone(), is_nullable(column)));
+    if column.form.descriptor.is_some() {
+        let offsets: Vec<i32> = values.offsets.iter().map(|&offset| offset as i32).collect();
+        Ok(Arc::new(ListArray::try_new(item, OffsetBuffer::new(ScalarBuffer::from(offsets)), elements, Option::None)?))
+    } else if column.form.repeat == 1 {
+        Ok(elements)
+    } else {
+        Ok(Arc::new(FixedSizeListArray::try_new(item, column.form.repeat as i32, elements, Option::None)?))
+    }
+}
+
+fn element_array(column: &Column, data: &ColumnData) -> Result<ArrayRef, ArrowError> {
+    let scaled = column.scale != 1f64 || column.zero != 0f64;
+    let unsigned = column.scale == 1f64;
+    let array: ArrayRef = match *data {
+        ColumnData::Logical(ref values) => Arc::new(BooleanArray::from(values.clone())),
+        ColumnData::Bit(ref values) => Arc::new(BooleanArray::from(values.clone())),
+        ColumnData::UnsignedByte(ref values) => {
+            if unsigned && column.zero == -128f64 {
+                Arc::new(integers::<Int8Type, u8, _>(values, column.null, |v| (v ^ 0x80) as i8))
+            } else if scaled {
+                Arc::new(physical(values, column))
+            } else {
+                Arc::new(integers::<UInt8Type, u8, _>(values, column.null, |v| v))
+            }
+        }
+        ColumnData::Short(ref values) => {
+            if unsigned && column.zero == 32768f64 {
+                Arc::new(integers::<UInt16Type, i16, _>(values, column.null, |v| (v as u16) ^ 0x8000))
+            } else if scaled {
+                Arc::new(physical(values, column))
+            } else {
+                Arc::new(integers::<Int16Type, i16, _>(values, column.null, |v| v))
+            }
+        }
+        ColumnData::Int(ref values) => {
+            if unsigned && column.zero == 2147483648f64 {
+                Arc::new(integers::<UInt32Type, i32, _>(values, column.null, |v| (v as u32) ^ 0x8000_0000))
+            } else if scaled {
+                Arc::new(physical(values, column))
+            } else {
+                Arc::new(integers::<Int32Type, i32, _>(values, column.null, |v| v))
+            }
+        }
+        ColumnData::Long(ref values) => {
+            if unsigned && column.zero == 9223372036854775808f64 {
+                Arc::new(integers::<UInt64Type, i64, _>(values, column.null, |v| (v as u64) ^ 0x8000_0000_0000_0000))
+            } else if scaled {
+                Arc::new(physical(values, column))
+            } else {
+                Arc::new(integers::<Int64Type, i64, _>(values, column.null, |v| v))
+            }
+        }
+        ColumnData::Character(_) => unreachable!("character columns are converted to strings"),
+        ColumnData::Float(ref values) => {
+            if scaled {
+                Arc::new(values.iter().map(|&v| v as f64 * column.scale + column.zero).collect::<PrimitiveArray<Float64Type>>())
+            } else {
+                Arc::new(values.iter().cloned().collect::<PrimitiveArray<Float32Type>>())
+            }
+        }
+        ColumnData::Double(ref values) => {
+            Arc::new(values.iter().map(|&v| v * column.scale + column.zero).collect::<PrimitiveArray<Float64Type>>())
+        }
+        ColumnData::ComplexFloat(ref values) => {
+            let parts: PrimitiveArray<Float32Type> = values.iter().flat_map(|&(re, im)| vec!(re, im)).collect();
+            let item = Arc::new(Field::new("item", DataType::Float32, false));
+            Arc::new(FixedSizeListArray::try_new(item, 2, Arc::new(parts), Option::None)?)
+        }
+        ColumnData::ComplexDouble(ref values) => {
+            let parts: PrimitiveArray<Float64Type> = values.iter().flat_map(|&(re, im)| vec!(re, im)).collect();
+            let item = Arc::new(Field::new("item", DataType::Float64, false));
+            Arc::new(FixedSizeListArray::try_new(item, 2, Arc::new(parts), Option::None)?)
+        }
+    };
+    Ok(array)
+}
+
+fn integers<T, R, F>(values: &[R], null: Option<i64>, convert: F) -> PrimitiveArray<T>
+    where T: ArrowPrimitiveType, R: Copy + Into<i64>, F: Fn(R) -> T::Native {
+    values.iter()
+        .map(|&v| if Option::Some(v.into()) == null { Option::None } else { Option::Some(convert(v)) })
+        .collect()
+}
+
+fn physical<R: Copy + Into<i64>>(values: &[R], column: &Column) -> PrimitiveArray<Float64Type> {
+    values.iter()
+        .map(|&v| {
+            let raw: i64 = v.into();
+            if Option::Some(raw) == column.null {
+                Option::None
+            } else {
+                Option::Some(raw as f64 * column.scale + column.zero)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::arrow_array::{Array, FixedSizeListArray, Int32Array, StringArray};
+    use super::arrow_schema::DataType;
+    use super::super::super::parser::fits;
+    use super::super::super::types::{Header, KeywordRecord, Keyword, Value};
+    use super::super::BinTable;
+
+    #[test]
+    fn target_pixel_file_should_convert_to_a_record_batch() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let table = BinTable::new(&fits[1].header).unwrap();
+
+        let batch = table.to_record_batch(fits[1].data()).unwrap();
+
+        assert_eq!(batch.num_rows(), 3599);
+        assert_eq!(batch.num_columns(), 12);
+        let schema = batch.schema();
+        let time = schema.field_with_name("TIME").unwrap();
+        assert_eq!(time.data_type(), &DataType::Float64);
+        assert_eq!(time.metadata().get("unit").map(|unit| unit.as_str()), Option::Some("BJD - 2454833"));
+        let raw_counts = batch.column(3).as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+        assert_eq!(raw_counts.value_length(), 121);
+        assert!(schema.field(3).is_nullable());
+    }
+
+    #[test]
+    fn nulls_unsigned_integers_and_strings_should_convert() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString("J".into()), Option::None),
+            KeywordRecord::new(Keyword::TNULLn(1u16), Value::Integer(-1i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(2u16), Value::CharacterString("I".into()), Option::None),
+            KeywordRecord::new(Keyword::TZEROn(2u16), Value::Integer(32768i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(3u16), Value::CharacterString("2A".into()), Option::None),
+        ));
+        let data = [
+            0, 0, 0, 5, 0x80, 0, b'a', b' ',
+            0xff, 0xff, 0xff, 0xff, 0x7f, 0xff, b'b', b'c',
+        ];
+        let table = BinTable::new(&header).unwrap();
+
+        let batch = table.to_record_batch(&data).unwrap();
+
+        let first = batch.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!(first.value(0), 5);
+        assert!(first.is_null(1));
+        assert_eq!(batch.schema().field(1).data_type(), &DataType::UInt16);
+        let strings = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(strings.value(0), "a");
+        assert_eq!(strings.value(1), "bc");
+    }
+}
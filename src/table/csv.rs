@@ -0,0 +1,348 @@
+//! Import of CSV files as binary tables.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+use std::io::Read;
+use std::str::FromStr;
+use super::super::types::{Header, KeywordRecord, Keyword, Value};
+use super::{BinTable, BinType, TForm};
+
+/// A column of a CSV file and the way it is stored in a binary table.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CsvColumn {
+    /// The name of the column, stored as `TTYPEn`.
+    pub name: String,
+    /// The type of the elements of the column.
+    pub kind: BinType,
+    /// The physical unit of the column, stored as `TUNITn`.
+    pub unit: Option<String>,
+}
+
+/// A binary table extension built from a CSV file, ready to be written.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableHdu {
+    columns: Vec<CsvColumn>,
+    forms: Vec<String>,
+    nulls: Vec<Option<i64>>,
+    row_width: usize,
+    rows: usize,
+    data: Vec<u8>,
+}
+
+impl TableHdu {
+    /// The header of this binary table extension.
+    pub fn header(&self) -> Header<'_> {
+        let mut records = vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::Some("binary table extension".into())),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(self.row_width as i64), Option::Some("width of a row in bytes".into())),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(self.rows as i64), Option::Some("number of rows".into())),
+            KeywordRecord::new(Keyword::PCOUNT, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::GCOUNT, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(self.columns.len() as i64), Option::None),
+        );
+        for (index, column) in self.columns.iter().enumerate() {
+            let n = index as u16 + 1;
+            records.push(KeywordRecord::new(Keyword::TTYPEn(n), Value::CharacterString(Cow::Borrowed(&column.name)), Option::None));
+            records.push(KeywordRecord::new(Keyword::TFORMn(n), Value::CharacterString(Cow::Borrowed(&self.forms[index])), Option::None));
+            if let Option::Some(ref unit) = column.unit {
+                records.push(KeywordRecord::new(Keyword::TUNITn(n), Value::CharacterString(Cow::Borrowed(unit)), Option::None));
+            }
+            if let Option::Some(null) = self.nulls[index] {
+                records.push(KeywordRecord::new(Keyword::TNULLn(n), Value::Integer(null), Option::None));
+            }
+        }
+        records.push(KeywordRecord::new(Keyword::END, Value::Undefined, Option::None));
+        Header::new(records)
+    }
+
+    /// The data array of this binary table extension, without padding.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The description of this binary table.
+    pub fn table(&self) -> BinTable {
+        BinTable::new(&self.header()).expect("a generated header describes a binary table")
+    }
+
+    /// The bytes of this HDU as they appear in a FITS file: the header followed
+    /// by the data array, each padded to whole blocks.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header().to_fits_string().into_bytes();
+        bytes.extend_from_slice(&self.data);
+        let padding = (2880 - self.data.len() % 2880) % 2880;
+        bytes.extend(::std::iter::repeat_n(0u8, padding));
+        bytes
+    }
+}
+
+/// Problems that could occur when importing a CSV file.
+#[derive(Debug)]
+pub enum CsvError {
+    /// The CSV file could not be read.
+    Io(io::Error),
+    /// The CSV file has no header line with the column names.
+    NoHeader,
+    /// A quoted field is not closed.
+    UnterminatedQuote,
+    /// The schema has a different number of columns than the CSV file.
+    SchemaLength,
+    /// The schema has a type that can not be imported.
+    UnsupportedType(BinType),
+    /// The data row, starting at 1, has a different number of fields than the header.
+    FieldCount(usize),
+    /// The field in the data row and column, both starting at 1, is not valid for its type.
+    InvalidValue(usize, usize),
+}
+
+impl From<io::Error> for CsvError {
+    fn from(error: io::Error) -> CsvError {
+        CsvError::Io(error)
+    }
+}
+
+/// Build a binary table from a CSV file with a header line of column names.
+///
+/// Without a `schema` the type of each column is inferred: 64-bit integers when
+/// all fields are integers, doubles when they are numbers, logicals for `T`,
+/// `F`, `true` or `false` and characters otherwise. Empty fields become `TNULLn`
+/// for integers, `NaN` for floats, undefined logicals and blank strings.
+/// Character columns are as wide as their longest field.
+pub fn read_csv<R: Read>(mut reader: R, schema: Option<&[CsvColumn]>) -> Result<TableHdu, CsvError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut records = records(&text)?.into_iter();
+    let names = records.next().ok_or(CsvError::NoHeader)?;
+    let rows: Vec<Vec<String>> = records.filter(|record| *record != [""]).collect();
+    if let Option::Some(index) = rows.iter().position(|row| row.len() != names.len()) {
+        return Err(CsvError::FieldCount(index + 1))
+    }
+
+    let columns: Vec<CsvColumn> = match schema {
+        Option::Some(schema) => {
+            if schema.len() != names.len() {
+                return Err(CsvError::SchemaLength)
+            }
+            if let Option::Some(column) = schema.iter().find(|column| !is_supported(column.kind)) {
+                return Err(CsvError::UnsupportedType(column.kind))
+            }
+            schema.to_vec()
+        }
+        Option::None => names.iter().enumerate()
+            .map(|(index, name)| CsvColumn {
+                name: name.trim().to_string(),
+                kind: infer(rows.iter().map(|row| row[index].as_str())),
+                unit: Option::None,
+            })
+            .collect(),
+    };
+
+    let mut forms = vec!();
+    let mut nulls = vec!();
+    for (index, column) in columns.iter().enumerate() {
+        let fields = || rows.iter().map(|row| row[index].as_str());
+        let repeat = if column.kind == BinType::Character {
+            fields().map(str::len).max().unwrap_or(0).max(1)
+        } else {
+            1
+        };
+        forms.push(TForm { repeat, kind: column.kind, descriptor: Option::None, max: Option::None });
+        nulls.push(if fields().any(|field| field.trim().is_empty()) { null(column.kind) } else { Option::None });
+    }
+
+    // A single element, or a byte per character, always has a width.
+    let row_width = forms.iter().filter_map(TForm::width).sum();
+    let mut data = Vec::with_capacity(row_width * rows.len());
+    for (r, row) in rows.iter().enumerate() {
+        for (c, field) in row.iter().enumerate() {
+            encode(&mut data, &forms[c], nulls[c], field)
+                .ok_or(CsvError::InvalidValue(r + 1, c + 1))?;
+        }
+    }
+
+    Ok(TableHdu {
+        columns,
+        forms: forms.iter().map(TForm::to_string).collect(),
+        nulls,
+        row_width,
+        rows: rows.len(),
+        data,
+    })
+}
+
+/// Splits CSV text into records of fields, following RFC 4180: fields can be
+/// quoted with `"`, contain separators and newlines, and escape quotes as `""`.
+fn records(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = vec!();
+    let mut record = vec!();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Option::Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Option::Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(::std::mem::take(&mut field)),
+            '\r' if !quoted => (),
+            '\n' if !quoted => {
+                record.push(::std::mem::take(&mut field));
+                records.push(::std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(CsvError::UnterminatedQuote)
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn is_supported(kind: BinType) -> bool {
+    matches!(kind, BinType::Logical | BinType::UnsignedByte | BinType::Short | BinType::Int | BinType::Long | BinType::Character | BinType::Float | BinType::Double)
+}
+
+fn infer<'a, I: Iterator<Item = &'a str> + Clone>(fields: I) -> BinType {
+    let mut values = fields.filter(|field| !field.trim().is_empty()).peekable();
+    if values.peek().is_none() {
+        BinType::Character
+    } else if values.clone().all(|field| i64::from_str(field.trim()).is_ok()) {
+        BinType::Long
+    } else if values.clone().all(|field| f64::from_str(field.trim()).is_ok()) {
+        BinType::Double
+    } else if values.all(|field| logical(field).is_some()) {
+        BinType::Logical
+    } else {
+        BinType::Character
+    }
+}
+
+fn logical(field: &str) -> Option<u8> {
+    match field.trim().to_lowercase().as_str() {
+        "t" | "true" => Option::Some(b'T'),
+        "f" | "false" => Option::Some(b'F'),
+        _ => Option::None,
+    }
+}
+
+/// The value that signifies an empty field in an integer column.
+fn null(kind: BinType) -> Option<i64> {
+    match kind {
+        BinType::UnsignedByte => Option::Some(u8::MAX as i64),
+        BinType::Short => Option::Some(i16::MIN as i64),
+        BinType::Int => Option::Some(i32::MIN as i64),
+        BinType::Long => Option::Some(i64::MIN),
+        _ => Option::None,
+    }
+}
+
+fn encode(data: &mut Vec<u8>, form: &TForm, null: Option<i64>, field: &str) -> Option<()> {
+    let trimmed = field.trim();
+    let integer = || if trimmed.is_empty() { null } else { i64::from_str(trimmed).ok() };
+    let real = || if trimmed.is_empty() { Option::Some(f64::NAN) } else { f64::from_str(trimmed).ok() };
+    match form.kind {
+        BinType::Logical => data.push(if trimmed.is_empty() { 0u8 } else { logical(trimmed)? }),
+        BinType::UnsignedByte => data.push(u8::try_from(integer()?).ok()?),
+        BinType::Short => data.extend_from_slice(&i16::try_from(integer()?).ok()?.to_be_bytes()),
+        BinType::Int => data.extend_from_slice(&i32::try_from(integer()?).ok()?.to_be_bytes()),
+        BinType::Long => data.extend_from_slice(&integer()?.to_be_bytes()),
+        BinType::Float => data.extend_from_slice(&(real()? as f32).to_be_bytes()),
+        BinType::Double => data.extend_from_slice(&real()?.to_be_bytes()),
+        BinType::Character => {
+            data.extend_from_slice(field.as_bytes());
+            data.extend(::std::iter::repeat_n(b' ', form.repeat - field.len()));
+        }
+        _ => return Option::None,
+    }
+    Option::Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::fits;
+    use super::super::super::types::{Header, KeywordRecord, Keyword, Value};
+    use super::super::{BinType, ColumnData};
+    use super::{read_csv, CsvColumn, CsvError};
+
+    const CATALOG: &str = "id,mag,name,flag\n1,1.5,\"Vega, a star\",T\n,2.25,Sirius,F\r\n3,,\"say \"\"hi\"\"\",\n";
+
+    #[test]
+    fn csv_should_be_imported_with_inferred_types() {
+        let hdu = read_csv(CATALOG.as_bytes(), Option::None).unwrap();
+        let table = hdu.table();
+
+        let forms: Vec<String> = table.columns().iter().map(|column| column.form.to_string()).collect();
+        assert_eq!(forms, vec!("1K", "1D", "12A", "1L"));
+        assert_eq!(table.rows(), 3);
+        assert_eq!(table.columns()[0].null, Option::Some(i64::MIN));
+        assert_eq!(table.read_column(hdu.data(), 0).unwrap().data, ColumnData::Long(vec!(1, i64::MIN, 3)));
+        let names = table.read_column(hdu.data(), 2).unwrap();
+        assert_eq!(names.text(0), Option::Some("Vega, a star"));
+        assert_eq!(names.text(2), Option::Some("say \"hi\""));
+        assert_eq!(table.read_column(hdu.data(), 3).unwrap().data,
+                   ColumnData::Logical(vec!(Option::Some(true), Option::Some(false), Option::None)));
+    }
+
+    #[test]
+    fn csv_should_be_imported_with_a_schema() {
+        let schema = vec!(
+            CsvColumn { name: "ID".to_string(), kind: BinType::Short, unit: Option::None },
+            CsvColumn { name: "MAG".to_string(), kind: BinType::Float, unit: Option::Some("mag".to_string()) },
+            CsvColumn { name: "NAME".to_string(), kind: BinType::Character, unit: Option::None },
+            CsvColumn { name: "FLAG".to_string(), kind: BinType::Logical, unit: Option::None },
+        );
+
+        let hdu = read_csv(CATALOG.as_bytes(), Option::Some(&schema)).unwrap();
+
+        let header = hdu.header();
+        assert_eq!(header.character_string_value_of(&Keyword::TFORMn(1)), Ok("1I"));
+        assert_eq!(header.character_string_value_of(&Keyword::TUNITn(2)), Ok("mag"));
+        assert_eq!(header.integer_value_of(&Keyword::TNULLn(1)), Ok(i16::MIN as i64));
+        assert_eq!(header.integer_value_of(&Keyword::NAXISn(1)), Ok(2 + 4 + 12 + 1));
+    }
+
+    #[test]
+    fn csv_errors_should_be_reported() {
+        assert!(matches!(read_csv("a,b\n1\n".as_bytes(), Option::None), Err(CsvError::FieldCount(1))));
+        assert!(matches!(read_csv("a\n\"open\n".as_bytes(), Option::None), Err(CsvError::UnterminatedQuote)));
+        assert!(matches!(read_csv("".as_bytes(), Option::None), Err(CsvError::NoHeader)));
+        let schema = vec!(CsvColumn { name: "A".to_string(), kind: BinType::UnsignedByte, unit: Option::None });
+        assert!(matches!(read_csv("a\n256\n".as_bytes(), Option::Some(&schema)), Err(CsvError::InvalidValue(1, 1))));
+    }
+
+    #[test]
+    fn imported_table_should_be_readable_as_fits() {
+        let hdu = read_csv(CATALOG.as_bytes(), Option::None).unwrap();
+        let primary = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::EXTEND, Value::Logical(true), Option::None),
+        ));
+        let mut bytes = primary.to_fits_string().into_bytes();
+        bytes.extend(hdu.to_bytes());
+
+        let fits = match fits(&bytes) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        assert_eq!(bytes.len(), 3 * 2880);
+        assert_eq!(fits.len(), 2);
+        assert_eq!(fits[1].data(), hdu.data());
+    }
+}
@@ -0,0 +1,135 @@
+//! The Green Bank convention, where keywords that vary from row to row of a
+//! binary table are stored as columns named after them.
+
+use std::borrow::Cow;
+use super::{BinTable, BinType, Column, ColumnData, ColumnValues, TableError};
+use super::super::types::{Header, Keyword, Value, ValueRetrievalError};
+
+/// Resolves the keywords of the rows of a binary table under the Green Bank
+/// convention: a scalar column named after a keyword holds its value for each
+/// row, and other keywords take their value from the header.
+pub struct GreenBank<'h, 'a: 'h> {
+    header: &'h Header<'a>,
+    columns: Vec<(String, Column, ColumnValues)>,
+    rows: usize,
+}
+
+impl<'h, 'a> GreenBank<'h, 'a> {
+    /// Decode the virtual keywords of the table described by `header` from
+    /// `data`, its data array. Columns of arrays and variable length arrays
+    /// are not keywords and are ignored.
+    pub fn new(header: &'h Header<'a>, data: &[u8]) -> Result<GreenBank<'h, 'a>, TableError> {
+        let table = BinTable::new(header)?;
+        let indices: Vec<usize> = table.columns().iter()
+            .enumerate()
+            .filter(|&(_, column)| column.name.is_some() && is_scalar(column))
+            .map(|(index, _)| index)
+            .collect();
+        let values = table.read_columns(data, &indices)?;
+        let columns = indices.into_iter().zip(values)
+            .map(|(index, values)| {
+                let column = table.columns()[index].clone();
+                let name = column.name.as_ref().expect("virtual keywords are named").to_ascii_uppercase();
+                (name, column, values)
+            })
+            .collect();
+        Ok(GreenBank { header, columns, rows: table.rows() })
+    }
+
+    /// The number of rows of the table.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Determines if `keyword` varies by row, i.e. there is a column named
+    /// after it.
+    pub fn is_virtual(&self, keyword: &Keyword) -> bool {
+        self.column_of(keyword).is_some()
+    }
+
+    /// The value of `keyword` for row `row`, from its column when there is
+    /// one and from the header otherwise. Null values are `Value::Undefined`.
+    ///
+    /// Panics when `row` is not less than the number of rows.
+    pub fn value_of(&self, row: usize, keyword: &Keyword) -> Result<Value<'a>, ValueRetrievalError> {
+        assert!(row < self.rows, "row {} of a table with {} rows", row, self.rows);
+        match self.column_of(keyword) {
+            Option::Some((column, values)) => Ok(cell(column, values, row)),
+            Option::None => self.header.value_of(keyword),
+        }
+    }
+
+    fn column_of(&self, keyword: &Keyword) -> Option<(&Column, &ColumnValues)> {
+        let name = keyword.to_string();
+        self.columns.iter()
+            .find(|&(column, _, _)| *column == name)
+            .map(|(_, column, values)| (column, values))
+    }
+}
+
+/// Character columns and columns of a single element can hold keywords.
+fn is_scalar(column: &Column) -> bool {
+    column.form.descriptor.is_none() && (column.form.repeat == 1 || column.form.kind == BinType::Character)
+}
+
+/// The value of the single element of `column` in `row`.
+fn cell(column: &Column, values: &ColumnValues, row: usize) -> Value<'static> {
+    let i = values.offsets[row];
+    let integer = |value: i64| {
+        if Option::Some(value) == column.null {
+            Value::Undefined
+        } else if column.scale == 1f64 && column.zero == 0f64 {
+            Value::Integer(value)
+        } else {
+            Value::Real(value as f64 * column.scale + column.zero)
+        }
+    };
+    let real = |value: f64| if value.is_nan() { Value::Undefined } else { Value::Real(value * column.scale + column.zero) };
+    match values.data {
+        ColumnData::Logical(ref v) => v[i].map(Value::Logical).unwrap_or(Value::Undefined),
+        ColumnData::Bit(ref v) => Value::Logical(v[i]),
+        ColumnData::UnsignedByte(ref v) => integer(v[i] as i64),
+        ColumnData::Short(ref v) => integer(v[i] as i64),
+        ColumnData::Int(ref v) => integer(v[i] as i64),
+        ColumnData::Long(ref v) => integer(v[i]),
+        ColumnData::Character(_) => match values.text(row) {
+            Option::Some(text) => Value::CharacterString(Cow::Owned(text.to_string())),
+            Option::None => Value::Undefined,
+        },
+        ColumnData::Float(ref v) => real(v[i] as f64),
+        ColumnData::Double(ref v) => real(v[i]),
+        ColumnData::ComplexFloat(ref v) => Value::Complex((v[i].0 as f64, v[i].1 as f64)),
+        ColumnData::ComplexDouble(ref v) => Value::Complex(v[i]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::super::types::{Keyword, Value, ValueRetrievalError};
+    use super::GreenBank;
+
+    #[test]
+    fn green_bank_should_resolve_columns_before_the_header() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 19\nNAXIS2 = 2\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 4\nTTYPE1 = 'OBJECT'\nTFORM1 = '8A'\nTTYPE2 = 'EXPOSURE'\nTFORM2 = 'E'\nTTYPE3 = 'SCAN'\nTFORM3 = 'J'\nTNULL3 = -1\nTTYPE4 = 'DATA'\nTFORM4 = '3B'\nOBJECT = 'ORION'\nTELESCOP = 'GBT'\nDATA = 7").unwrap();
+        let mut data = vec!();
+        for &(object, exposure, scan) in &[(&b"W3OH    "[..], 1.5f32, 12i32), (&b"ORION-KL"[..], 2.0f32, -1i32)] {
+            data.extend_from_slice(object);
+            data.extend_from_slice(&exposure.to_bits().to_be_bytes());
+            data.extend_from_slice(&scan.to_be_bytes());
+            data.extend_from_slice(&[1, 2, 3]);
+        }
+
+        let resolver = GreenBank::new(&header, &data).unwrap();
+
+        assert_eq!(resolver.rows(), 2);
+        assert_eq!(resolver.value_of(0, &Keyword::OBJECT), Ok(Value::CharacterString("W3OH".into())));
+        assert_eq!(resolver.value_of(1, &Keyword::OBJECT), Ok(Value::CharacterString("ORION-KL".into())));
+        assert_eq!(resolver.value_of(0, &Keyword::EXPOSURE), Ok(Value::Real(1.5)));
+        assert_eq!(resolver.value_of(1, &"SCAN".parse().unwrap()), Ok(Value::Undefined));
+        assert_eq!(resolver.value_of(1, &Keyword::TELESCOP), Ok(Value::CharacterString("GBT".into())));
+        assert_eq!(resolver.value_of(0, &"DATA".parse().unwrap()), Ok(Value::Integer(7)));
+        assert!(!resolver.is_virtual(&"DATA".parse().unwrap()));
+        assert_eq!(resolver.value_of(0, &Keyword::OBSERVER), Err(ValueRetrievalError::KeywordNotPresent));
+    }
+}
@@ -0,0 +1,124 @@
+//! Checks of the structure of binary tables, beyond what is needed to decode
+//! them.
+
+use std::fmt::{Display, Error, Formatter};
+use super::{BinTable, TableError};
+use super::super::types::{Header, Keyword};
+
+/// A problem with the structure of a binary table.
+#[derive(Debug, PartialEq)]
+#[allow(missing_docs)]
+pub enum TableLint {
+    /// The header does not describe a binary table that can be decoded.
+    Invalid(TableError),
+    /// The column with this number, starting at 1, has no `TTYPEn`.
+    MissingName(u16),
+    /// The widths of the columns, from their `TFORMn`, do not add up to
+    /// `NAXIS1`.
+    RowWidth { columns: usize, naxis1: usize },
+    /// `THEAP` points inside the main table, which is `table` bytes long.
+    HeapInsideTable { theap: usize, table: usize },
+    /// The variable length arrays reach `needed` bytes past the main table,
+    /// more than `PCOUNT`.
+    HeapBeyondPcount { pcount: usize, needed: usize },
+}
+
+impl Display for TableLint {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            TableLint::Invalid(ref error) => write!(f, "the table can not be decoded: {:?}", error),
+            TableLint::MissingName(n) => write!(f, "column {} has no TTYPE{}", n, n),
+            TableLint::RowWidth { columns, naxis1 } => write!(f, "the columns are {} bytes wide, but NAXIS1 = {}", columns, naxis1),
+            TableLint::HeapInsideTable { theap, table } => write!(f, "THEAP = {} lies inside the main table of {} bytes", theap, table),
+            TableLint::HeapBeyondPcount { pcount, needed } => write!(f, "variable length arrays reach {} bytes past the main table, beyond PCOUNT = {}", needed, pcount),
+        }
+    }
+}
+
+impl BinTable {
+    /// Check the binary table described by `header`: that every column has a
+    /// name, that the columns fill `NAXIS1` and that the heap does not start
+    /// inside the main table. A header that does not describe a binary table
+    /// gives a single `TableLint::Invalid`.
+    pub fn lint(header: &Header) -> Vec<TableLint> {
+        let table = match BinTable::describe(header) {
+            Ok(table) => table,
+            Err(error) => return vec!(TableLint::Invalid(error)),
+        };
+        let mut lints: Vec<TableLint> = table.columns.iter()
+            .enumerate()
+            .filter(|&(_, column)| column.name.is_none())
+            .map(|(index, _)| TableLint::MissingName(index as u16 + 1))
+            .collect();
+        if table.columns_width != table.row_width {
+            lints.push(TableLint::RowWidth { columns: table.columns_width, naxis1: table.row_width });
+        }
+        let size = table.row_width * table.rows;
+        if table.heap_offset < size {
+            lints.push(TableLint::HeapInsideTable { theap: table.heap_offset, table: size });
+        }
+        lints
+    }
+
+    /// Check that the variable length arrays of this table in `data`, its data
+    /// array, lie within the `PCOUNT` bytes that follow the main table.
+    pub fn lint_heap(&self, data: &[u8]) -> Vec<TableLint> {
+        let size = self.row_width * self.rows;
+        if data.len() < size {
+            return vec!(TableLint::Invalid(TableError::DataTooShort))
+        }
+        let mut needed = 0;
+        for (index, column) in self.columns.iter().enumerate() {
+            let descriptor = match column.form.descriptor {
+                Option::Some(descriptor) => descriptor,
+                Option::None => continue,
+            };
+            let width = match column.form.width() {
+                Option::Some(width) => width,
+                Option::None => return vec!(TableLint::Invalid(TableError::InvalidValue(Keyword::TFORMn(index as u16 + 1)))),
+            };
+            for row in 0..self.rows {
+                let start = row * self.row_width + column.offset;
+                let (count, offset) = descriptor.read(&data[start..start + width]);
+                if count == 0 {
+                    continue
+                }
+                let end = self.heap_offset
+                    .saturating_add(offset)
+                    .saturating_add(column.form.kind.width(count).unwrap_or(usize::MAX));
+                needed = needed.max(end.saturating_sub(size));
+            }
+        }
+        if needed > self.heap_size {
+            vec!(TableLint::HeapBeyondPcount { pcount: self.heap_size, needed })
+        } else {
+            vec!()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::{BinTable, TableError};
+    use super::TableLint;
+
+    #[test]
+    fn lint_should_check_names_widths_and_the_heap() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 14\nNAXIS2 = 2\nPCOUNT = 4\nGCOUNT = 1\nTFIELDS = 2\nTTYPE1 = 'TIME'\nTFORM1 = 'E'\nTFORM2 = '1PI'\nTHEAP = 20").unwrap();
+        let table = BinTable::new(&template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 12\nNAXIS2 = 2\nPCOUNT = 4\nGCOUNT = 1\nTFIELDS = 2\nTFORM1 = 'E'\nTFORM2 = '1PI'").unwrap()).unwrap();
+        let mut data = vec!(0u8; 24 + 4);
+        data[4..12].copy_from_slice(&[0, 0, 0, 2, 0, 0, 0, 16]);
+
+        assert_eq!(BinTable::lint(&header), vec!(
+            TableLint::MissingName(2),
+            TableLint::RowWidth { columns: 12, naxis1: 14 },
+            TableLint::HeapInsideTable { theap: 20, table: 28 },
+        ));
+        assert_eq!(table.lint_heap(&data), vec!(TableLint::HeapBeyondPcount { pcount: 4, needed: 20 }));
+        assert_eq!(table.lint_heap(&data[..10]), vec!(TableLint::Invalid(TableError::DataTooShort)));
+        assert_eq!(BinTable::lint(&template("SIMPLE = T").unwrap()), vec!(TableLint::Invalid(TableError::NotABinaryTable)));
+        assert_eq!(BinTable::new(&header), Err(TableError::RowWidth(12, 14)));
+        assert_eq!(TableLint::MissingName(2).to_string(), "column 2 has no TTYPE2");
+    }
+}
@@ -0,0 +1,831 @@
+//! The table module describes the columns of binary table extensions and
+//! decodes their data.
+
+use std::convert::TryFrom;
+use std::fmt::{Display, Error, Formatter};
+use std::ops::Range;
+use std::str;
+use std::str::FromStr;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use super::types::{Header, Keyword, ValueRetrievalError};
+
+mod csv;
+mod green_bank;
+mod lint;
+mod rows;
+mod sort;
+mod view;
+mod votable;
+
+pub use self::csv::{read_csv, CsvColumn, CsvError, TableHdu};
+pub use self::green_bank::GreenBank;
+pub use self::lint::TableLint;
+pub use self::rows::{between, energy_range, in_mask, in_region, time_range, Row, Subtable};
+pub use self::view::TableView;
+
+#[cfg(feature = "arrow")]
+mod arrow;
+
+#[cfg(feature = "arrow")]
+pub use self::arrow::ArrowConversionError;
+
+#[cfg(feature = "parquet")]
+mod parquet;
+
+#[cfg(feature = "parquet")]
+pub use self::parquet::ParquetExportError;
+
+/// The number of rows above which `BinTable::read_column` decodes a column in
+/// chunks of this many rows in parallel, with the `rayon` feature.
+#[cfg(feature = "rayon")]
+pub const PARALLEL_ROWS: usize = 1 << 16;
+
+/// The description of a `BINTABLE` extension: its columns and the geometry of
+/// its rows and heap.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BinTable {
+    columns: Vec<Column>,
+    row_width: usize,
+    rows: usize,
+    heap_offset: usize,
+    heap_size: usize,
+    columns_width: usize,
+}
+
+impl BinTable {
+    /// Create a `BinTable` from the header of a binary table extension.
+    ///
+    /// The widths of the columns, the repeat count times the size of the type
+    /// of their `TFORMn`, have to add up to `NAXIS1`.
+    pub fn new(header: &Header) -> Result<BinTable, TableError> {
+        let table = BinTable::describe(header)?;
+        if table.columns_width != table.row_width {
+            return Err(TableError::RowWidth(table.columns_width, table.row_width))
+        }
+        Ok(table)
+    }
+
+    /// The table described by `header`, whether or not its columns fill its
+    /// rows.
+    fn describe(header: &Header) -> Result<BinTable, TableError> {
+        match header.character_string_value_of(&Keyword::XTENSION) {
+            Ok(xtension) if xtension.trim_end() == "BINTABLE" => (),
+            _ => return Err(TableError::NotABinaryTable),
+        }
+        let row_width = required(header, Keyword::NAXISn(1))?;
+        let rows = required(header, Keyword::NAXISn(2))?;
+        let fields = required(header, Keyword::TFIELDS)?;
+        if fields > 999 {
+            return Err(TableError::InvalidValue(Keyword::TFIELDS))
+        }
+        let heap_size = usize::try_from(optional_integer(header, Keyword::PCOUNT)?.unwrap_or(0))
+            .map_err(|_| TableError::InvalidValue(Keyword::PCOUNT))?;
+        let rows_size = row_width.checked_mul(rows)
+            .ok_or(TableError::InvalidValue(Keyword::NAXISn(2)))?;
+        let heap_offset = match optional_integer(header, Keyword::THEAP)? {
+            Option::Some(offset) => usize::try_from(offset).map_err(|_| TableError::InvalidValue(Keyword::THEAP))?,
+            Option::None => rows_size,
+        };
+
+        let mut columns = vec!();
+        let mut offset = 0;
+        for n in 1..=(fields as u16) {
+            let column = Column::new(header, n, offset)?;
+            offset = column.form.width()
+                .and_then(|width| offset.checked_add(width))
+                .ok_or(TableError::InvalidValue(Keyword::TFORMn(n)))?;
+            columns.push(column);
+        }
+
+        Ok(BinTable { columns, row_width, rows, heap_offset, heap_size, columns_width: offset })
+    }
+
+    /// The columns of this table, in order.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// The index of the column with name `name`. Names are compared
+    /// case-insensitively, as the standard recommends.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name.as_ref().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+    }
+
+    /// The number of rows, i.e. `NAXIS2`.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of bytes in a row, i.e. `NAXIS1`.
+    pub fn row_width(&self) -> usize {
+        self.row_width
+    }
+
+    /// The offset in bytes of the heap from the start of the data, i.e. `THEAP`.
+    pub fn heap_offset(&self) -> usize {
+        self.heap_offset
+    }
+
+    /// The number of bytes following the main table, i.e. `PCOUNT`.
+    pub fn heap_size(&self) -> usize {
+        self.heap_size
+    }
+
+    /// Decode the column at `index` from `data`, the data array of the table.
+    ///
+    /// With the `rayon` feature, the rows of tables with more than
+    /// `PARALLEL_ROWS` rows are decoded in chunks on the rayon thread pool.
+    pub fn read_column(&self, data: &[u8], index: usize) -> Result<ColumnValues, TableError> {
+        #[cfg(feature = "rayon")]
+        {
+            if self.rows > PARALLEL_ROWS {
+                let starts: Vec<usize> = (0..self.rows).step_by(PARALLEL_ROWS).collect();
+                let chunks = starts.par_iter()
+                    .map(|&start| self.read_column_rows(data, index, start..(start + PARALLEL_ROWS).min(self.rows)))
+                    .collect::<Result<Vec<ColumnValues>, TableError>>()?;
+                let mut chunks = chunks.into_iter();
+                let mut values = chunks.next().expect("a table with rows has a chunk");
+                for chunk in chunks {
+                    values.append(chunk);
+                }
+                return Ok(values)
+            }
+        }
+        self.read_column_rows(data, index, 0..self.rows)
+    }
+
+    /// Decode the columns at `indices` from `data`, the data array of the
+    /// table, in the order of `indices`.
+    ///
+    /// With the `rayon` feature the columns are decoded in parallel on the
+    /// rayon thread pool.
+    pub fn read_columns(&self, data: &[u8], indices: &[usize]) -> Result<Vec<ColumnValues>, TableError> {
+        #[cfg(feature = "rayon")]
+        let values = indices.par_iter().map(|&index| self.read_column(data, index)).collect();
+        #[cfg(not(feature = "rayon"))]
+        let values = indices.iter().map(|&index| self.read_column(data, index)).collect();
+        values
+    }
+
+    fn read_column_rows(&self, data: &[u8], index: usize, rows: Range<usize>) -> Result<ColumnValues, TableError> {
+        let column = self.columns.get(index).ok_or(TableError::NoSuchColumn(index))?;
+        let width = column.form.width().ok_or(TableError::InvalidValue(Keyword::TFORMn(index as u16 + 1)))?;
+        if data.len() < self.row_width * self.rows {
+            return Err(TableError::DataTooShort)
+        }
+        let rows = rows.map(|row| {
+            let start = row * self.row_width + column.offset;
+            &data[start..(start + width)]
+        });
+        match column.form.descriptor {
+            Option::None => {
+                let mut values = ColumnValues::empty(column.form.kind);
+                for field in rows {
+                    values.push(column.form.kind, column.form.repeat, field);
+                }
+                Ok(values)
+            }
+            Option::Some(descriptor) => {
+                let heap = &data[self.heap_offset.min(data.len())..];
+                let mut values = ColumnValues::empty(column.form.kind);
+                for field in rows {
+                    let (count, offset) = descriptor.read(field);
+                    if count / 8 > heap.len() {
+                        return Err(TableError::HeapOutOfRange)
+                    }
+                    let end = match column.form.kind.width(count).and_then(|width| offset.checked_add(width)) {
+                        Option::Some(end) if end <= heap.len() => end,
+                        _ => return Err(TableError::HeapOutOfRange),
+                    };
+                    values.push(column.form.kind, count, &heap[offset..end]);
+                }
+                Ok(values)
+            }
+        }
+    }
+}
+
+fn required(header: &Header, keyword: Keyword) -> Result<usize, TableError> {
+    match header.integer_value_of(&keyword) {
+        Ok(n) => usize::try_from(n).map_err(|_| TableError::InvalidValue(keyword)),
+        Err(error) => Err(TableError::MissingKeyword(keyword, error)),
+    }
+}
+
+fn optional_integer(header: &Header, keyword: Keyword) -> Result<Option<i64>, TableError> {
+    match header.integer_value_of(&keyword) {
+        Ok(n) if n >= 0 => Ok(Option::Some(n)),
+        Ok(_) => Err(TableError::InvalidValue(keyword)),
+        Err(ValueRetrievalError::KeywordNotPresent) => Ok(Option::None),
+        Err(error) => Err(TableError::MissingKeyword(keyword, error)),
+    }
+}
+
+fn optional_string(header: &Header, keyword: Keyword) -> Option<String> {
+    header.character_string_value_of(&keyword)
+        .ok()
+        .map(|value| value.trim_end().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The description of a single column of a binary table.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Column {
+    /// The name of the column, from `TTYPEn`.
+    pub name: Option<String>,
+    /// The data format of the column, from `TFORMn`.
+    pub form: TForm,
+    /// The physical unit of the column, from `TUNITn`.
+    pub unit: Option<String>,
+    /// The unified content descriptor of the column, from `TUCDn`.
+    pub ucd: Option<String>,
+    /// The scale factor of the column, from `TSCALn`, defaults to 1.
+    pub scale: f64,
+    /// The offset of the column, from `TZEROn`, defaults to 0.
+    pub zero: f64,
+    /// The value signifying an undefined integer, from `TNULLn`.
+    pub null: Option<i64>,
+    /// The offset in bytes of this column from the start of a row.
+    pub offset: usize,
+}
+
+impl Column {
+    fn new(header: &Header, n: u16, offset: usize) -> Result<Column, TableError> {
+        let tform = header.character_string_value_of(&Keyword::TFORMn(n))
+            .map_err(|error| TableError::MissingKeyword(Keyword::TFORMn(n), error))?;
+        let form = TForm::from_str(tform)
+            .map_err(|_| TableError::InvalidValue(Keyword::TFORMn(n)))?;
+        Ok(Column {
+            name: optional_string(header, Keyword::TTYPEn(n)),
+            form,
+            unit: optional_string(header, Keyword::TUNITn(n)),
+            ucd: optional_string(header, Keyword::TUCDn(n)),
+            scale: header.real_value_of(&Keyword::TSCALn(n)).unwrap_or(1f64),
+            zero: header.real_value_of(&Keyword::TZEROn(n)).unwrap_or(0f64),
+            null: header.integer_value_of(&Keyword::TNULLn(n)).ok(),
+            offset,
+        })
+    }
+}
+
+/// The data format of a column, `rTa` in the standard: a repeat count, a
+/// type and, for variable length arrays, a descriptor.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TForm {
+    /// The repeat count. For variable length arrays the number of descriptors.
+    pub repeat: usize,
+    /// The type of the elements.
+    pub kind: BinType,
+    /// The descriptor of a variable length array, which stores its elements in the heap.
+    pub descriptor: Option<Descriptor>,
+    /// The maximum number of elements of a variable length array, when given.
+    pub max: Option<usize>,
+}
+
+impl TForm {
+    /// The number of bytes a field of this format occupies in a row, `None`
+    /// when the repeat count is too large for it to fit in a `usize`.
+    pub fn width(&self) -> Option<usize> {
+        match self.descriptor {
+            Option::Some(descriptor) => self.repeat.checked_mul(descriptor.size()),
+            Option::None => self.kind.width(self.repeat),
+        }
+    }
+}
+
+impl Display for TForm {
+    /// Formats this format as the value of `TFORMn`, e.g. `3J` or `1PE(100)`.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.repeat)?;
+        if let Option::Some(descriptor) = self.descriptor {
+            write!(f, "{}", if descriptor == Descriptor::P { 'P' } else { 'Q' })?;
+        }
+        write!(f, "{}", self.kind.code())?;
+        if let Option::Some(max) = self.max {
+            write!(f, "({})", max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems that could occur when parsing a `str` for a `TForm` are enumerated here.
+#[derive(Debug, PartialEq)]
+pub enum ParseTFormError {
+    /// When the repeat count is not a number.
+    NotANumber,
+    /// When the type code is not one of the standard codes.
+    UnknownType,
+}
+
+impl FromStr for TForm {
+    type Err = ParseTFormError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let repeat = if digits == 0 {
+            1
+        } else {
+            usize::from_str(&s[..digits]).map_err(|_| ParseTFormError::NotANumber)?
+        };
+        let mut codes = s[digits..].chars();
+        let code = codes.next().ok_or(ParseTFormError::UnknownType)?;
+        match code {
+            'P' | 'Q' => {
+                let descriptor = if code == 'P' { Descriptor::P } else { Descriptor::Q };
+                let element = codes.next().ok_or(ParseTFormError::UnknownType)?;
+                let kind = BinType::from_code(element).ok_or(ParseTFormError::UnknownType)?;
+                let rest = codes.as_str().trim();
+                let max = if rest.starts_with('(') && rest.ends_with(')') {
+                    Option::Some(usize::from_str(&rest[1..(rest.len() - 1)]).map_err(|_| ParseTFormError::NotANumber)?)
+                } else {
+                    Option::None
+                };
+                Ok(TForm { repeat, kind, descriptor: Option::Some(descriptor), max })
+            }
+            code => {
+                let kind = BinType::from_code(code).ok_or(ParseTFormError::UnknownType)?;
+                Ok(TForm { repeat, kind, descriptor: Option::None, max: Option::None })
+            }
+        }
+    }
+}
+
+/// The types of the elements of a binary table column.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinType {
+    /// `L`, a logical encoded as `T`, `F` or 0 for undefined.
+    Logical,
+    /// `X`, a single bit.
+    Bit,
+    /// `B`, an unsigned byte.
+    UnsignedByte,
+    /// `I`, a 16-bit integer.
+    Short,
+    /// `J`, a 32-bit integer.
+    Int,
+    /// `K`, a 64-bit integer.
+    Long,
+    /// `A`, an ASCII character.
+    Character,
+    /// `E`, a single precision float.
+    Float,
+    /// `D`, a double precision float.
+    Double,
+    /// `C`, a pair of single precision floats.
+    ComplexFloat,
+    /// `M`, a pair of double precision floats.
+    ComplexDouble,
+}
+
+impl BinType {
+    fn from_code(code: char) -> Option<BinType> {
+        match code {
+            'L' => Option::Some(BinType::Logical),
+            'X' => Option::Some(BinType::Bit),
+            'B' => Option::Some(BinType::UnsignedByte),
+            'I' => Option::Some(BinType::Short),
+            'J' => Option::Some(BinType::Int),
+            'K' => Option::Some(BinType::Long),
+            'A' => Option::Some(BinType::Character),
+            'E' => Option::Some(BinType::Float),
+            'D' => Option::Some(BinType::Double),
+            'C' => Option::Some(BinType::ComplexFloat),
+            'M' => Option::Some(BinType::ComplexDouble),
+            _ => Option::None,
+        }
+    }
+
+    /// The code of this type in `TFORMn`.
+    pub fn code(&self) -> char {
+        match *self {
+            BinType::Logical => 'L',
+            BinType::Bit => 'X',
+            BinType::UnsignedByte => 'B',
+            BinType::Short => 'I',
+            BinType::Int => 'J',
+            BinType::Long => 'K',
+            BinType::Character => 'A',
+            BinType::Float => 'E',
+            BinType::Double => 'D',
+            BinType::ComplexFloat => 'C',
+            BinType::ComplexDouble => 'M',
+        }
+    }
+
+    /// The size in bytes of a single element of this type. Bits are packed, so
+    /// a single bit takes a whole byte.
+    pub fn size(&self) -> usize {
+        match *self {
+            BinType::Logical | BinType::Bit | BinType::UnsignedByte | BinType::Character => 1,
+            BinType::Short => 2,
+            BinType::Int | BinType::Float => 4,
+            BinType::Long | BinType::Double | BinType::ComplexFloat => 8,
+            BinType::ComplexDouble => 16,
+        }
+    }
+
+    /// The size in bytes of `repeat` elements of this type, `None` when it
+    /// does not fit in a `usize`.
+    pub fn width(&self, repeat: usize) -> Option<usize> {
+        match *self {
+            BinType::Bit => Option::Some(repeat.div_ceil(8)),
+            _ => repeat.checked_mul(self.size()),
+        }
+    }
+}
+
+/// The descriptors of variable length arrays.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Descriptor {
+    /// A pair of 32-bit integers: element count and heap offset.
+    P,
+    /// A pair of 64-bit integers: element count and heap offset.
+    Q,
+}
+
+impl Descriptor {
+    /// The size in bytes of this descriptor.
+    pub fn size(&self) -> usize {
+        match *self {
+            Descriptor::P => 8,
+            Descriptor::Q => 16,
+        }
+    }
+
+    fn read(&self, field: &[u8]) -> (usize, usize) {
+        match *self {
+            Descriptor::P => (be_i32(&field[0..4]) as u32 as usize, be_i32(&field[4..8]) as u32 as usize),
+            Descriptor::Q => (
+                usize::try_from(be_i64(&field[0..8])).unwrap_or(usize::MAX),
+                usize::try_from(be_i64(&field[8..16])).unwrap_or(usize::MAX),
+            ),
+        }
+    }
+}
+
+/// The decoded values of a column, laid out flat with the boundaries of each
+/// row in `offsets`, so that fixed and variable length columns are alike.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnValues {
+    /// The elements of all rows.
+    pub data: ColumnData,
+    /// Row `i` consists of the elements `offsets[i]..offsets[i + 1]`.
+    pub offsets: Vec<usize>,
+}
+
+impl ColumnValues {
+    fn empty(kind: BinType) -> ColumnValues {
+        let data = match kind {
+            BinType::Logical => ColumnData::Logical(vec!()),
+            BinType::Bit => ColumnData::Bit(vec!()),
+            BinType::UnsignedByte => ColumnData::UnsignedByte(vec!()),
+            BinType::Short => ColumnData::Short(vec!()),
+            BinType::Int => ColumnData::Int(vec!()),
+            BinType::Long => ColumnData::Long(vec!()),
+            BinType::Character => ColumnData::Character(vec!()),
+            BinType::Float => ColumnData::Float(vec!()),
+            BinType::Double => ColumnData::Double(vec!()),
+            BinType::ComplexFloat => ColumnData::ComplexFloat(vec!()),
+            BinType::ComplexDouble => ColumnData::ComplexDouble(vec!()),
+        };
+        ColumnValues { data, offsets: vec!(0) }
+    }
+
+    fn push(&mut self, kind: BinType, count: usize, bytes: &[u8]) {
+        let element = kind.size();
+        match self.data {
+            ColumnData::Logical(ref mut values) => values.extend(bytes.iter().map(|&b| match b {
+                b'T' => Option::Some(true),
+                b'F' => Option::Some(false),
+                _ => Option::None,
+            })),
+            ColumnData::Bit(ref mut values) => values.extend((0..count).map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0)),
+            ColumnData::UnsignedByte(ref mut values) => values.extend_from_slice(bytes),
+            ColumnData::Short(ref mut values) => values.extend(bytes.chunks(element).map(be_i16)),
+            ColumnData::Int(ref mut values) => values.extend(bytes.chunks(element).map(be_i32)),
+            ColumnData::Long(ref mut values) => values.extend(bytes.chunks(element).map(be_i64)),
+            ColumnData::Character(ref mut values) => values.extend_from_slice(bytes),
+            ColumnData::Float(ref mut values) => values.extend(bytes.chunks(element).map(be_f32)),
+            ColumnData::Double(ref mut values) => values.extend(bytes.chunks(element).map(be_f64)),
+            ColumnData::ComplexFloat(ref mut values) => values.extend(bytes.chunks(element).map(|c| (be_f32(&c[0..4]), be_f32(&c[4..8])))),
+            ColumnData::ComplexDouble(ref mut values) => values.extend(bytes.chunks(element).map(|c| (be_f64(&c[0..8]), be_f64(&c[8..16])))),
+        }
+        let last = self.offsets[self.offsets.len() - 1];
+        self.offsets.push(last + count);
+    }
+
+    #[cfg(feature = "rayon")]
+    fn append(&mut self, other: ColumnValues) {
+        let last = self.offsets[self.offsets.len() - 1];
+        self.offsets.extend(other.offsets[1..].iter().map(|offset| last + offset));
+        match (&mut self.data, other.data) {
+            (ColumnData::Logical(values), ColumnData::Logical(more)) => values.extend(more),
+            (ColumnData::Bit(values), ColumnData::Bit(more)) => values.extend(more),
+            (ColumnData::UnsignedByte(values), ColumnData::UnsignedByte(more)) => values.extend(more),
+            (ColumnData::Short(values), ColumnData::Short(more)) => values.extend(more),
+            (ColumnData::Int(values), ColumnData::Int(more)) => values.extend(more),
+            (ColumnData::Long(values), ColumnData::Long(more)) => values.extend(more),
+            (ColumnData::Character(values), ColumnData::Character(more)) => values.extend(more),
+            (ColumnData::Float(values), ColumnData::Float(more)) => values.extend(more),
+            (ColumnData::Double(values), ColumnData::Double(more)) => values.extend(more),
+            (ColumnData::ComplexFloat(values), ColumnData::ComplexFloat(more)) => values.extend(more),
+            (ColumnData::ComplexDouble(values), ColumnData::ComplexDouble(more)) => values.extend(more),
+            _ => unreachable!("the chunks of a column have the same type"),
+        }
+    }
+
+    /// The number of rows.
+    pub fn rows(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// The physical values of the elements of a numeric column, `TZEROn +
+    /// TSCALn * raw`, with integers equal to `TNULLn` as `NaN`. `None` for
+    /// logical, bit, character and complex columns.
+    pub fn physical(&self, column: &Column) -> Option<Vec<f64>> {
+        let integer = |raw: i64| {
+            if Option::Some(raw) == column.null {
+                f64::NAN
+            } else {
+                raw as f64 * column.scale + column.zero
+            }
+        };
+        let values = match self.data {
+            ColumnData::UnsignedByte(ref v) => v.iter().map(|&x| integer(x as i64)).collect(),
+            ColumnData::Short(ref v) => v.iter().map(|&x| integer(x as i64)).collect(),
+            ColumnData::Int(ref v) => v.iter().map(|&x| integer(x as i64)).collect(),
+            ColumnData::Long(ref v) => v.iter().map(|&x| integer(x)).collect(),
+            ColumnData::Float(ref v) => v.iter().map(|&x| x as f64 * column.scale + column.zero).collect(),
+            ColumnData::Double(ref v) => v.iter().map(|&x| x * column.scale + column.zero).collect(),
+            _ => return Option::None,
+        };
+        Option::Some(values)
+    }
+
+    /// The text of a character column in row `row`, without trailing spaces
+    /// or NUL padding. `None` for other columns or invalid text.
+    pub fn text(&self, row: usize) -> Option<&str> {
+        match self.data {
+            ColumnData::Character(ref bytes) => {
+                let start = *self.offsets.get(row)?;
+                let end = *self.offsets.get(row + 1)?;
+                let field = &bytes[start..end];
+                let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+                str::from_utf8(&field[..end]).ok().map(|text| text.trim_end())
+            }
+            _ => Option::None,
+        }
+    }
+}
+
+/// The elements of a decoded column, by type.
+#[derive(Debug, PartialEq, Clone)]
+#[allow(missing_docs)]
+pub enum ColumnData {
+    Logical(Vec<Option<bool>>),
+    Bit(Vec<bool>),
+    UnsignedByte(Vec<u8>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    Character(Vec<u8>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    ComplexFloat(Vec<(f32, f32)>),
+    ComplexDouble(Vec<(f64, f64)>),
+}
+
+/// Problems that could occur when describing or reading a binary table.
+#[derive(Debug, PartialEq)]
+pub enum TableError {
+    /// The header is not the header of a `BINTABLE` extension.
+    NotABinaryTable,
+    /// A keyword the table needs could not be retrieved.
+    MissingKeyword(Keyword, ValueRetrievalError),
+    /// The value of the keyword is not valid for a binary table.
+    InvalidValue(Keyword),
+    /// There is no column with this index.
+    NoSuchColumn(usize),
+    /// There is no column with this name.
+    NoSuchColumnName(String),
+    /// There is no row with this index.
+    NoSuchRow(usize),
+    /// The columns are the first number of bytes wide, but `NAXIS1`, the
+    /// width of a row, is the second.
+    RowWidth(usize, usize),
+    /// The data is shorter than the rows of the table.
+    DataTooShort,
+    /// A variable length array points outside of the heap.
+    HeapOutOfRange,
+}
+
+fn be_i16(bytes: &[u8]) -> i16 {
+    i16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn be_i32(bytes: &[u8]) -> i32 {
+    i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn be_i64(bytes: &[u8]) -> i64 {
+    let mut buffer = [0u8; 8];
+    buffer.copy_from_slice(&bytes[0..8]);
+    i64::from_be_bytes(buffer)
+}
+
+fn be_f32(bytes: &[u8]) -> f32 {
+    f32::from_bits(be_i32(bytes) as u32)
+}
+
+fn be_f64(bytes: &[u8]) -> f64 {
+    f64::from_bits(be_i64(bytes) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use nom::IResult;
+    use super::super::parser::fits;
+    use super::super::types::{Header, KeywordRecord, Keyword, Value};
+    use super::*;
+
+    #[test]
+    fn tform_should_be_parsed_from_str() {
+        let data = vec!(
+            ("D", TForm { repeat: 1, kind: BinType::Double, descriptor: Option::None, max: Option::None }),
+            ("121J    ", TForm { repeat: 121, kind: BinType::Int, descriptor: Option::None, max: Option::None }),
+            ("16X", TForm { repeat: 16, kind: BinType::Bit, descriptor: Option::None, max: Option::None }),
+            ("1PE(100)", TForm { repeat: 1, kind: BinType::Float, descriptor: Option::Some(Descriptor::P), max: Option::Some(100) }),
+            ("QB", TForm { repeat: 1, kind: BinType::UnsignedByte, descriptor: Option::Some(Descriptor::Q), max: Option::None }),
+        );
+
+        for (input, expected) in data {
+            assert_eq!(TForm::from_str(input), Ok(expected));
+        }
+        assert_eq!(TForm::from_str("3Z"), Err(ParseTFormError::UnknownType));
+    }
+
+    #[test]
+    fn tform_should_display_as_tform_value() {
+        for &(input, expected) in &[("D", "1D"), ("121J", "121J"), ("1PE(100)", "1PE(100)"), ("QB", "1QB")] {
+            assert_eq!(TForm::from_str(input).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn tform_width_should_account_for_repeat_bits_and_descriptors() {
+        assert_eq!(TForm::from_str("121E").unwrap().width(), Option::Some(484));
+        assert_eq!(TForm::from_str("9X").unwrap().width(), Option::Some(2));
+        assert_eq!(TForm::from_str("2PJ").unwrap().width(), Option::Some(16));
+        assert_eq!(TForm::from_str("1QD").unwrap().width(), Option::Some(16));
+        assert_eq!(TForm::from_str("4611686018427387904J").unwrap().width(), Option::None);
+    }
+
+    fn table_header<'a>(records: Vec<KeywordRecord<'a>>) -> Header<'a> {
+        let mut all = vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+        );
+        all.extend(records);
+        Header::new(all)
+    }
+
+    #[test]
+    fn bintable_should_decode_fixed_width_columns() {
+        let header = table_header(vec!(
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(9i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::TTYPEn(1u16), Value::CharacterString("COUNT".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString("2I".into()), Option::None),
+            KeywordRecord::new(Keyword::TNULLn(1u16), Value::Integer(-1i64), Option::None),
+            KeywordRecord::new(Keyword::TTYPEn(2u16), Value::CharacterString("NAME".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(2u16), Value::CharacterString("4A".into()), Option::None),
+            KeywordRecord::new(Keyword::TTYPEn(3u16), Value::CharacterString("FLAG".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(3u16), Value::CharacterString("L".into()), Option::None),
+        ));
+        let data = [
+            0, 1, 0xff, 0xff, b'a', b'b', b' ', b' ', b'T',
+            0, 3, 0, 4, b'c', b'd', b'e', b'f', 0,
+        ];
+
+        let table = BinTable::new(&header).unwrap();
+
+        assert_eq!(table.columns()[1].offset, 4);
+        assert_eq!(table.column_index("flag"), Option::Some(2));
+        assert_eq!(table.columns()[0].null, Option::Some(-1i64));
+        let counts = table.read_column(&data, 0).unwrap();
+        assert_eq!(counts.data, ColumnData::Short(vec!(1, -1, 3, 4)));
+        assert_eq!(counts.offsets, vec!(0, 2, 4));
+        let names = table.read_column(&data, 1).unwrap();
+        assert_eq!(names.text(0), Option::Some("ab"));
+        assert_eq!(names.text(1), Option::Some("cdef"));
+        assert_eq!(names.text(2), Option::None);
+        let flags = table.read_column(&data, 2).unwrap();
+        assert_eq!(flags.data, ColumnData::Logical(vec!(Option::Some(true), Option::None)));
+    }
+
+    #[test]
+    fn bintable_should_decode_variable_length_arrays_from_the_heap() {
+        let header = table_header(vec!(
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::PCOUNT, Value::Integer(6i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString("1PI(2)".into()), Option::None),
+        ));
+        let data = [
+            0, 0, 0, 1, 0, 0, 0, 4,
+            0, 0, 0, 2, 0, 0, 0, 0,
+            0, 7, 0, 8, 0, 9,
+        ];
+
+        let table = BinTable::new(&header).unwrap();
+        let values = table.read_column(&data, 0).unwrap();
+
+        assert_eq!(values.data, ColumnData::Short(vec!(9, 7, 8)));
+        assert_eq!(values.offsets, vec!(0, 1, 3));
+    }
+
+    #[test]
+    fn read_columns_should_decode_large_tables_like_a_single_pass() {
+        let rows = 3 * (1 << 16) + 5;
+        let header = table_header(vec!(
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(12i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(rows as i64), Option::None),
+            KeywordRecord::new(Keyword::PCOUNT, Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString("1J".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(2u16), Value::CharacterString("1PB(3)".into()), Option::None),
+        ));
+        let mut data = vec!();
+        for row in 0..rows {
+            data.extend_from_slice(&(row as i32).to_be_bytes());
+            data.extend_from_slice(&((row % 4) as i32).to_be_bytes());
+            data.extend_from_slice(&0i32.to_be_bytes());
+        }
+        data.extend_from_slice(&[7, 8, 9]);
+
+        let table = BinTable::new(&header).unwrap();
+        let values = table.read_columns(&data, &[1, 0]).unwrap();
+
+        assert_eq!(values[0], table.read_column_rows(&data, 1, 0..rows).unwrap());
+        assert_eq!(values[1], table.read_column_rows(&data, 0, 0..rows).unwrap());
+        assert_eq!(values[1].data, ColumnData::Int((0..rows as i32).collect()));
+        assert_eq!(values[0].offsets[rows], rows / 4 * 6);
+    }
+
+    #[test]
+    fn bintable_should_read_the_columns_of_the_target_pixel_file() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let table = BinTable::new(&fits[1].header).unwrap();
+
+        assert_eq!(table.columns().len(), 12);
+        assert_eq!(table.rows(), 3599);
+        assert_eq!(table.columns()[3].null, Option::Some(-1i64));
+        let flux = table.read_column(fits[1].data(), table.column_index("FLUX").unwrap()).unwrap();
+        assert_eq!(flux.rows(), 3599);
+        assert_eq!(flux.offsets[1], 121);
+    }
+
+    #[test]
+    fn bintable_should_only_describe_binary_tables() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("IMAGE   ".into()), Option::None),
+        ));
+
+        assert_eq!(BinTable::new(&header), Err(TableError::NotABinaryTable));
+    }
+
+    #[test]
+    fn bintable_should_reject_too_many_fields_and_too_wide_columns() {
+        let fields = |tfields: i64| table_header(vec!(
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(tfields), Option::None),
+        ));
+        let widths = |first: &'static str, second: &'static str| table_header(vec!(
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString(first.into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(2u16), Value::CharacterString(second.into()), Option::None),
+        ));
+
+        assert_eq!(BinTable::new(&fields(65535)), Err(TableError::InvalidValue(Keyword::TFIELDS)));
+        assert_eq!(BinTable::new(&fields(65537)), Err(TableError::InvalidValue(Keyword::TFIELDS)));
+        assert_eq!(BinTable::new(&widths("4611686018427387904J", "1J")), Err(TableError::InvalidValue(Keyword::TFORMn(1))));
+        assert_eq!(BinTable::new(&widths("1J", "18446744073709551615A")), Err(TableError::InvalidValue(Keyword::TFORMn(2))));
+        assert_eq!(BinTable::lint(&widths("4611686018427387904J", "1J")), vec!(TableLint::Invalid(TableError::InvalidValue(Keyword::TFORMn(1)))));
+    }
+}
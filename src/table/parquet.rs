@@ -0,0 +1,101 @@
+//! Export of binary tables to Parquet files.
+
+extern crate parquet;
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use self::parquet::arrow::ArrowWriter;
+use self::parquet::errors::ParquetError;
+use self::parquet::file::properties::WriterProperties;
+use super::{ArrowConversionError, BinTable};
+
+/// The number of table rows that are decoded and written as one row group.
+const ROWS_PER_GROUP: usize = 65536;
+
+/// Problems that could occur when exporting a binary table to Parquet.
+#[derive(Debug)]
+pub enum ParquetExportError {
+    /// The table could not be converted into Arrow arrays.
+    Conversion(ArrowConversionError),
+    /// The Parquet file could not be encoded.
+    Parquet(ParquetError),
+    /// The file could not be created.
+    Io(io::Error),
+}
+
+impl From<ArrowConversionError> for ParquetExportError {
+    fn from(error: ArrowConversionError) -> ParquetExportError {
+        ParquetExportError::Conversion(error)
+    }
+}
+
+impl From<ParquetError> for ParquetExportError {
+    fn from(error: ParquetError) -> ParquetExportError {
+        ParquetExportError::Parquet(error)
+    }
+}
+
+impl From<io::Error> for ParquetExportError {
+    fn from(error: io::Error) -> ParquetExportError {
+        ParquetExportError::Io(error)
+    }
+}
+
+impl BinTable {
+    /// Write this table, with data array `data`, to a Parquet file at `path`.
+    ///
+    /// Columns are converted as in `to_record_batch`, so names, units and
+    /// nullability are preserved in the Arrow schema stored with the file. Rows
+    /// are decoded and written in groups, so only one group is held in memory.
+    pub fn write_parquet<P: AsRef<Path>>(&self, data: &[u8], path: P) -> Result<(), ParquetExportError> {
+        let file = File::create(path)?;
+        let first = self.record_batch(data, 0..self.rows.min(ROWS_PER_GROUP))?;
+        // Dictionary encoding rarely pays off for measured values and is very
+        // slow on columns with many distinct floats.
+        let properties = WriterProperties::builder().set_dictionary_enabled(false).build();
+        let mut writer = ArrowWriter::try_new(file, first.schema(), Option::Some(properties))?;
+        writer.write(&first)?;
+        let mut start = first.num_rows();
+        while start < self.rows {
+            let end = self.rows.min(start + ROWS_PER_GROUP);
+            writer.write(&self.record_batch(data, start..end)?)?;
+            writer.flush()?;
+            start = end;
+        }
+        writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use nom::IResult;
+    use super::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use super::super::super::parser::fits;
+    use super::super::BinTable;
+
+    #[test]
+    fn target_pixel_file_should_be_written_as_parquet() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let table = BinTable::new(&fits[1].header).unwrap();
+        let path = env::temp_dir().join("fits-rs-target-pixel-file.parquet");
+
+        table.write_parquet(fits[1].data(), &path).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        let schema = reader.schema().clone();
+        let rows: usize = reader.build().unwrap().map(|batch| batch.unwrap().num_rows()).sum();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(rows, 3599);
+        let time = schema.field_with_name("TIME").unwrap();
+        assert_eq!(time.metadata().get("unit").map(|unit| unit.as_str()), Option::Some("BJD - 2454833"));
+        assert!(schema.field_with_name("RAW_CNTS").unwrap().is_nullable());
+    }
+}
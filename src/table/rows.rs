@@ -0,0 +1,264 @@
+//! Access to the rows of binary tables, and tables made of some of them.
+
+use std::ops::Range;
+use std::str;
+use super::{be_f32, be_f64, be_i16, be_i32, be_i64, BinTable, BinType, TableError};
+use super::super::region::{Mask, Region, RegionError};
+use super::super::types::{Header, HDU, Keyword, Value};
+use super::super::wcs::Wcs;
+
+/// A row of a binary table.
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'t> {
+    table: &'t BinTable,
+    index: usize,
+    bytes: &'t [u8],
+}
+
+impl<'t> Row<'t> {
+    /// The zero based index of this row in its table.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The bytes of this row, `NAXIS1` of them.
+    pub fn bytes(&self) -> &'t [u8] {
+        self.bytes
+    }
+
+    /// The physical value of the first element of the column called `name`,
+    /// see `real_at`.
+    pub fn real(&self, name: &str) -> Option<f64> {
+        self.real_at(self.table.column_index(name)?)
+    }
+
+    /// The physical value of the first element of the column at `index`,
+    /// `TSCALn * raw + TZEROn`. `None` for null values, non numeric columns
+    /// and variable length arrays.
+    pub fn real_at(&self, index: usize) -> Option<f64> {
+        let column = self.table.columns().get(index)?;
+        if column.form.descriptor.is_some() || column.form.repeat == 0 {
+            return Option::None
+        }
+        let field = &self.bytes[column.offset..];
+        let integer = |raw: i64| {
+            if Option::Some(raw) == column.null {
+                Option::None
+            } else {
+                Option::Some(raw as f64 * column.scale + column.zero)
+            }
+        };
+        let value = match column.form.kind {
+            BinType::UnsignedByte => integer(field[0] as i64)?,
+            BinType::Short => integer(be_i16(field) as i64)?,
+            BinType::Int => integer(be_i32(field) as i64)?,
+            BinType::Long => integer(be_i64(field))?,
+            BinType::Float => be_f32(field) as f64 * column.scale + column.zero,
+            BinType::Double => be_f64(field) * column.scale + column.zero,
+            _ => return Option::None,
+        };
+        if value.is_nan() { Option::None } else { Option::Some(value) }
+    }
+
+    /// The text of the character column called `name`, without trailing
+    /// spaces or NUL padding. `None` for other columns or invalid text.
+    pub fn text(&self, name: &str) -> Option<&'t str> {
+        let column = &self.table.columns()[self.table.column_index(name)?];
+        if column.form.kind != BinType::Character || column.form.descriptor.is_some() {
+            return Option::None
+        }
+        let field = &self.bytes[column.offset..column.offset + column.form.repeat];
+        let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        str::from_utf8(&field[..end]).ok().map(|text| text.trim_end())
+    }
+}
+
+impl BinTable {
+    /// The rows of this table in `data`, its data array, one at a time.
+    pub fn iter_rows<'t>(&'t self, data: &'t [u8]) -> Result<impl Iterator<Item = Row<'t>> + 't, TableError> {
+        if data.len() < self.row_width * self.rows {
+            return Err(TableError::DataTooShort)
+        }
+        Ok((0..self.rows).map(move |index| Row {
+            table: self,
+            index,
+            bytes: &data[index * self.row_width..(index + 1) * self.row_width],
+        }))
+    }
+
+    /// The table of the rows of this table, described by `header` with data
+    /// array `data`, for which `predicate` holds, in order. Rows are visited
+    /// one at a time and only the rows that are kept are copied.
+    pub fn filter<'a, P: FnMut(&Row) -> bool>(&self, header: &Header<'a>, data: &[u8], mut predicate: P) -> Result<Subtable<'a>, TableError> {
+        let mut rows = vec!();
+        for row in self.iter_rows(data)? {
+            if predicate(&row) {
+                rows.push(row.index);
+            }
+        }
+        self.take_rows(header, data, &rows)
+    }
+
+    /// The table of the rows at `rows` of this table, described by `header`
+    /// with data array `data`, in the order of `rows`.
+    ///
+    /// `NAXIS2` is updated and `CHECKSUM` and `DATASUM` are removed. The heap
+    /// is copied whole, right after the rows, so variable length arrays stay
+    /// valid.
+    pub fn take_rows<'a>(&self, header: &Header<'a>, data: &[u8], rows: &[usize]) -> Result<Subtable<'a>, TableError> {
+        let rows_size = self.row_width * self.rows;
+        let heap_end = rows_size.checked_add(self.heap_size).ok_or(TableError::DataTooShort)?;
+        if data.len() < heap_end {
+            return Err(TableError::DataTooShort)
+        }
+        let heap = &data[self.heap_offset.min(heap_end)..heap_end];
+        let mut bytes = Vec::with_capacity(rows.len() * self.row_width + heap.len());
+        for &row in rows {
+            if row >= self.rows {
+                return Err(TableError::NoSuchRow(row))
+            }
+            bytes.extend_from_slice(&data[row * self.row_width..(row + 1) * self.row_width]);
+        }
+        bytes.extend_from_slice(heap);
+
+        let mut header = Header::new(header.records().to_vec());
+        header.set(Keyword::NAXISn(2), Value::Integer(rows.len() as i64), Option::None).expect("NAXISn can be set");
+        header.set(Keyword::PCOUNT, Value::Integer(heap.len() as i64), Option::None).expect("PCOUNT can be set");
+        if heap.is_empty() {
+            let _ = header.remove(&Keyword::THEAP);
+        } else {
+            header.set(Keyword::THEAP, Value::Integer((rows.len() * self.row_width) as i64), Option::None).expect("THEAP can be set");
+        }
+        for keyword in [Keyword::CHECKSUM, Keyword::DATASUM].iter() {
+            let _ = header.remove(keyword);
+        }
+        Ok(Subtable { header, data: bytes })
+    }
+}
+
+/// A table made of some of the rows of a binary table, with a header that
+/// describes it.
+#[derive(Debug, PartialEq)]
+pub struct Subtable<'a> {
+    /// The header of the table with the number of rows and the heap updated.
+    pub header: Header<'a>,
+    /// The rows of the table followed by its heap.
+    pub data: Vec<u8>,
+}
+
+impl<'a> Subtable<'a> {
+    /// The table as an HDU, ready to be written.
+    pub fn hdu<'b>(&'b self) -> HDU<'b> {
+        HDU::with_data(Header::new(self.header.records().to_vec()), &self.data)
+    }
+}
+
+/// Rows of which the value of the column called `name` lies in `range`.
+/// Rows without a value, or tables without the column, do not match.
+pub fn between(name: &str, range: Range<f64>) -> impl FnMut(&Row) -> bool {
+    let name = name.to_string();
+    let mut index: Option<Option<usize>> = Option::None;
+    move |row: &Row| {
+        let index = *index.get_or_insert_with(|| row.table.column_index(&name));
+        index.and_then(|index| row.real_at(index)).is_some_and(|value| range.contains(&value))
+    }
+}
+
+/// Events with an `ENERGY` in `range`, in the unit of the column.
+pub fn energy_range(range: Range<f64>) -> impl FnMut(&Row) -> bool {
+    between("ENERGY", range)
+}
+
+/// Events with a `TIME` in `range`, in the unit of the column.
+pub fn time_range(range: Range<f64>) -> impl FnMut(&Row) -> bool {
+    between("TIME", range)
+}
+
+/// Rows of which the pixel coordinates in columns `x` and `y`, in the image
+/// of `mask`, lie in the mask. The center of the first pixel is at `(1, 1)`.
+pub fn in_mask<'m>(mask: &'m Mask, x: &str, y: &str) -> impl FnMut(&Row) -> bool + 'm {
+    let (x, y) = (x.to_string(), y.to_string());
+    move |row: &Row| match (row.real(&x), row.real(&y)) {
+        (Option::Some(x), Option::Some(y)) if x >= 0.5 && y >= 0.5 => mask.get((x - 0.5) as usize, (y - 0.5) as usize),
+        _ => false,
+    }
+}
+
+/// Rows of which the celestial coordinates in columns `longitude` and
+/// `latitude`, in degrees, lie in `regions`, located with `wcs`. Included
+/// and excluded regions combine as in `region::Mask::new`.
+pub fn in_region(regions: &[Region], wcs: &Wcs, longitude: &str, latitude: &str) -> Result<impl FnMut(&Row) -> bool, RegionError> {
+    let shapes = regions.iter()
+        .map(|region| region.to_image(Option::Some(wcs)).map(|shape| (shape, region.exclude)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let any_included = shapes.iter().any(|&(_, exclude)| !exclude);
+    let wcs = wcs.clone();
+    let (longitude, latitude) = (longitude.to_string(), latitude.to_string());
+    Ok(move |row: &Row| {
+        let (x, y) = match (row.real(&longitude), row.real(&latitude)) {
+            (Option::Some(longitude), Option::Some(latitude)) => match wcs.sky_to_pixel(longitude, latitude) {
+                Ok(pixel) => pixel,
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+        let included = !any_included || shapes.iter().any(|(shape, exclude)| !exclude && shape.contains(x, y));
+        included && !shapes.iter().any(|(shape, exclude)| *exclude && shape.contains(x, y))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::super::region::{parse_regions, Mask};
+    use super::super::super::types::{Header, Keyword};
+    use super::super::BinTable;
+    use super::{between, energy_range, in_mask, in_region, time_range};
+
+    fn events() -> (Header<'static>, Vec<u8>) {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 20\nNAXIS2 = 4\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 4\nTTYPE1 = 'TIME'\nTFORM1 = 'D'\nTTYPE2 = 'ENERGY'\nTFORM2 = 'J'\nTNULL2 = -1\nTTYPE3 = 'X'\nTFORM3 = 'E'\nTTYPE4 = 'Y'\nTFORM4 = 'E'\nCHECKSUM = '0000000000000000'").unwrap();
+        let mut data = vec!();
+        for &(time, energy, x, y) in &[(1.0f64, 300i32, 1f32, 1f32), (2.0, 1500, 2.2, 1.9), (3.0, -1, 2f32, 2f32), (4.0, 900, 5f32, 5f32)] {
+            data.extend_from_slice(&time.to_be_bytes());
+            data.extend_from_slice(&energy.to_be_bytes());
+            data.extend_from_slice(&x.to_be_bytes());
+            data.extend_from_slice(&y.to_be_bytes());
+        }
+        (header, data)
+    }
+
+    #[test]
+    fn filter_should_keep_the_rows_that_match() {
+        let (header, data) = events();
+        let table = BinTable::new(&header).unwrap();
+
+        let soft = table.filter(&header, &data, energy_range(500.0..2000.0)).unwrap();
+        let early = table.filter(&header, &data, time_range(0.0..2.5)).unwrap();
+        let both = table.filter(&header, &data, |row| row.real("ENERGY").is_some() && row.index() % 2 == 1).unwrap();
+
+        let filtered = BinTable::new(&soft.header).unwrap();
+        assert_eq!(filtered.rows(), 2);
+        assert_eq!(soft.data, [&data[20..40], &data[60..80]].concat());
+        assert!(soft.header.value_of(&Keyword::CHECKSUM).is_err());
+        assert_eq!(filtered.iter_rows(&soft.data).unwrap().map(|row| row.real("TIME").unwrap()).collect::<Vec<f64>>(), vec!(2.0, 4.0));
+        assert_eq!(BinTable::new(&early.header).unwrap().rows(), 2);
+        assert_eq!(BinTable::new(&both.header).unwrap().rows(), 2);
+        assert_eq!(table.filter(&header, &data, between("PHA", 0.0..1.0)).unwrap().data.len(), 0);
+        assert_eq!(soft.hdu().data(), &soft.data[..]);
+    }
+
+    #[test]
+    fn region_predicates_should_locate_events() {
+        let (header, data) = events();
+        let table = BinTable::new(&header).unwrap();
+        let mask = Mask::new(&parse_regions("box(2,2,3,3)\n-circle(1,1,0.5)").unwrap(), 8, 8, Option::None).unwrap();
+        let image = template("SIMPLE = T\nNAXIS = 2\nNAXIS1 = 8\nNAXIS2 = 8\nCTYPE1 = 'RA---TAN'\nCTYPE2 = 'DEC--TAN'\nCRPIX1 = 1.0\nCRPIX2 = 1.0\nCRVAL1 = 1.0\nCRVAL2 = 1.0\nCDELT1 = 1.0\nCDELT2 = 1.0").unwrap();
+        let regions = parse_regions("fk5;circle(1.0,1.0,0.1)").unwrap();
+
+        let masked = table.filter(&header, &data, in_mask(&mask, "X", "Y")).unwrap();
+        let located = table.filter(&header, &data, in_region(&regions, &image.wcs().unwrap(), "X", "Y").unwrap()).unwrap();
+
+        assert_eq!(masked.data, data[20..60].to_vec());
+        assert_eq!(located.data, data[0..20].to_vec());
+    }
+}
@@ -0,0 +1,123 @@
+//! Ordering the rows of binary tables by the values of their columns.
+
+use std::cmp::Ordering;
+use super::{BinTable, ColumnData, ColumnValues, Subtable, TableError};
+use super::super::types::Header;
+
+/// The value a row is ordered by: the first element of its field.
+#[derive(Debug, PartialEq)]
+enum Key {
+    Number(f64),
+    Text(String),
+    Null,
+}
+
+impl Key {
+    /// Numbers in ascending order, text by bytes, and nulls last.
+    fn compare(&self, other: &Key) -> Ordering {
+        match (self, other) {
+            (Key::Number(a), Key::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Key::Text(a), Key::Text(b)) => a.cmp(b),
+            (Key::Null, Key::Null) => Ordering::Equal,
+            (Key::Null, _) => Ordering::Greater,
+            (_, Key::Null) => Ordering::Less,
+            (Key::Number(_), Key::Text(_)) => Ordering::Less,
+            (Key::Text(_), Key::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl BinTable {
+    /// The order of the rows of this table in `data`, its data array, when
+    /// sorted by the column called `name`: the first element of the sorted
+    /// table is the index of its first row. See `sort_by_columns`.
+    pub fn sort_by_column(&self, data: &[u8], name: &str) -> Result<Vec<usize>, TableError> {
+        self.sort_by_columns(data, &[name])
+    }
+
+    /// The order of the rows of this table in `data` when sorted by the
+    /// columns called `names`, the first column first.
+    ///
+    /// Rows are ordered by the first element of their fields, numbers by their
+    /// physical value and text by its bytes, without trailing spaces. Null
+    /// values, `NaN` and undefined logicals go last. The sort is stable, so
+    /// rows with equal values keep their order.
+    pub fn sort_by_columns(&self, data: &[u8], names: &[&str]) -> Result<Vec<usize>, TableError> {
+        let indices = names.iter()
+            .map(|name| self.column_index(name).ok_or_else(|| TableError::NoSuchColumnName(name.to_string())))
+            .collect::<Result<Vec<usize>, TableError>>()?;
+        let values = self.read_columns(data, &indices)?;
+        let keys: Vec<Vec<Key>> = indices.iter().zip(values.iter())
+            .map(|(&index, values)| keys(self, index, values))
+            .collect();
+        let mut order: Vec<usize> = (0..self.rows).collect();
+        order.sort_by(|&a, &b| keys.iter()
+            .map(|column| column[a].compare(&column[b]))
+            .find(|&ordering| ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal));
+        Ok(order)
+    }
+
+    /// This table, described by `header` with data array `data`, with its rows
+    /// sorted by the columns called `names`, see `sort_by_columns`.
+    pub fn sorted<'a>(&self, header: &Header<'a>, data: &[u8], names: &[&str]) -> Result<Subtable<'a>, TableError> {
+        let order = self.sort_by_columns(data, names)?;
+        self.take_rows(header, data, &order)
+    }
+}
+
+fn keys(table: &BinTable, index: usize, values: &ColumnValues) -> Vec<Key> {
+    let column = &table.columns()[index];
+    let physical = values.physical(column);
+    (0..values.rows())
+        .map(|row| {
+            let (start, end) = (values.offsets[row], values.offsets[row + 1]);
+            if let Option::Some(ref physical) = physical {
+                return match physical[start..end].first() {
+                    Option::Some(value) if !value.is_nan() => Key::Number(*value),
+                    _ => Key::Null,
+                }
+            }
+            match values.data {
+                ColumnData::Character(_) => values.text(row).map(|text| Key::Text(text.to_string())).unwrap_or(Key::Null),
+                ColumnData::Logical(ref v) => match v[start..end].first() {
+                    Option::Some(&Option::Some(b)) => Key::Number(if b { 1f64 } else { 0f64 }),
+                    _ => Key::Null,
+                },
+                ColumnData::Bit(ref v) => v[start..end].first().map(|&b| Key::Number(if b { 1f64 } else { 0f64 })).unwrap_or(Key::Null),
+                ColumnData::ComplexFloat(ref v) => v[start..end].first().map(|&(re, _)| Key::Number(re as f64)).unwrap_or(Key::Null),
+                ColumnData::ComplexDouble(ref v) => v[start..end].first().map(|&(re, _)| Key::Number(re)).unwrap_or(Key::Null),
+                _ => Key::Null,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parser::template;
+    use super::super::{BinTable, TableError};
+
+    #[test]
+    fn sort_should_be_stable_and_put_nulls_last() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 8\nNAXIS2 = 5\nPCOUNT = 0\nGCOUNT = 1\nTFIELDS = 2\nTTYPE1 = 'TIME'\nTFORM1 = 'E'\nTTYPE2 = 'NAME'\nTFORM2 = '4A'").unwrap();
+        let mut data = vec!();
+        for &(time, name) in &[(3f32, b"b   "), (f32::NAN, b"a   "), (1f32, b"c   "), (3f32, b"a   "), (1f32, b"c   ")] {
+            data.extend_from_slice(&time.to_be_bytes());
+            data.extend_from_slice(name);
+        }
+        let table = BinTable::new(&header).unwrap();
+
+        let by_time = table.sort_by_column(&data, "TIME").unwrap();
+        let by_time_and_name = table.sort_by_columns(&data, &["TIME", "NAME"]).unwrap();
+        let by_name = table.sort_by_column(&data, "name").unwrap();
+        let sorted = table.sorted(&header, &data, &["TIME"]).unwrap();
+
+        assert_eq!(by_time, vec!(2, 4, 0, 3, 1));
+        assert_eq!(by_time_and_name, vec!(2, 4, 3, 0, 1));
+        assert_eq!(by_name, vec!(1, 3, 0, 2, 4));
+        assert_eq!(&sorted.data[0..8], &data[16..24]);
+        assert_eq!(&sorted.data[32..40], &data[8..16]);
+        assert_eq!(table.sort_by_column(&data, "FLUX"), Err(TableError::NoSuchColumnName("FLUX".to_string())));
+    }
+}
@@ -0,0 +1,123 @@
+//! Views of some of the rows and columns of binary tables, which decode only
+//! the bytes they need.
+
+use std::ops::Range;
+use super::{BinTable, ColumnValues, Row, TableError};
+
+/// Some of the rows and columns of a binary table. Decoding a view touches
+/// only the fields of its rows and columns, so a view of a table in a memory
+/// mapped file reads only those parts of the file.
+#[derive(Debug, Clone)]
+pub struct TableView<'t> {
+    table: &'t BinTable,
+    data: &'t [u8],
+    rows: Range<usize>,
+    columns: Vec<usize>,
+}
+
+impl BinTable {
+    /// A view of all rows and columns of this table in `data`, its data array.
+    pub fn view<'t>(&'t self, data: &'t [u8]) -> TableView<'t> {
+        TableView { table: self, data, rows: 0..self.rows, columns: (0..self.columns.len()).collect() }
+    }
+
+    /// A view of the zero based `rows` of this table in `data`, see
+    /// `TableView::rows_range`.
+    pub fn rows_range<'t>(&'t self, data: &'t [u8], rows: Range<usize>) -> Result<TableView<'t>, TableError> {
+        self.view(data).rows_range(rows)
+    }
+
+    /// A view of the columns called `names` of this table in `data`, see
+    /// `TableView::select`.
+    pub fn select<'t>(&'t self, data: &'t [u8], names: &[&str]) -> Result<TableView<'t>, TableError> {
+        self.view(data).select(names)
+    }
+
+    /// The range of bytes of the data array that holds the zero based `rows`,
+    /// to read them from a file without reading the rest of the table.
+    pub fn byte_range(&self, rows: Range<usize>) -> Result<Range<u64>, TableError> {
+        if rows.start > rows.end || rows.end > self.rows {
+            return Err(TableError::NoSuchRow(rows.end))
+        }
+        let width = self.row_width as u64;
+        Ok(rows.start as u64 * width..rows.end as u64 * width)
+    }
+}
+
+impl<'t> TableView<'t> {
+    /// The zero based `rows` of this view, counted from the first row of the
+    /// view.
+    pub fn rows_range(self, rows: Range<usize>) -> Result<TableView<'t>, TableError> {
+        if rows.start > rows.end || rows.end > self.rows.len() {
+            return Err(TableError::NoSuchRow(self.rows.start + rows.end))
+        }
+        let start = self.rows.start;
+        Ok(TableView { rows: start + rows.start..start + rows.end, ..self })
+    }
+
+    /// The columns of this view called `names`, in the order of `names`.
+    /// Names are compared case-insensitively.
+    pub fn select(self, names: &[&str]) -> Result<TableView<'t>, TableError> {
+        let columns = names.iter()
+            .map(|name| self.table.column_index(name)
+                .filter(|index| self.columns.contains(index))
+                .ok_or_else(|| TableError::NoSuchColumnName(name.to_string())))
+            .collect::<Result<Vec<usize>, TableError>>()?;
+        Ok(TableView { columns, ..self })
+    }
+
+    /// The rows of the table in this view.
+    pub fn rows(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    /// The indices in the table of the columns in this view.
+    pub fn columns(&self) -> &[usize] {
+        &self.columns
+    }
+
+    /// Decode the columns of this view, in order, for the rows of this view.
+    pub fn read_columns(&self) -> Result<Vec<ColumnValues>, TableError> {
+        self.columns.iter()
+            .map(|&index| self.table.read_column_rows(self.data, index, self.rows.clone()))
+            .collect()
+    }
+
+    /// The rows of this view, one at a time.
+    pub fn iter_rows(&self) -> Result<impl Iterator<Item = Row<'t>> + 't, TableError> {
+        let rows = self.rows.clone();
+        Ok(self.table.iter_rows(self.data)?.skip(rows.start).take(rows.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::fits;
+    use super::super::{BinTable, TableError};
+
+    #[test]
+    fn views_should_decode_only_their_rows_and_columns() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let file = match fits(data) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let table = BinTable::new(&file[1].header).unwrap();
+        let (time, flux) = (table.column_index("TIME").unwrap(), table.column_index("FLUX").unwrap());
+        let all = table.read_columns(file[1].data(), &[time, flux]).unwrap();
+
+        let view = table.select(file[1].data(), &["time", "FLUX"]).unwrap().rows_range(10..30).unwrap().rows_range(5..10).unwrap();
+        let values = view.read_columns().unwrap();
+
+        assert_eq!(view.rows(), 15..20);
+        assert_eq!(view.columns(), &[time, flux]);
+        assert_eq!(values[0].rows(), 5);
+        assert_eq!(values[1].offsets.len(), 6);
+        assert_eq!(values[0].physical(&table.columns()[time]).unwrap(), all[0].physical(&table.columns()[time]).unwrap()[15..20].to_vec());
+        assert_eq!(view.iter_rows().unwrap().map(|row| row.index()).collect::<Vec<usize>>(), (15..20).collect::<Vec<usize>>());
+        assert_eq!(table.byte_range(15..20), Ok(15 * table.row_width() as u64..20 * table.row_width() as u64));
+        assert_eq!(table.rows_range(file[1].data(), 0..table.rows() + 1).err(), Option::Some(TableError::NoSuchRow(table.rows() + 1)));
+        assert_eq!(table.select(file[1].data(), &["NOPE"]).err(), Option::Some(TableError::NoSuchColumnName("NOPE".to_string())));
+    }
+}
@@ -0,0 +1,181 @@
+//! Conversion of binary tables into VOTable documents.
+
+use std::fmt::Display;
+use super::{BinTable, BinType, Column, ColumnData, ColumnValues, TableError};
+
+impl BinTable {
+    /// Convert this table, with data array `data`, into a VOTable document
+    /// with the rows inline as `TABLEDATA`.
+    ///
+    /// Every column becomes a `FIELD` with the name of `TTYPEn`, the unit of
+    /// `TUNITn` and the unified content descriptor of `TUCDn`, when present.
+    /// Variable length arrays get an `arraysize` of `*`. Integer columns keep
+    /// `TNULLn` as the null value, scaled columns are written as their physical
+    /// values with `NaN` for nulls.
+    pub fn to_votable(&self, data: &[u8]) -> Result<String, TableError> {
+        let indices: Vec<usize> = (0..self.columns.len()).collect();
+        let values = self.read_columns(data, &indices)?;
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<VOTABLE version=\"1.4\" xmlns=\"http://www.ivoa.net/xml/VOTable/v1.3\">\n");
+        xml.push_str("<RESOURCE>\n<TABLE>\n");
+        for (index, column) in self.columns.iter().enumerate() {
+            xml.push_str(&field(column, index));
+        }
+        xml.push_str("<DATA>\n<TABLEDATA>\n");
+        for row in 0..self.rows {
+            xml.push_str("<TR>");
+            for (column, values) in self.columns.iter().zip(values.iter()) {
+                xml.push_str("<TD>");
+                xml.push_str(&escape(&cell(column, values, row)));
+                xml.push_str("</TD>");
+            }
+            xml.push_str("</TR>\n");
+        }
+        xml.push_str("</TABLEDATA>\n</DATA>\n</TABLE>\n</RESOURCE>\n</VOTABLE>\n");
+        Ok(xml)
+    }
+}
+
+fn field(column: &Column, index: usize) -> String {
+    let name = column.name.clone().unwrap_or_else(|| format!("COL{}", index + 1));
+    let mut element = format!("<FIELD name=\"{}\" datatype=\"{}\"", escape(&name), datatype(column));
+    if column.form.descriptor.is_some() {
+        element.push_str(" arraysize=\"*\"");
+    } else if column.form.repeat != 1 || column.form.kind == BinType::Character {
+        element.push_str(&format!(" arraysize=\"{}\"", column.form.repeat));
+    }
+    if let Option::Some(ref unit) = column.unit {
+        element.push_str(&format!(" unit=\"{}\"", escape(unit)));
+    }
+    if let Option::Some(ref ucd) = column.ucd {
+        element.push_str(&format!(" ucd=\"{}\"", escape(ucd)));
+    }
+    match column.null {
+        Option::Some(null) if is_integer(column) && !is_scaled(column) => {
+            element.push_str(&format!(">\n<VALUES null=\"{}\"/>\n</FIELD>\n", null));
+        }
+        _ => element.push_str("/>\n"),
+    }
+    element
+}
+
+fn datatype(column: &Column) -> &'static str {
+    if is_scaled(column) && column.form.kind != BinType::ComplexFloat && column.form.kind != BinType::ComplexDouble {
+        return "double"
+    }
+    match column.form.kind {
+        BinType::Logical => "boolean",
+        BinType::Bit => "bit",
+        BinType::UnsignedByte => "unsignedByte",
+        BinType::Short => "short",
+        BinType::Int => "int",
+        BinType::Long => "long",
+        BinType::Character => "char",
+        BinType::Float => "float",
+        BinType::Double => "double",
+        BinType::ComplexFloat => "floatComplex",
+        BinType::ComplexDouble => "doubleComplex",
+    }
+}
+
+fn is_integer(column: &Column) -> bool {
+    matches!(column.form.kind, BinType::UnsignedByte | BinType::Short | BinType::Int | BinType::Long)
+}
+
+fn is_scaled(column: &Column) -> bool {
+    column.scale != 1f64 || column.zero != 0f64
+}
+
+fn cell(column: &Column, values: &ColumnValues, row: usize) -> String {
+    let range = values.offsets[row]..values.offsets[row + 1];
+    let elements: Vec<String> = match values.data {
+        ColumnData::Logical(ref v) => v[range].iter().map(|b| match *b {
+            Option::Some(true) => "T".to_string(),
+            Option::Some(false) => "F".to_string(),
+            Option::None => "?".to_string(),
+        }).collect(),
+        ColumnData::Bit(ref v) => v[range].iter().map(|&b| if b { "1" } else { "0" }.to_string()).collect(),
+        ColumnData::UnsignedByte(ref v) => integers(column, &v[range]),
+        ColumnData::Short(ref v) => integers(column, &v[range]),
+        ColumnData::Int(ref v) => integers(column, &v[range]),
+        ColumnData::Long(ref v) => integers(column, &v[range]),
+        ColumnData::Character(_) => return values.text(row).unwrap_or("").to_string(),
+        ColumnData::Float(ref v) => v[range].iter().map(|&x| {
+            if is_scaled(column) { real(x as f64 * column.scale + column.zero) } else { real(x) }
+        }).collect(),
+        ColumnData::Double(ref v) => v[range].iter().map(|&x| real(x * column.scale + column.zero)).collect(),
+        ColumnData::ComplexFloat(ref v) => v[range].iter().map(|&(re, im)| format!("{} {}", real(re), real(im))).collect(),
+        ColumnData::ComplexDouble(ref v) => v[range].iter().map(|&(re, im)| format!("{} {}", real(re), real(im))).collect(),
+    };
+    elements.join(" ")
+}
+
+fn integers<T: Copy + Into<i64>>(column: &Column, values: &[T]) -> Vec<String> {
+    values.iter()
+        .map(|&v| {
+            let raw: i64 = v.into();
+            if !is_scaled(column) {
+                raw.to_string()
+            } else if Option::Some(raw) == column.null {
+                "NaN".to_string()
+            } else {
+                real(raw as f64 * column.scale + column.zero)
+            }
+        })
+        .collect()
+}
+
+fn real<T: Copy + Display + Into<f64>>(x: T) -> String {
+    let value: f64 = x.into();
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0f64 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        x.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::types::{Header, KeywordRecord, Keyword, Value};
+    use super::super::BinTable;
+
+    #[test]
+    fn bintable_should_convert_to_a_votable() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(9i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::TTYPEn(1u16), Value::CharacterString("ID".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(1u16), Value::CharacterString("J".into()), Option::None),
+            KeywordRecord::new(Keyword::TNULLn(1u16), Value::Integer(-1i64), Option::None),
+            KeywordRecord::new(Keyword::TTYPEn(2u16), Value::CharacterString("MAG".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(2u16), Value::CharacterString("E".into()), Option::None),
+            KeywordRecord::new(Keyword::TUNITn(2u16), Value::CharacterString("mag".into()), Option::None),
+            KeywordRecord::new(Keyword::TUCDn(2u16), Value::CharacterString("phot.mag".into()), Option::None),
+            KeywordRecord::new(Keyword::TTYPEn(3u16), Value::CharacterString("NAME".into()), Option::None),
+            KeywordRecord::new(Keyword::TFORMn(3u16), Value::CharacterString("1A".into()), Option::None),
+        ));
+        let data = [
+            0, 0, 0, 7, 0x3f, 0xc0, 0, 0, b'<',
+            0xff, 0xff, 0xff, 0xff, 0x7f, 0xc0, 0, 0, b'b',
+        ];
+        let table = BinTable::new(&header).unwrap();
+
+        let votable = table.to_votable(&data).unwrap();
+
+        assert!(votable.contains("<FIELD name=\"ID\" datatype=\"int\">\n<VALUES null=\"-1\"/>\n</FIELD>\n"));
+        assert!(votable.contains("<FIELD name=\"MAG\" datatype=\"float\" unit=\"mag\" ucd=\"phot.mag\"/>\n"));
+        assert!(votable.contains("<FIELD name=\"NAME\" datatype=\"char\" arraysize=\"1\"/>\n"));
+        assert!(votable.contains("<TR><TD>7</TD><TD>1.5</TD><TD>&lt;</TD></TR>\n<TR><TD>-1</TD><TD>NaN</TD><TD>b</TD></TR>\n"));
+    }
+}
@@ -0,0 +1,310 @@
+//! The testkit module generates FITS files from a specification, so that code
+//! using this crate can be tested on files of any shape without shipping them.
+//!
+//! The data arrays are filled with pseudo-random values from a seed, the same
+//! specification and seed generate the same bytes. Floating point values are
+//! finite, logicals are `T` or `F`, characters are printable and variable
+//! length arrays are empty, so that every generated file is valid.
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Error};
+use std::str::FromStr;
+use super::table::{BinType, TForm};
+use super::types::{HDU, Header, Keyword, KeywordRecord, Value};
+use super::writer::{check_header, write_hdu, HeaderWriteError};
+
+/// The specification of an HDU to generate.
+#[derive(Debug, PartialEq, Clone)]
+pub enum HduSpec {
+    /// An image with `BITPIX` `bitpix` and the axis lengths of `shape`.
+    Image {
+        /// The `BITPIX` of the image: 8, 16, 32, 64, -32 or -64.
+        bitpix: i64,
+        /// The lengths of the axes, `NAXIS1` first.
+        shape: Vec<usize>,
+    },
+    /// A binary table with a column per `TFORMn` in `forms` and `rows` rows.
+    Table {
+        /// The `TFORMn` of the columns, e.g. `1J` or `10A`.
+        forms: Vec<String>,
+        /// The number of rows.
+        rows: usize,
+    },
+}
+
+/// The specification of a FITS file to generate, built HDU by HDU.
+///
+/// When the first HDU is not an image, an empty primary HDU precedes it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FitsSpec {
+    hdus: Vec<HduSpec>,
+    seed: u64,
+}
+
+impl Default for FitsSpec {
+    fn default() -> FitsSpec {
+        FitsSpec { hdus: vec!(), seed: 1 }
+    }
+}
+
+impl FitsSpec {
+    /// A specification without HDUs, which generates an empty primary HDU.
+    pub fn new() -> FitsSpec {
+        FitsSpec::default()
+    }
+
+    /// Fill the data arrays with values derived from `seed`.
+    pub fn seed(mut self, seed: u64) -> FitsSpec {
+        self.seed = seed;
+        self
+    }
+
+    /// Add an image with `BITPIX` `bitpix` and the axis lengths `shape`.
+    pub fn image(mut self, bitpix: i64, shape: &[usize]) -> FitsSpec {
+        self.hdus.push(HduSpec::Image { bitpix, shape: shape.to_vec() });
+        self
+    }
+
+    /// Add a binary table with columns of the formats `forms` and `rows` rows.
+    pub fn table(mut self, forms: &[&str], rows: usize) -> FitsSpec {
+        self.hdus.push(HduSpec::Table { forms: forms.iter().map(|form| form.to_string()).collect(), rows });
+        self
+    }
+
+    /// The HDUs of this specification, in order.
+    pub fn hdus(&self) -> &[HduSpec] {
+        &self.hdus
+    }
+
+    /// Generate the FITS file of this specification, padded to whole blocks.
+    pub fn generate(&self) -> Result<Vec<u8>, SpecError> {
+        let mut random = Random::new(self.seed);
+        let mut out = vec!();
+        let mut specs: Vec<Cow<HduSpec>> = self.hdus.iter().map(Cow::Borrowed).collect();
+        match specs.first().map(|spec| spec.as_ref()) {
+            Option::Some(&HduSpec::Image { .. }) => (),
+            _ => specs.insert(0, Cow::Owned(HduSpec::Image { bitpix: 8, shape: vec!() })),
+        }
+        let extend = specs.len() > 1;
+        for (index, spec) in specs.iter().enumerate() {
+            let (records, data) = match **spec {
+                HduSpec::Image { bitpix, ref shape } => image(index, bitpix, shape, extend, &mut random)?,
+                HduSpec::Table { ref forms, rows } => table(index, forms, rows, &mut random)?,
+            };
+            let header = Header::new(records);
+            check_header(&header).map_err(|error| SpecError::Unwritable(index, error))?;
+            write_hdu(&mut out, &HDU::with_data(header, &data)).expect("the header is checked and writing to a vector does not fail");
+        }
+        Ok(out)
+    }
+}
+
+/// Problems with a specification that could occur when generating a file.
+#[derive(Debug, PartialEq)]
+pub enum SpecError {
+    /// The `BITPIX` of the image at this index is not one of the standard values.
+    InvalidBitpix(usize, i64),
+    /// A `TFORMn` of the table at this index, with this column index, could not
+    /// be parsed or makes the rows too wide.
+    InvalidTForm(usize, usize),
+    /// The header of the HDU at this index can not be written.
+    Unwritable(usize, HeaderWriteError),
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            SpecError::InvalidBitpix(hdu, bitpix) => write!(f, "HDU {}: invalid BITPIX {}", hdu, bitpix),
+            SpecError::InvalidTForm(hdu, column) => write!(f, "HDU {}: invalid TFORM of column {}", hdu, column + 1),
+            SpecError::Unwritable(hdu, ref error) => write!(f, "HDU {}: {}", hdu, error),
+        }
+    }
+}
+
+fn image<'a>(index: usize, bitpix: i64, shape: &[usize], extend: bool, random: &mut Random) -> Result<(Vec<KeywordRecord<'a>>, Vec<u8>), SpecError> {
+    let size = match bitpix {
+        8 => 1,
+        16 => 2,
+        32 | -32 => 4,
+        64 | -64 => 8,
+        _ => return Err(SpecError::InvalidBitpix(index, bitpix)),
+    };
+    let mut records = vec!();
+    if index == 0 {
+        records.push(KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None));
+    } else {
+        records.push(KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("IMAGE".into()), Option::None));
+    }
+    records.push(KeywordRecord::new(Keyword::BITPIX, Value::Integer(bitpix), Option::None));
+    records.push(KeywordRecord::new(Keyword::NAXIS, Value::Integer(shape.len() as i64), Option::None));
+    for (axis, &length) in shape.iter().enumerate() {
+        records.push(KeywordRecord::new(Keyword::NAXISn(axis as u16 + 1), Value::Integer(length as i64), Option::None));
+    }
+    if index == 0 {
+        if extend {
+            records.push(KeywordRecord::new(Keyword::EXTEND, Value::Logical(true), Option::None));
+        }
+    } else {
+        records.push(KeywordRecord::new(Keyword::PCOUNT, Value::Integer(0), Option::None));
+        records.push(KeywordRecord::new(Keyword::GCOUNT, Value::Integer(1), Option::None));
+    }
+    let pixels = if shape.is_empty() { 0 } else { shape.iter().product() };
+    let mut data = Vec::with_capacity(pixels * size);
+    for _ in 0..pixels {
+        match bitpix {
+            -32 => data.extend_from_slice(&random.float().to_be_bytes()),
+            -64 => data.extend_from_slice(&random.double().to_be_bytes()),
+            _ => data.extend_from_slice(&random.next().to_be_bytes()[..size]),
+        }
+    }
+    Ok((records, data))
+}
+
+fn table<'a>(index: usize, forms: &[String], rows: usize, random: &mut Random) -> Result<(Vec<KeywordRecord<'a>>, Vec<u8>), SpecError> {
+    let forms = forms.iter().enumerate()
+        .map(|(column, form)| TForm::from_str(form).map_err(|_| SpecError::InvalidTForm(index, column)))
+        .collect::<Result<Vec<TForm>, SpecError>>()?;
+    let mut widths = vec!();
+    for (column, form) in forms.iter().enumerate() {
+        widths.push(form.width().ok_or(SpecError::InvalidTForm(index, column))?);
+    }
+    let row_width = widths.iter().try_fold(0usize, |sum, &width| sum.checked_add(width))
+        .ok_or(SpecError::InvalidTForm(index, forms.len() - 1))?;
+    let mut records = vec!(
+        KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
+        KeywordRecord::new(Keyword::BITPIX, Value::Integer(8), Option::None),
+        KeywordRecord::new(Keyword::NAXIS, Value::Integer(2), Option::None),
+        KeywordRecord::new(Keyword::NAXISn(1), Value::Integer(row_width as i64), Option::None),
+        KeywordRecord::new(Keyword::NAXISn(2), Value::Integer(rows as i64), Option::None),
+        KeywordRecord::new(Keyword::PCOUNT, Value::Integer(0), Option::None),
+        KeywordRecord::new(Keyword::GCOUNT, Value::Integer(1), Option::None),
+        KeywordRecord::new(Keyword::TFIELDS, Value::Integer(forms.len() as i64), Option::None),
+    );
+    for (column, form) in forms.iter().enumerate() {
+        let n = column as u16 + 1;
+        records.push(KeywordRecord::new(Keyword::TTYPEn(n), Value::CharacterString(Cow::Owned(format!("COL{}", n))), Option::None));
+        records.push(KeywordRecord::new(Keyword::TFORMn(n), Value::CharacterString(Cow::Owned(form.to_string())), Option::None));
+    }
+    let mut data = Vec::with_capacity(row_width * rows);
+    for _ in 0..rows {
+        for (form, &width) in forms.iter().zip(widths.iter()) {
+            field(form, width, random, &mut data);
+        }
+    }
+    Ok((records, data))
+}
+
+/// Appends a field of format `form`, `width` bytes wide, to `data`.
+fn field(form: &TForm, width: usize, random: &mut Random, data: &mut Vec<u8>) {
+    if form.descriptor.is_some() {
+        data.extend(vec!(0u8; width));
+        return
+    }
+    let elements = match form.kind {
+        BinType::Bit => width,
+        _ => form.repeat,
+    };
+    for _ in 0..elements {
+        match form.kind {
+            BinType::Logical => data.push(if random.next() & 1 == 0 { b'F' } else { b'T' }),
+            BinType::Character => data.push(b' ' + (random.next() % 95) as u8),
+            BinType::Float => data.extend_from_slice(&random.float().to_be_bytes()),
+            BinType::Double => data.extend_from_slice(&random.double().to_be_bytes()),
+            BinType::ComplexFloat => {
+                data.extend_from_slice(&random.float().to_be_bytes());
+                data.extend_from_slice(&random.float().to_be_bytes());
+            }
+            BinType::ComplexDouble => {
+                data.extend_from_slice(&random.double().to_be_bytes());
+                data.extend_from_slice(&random.double().to_be_bytes());
+            }
+            kind => data.extend_from_slice(&random.next().to_be_bytes()[..kind.size()]),
+        }
+    }
+}
+
+/// A xorshift generator, good enough to fill data arrays.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed } }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A finite single precision float between -2^15 and 2^15.
+    fn float(&mut self) -> f32 {
+        (self.next() as i32 as f32) / 65536f32
+    }
+
+    /// A finite double precision float between -2^31 and 2^31.
+    fn double(&mut self) -> f64 {
+        (self.next() as i64 as f64) / 4294967296f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::image::Image;
+    use super::super::lint::{validate, Severity};
+    use super::super::parser::fits;
+    use super::super::table::BinTable;
+    use super::super::types::Keyword;
+    use super::{FitsSpec, SpecError};
+
+    #[test]
+    fn generated_files_should_parse_and_validate() {
+        let data = FitsSpec::new()
+            .seed(42)
+            .image(-32, &[4, 3])
+            .table(&["1J", "2E", "10A", "1L", "3X", "1PD", "1M"], 5)
+            .image(16, &[2, 2, 2])
+            .generate()
+            .unwrap();
+
+        assert_eq!(data.len() % 2880, 0);
+        let file = match fits(&data) {
+            IResult::Done(rest, file) => {
+                assert!(rest.is_empty());
+                file
+            }
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        assert_eq!(file.len(), 3);
+        assert_eq!(file[0].header.naxes(), Ok(vec!(4, 3)));
+        assert!(Image::new(&file[0].header, file[0].data()).is_ok());
+        let table = BinTable::new(&file[1].header).unwrap();
+        assert_eq!(table.rows(), 5);
+        for column in 0..table.columns().len() {
+            assert!(table.read_column(file[1].data(), column).is_ok());
+        }
+        assert_eq!(file[2].header.character_string_value_of(&Keyword::XTENSION), Ok("IMAGE   "));
+        assert!(validate(&file).iter().all(|finding| finding.severity != Severity::Error), "{:?}", validate(&file));
+        assert_eq!(FitsSpec::new().seed(42).image(-32, &[4, 3]).generate().unwrap()[2880..], data[2880..5760]);
+    }
+
+    #[test]
+    fn tables_should_follow_an_empty_primary_hdu() {
+        let data = FitsSpec::new().table(&["1K"], 2).generate().unwrap();
+
+        match fits(&data) {
+            IResult::Done(_, file) => {
+                assert_eq!(file.len(), 2);
+                assert_eq!(file[0].header.integer_value_of(&Keyword::NAXIS), Ok(0));
+                assert_eq!(file[1].data().len(), 16);
+            }
+            _ => panic!("Did not expect the parse to fail"),
+        }
+        assert_eq!(FitsSpec::new().image(12, &[1]).generate(), Err(SpecError::InvalidBitpix(0, 12)));
+        assert_eq!(FitsSpec::new().table(&["1J", "Z"], 1).generate(), Err(SpecError::InvalidTForm(1, 1)));
+    }
+}
@@ -0,0 +1,108 @@
+//! Handles on data arrays that share ownership of the buffer of their file.
+
+use std::fmt::{Debug, Error, Formatter};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+use super::HDU;
+
+/// The data array of an HDU as a range of an `Arc<[u8]>` holding its file.
+///
+/// Cloning a `DataRef` does not copy the data, and a `DataRef` can be sent to
+/// other threads, so one file read into memory can be served to many workers.
+/// It dereferences to the bytes of the data array.
+#[derive(Clone)]
+pub struct DataRef {
+    buffer: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl DataRef {
+    /// The bytes of `buffer` in `range`. `None` when `range` does not lie
+    /// within `buffer`.
+    pub fn new(buffer: Arc<[u8]>, range: Range<usize>) -> Option<DataRef> {
+        if range.start <= range.end && range.end <= buffer.len() {
+            Option::Some(DataRef { buffer, range })
+        } else {
+            Option::None
+        }
+    }
+
+    /// The data array of `hdu`, which was parsed from `buffer`. `None` when
+    /// the data array of `hdu` does not lie within `buffer`.
+    pub fn of(buffer: &Arc<[u8]>, hdu: &HDU) -> Option<DataRef> {
+        let data = hdu.data();
+        if data.is_empty() {
+            return DataRef::new(buffer.clone(), 0..0)
+        }
+        let start = (data.as_ptr() as usize).checked_sub(buffer.as_ptr() as usize)?;
+        DataRef::new(buffer.clone(), start..(start + data.len()))
+    }
+
+    /// The buffer this data array is part of.
+    pub fn buffer(&self) -> &Arc<[u8]> {
+        &self.buffer
+    }
+
+    /// The range of the buffer that holds this data array.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
+
+impl Deref for DataRef {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for DataRef {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl PartialEq for DataRef {
+    fn eq(&self, other: &DataRef) -> bool {
+        **self == **other
+    }
+}
+
+impl Debug for DataRef {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "DataRef({} bytes)", self.range.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use nom::IResult;
+    use super::super::super::checksum;
+    use super::super::super::parser::fits;
+    use super::DataRef;
+
+    #[test]
+    fn data_refs_should_serve_a_file_to_many_threads() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let buffer: Arc<[u8]> = Arc::from(&data[..]);
+        let (refs, sums): (Vec<DataRef>, Vec<u32>) = match fits(&buffer) {
+            IResult::Done(_, file) => file.iter()
+                .map(|hdu| (DataRef::of(&buffer, hdu).unwrap(), checksum::sum(hdu.data())))
+                .unzip(),
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let workers: Vec<_> = refs.iter().cloned()
+            .map(|data| thread::spawn(move || checksum::sum(&data)))
+            .collect();
+        let results: Vec<u32> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+
+        assert_eq!(results, sums);
+        assert_eq!(refs[0].len(), 0);
+        assert!(Arc::ptr_eq(refs[1].buffer(), &buffer));
+        assert_eq!(DataRef::new(buffer.clone(), 10..buffer.len() + 1), Option::None);
+    }
+}
@@ -0,0 +1,268 @@
+//! The kinds of HDUs and access to extensions this crate does not decode.
+//!
+//! Downstream crates can decode such extensions, or conventions layered on the
+//! extensions this crate knows, by registering an `ExtensionDecoder` with an
+//! `ExtensionRegistry`. `Fits::classify` consults the registry before falling
+//! back to `HDU::kind`.
+
+use std::any::Any;
+use super::{Fits, HDU, Keyword, NaxesError, ValueRetrievalError};
+
+/// The kind of an HDU, by its `XTENSION`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum HduKind {
+    /// The primary HDU, which has no `XTENSION`.
+    Primary,
+    /// An `IMAGE` extension.
+    Image,
+    /// An ASCII `TABLE` extension.
+    AsciiTable,
+    /// A `BINTABLE` extension.
+    BinaryTable,
+    /// An extension of a type this crate does not decode, e.g. `IUEIMAGE`,
+    /// with its `XTENSION` without trailing spaces.
+    Foreign(String),
+}
+
+impl<'a> HDU<'a> {
+    /// The kind of this HDU, by its `XTENSION`. An HDU without `XTENSION`, or
+    /// with an `XTENSION` that is not a character string, is primary.
+    pub fn kind(&self) -> HduKind {
+        match self.header.character_string_value_of(&Keyword::XTENSION) {
+            Ok(xtension) => match xtension.trim_end() {
+                "IMAGE" => HduKind::Image,
+                "TABLE" => HduKind::AsciiTable,
+                "BINTABLE" => HduKind::BinaryTable,
+                other => HduKind::Foreign(other.to_string()),
+            },
+            Err(_) => HduKind::Primary,
+        }
+    }
+
+    /// This HDU as an extension of a type this crate does not decode, with
+    /// its raw data and the geometry every extension has.
+    pub fn foreign_extension(&self) -> Result<ForeignExtension<'_>, ForeignExtensionError> {
+        let xtension = match self.kind() {
+            HduKind::Foreign(_) => self.header.character_string_value_of(&Keyword::XTENSION)
+                .expect("foreign extensions have an XTENSION")
+                .trim_end(),
+            _ => return Err(ForeignExtensionError::NotForeign),
+        };
+        let required = |keyword: Keyword| self.header.integer_value_of(&keyword)
+            .map_err(|error| ForeignExtensionError::MissingKeyword(keyword, error));
+        Ok(ForeignExtension {
+            xtension,
+            bitpix: required(Keyword::BITPIX)?,
+            naxes: self.header.naxes().map_err(ForeignExtensionError::Axes)?,
+            pcount: required(Keyword::PCOUNT)?,
+            gcount: required(Keyword::GCOUNT)?,
+            data: self.data(),
+        })
+    }
+}
+
+/// An extension of a type this crate does not decode. Its data array is kept
+/// as is, so writing the HDU copies it through unchanged.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ForeignExtension<'h> {
+    /// The type of the extension, `XTENSION` without trailing spaces.
+    pub xtension: &'h str,
+    /// The number of bits per value, `BITPIX`.
+    pub bitpix: i64,
+    /// The lengths of the axes, `NAXIS1` first.
+    pub naxes: Vec<u64>,
+    /// The number of values following the array in each group, `PCOUNT`.
+    pub pcount: i64,
+    /// The number of groups, `GCOUNT`.
+    pub gcount: i64,
+    /// The raw bytes of the data array, `|BITPIX| * GCOUNT * (PCOUNT + NAXIS1 * ... * NAXISn) / 8` of them.
+    pub data: &'h [u8],
+}
+
+/// Problems that could occur when viewing an HDU as a foreign extension.
+#[derive(Debug, PartialEq)]
+pub enum ForeignExtensionError {
+    /// The HDU is primary or an extension this crate decodes.
+    NotForeign,
+    /// A keyword every extension has could not be retrieved.
+    MissingKeyword(Keyword, ValueRetrievalError),
+    /// The axes could not be determined.
+    Axes(NaxesError),
+}
+
+/// Decodes HDUs of a custom `XTENSION` or following a convention.
+pub trait ExtensionDecoder: Send + Sync {
+    /// The name of the extension type or convention, e.g. `IUEIMAGE`.
+    fn name(&self) -> &str;
+    /// Determines if `hdu` is decoded by this decoder.
+    fn accepts(&self, hdu: &HDU) -> bool;
+    /// Decode `hdu`, which this decoder accepts. Callers downcast the result
+    /// to the type the decoder documents.
+    fn decode(&self, hdu: &HDU) -> Result<Box<dyn Any + Send>, String>;
+}
+
+/// The decoders registered for custom extensions. The first decoder that
+/// accepts an HDU handles it.
+pub struct ExtensionRegistry {
+    decoders: Vec<Box<dyn ExtensionDecoder>>,
+}
+
+impl ExtensionRegistry {
+    /// A registry without any decoders.
+    pub fn new() -> ExtensionRegistry {
+        ExtensionRegistry { decoders: vec!() }
+    }
+
+    /// Add `decoder` to the decoders of this registry.
+    pub fn with_decoder<D: ExtensionDecoder + 'static>(mut self, decoder: D) -> ExtensionRegistry {
+        self.register(decoder);
+        self
+    }
+
+    /// Add `decoder` to the decoders of this registry.
+    pub fn register<D: ExtensionDecoder + 'static>(&mut self, decoder: D) {
+        self.decoders.push(Box::new(decoder));
+    }
+
+    /// The first decoder that accepts `hdu`.
+    pub fn decoder_for(&self, hdu: &HDU) -> Option<&dyn ExtensionDecoder> {
+        self.decoders.iter().map(|decoder| &**decoder).find(|decoder| decoder.accepts(hdu))
+    }
+
+    /// Classify `hdu`, by its decoder if one accepts it and by its kind otherwise.
+    pub fn classify(&self, hdu: &HDU) -> HduClass<'_> {
+        match self.decoder_for(hdu) {
+            Option::Some(decoder) => HduClass::Decoded(decoder),
+            Option::None => HduClass::Builtin(hdu.kind()),
+        }
+    }
+
+    /// Decode `hdu` with the first decoder that accepts it. `None` when no
+    /// decoder accepts it.
+    pub fn decode(&self, hdu: &HDU) -> Option<Result<Box<dyn Any + Send>, String>> {
+        self.decoder_for(hdu).map(|decoder| decoder.decode(hdu))
+    }
+}
+
+impl Default for ExtensionRegistry {
+    fn default() -> ExtensionRegistry {
+        ExtensionRegistry::new()
+    }
+}
+
+/// How an HDU is handled: by a registered decoder, or as one of the kinds this
+/// crate knows.
+pub enum HduClass<'r> {
+    /// The HDU is handled by this decoder.
+    Decoded(&'r dyn ExtensionDecoder),
+    /// No decoder accepts the HDU.
+    Builtin(HduKind),
+}
+
+impl<'r> HduClass<'r> {
+    /// The name of the decoder, or `None` when no decoder accepts the HDU.
+    pub fn decoder_name(&self) -> Option<&str> {
+        match *self {
+            HduClass::Decoded(decoder) => Option::Some(decoder.name()),
+            HduClass::Builtin(_) => Option::None,
+        }
+    }
+}
+
+impl<'a> Fits<'a> {
+    /// Classify all HDUs, starting with the primary HDU, dispatching to the
+    /// decoders of `registry`.
+    pub fn classify<'r>(&self, registry: &'r ExtensionRegistry) -> Vec<HduClass<'r>> {
+        self.iter().map(|hdu| registry.classify(hdu)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::{fits, template};
+    use super::super::super::writer::write_fits;
+    use super::super::HDU;
+    use std::any::Any;
+    use super::super::{Fits, Keyword};
+    use super::{ExtensionDecoder, ExtensionRegistry, ForeignExtensionError, HduClass, HduKind};
+
+    #[test]
+    fn foreign_extensions_should_keep_their_raw_data_and_geometry() {
+        let primary = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 0\nEXTEND = T").unwrap();
+        let header = template("XTENSION = 'IUEIMAGE'\nBITPIX = 16\nNAXIS = 2\nNAXIS1 = 3\nNAXIS2 = 2\nPCOUNT = 2\nGCOUNT = 3\nEXTNAME = 'RAW'").unwrap();
+        let bytes: Vec<u8> = (0..48).collect();
+        let mut file = primary.to_fits_string().into_bytes();
+        file.extend_from_slice(header.to_fits_string().as_bytes());
+        file.extend_from_slice(&bytes);
+        file.resize(2880 * 3, 0);
+
+        let parsed = match fits(&file) {
+            IResult::Done(_, parsed) => parsed,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let extension = parsed[1].foreign_extension().unwrap();
+
+        assert_eq!(parsed[0].kind(), HduKind::Primary);
+        assert_eq!(parsed[1].kind(), HduKind::Foreign("IUEIMAGE".to_string()));
+        assert_eq!((extension.xtension, extension.bitpix, extension.pcount, extension.gcount), ("IUEIMAGE", 16, 2, 3));
+        assert_eq!(extension.naxes, vec!(3, 2));
+        assert_eq!(extension.data, &bytes[..]);
+        assert_eq!(parsed[0].foreign_extension(), Err(ForeignExtensionError::NotForeign));
+        let mut written = vec!();
+        write_fits(&mut written, &parsed).unwrap();
+        assert_eq!(written, file);
+    }
+
+    #[test]
+    fn kind_should_follow_xtension() {
+        let kind = |text| HDU::new(template(text).unwrap()).kind();
+
+        assert_eq!(kind("XTENSION = 'IMAGE   '"), HduKind::Image);
+        assert_eq!(kind("XTENSION = 'TABLE'"), HduKind::AsciiTable);
+        assert_eq!(kind("XTENSION = 'BINTABLE'"), HduKind::BinaryTable);
+        assert_eq!(kind("XTENSION = 'A3DTABLE'"), HduKind::Foreign("A3DTABLE".to_string()));
+        assert_eq!(kind("SIMPLE = T"), HduKind::Primary);
+    }
+
+    struct IueImage;
+
+    impl ExtensionDecoder for IueImage {
+        fn name(&self) -> &str {
+            "IUEIMAGE"
+        }
+
+        fn accepts(&self, hdu: &HDU) -> bool {
+            hdu.kind() == HduKind::Foreign("IUEIMAGE".to_string())
+        }
+
+        fn decode(&self, hdu: &HDU) -> Result<Box<dyn Any + Send>, String> {
+            hdu.header.integer_value_of(&Keyword::GCOUNT)
+                .map(|gcount| Box::new(gcount) as Box<dyn Any + Send>)
+                .map_err(|error| format!("{:?}", error))
+        }
+    }
+
+    #[test]
+    fn registered_decoders_should_classify_their_extensions() {
+        let registry = ExtensionRegistry::new().with_decoder(IueImage);
+        let file = Fits::new(
+            HDU::new(template("SIMPLE = T").unwrap()),
+            vec!(
+                HDU::new(template("XTENSION = 'IUEIMAGE'\nGCOUNT = 3").unwrap()),
+                HDU::new(template("XTENSION = 'VENDOR'").unwrap()),
+            ),
+        );
+
+        let classes = file.classify(&registry);
+
+        assert_eq!(classes.iter().map(HduClass::decoder_name).collect::<Vec<_>>(), vec!(Option::None, Option::Some("IUEIMAGE"), Option::None));
+        match classes[2] {
+            HduClass::Builtin(ref kind) => assert_eq!(*kind, HduKind::Foreign("VENDOR".to_string())),
+            HduClass::Decoded(_) => panic!("Did not expect VENDOR to be decoded"),
+        }
+        let decoded = registry.decode(&file[1]).unwrap().unwrap();
+        assert_eq!(decoded.downcast_ref::<i64>(), Option::Some(&3));
+        assert!(registry.decode(&file[0]).is_none());
+    }
+}
@@ -0,0 +1,101 @@
+//! Arbitrary headers, records, values and keywords for fuzzing.
+//!
+//! The generated keywords parse back from their text, character strings and
+//! comments hold the printable ASCII a card allows and reals are finite, so
+//! that the generated headers can be written as cards.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+use arbitrary::{Arbitrary, Result, Unstructured};
+use super::{Header, Keyword, KeywordRecord, Value, NAMED_KEYWORDS};
+
+/// The characters allowed in a keyword.
+const KEYWORD_CHARACTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-_";
+
+impl<'a> Arbitrary<'a> for Keyword {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.ratio(1u8, 2u8)? {
+            return u.choose_iter(NAMED_KEYWORDS.values()).cloned()
+        }
+        let length = u.int_in_range(1..=8)?;
+        let mut text = String::with_capacity(length);
+        for _ in 0..length {
+            text.push(char::from(*u.choose(KEYWORD_CHARACTERS)?));
+        }
+        Ok(Keyword::from_str(&text).unwrap_or(Keyword::COMMENT))
+    }
+}
+
+impl<'a, 'b> Arbitrary<'a> for Value<'b> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=5)? {
+            0 => Value::CharacterString(Cow::Owned(printable(u, 68)?)),
+            1 => Value::Logical(bool::arbitrary(u)?),
+            2 => Value::Integer(i64::arbitrary(u)?),
+            3 => Value::Real(finite(u)?),
+            4 => Value::Complex((finite(u)?, finite(u)?)),
+            _ => Value::Undefined,
+        })
+    }
+}
+
+impl<'a, 'b> Arbitrary<'a> for KeywordRecord<'b> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let keyword = Keyword::arbitrary(u)?;
+        if keyword.is_commentary() {
+            return Ok(KeywordRecord::new(keyword, Value::Undefined, Option::Some(Cow::Owned(printable(u, 72)?))))
+        }
+        let value = Value::arbitrary(u)?;
+        let comment = if bool::arbitrary(u)? { Option::Some(Cow::Owned(printable(u, 47)?)) } else { Option::None };
+        Ok(KeywordRecord::new(keyword, value, comment))
+    }
+}
+
+impl<'a, 'b> Arbitrary<'a> for Header<'b> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let records = u.arbitrary_iter::<KeywordRecord<'b>>()?
+            .filter(|record| record.as_ref().map(|record| record.keyword != Keyword::END).unwrap_or(true))
+            .collect::<Result<Vec<KeywordRecord<'b>>>>()?;
+        Ok(Header::new(records))
+    }
+}
+
+/// Printable ASCII of at most `maximum` characters, without trailing spaces.
+fn printable(u: &mut Unstructured, maximum: usize) -> Result<String> {
+    let length = u.int_in_range(0..=maximum)?;
+    let mut text = String::with_capacity(length);
+    for _ in 0..length {
+        text.push(char::from(u.int_in_range(32u8..=126u8)?));
+    }
+    Ok(text.trim_end().to_string())
+}
+
+/// A finite real, non-finite reals can not be written in a card.
+fn finite(u: &mut Unstructured) -> Result<f64> {
+    let real = f64::arbitrary(u)?;
+    Ok(if real.is_finite() { real } else { 0f64 })
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+    use super::super::{Header, Keyword};
+
+    #[test]
+    fn arbitrary_headers_should_have_valid_keywords() {
+        let data: Vec<u8> = (0..4096u32).map(|n| (n.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let mut u = Unstructured::new(&data);
+
+        let mut records = 0;
+        while !u.is_empty() {
+            let header = Header::arbitrary(&mut u).unwrap();
+            records += header.len();
+            for record in header.records() {
+                assert_ne!(record.keyword(), &Keyword::END);
+                assert_eq!(record.keyword().to_string().parse::<Keyword>().ok().as_ref(), Option::Some(record.keyword()));
+            }
+        }
+
+        assert!(records > 0);
+    }
+}
@@ -1,14 +1,48 @@
 //! The types modules describes all the structures to express FITS files.
 
+use phf::phf_map;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::collections::hash_map::Entry;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::iter;
+use std::iter::{Chain, Once};
+use std::ops::{Index, Range};
+use std::slice;
+use std::str;
+use std::vec;
 use std::str::FromStr;
-use std::fmt::{Display, Formatter, Error};
+use std::fmt::{Debug, Display, Formatter, Error};
+use nom::IResult;
+use super::parser::parse_header;
+use super::writer::{Exponent, FormatPolicy};
+
+mod data_ref;
+mod extension;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+mod owned;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "bytes")]
+mod shared;
+
+pub use self::data_ref::DataRef;
+pub use self::extension::{ExtensionDecoder, ExtensionRegistry, ForeignExtension, ForeignExtensionError, HduClass, HduKind};
+pub use self::owned::{OwnedFits, OwnedHDU, OwnedHeader};
+#[cfg(feature = "bytes")]
+pub use self::shared::{SharedFits, SharedHDU};
 
 /// Representation of a FITS file.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Fits<'a> {
     /// The primary HDU
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub primary_hdu: HDU<'a>,
     /// The extention HDUs
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub extensions: Vec<HDU<'a>>,
 }
 
@@ -20,150 +54,1286 @@ impl<'a> Fits<'a> {
             extensions: extensions,
         }
     }
+
+    /// Find the first HDU with `EXTNAME` equal to `name`.
+    ///
+    /// Names are compared case-insensitively and trailing spaces are not significant.
+    pub fn by_name(&self, name: &str) -> Option<&HDU<'a>> {
+        self.iter().find(|hdu| hdu.has_name(name))
+    }
+
+    /// Find the first HDU with `EXTNAME` equal to `name` and `EXTVER` equal to `version`.
+    ///
+    /// An HDU without `EXTVER` has version 1.
+    pub fn by_name_ver(&self, name: &str, version: i64) -> Option<&HDU<'a>> {
+        self.iter().find(|hdu| hdu.has_name(name) && hdu.version() == version)
+    }
+
+    /// Iterate over all HDUs, starting with the primary HDU.
+    pub fn iter<'b>(&'b self) -> Chain<Once<&'b HDU<'a>>, slice::Iter<'b, HDU<'a>>> {
+        iter::once(&self.primary_hdu).chain(self.extensions.iter())
+    }
+
+    /// The HDU at `index`, where the primary HDU has index 0.
+    pub fn get(&self, index: usize) -> Option<&HDU<'a>> {
+        if index == 0 {
+            Option::Some(&self.primary_hdu)
+        } else {
+            self.extensions.get(index - 1)
+        }
+    }
+
+    /// The number of HDUs, including the primary HDU.
+    pub fn len(&self) -> usize {
+        1 + self.extensions.len()
+    }
+
+    /// Determines if there are no HDUs. Because a FITS file always has a
+    /// primary HDU this is never the case.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Remove the extension at `index`, where the primary HDU has index 0,
+    /// and return it. `NEXTEND` is updated when the primary header has it.
+    pub fn remove_hdu(&mut self, index: usize) -> Result<HDU<'a>, FitsEditError> {
+        let position = self.extension_position(index)?;
+        let hdu = self.extensions.remove(position);
+        self.update_nextend();
+        Ok(hdu)
+    }
+
+    /// Move the extension at index `from` to index `to`, shifting the
+    /// extensions in between. The primary HDU stays at index 0.
+    pub fn move_hdu(&mut self, from: usize, to: usize) -> Result<(), FitsEditError> {
+        let from = self.extension_position(from)?;
+        let to = self.extension_position(to)?;
+        let hdu = self.extensions.remove(from);
+        self.extensions.insert(to, hdu);
+        Ok(())
+    }
+
+    fn extension_position(&self, index: usize) -> Result<usize, FitsEditError> {
+        match index {
+            0 => Err(FitsEditError::PrimaryHdu),
+            index if index > self.extensions.len() => Err(FitsEditError::OutOfBounds(index)),
+            index => Ok(index - 1),
+        }
+    }
+
+    fn update_nextend(&mut self) {
+        if self.primary_hdu.header.value_opt(&Keyword::NEXTEND).is_some() {
+            let count = Value::Integer(self.extensions.len() as i64);
+            self.primary_hdu.header.set(Keyword::NEXTEND, count, Option::None).expect("NEXTEND can be set");
+        }
+    }
+
+    /// Copy everything this file borrows from its input, so that it can be
+    /// kept after the input is gone.
+    pub fn into_owned(self) -> Fits<'static> {
+        Fits {
+            primary_hdu: self.primary_hdu.into_owned(),
+            extensions: self.extensions.into_iter().map(HDU::into_owned).collect(),
+        }
+    }
+}
+
+impl<'a> Index<usize> for Fits<'a> {
+    type Output = HDU<'a>;
+
+    fn index(&self, index: usize) -> &HDU<'a> {
+        self.get(index).expect("HDU index out of bounds")
+    }
+}
+
+impl<'a> IntoIterator for Fits<'a> {
+    type Item = HDU<'a>;
+    type IntoIter = Chain<Once<HDU<'a>>, vec::IntoIter<HDU<'a>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        iter::once(self.primary_hdu).chain(self.extensions)
+    }
+}
+
+impl<'b, 'a> IntoIterator for &'b Fits<'a> {
+    type Item = &'b HDU<'a>;
+    type IntoIter = Chain<Once<&'b HDU<'a>>, slice::Iter<'b, HDU<'a>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 /// Header Data Unit, combination of a header and an optional data array.
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HDU<'a> {
     /// The header of this HDU.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub header: Header<'a>,
     /// The optional data array of this HDU.
-    data_array: Option<DataArray>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    data_array: Option<DataArray<'a>>,
+    /// The bytes this HDU was parsed from, when it was parsed.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<Source<'a>>,
+}
+
+impl<'a> PartialEq for HDU<'a> {
+    /// HDUs are equal when their headers and data arrays are, whether they
+    /// were parsed or not.
+    fn eq(&self, other: &HDU) -> bool {
+        self.header == other.header && self.data_array == other.data_array
+    }
 }
 
 impl<'a> HDU<'a> {
     /// Create an HDU with a header, setting the data_array to none.
     pub fn new(header: Header<'a>) -> HDU<'a> {
-        HDU { header: header, data_array: Option::None }
+        HDU { header: header, data_array: Option::None, source: Option::None }
+    }
+
+    /// Create an HDU with a header and the bytes following it. Only the bytes
+    /// of the data array are kept, i.e. the padding to whole blocks is dropped.
+    pub fn with_data(header: Header<'a>, data: &'a [u8]) -> HDU<'a> {
+        let size = header.data_size().min(data.len());
+        let data_array = if size > 0 {
+            Option::Some(DataArray { bytes: Cow::Borrowed(&data[..size]), edited: false })
+        } else {
+            Option::None
+        };
+        HDU { header, data_array, source: Option::None }
+    }
+
+    /// Create an HDU like `with_data` for a `header` parsed from
+    /// `header_bytes`, followed by `data`, the data array and its padding as
+    /// they appear in the input. The bytes are kept to verify checksums.
+    pub(crate) fn parsed(header: Header<'a>, header_bytes: &'a [u8], data: &'a [u8]) -> HDU<'a> {
+        let mut hdu = HDU::with_data(header, data);
+        let padding = &data[hdu.data().len()..];
+        hdu.source = Option::Some(Source { header: Cow::Borrowed(header_bytes), padding: Cow::Borrowed(padding) });
+        hdu
+    }
+
+    /// The bytes of the header and of the padding of the data array this HDU
+    /// was parsed from, if it was parsed and its header and data array are
+    /// unchanged since.
+    pub(crate) fn unchanged_source(&self) -> Option<(&[u8], &[u8])> {
+        let source = self.source.as_ref()?;
+        if self.is_data_edited() {
+            return Option::None
+        }
+        match parse_header(&source.header) {
+            IResult::Done(_, ref parsed) if *parsed == self.header => Option::Some((source.header.as_ref(), source.padding.as_ref())),
+            _ => Option::None,
+        }
+    }
+
+    /// The bytes of the data array of this HDU, empty when there is no data array.
+    pub fn data(&self) -> &[u8] {
+        match self.data_array {
+            Option::Some(ref data_array) => &data_array.bytes,
+            Option::None => &[],
+        }
+    }
+
+    /// The bytes of the data array of this HDU, to edit in place.
+    ///
+    /// The data array is marked as edited, so that the writer recomputes
+    /// `DATASUM` and `CHECKSUM` when the header has them.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        match self.data_array {
+            Option::Some(ref mut data_array) => {
+                data_array.edited = true;
+                data_array.bytes.to_mut()
+            }
+            Option::None => &mut [],
+        }
+    }
+
+    /// Replace the data array of this HDU with `data`, which should match the
+    /// axes of the header. Like `data_mut`, marks the data array as edited.
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.data_array = if data.is_empty() {
+            Option::None
+        } else {
+            Option::Some(DataArray { bytes: Cow::Owned(data), edited: true })
+        };
+    }
+
+    /// Whether the data array was edited since it was read, by `data_mut` or
+    /// `set_data`. The checksums in the header no longer match it.
+    pub fn is_data_edited(&self) -> bool {
+        self.data_array.as_ref().is_some_and(|data_array| data_array.edited)
+    }
+
+    pub(crate) fn mark_data_edited(&mut self) {
+        if let Option::Some(ref mut data_array) = self.data_array {
+            data_array.edited = true;
+        }
+    }
+
+    /// Copy the strings of the header and the data array that this HDU
+    /// borrows, so that it no longer borrows from its input.
+    pub fn into_owned(self) -> HDU<'static> {
+        HDU {
+            header: self.header.into_owned(),
+            data_array: self.data_array.map(|data_array| DataArray {
+                bytes: Cow::Owned(data_array.bytes.into_owned()),
+                edited: data_array.edited,
+            }),
+            source: self.source.map(|source| Source {
+                header: Cow::Owned(source.header.into_owned()),
+                padding: Cow::Owned(source.padding.into_owned()),
+            }),
+        }
+    }
+
+    fn has_name(&self, name: &str) -> bool {
+        match self.header.character_string_value_of(&Keyword::EXTNAME) {
+            Ok(extname) => extname.trim_end().eq_ignore_ascii_case(name.trim_end()),
+            Err(_) => false,
+        }
+    }
+
+    fn version(&self) -> i64 {
+        self.header.integer_value_of(&Keyword::EXTVER).unwrap_or(1i64)
     }
 }
 
 /// The primary header of a FITS file.
 #[derive(Debug, PartialEq)]
 pub struct Header<'a> {
-    /// The keyword records of the primary header.
-    pub keyword_records: Vec<KeywordRecord<'a>>,
+    records: Vec<KeywordRecord<'a>>,
+    index: KeywordIndex,
+    shape: Shape,
 }
 
 impl<'a> Header<'a> {
-    /// Create a Header with a given set of keyword_records
-    pub fn new(keyword_records: Vec<KeywordRecord<'a>>) -> Header<'a> {
-        Header { keyword_records: keyword_records }
+    /// Create a Header with a given set of records
+    pub fn new(records: Vec<KeywordRecord<'a>>) -> Header<'a> {
+        let index = index_of(&records);
+        let mut header = Header { records, index, shape: Shape::default() };
+        header.shape = Shape::of(&header);
+        header
+    }
+
+    /// All records of this header, including commentary, in header order.
+    pub fn records(&self) -> &[KeywordRecord<'a>] {
+        &self.records
+    }
+
+    /// Iterate over the records of this header that carry a value, i.e. all
+    /// records except commentary.
+    pub fn keyword_records<'b>(&'b self) -> impl Iterator<Item = &'b KeywordRecord<'a>> + 'b {
+        self.records
+            .iter()
+            .filter(|record| !record.keyword.is_commentary())
+    }
+
+    /// Copy the strings this header borrows, so that it no longer borrows
+    /// from its input.
+    pub fn into_owned(self) -> Header<'static> {
+        Header {
+            records: self.records.into_iter().map(KeywordRecord::into_owned).collect(),
+            index: self.index,
+            shape: self.shape,
+        }
+    }
+
+    /// The number of records in this header.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Determines if this header has no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
     }
 
     /// Determines the size in bits of the data array following this header.
+    ///
+    /// Sizes that do not fit in a `usize` saturate, see `padded_data_length`
+    /// for the exact size.
     pub fn data_array_size(&self) -> usize {
-        if self.is_primary() {
-            lmle(self.primary_data_array_size(), 2880*8)
+        self.padded_data_length()
+            .and_then(|length| length.checked_mul(8))
+            .and_then(|bits| usize::try_from(bits).ok())
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Determines the size in bytes of the data array following this header,
+    /// without the padding to whole blocks.
+    ///
+    /// Sizes that do not fit in a `usize` saturate, see `data_length` for the
+    /// exact size.
+    pub fn data_size(&self) -> usize {
+        self.data_length()
+            .and_then(|length| usize::try_from(length).ok())
+            .unwrap_or(usize::MAX)
+    }
+
+    /// The size in bytes of the data array following this header, without the
+    /// padding to whole blocks. `None` when the size does not fit in a `u64`
+    /// or `PCOUNT` or `GCOUNT` is negative.
+    pub fn data_length(&self) -> Option<u64> {
+        self.shape.bits.map(|bits| bits / 8)
+    }
+
+    /// The size in bytes of the data array following this header, including
+    /// the padding to whole blocks. `None` like `data_length`.
+    pub fn padded_data_length(&self) -> Option<u64> {
+        self.shape.bits
+            .map(|bits| bits.div_ceil(8))
+            .and_then(|length| length.checked_add(2879))
+            .map(|length| length / 2880 * 2880)
+    }
+
+    /// The `BITPIX` of this header, `None` when it is missing or not an
+    /// integer.
+    pub fn bitpix(&self) -> Option<i64> {
+        self.shape.bitpix
+    }
+
+    /// The bytes of this header as they appear in a FITS file: 80 column cards,
+    /// closed by an `END` card and padded with blank cards to whole blocks.
+    /// Long strings are continued over `CONTINUE` cards. Characters other than
+    /// printable ASCII, which `writer::check_header` rejects, become `?`.
+    pub fn to_fits_string(&self) -> String {
+        self.to_fits_string_with(&FormatPolicy::default())
+    }
+
+    /// The bytes of this header like `to_fits_string`, with the cards laid
+    /// out by `policy`.
+    pub fn to_fits_string_with(&self, policy: &FormatPolicy) -> String {
+        let mut result = String::new();
+        for card in self.cards(policy) {
+            result.push_str(&card);
+        }
+        result.push_str(&format!("{:80}", "END"));
+        let padding = (2880 - result.len() % 2880) % 2880;
+        result.push_str(&" ".repeat(padding));
+        result
+    }
+
+    /// This header as a template, with a record per line in the format of
+    /// cfitsio header templates. `parser::template` constructs a header from it.
+    pub fn to_template(&self) -> String {
+        let mut result = String::new();
+        for record in self.records.iter().filter(|record| record.keyword != Keyword::END) {
+            result.push_str(record.to_string().trim_end());
+            result.push('\n');
+        }
+        result
+    }
+
+    /// This header as a JSON object with a member per keyword, in header order.
+    ///
+    /// Each keyword maps to an object with its `value` and `comment`. Integers,
+    /// reals and logicals keep their JSON types, complex numbers become
+    /// `[real, imaginary]` and undefined values `null`. The text of commentary
+    /// records is grouped into an array per keyword. Only the first occurrence of
+    /// any other keyword is included, just like `value_of`.
+    pub fn to_json(&self) -> String {
+        let mut members: Vec<(String, Option<String>)> = vec!();
+        let mut commentary: HashMap<String, Vec<String>> = HashMap::new();
+        for (position, record) in self.records.iter().enumerate().filter(|&(_, record)| record.keyword != Keyword::END) {
+            let key = record.keyword.to_string();
+            if record.keyword.is_commentary() {
+                let texts = commentary.entry(key.clone()).or_default();
+                if texts.is_empty() {
+                    members.push((key, Option::None));
+                }
+                texts.push(json_string(record.comment().unwrap_or("")));
+            } else if self.index[&record.keyword].first == position {
+                let comment = record.comment().map_or("null".to_string(), |comment| json_string(comment.trim_end()));
+                members.push((key, Option::Some(format!("{{\"value\":{},\"comment\":{}}}", json_value(&record.value), comment))));
+            }
+        }
+        let members: Vec<String> = members.into_iter()
+            .map(|(key, member)| {
+                let member = member.unwrap_or_else(|| format!("[{}]", commentary[&key].join(",")));
+                format!("{}:{}", json_string(&key), member)
+            })
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
+
+    /// Determines the size in bits of this header, including the `END` record
+    /// and the padding to a whole number of blocks.
+    pub fn header_size(&self) -> usize {
+        lmle((self.cards(&FormatPolicy::default()).len() + 1) * 80 * 8, 2880*8)
+    }
+
+    /// The cards of the records of this header, without `END`. Long strings
+    /// take several cards, see `KeywordRecord::cards`, and a `LONGSTRN` card
+    /// is added before the first when the header has none.
+    fn cards(&self, policy: &FormatPolicy) -> Vec<String> {
+        let mut cards = vec!();
+        let mut longstrn = self.has_keyword_record(&Keyword::LONGSTRN);
+        for record in self.records.iter().filter(|record| record.keyword != Keyword::END) {
+            let record_cards = record.cards_with(policy);
+            if record_cards.len() > 1 && !longstrn {
+                let warning = Option::Some(Cow::Borrowed("the OGIP long string convention may be used"));
+                cards.push(KeywordRecord::new(Keyword::LONGSTRN, Value::CharacterString(Cow::Borrowed("OGIP 1.0")), warning).card_with(policy));
+                longstrn = true;
+            }
+            cards.extend(record_cards);
+        }
+        cards
+    }
+
+    /// Set the value and comment of `keyword`.
+    ///
+    /// The first occurrence of `keyword` is updated, keeping its comment when
+    /// `comment` is `None`. When `keyword` is not present, or is commentary, a
+    /// new record is appended, before the `END` record if there is one.
+    pub fn set(&mut self, keyword: Keyword, value: Value<'a>, comment: Option<Cow<'a, str>>) -> Result<(), HeaderEditError> {
+        if keyword == Keyword::END {
+            return Err(HeaderEditError::EndKeyword)
+        }
+        let position = if keyword.is_commentary() {
+            Option::None
         } else {
-            lmle(self.extention_data_array_size(), 2880*8)
+            self.index.get(&keyword).map(|positions| positions.first)
+        };
+        match position {
+            Option::Some(position) => {
+                let record = &mut self.records[position];
+                record.value = value;
+                if comment.is_some() {
+                    record.comment = comment;
+                }
+                self.shape = Shape::of(self);
+            }
+            Option::None => {
+                let position = self.index
+                    .get(&Keyword::END)
+                    .map(|positions| positions.first)
+                    .unwrap_or(self.records.len());
+                self.records.insert(position, KeywordRecord::new(keyword, value, comment));
+                self.reindex();
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the first occurrence of `keyword`, returning the removed record.
+    pub fn remove(&mut self, keyword: &Keyword) -> Result<KeywordRecord<'a>, HeaderEditError> {
+        if *keyword == Keyword::END {
+            return Err(HeaderEditError::EndKeyword)
         }
+        let position = self.index
+            .get(keyword)
+            .map(|positions| positions.first)
+            .ok_or(HeaderEditError::KeywordNotPresent)?;
+        let record = self.records.remove(position);
+        self.reindex();
+        Ok(record)
+    }
+
+    /// Insert `record` directly after the first occurrence of `keyword`.
+    pub fn insert_after(&mut self, keyword: &Keyword, record: KeywordRecord<'a>) -> Result<(), HeaderEditError> {
+        if *keyword == Keyword::END || record.keyword == Keyword::END {
+            return Err(HeaderEditError::EndKeyword)
+        }
+        let position = self.index
+            .get(keyword)
+            .map(|positions| positions.first)
+            .ok_or(HeaderEditError::KeywordNotPresent)?;
+        self.records.insert(position + 1, record);
+        self.reindex();
+        Ok(())
+    }
+
+    /// Rename the first occurrence of `keyword` to `name`, keeping its value,
+    /// comment and position.
+    pub fn rename(&mut self, keyword: &Keyword, name: Keyword) -> Result<(), HeaderEditError> {
+        if *keyword == Keyword::END || name == Keyword::END {
+            return Err(HeaderEditError::EndKeyword)
+        }
+        let position = self.index
+            .get(keyword)
+            .map(|positions| positions.first)
+            .ok_or(HeaderEditError::KeywordNotPresent)?;
+        self.records[position].keyword = name;
+        self.reindex();
+        Ok(())
+    }
+
+    /// Rebuild the index and the shape after the records changed.
+    fn reindex(&mut self) {
+        self.index = index_of(&self.records);
+        self.shape = Shape::of(self);
     }
 
     fn is_primary(&self) -> bool {
         self.has_keyword_record(&Keyword::SIMPLE)
     }
 
+    /// Determines if this is the primary header of random groups: `GROUPS = T`,
+    /// `NAXIS` of at least 1 and `NAXIS1 = 0`.
+    pub fn is_random_groups(&self) -> bool {
+        self.is_primary()
+            && self.logical_value_of(&Keyword::GROUPS) == Ok(true)
+            && self.integer_value_of(&Keyword::NAXIS).is_ok_and(|naxis| naxis >= 1)
+            && self.integer_value_of(&Keyword::NAXISn(1)) == Ok(0)
+    }
+
     fn has_keyword_record(&self, keyword: &Keyword) -> bool {
-        for keyword_record in &self.keyword_records {
-            if *keyword == keyword_record.keyword {
-                return true
+        self.index.contains_key(keyword)
+    }
+
+    /// Retrieve the integer value associated with `keyword`.
+    pub fn integer_value_of(&self, keyword: &Keyword) -> Result<i64, ValueRetrievalError> {
+        self.value_of(keyword).and_then(integer)
+    }
+
+    /// Retrieve the real value associated with `keyword`.
+    ///
+    /// Integer values are promoted to reals, because writers are free to drop
+    /// the decimal point of values like `EQUINOX = 2000`.
+    pub fn real_value_of(&self, keyword: &Keyword) -> Result<f64, ValueRetrievalError> {
+        self.value_of(keyword).and_then(real)
+    }
+
+    /// Retrieve the logical value associated with `keyword`.
+    pub fn logical_value_of(&self, keyword: &Keyword) -> Result<bool, ValueRetrievalError> {
+        self.value_of(keyword).and_then(logical)
+    }
+
+    /// Retrieve the complex value, as a `(real, imaginary)` pair, associated with `keyword`.
+    pub fn complex_value_of(&self, keyword: &Keyword) -> Result<(f64, f64), ValueRetrievalError> {
+        self.value_of(keyword).and_then(complex)
+    }
+
+    /// Retrieve the character string value associated with `keyword`.
+    pub fn character_string_value_of(&self, keyword: &Keyword) -> Result<&str, ValueRetrievalError> {
+        self.first_value_of(keyword).and_then(character_string)
+    }
+
+    /// Retrieve the value associated with `keyword`.
+    ///
+    /// When `keyword` occurs more than once, the value of the first occurrence is returned.
+    pub fn value_of(&self, keyword: &Keyword) -> Result<Value<'a>, ValueRetrievalError> {
+        self.first_value_of(keyword).cloned()
+    }
+
+    /// Retrieve the value associated with `keyword`, telling a missing keyword,
+    /// `None`, apart from a keyword without a value, `Some(None)`, like the
+    /// `PMRA =` records written when a proper motion is unknown.
+    pub fn value_opt(&self, keyword: &Keyword) -> Option<Option<Value<'a>>> {
+        self.first_value_of(keyword).ok().map(defined)
+    }
+
+    fn first_value_of(&self, keyword: &Keyword) -> Result<&Value<'a>, ValueRetrievalError> {
+        self.index
+            .get(keyword)
+            .map(|positions| &self.records[positions.first].value)
+            .ok_or(ValueRetrievalError::KeywordNotPresent)
+    }
+
+    /// Retrieve the value associated with the last occurrence of `keyword`.
+    ///
+    /// This follows cfitsio, where a repeated keyword overrides earlier occurrences.
+    pub fn last_value_of(&self, keyword: &Keyword) -> Result<Value<'a>, ValueRetrievalError> {
+        self.values_of(keyword)
+            .last()
+            .cloned()
+            .ok_or(ValueRetrievalError::KeywordNotPresent)
+    }
+
+    /// Iterate over the values of all occurrences of `keyword`, in header order.
+    pub fn values_of<'b>(&'b self, keyword: &'b Keyword) -> impl Iterator<Item = &'b Value<'a>> + 'b {
+        self.index
+            .get(keyword)
+            .into_iter()
+            .flat_map(|positions| positions.iter())
+            .map(move |position| &self.records[position].value)
+    }
+
+    /// Iterate over the text of the `HISTORY` records, in header order.
+    pub fn history<'b>(&'b self) -> impl Iterator<Item = &'b str> + 'b {
+        self.commentary_of(Keyword::HISTORY)
+    }
+
+    /// Iterate over the text of the `COMMENT` records, in header order.
+    pub fn comments<'b>(&'b self) -> impl Iterator<Item = &'b str> + 'b {
+        self.commentary_of(Keyword::COMMENT)
+    }
+
+    fn commentary_of<'b>(&'b self, keyword: Keyword) -> impl Iterator<Item = &'b str> + 'b {
+        self.records
+            .iter()
+            .filter(move |keyword_record| keyword_record.keyword == keyword)
+            .map(|keyword_record| keyword_record.comment().unwrap_or(""))
+    }
+
+    /// Group adjacent commentary records with the same keyword into blocks,
+    /// in header order. Producers often wrap a single text over several
+    /// `COMMENT` or `HISTORY` cards, a block joins their text with spaces.
+    pub fn commentary_blocks(&self) -> Vec<CommentaryBlock> {
+        let mut blocks: Vec<CommentaryBlock> = vec!();
+        for (index, record) in self.records.iter().enumerate() {
+            if !record.keyword.is_commentary() {
+                continue;
+            }
+            let text = record.comment().unwrap_or("").trim_end();
+            match blocks.last_mut() {
+                Option::Some(ref mut block) if block.cards.end == index && block.keyword == record.keyword => {
+                    if !text.is_empty() {
+                        if !block.text.is_empty() {
+                            block.text.push(' ');
+                        }
+                        block.text.push_str(text);
+                    }
+                    block.cards.end = index + 1;
+                }
+                _ => blocks.push(CommentaryBlock {
+                    keyword: record.keyword.clone(),
+                    text: text.to_string(),
+                    cards: index..index + 1,
+                }),
             }
         }
-        false
+        blocks
     }
 
-    fn primary_data_array_size(&self) -> usize {
-        (self.integer_value_of(&Keyword::BITPIX).unwrap_or(0i64).abs() * self.naxis_product()) as usize
+    /// Provide a view on this header that resolves keywords missing from this
+    /// header in `primary`, when this header has `INHERIT = T`.
+    ///
+    /// Following the convention, the mandatory structural keywords, commentary,
+    /// `EXTNAME`, `EXTVER`, `CHECKSUM` and `DATASUM` are never inherited.
+    pub fn with_inherited<'h>(&'h self, primary: &'h Header<'a>) -> InheritedHeader<'h, 'a> {
+        let inherits = self.logical_value_of(&Keyword::INHERIT).unwrap_or(false);
+        InheritedHeader {
+            header: self,
+            primary: if inherits { Option::Some(primary) } else { Option::None },
+        }
     }
 
-    fn extention_data_array_size(&self) -> usize {
-        (self.integer_value_of(&Keyword::BITPIX).unwrap_or(0i64).abs() *
-         self.integer_value_of(&Keyword::GCOUNT).unwrap_or(1i64) *
-         (self.integer_value_of(&Keyword::PCOUNT).unwrap_or(0i64) + self.naxis_product())) as usize
+    /// The lengths of the axes of the data array, `NAXIS1` first.
+    ///
+    /// A header with `NAXIS = 0` has no axes.
+    pub fn naxes(&self) -> Result<Vec<u64>, NaxesError> {
+        self.shape.naxes.clone()
     }
 
-    fn integer_value_of(&self, keyword: &Keyword) -> Result<i64, ValueRetrievalError> {
-        self.value_of(keyword).and_then(|value| {
-            match value {
-                Value::Integer(n) => Ok(n),
-                _ => Err(ValueRetrievalError::NotAnInteger),
+    fn read_naxes(&self) -> Result<Vec<u64>, NaxesError> {
+        let naxis = self.integer_value_of(&Keyword::NAXIS).map_err(NaxesError::NAXIS)?;
+        if !(0..=999).contains(&naxis) {
+            return Err(NaxesError::OutOfRange(Keyword::NAXIS, naxis))
+        }
+        let mut axes = vec!();
+        for n in 1..(naxis as u16 + 1) {
+            let keyword = Keyword::NAXISn(n);
+            let length = self.integer_value_of(&keyword)
+                .map_err(|error| NaxesError::NAXISn(n, error))?;
+            if length < 0 {
+                return Err(NaxesError::OutOfRange(keyword, length))
             }
-        })
+            axes.push(length as u64);
+        }
+        Ok(axes)
     }
 
-    fn value_of(&self, keyword: &Keyword) -> Result<Value, ValueRetrievalError> {
-        if self.has_keyword_record(&keyword) {
-            for keyword_record in &self.keyword_records {
-                if keyword_record.keyword == *keyword {
-                    return Ok(keyword_record.value.clone())
-                }
+}
+
+/// The `BITPIX`, axes and data array size of a header, computed once when the
+/// records change instead of on every use.
+#[derive(Debug, PartialEq)]
+struct Shape {
+    bitpix: Option<i64>,
+    naxes: Result<Vec<u64>, NaxesError>,
+    /// The size in bits of the data array, `None` when it does not fit in a
+    /// `u64` or `PCOUNT` or `GCOUNT` is negative.
+    bits: Option<u64>,
+}
+
+impl Shape {
+    fn of(header: &Header) -> Shape {
+        let bitpix = header.integer_value_of(&Keyword::BITPIX).ok();
+        let naxes = header.read_naxes();
+        let elements = match naxes {
+            Ok(ref axes) if !axes.is_empty() => axes.iter().try_fold(1u64, |product, &length| product.checked_mul(length)),
+            _ => Option::Some(0u64),
+        };
+        let bits_per_value = bitpix.unwrap_or(0i64).unsigned_abs();
+        let bits = if header.is_primary() && !header.is_random_groups() {
+            elements.and_then(|elements| bits_per_value.checked_mul(elements))
+        } else {
+            // Random groups have NAXIS1 = 0, each group holds PCOUNT parameters
+            // and an array of the other axes, like the groups of an extension.
+            let elements = match naxes {
+                Ok(ref axes) if header.is_random_groups() && !axes.is_empty() => axes[1..].iter().try_fold(1u64, |product, &length| product.checked_mul(length)),
+                _ => elements,
+            };
+            let gcount = u64::try_from(header.integer_value_of(&Keyword::GCOUNT).unwrap_or(1i64)).ok();
+            let pcount = u64::try_from(header.integer_value_of(&Keyword::PCOUNT).unwrap_or(0i64)).ok();
+            match (elements, gcount, pcount) {
+                (Option::Some(elements), Option::Some(gcount), Option::Some(pcount)) => pcount.checked_add(elements)
+                    .and_then(|values| values.checked_mul(gcount))
+                    .and_then(|values| values.checked_mul(bits_per_value)),
+                _ => Option::None,
             }
+        };
+        Shape { bitpix, naxes, bits }
+    }
+}
+
+impl Default for Shape {
+    fn default() -> Shape {
+        Shape { bitpix: Option::None, naxes: Ok(vec!()), bits: Option::Some(0) }
+    }
+}
+
+/// Problems that could occur when determining the axes of a header.
+#[derive(Debug, PartialEq, Clone)]
+#[allow(non_camel_case_types)]
+pub enum NaxesError {
+    /// The `NAXIS` value could not be retrieved.
+    NAXIS(ValueRetrievalError),
+    /// The `NAXISn` value for axis `n` could not be retrieved.
+    NAXISn(u16, ValueRetrievalError),
+    /// The value of the keyword is outside of the range allowed by the standard.
+    OutOfRange(Keyword, i64),
+}
+
+impl<'a> Display for Header<'a> {
+    /// Formats this header as 80 column cards, one per line, leaving out the
+    /// `END` card and the blank padding.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        for card in self.cards(&FormatPolicy::default()) {
+            writeln!(f, "{}", card)?;
         }
-        Err(ValueRetrievalError::KeywordNotPresent)
+        Ok(())
     }
+}
+
+/// Adjacent commentary records with the same keyword, see
+/// `Header::commentary_blocks`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CommentaryBlock {
+    /// The keyword of the records, `COMMENT`, `HISTORY` or blank.
+    pub keyword: Keyword,
+    /// The text of the records, joined with spaces.
+    pub text: String,
+    /// The indices of the records in `Header::records`.
+    pub cards: Range<usize>,
+}
+
+/// Problems that could occur when removing or moving HDUs of a `Fits`.
+#[derive(Debug, PartialEq)]
+pub enum FitsEditError {
+    /// There is no HDU at the index.
+    OutOfBounds(usize),
+    /// The primary HDU can not be removed or moved, nor can an extension take
+    /// its place.
+    PrimaryHdu,
+}
+
+/// Problems that could occur when editing a `Header`.
+#[derive(Debug, PartialEq)]
+pub enum HeaderEditError {
+    /// The keyword to edit, or to position a record relative to, is not present.
+    KeywordNotPresent,
+    /// The `END` record can not be edited, it always closes the header.
+    EndKeyword,
+}
+
+/// The positions of the records of a keyword, in header order. Most keywords
+/// occur once, so the first position is kept inline and only repeated
+/// keywords allocate.
+#[derive(Debug, PartialEq, Clone)]
+struct Positions {
+    first: usize,
+    rest: Vec<usize>,
+}
 
-    fn naxis_product(&self) -> i64 {
-        let limit = self.integer_value_of(&Keyword::NAXIS).unwrap_or(0i64);
-        if limit > 0 {
-            let mut product = 1i64;
-            for n in 0..limit {
-                let naxisn = Keyword::NAXISn((n + 1i64) as u16);
-                product *= self.integer_value_of(&naxisn)
-                    .expect(format!("NAXIS{} should be defined", n).as_str());
+impl Positions {
+    fn new(first: usize) -> Positions {
+        Positions { first, rest: vec!() }
+    }
+
+    fn iter<'b>(&'b self) -> impl Iterator<Item = usize> + 'b {
+        iter::once(self.first).chain(self.rest.iter().cloned())
+    }
+}
+
+/// The positions of the records of every keyword of a header.
+type KeywordIndex = HashMap<Keyword, Positions, BuildHasherDefault<KeywordHasher>>;
+
+/// The FNV-1a hash. Keywords are short and not chosen by an adversary, so it
+/// beats the default hasher when building an index for every header.
+struct KeywordHasher(u64);
+
+impl Default for KeywordHasher {
+    fn default() -> KeywordHasher {
+        KeywordHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for KeywordHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Maps every keyword to the positions of the records it occurs in, so that
+/// lookups do not have to scan the header.
+fn index_of(records: &[KeywordRecord]) -> KeywordIndex {
+    let mut index = KeywordIndex::with_capacity_and_hasher(records.len(), Default::default());
+    for (position, record) in records.iter().enumerate() {
+        match index.entry(record.keyword.clone()) {
+            Entry::Occupied(mut positions) => positions.get_mut().rest.push(position),
+            Entry::Vacant(positions) => {
+                positions.insert(Positions::new(position));
             }
-            product
-        } else {
-            0i64
         }
     }
+    index
 }
 
-/// When asking for a value, these things can go wrong.
+/// A header that falls back to a primary header for keywords it does not
+/// define. See `Header::with_inherited`.
 #[derive(Debug)]
+pub struct InheritedHeader<'h, 'a: 'h> {
+    header: &'h Header<'a>,
+    primary: Option<&'h Header<'a>>,
+}
+
+impl<'h, 'a> InheritedHeader<'h, 'a> {
+    /// Retrieve the value associated with `keyword`, from the extension header
+    /// if present and from the primary header otherwise.
+    pub fn value_of(&self, keyword: &Keyword) -> Result<Value<'a>, ValueRetrievalError> {
+        self.first_value_of(keyword).cloned()
+    }
+
+    /// Retrieve the value associated with `keyword` like `value_of`, `None`
+    /// when the keyword is missing and `Some(None)` when it has no value.
+    pub fn value_opt(&self, keyword: &Keyword) -> Option<Option<Value<'a>>> {
+        self.first_value_of(keyword).ok().map(defined)
+    }
+
+    fn first_value_of(&self, keyword: &Keyword) -> Result<&'h Value<'a>, ValueRetrievalError> {
+        match self.header.first_value_of(keyword) {
+            Err(ValueRetrievalError::KeywordNotPresent) => {
+                match self.primary {
+                    Option::Some(primary) if is_inheritable(keyword) => primary.first_value_of(keyword),
+                    _ => Err(ValueRetrievalError::KeywordNotPresent),
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Retrieve the integer value associated with `keyword`.
+    pub fn integer_value_of(&self, keyword: &Keyword) -> Result<i64, ValueRetrievalError> {
+        self.value_of(keyword).and_then(integer)
+    }
+
+    /// Retrieve the real value associated with `keyword`, promoting integers.
+    pub fn real_value_of(&self, keyword: &Keyword) -> Result<f64, ValueRetrievalError> {
+        self.value_of(keyword).and_then(real)
+    }
+
+    /// Retrieve the logical value associated with `keyword`.
+    pub fn logical_value_of(&self, keyword: &Keyword) -> Result<bool, ValueRetrievalError> {
+        self.value_of(keyword).and_then(logical)
+    }
+
+    /// Retrieve the complex value associated with `keyword`.
+    pub fn complex_value_of(&self, keyword: &Keyword) -> Result<(f64, f64), ValueRetrievalError> {
+        self.value_of(keyword).and_then(complex)
+    }
+
+    /// Retrieve the character string value associated with `keyword`.
+    pub fn character_string_value_of(&self, keyword: &Keyword) -> Result<&'h str, ValueRetrievalError> {
+        self.first_value_of(keyword).and_then(character_string)
+    }
+}
+
+fn is_inheritable(keyword: &Keyword) -> bool {
+    match *keyword {
+        Keyword::SIMPLE | Keyword::XTENSION | Keyword::BITPIX | Keyword::NAXIS |
+        Keyword::NAXISn(_) | Keyword::EXTEND | Keyword::PCOUNT | Keyword::GCOUNT |
+        Keyword::TFIELDS | Keyword::END | Keyword::INHERIT |
+        Keyword::EXTNAME | Keyword::EXTVER | Keyword::CHECKSUM | Keyword::DATASUM => false,
+        ref keyword => !keyword.is_commentary(),
+    }
+}
+
+/// `None` for an undefined value, the value otherwise.
+fn defined<'a>(value: &Value<'a>) -> Option<Value<'a>> {
+    match *value {
+        Value::Undefined => Option::None,
+        ref value => Option::Some(value.clone()),
+    }
+}
+
+pub(crate) fn integer(value: Value) -> Result<i64, ValueRetrievalError> {
+    match value {
+        Value::Integer(n) => Ok(n),
+        Value::Undefined => Err(ValueRetrievalError::ValueUndefined),
+        _ => Err(ValueRetrievalError::NotAnInteger),
+    }
+}
+
+pub(crate) fn real(value: Value) -> Result<f64, ValueRetrievalError> {
+    match value {
+        Value::Real(x) => Ok(x),
+        Value::Integer(n) => Ok(n as f64),
+        Value::Undefined => Err(ValueRetrievalError::ValueUndefined),
+        _ => Err(ValueRetrievalError::NotAReal),
+    }
+}
+
+pub(crate) fn logical(value: Value) -> Result<bool, ValueRetrievalError> {
+    match value {
+        Value::Logical(b) => Ok(b),
+        Value::Undefined => Err(ValueRetrievalError::ValueUndefined),
+        _ => Err(ValueRetrievalError::NotALogical),
+    }
+}
+
+pub(crate) fn complex(value: Value) -> Result<(f64, f64), ValueRetrievalError> {
+    match value {
+        Value::Complex(c) => Ok(c),
+        Value::Undefined => Err(ValueRetrievalError::ValueUndefined),
+        _ => Err(ValueRetrievalError::NotAComplex),
+    }
+}
+
+pub(crate) fn character_string<'b>(value: &'b Value) -> Result<&'b str, ValueRetrievalError> {
+    match *value {
+        Value::CharacterString(ref s) => Ok(s),
+        Value::Undefined => Err(ValueRetrievalError::ValueUndefined),
+        _ => Err(ValueRetrievalError::NotACharacterString),
+    }
+}
+
+/// When asking for a value, these things can go wrong.
+#[derive(Debug, PartialEq, Clone)]
 pub enum ValueRetrievalError {
     /// The value associated with this keyword is not an integer.
     NotAnInteger,
+    /// The value associated with this keyword is neither a real nor an integer.
+    NotAReal,
+    /// The value associated with this keyword is not a logical.
+    NotALogical,
+    /// The value associated with this keyword is not a complex number.
+    NotAComplex,
+    /// The value associated with this keyword is not a character string.
+    NotACharacterString,
     /// There is no value associated with this keyword.
     ValueUndefined,
     /// The keyword is not present in the header.
     KeywordNotPresent,
 }
 
-/// Placeholder for DataArray
-#[derive(Debug, PartialEq)]
-pub struct DataArray;
+/// The raw bytes of the data array of an HDU, in the big-endian encoding of
+/// the FITS file.
+pub struct DataArray<'a> {
+    bytes: Cow<'a, [u8]>,
+    edited: bool,
+}
+
+impl<'a> PartialEq for DataArray<'a> {
+    fn eq(&self, other: &DataArray) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+/// The bytes of the header and of the padding after the data array of a parsed
+/// HDU, as they appear in its input.
+struct Source<'a> {
+    header: Cow<'a, [u8]>,
+    padding: Cow<'a, [u8]>,
+}
+
+impl<'a> Debug for Source<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "Source({} header bytes)", self.header.len())
+    }
+}
+
+impl<'a> Debug for DataArray<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "DataArray({} bytes)", self.bytes.len())
+    }
+}
 
 /// A keyword record contains information about a FITS header. It consists of a
 /// keyword, the corresponding value and an optional comment.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct KeywordRecord<'a> {
     /// The keyword of this record.
     keyword: Keyword,
     /// The value of this record.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     value: Value<'a>,
     /// The comment of this record.
-    comment: Option<&'a str>
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    comment: Option<Cow<'a, str>>
 }
 
 impl<'a> KeywordRecord<'a> {
     /// Create a `KeywordRecord` from a specific `Keyword`.
-    pub fn new(keyword: Keyword, value: Value<'a>, comment: Option<&'a str>) -> KeywordRecord<'a> {
+    pub fn new(keyword: Keyword, value: Value<'a>, comment: Option<Cow<'a, str>>) -> KeywordRecord<'a> {
         KeywordRecord { keyword: keyword, value: value, comment: comment }
     }
 }
 
+impl<'a> KeywordRecord<'a> {
+    /// The keyword of this record.
+    pub fn keyword(&self) -> &Keyword {
+        &self.keyword
+    }
+
+    /// The value of this record.
+    pub fn value(&self) -> &Value<'a> {
+        &self.value
+    }
+
+    /// The comment of this record, or the text of a commentary record.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Copy the strings this record borrows.
+    pub fn into_owned(self) -> KeywordRecord<'static> {
+        KeywordRecord {
+            keyword: self.keyword,
+            value: self.value.into_owned(),
+            comment: self.comment.map(|comment| Cow::Owned(comment.into_owned())),
+        }
+    }
+
+    /// The 80 column cards of this record. A string too long for one card,
+    /// over 68 characters with its quotes doubled, is continued over
+    /// `CONTINUE` cards as in the long string convention: every part but the
+    /// last ends in `&`, and the comment follows the last part. Other records
+    /// take one card, formatted like `Display`. Characters other than printable
+    /// ASCII become `?`.
+    pub fn cards(&self) -> Vec<String> {
+        self.cards_with(&FormatPolicy::default())
+    }
+
+    /// The cards of this record like `cards`, laid out by `policy`.
+    pub fn cards_with(&self, policy: &FormatPolicy) -> Vec<String> {
+        let text = match self.value {
+            Value::CharacterString(ref text) if !self.keyword.is_commentary() && quoted_length(text) > 68 => text,
+            _ => return vec!(self.card_with(policy)),
+        };
+        let mut parts = vec!(String::new());
+        let mut length = 0;
+        for c in text.chars() {
+            let width = quoted_length(c.encode_utf8(&mut [0; 4]));
+            if length + width > 67 {
+                parts.push(String::new());
+                length = 0;
+            }
+            parts.last_mut().expect("there is a part").push(c);
+            length += width;
+        }
+        let last = parts.len() - 1;
+        parts.iter().enumerate()
+            .map(|(index, part)| {
+                let key = if index == 0 { format!("{:8}= ", self.keyword.to_string()) } else { format!("{:10}", "CONTINUE") };
+                let ampersand = if index < last { "&" } else { "" };
+                let mut card = format!("{}'{}{}'", key, part.replace('\'', "''"), ampersand);
+                if let (true, Option::Some(comment)) = (index == last, self.comment()) {
+                    push_comment(&mut card, comment, policy);
+                }
+                ascii_card(&card)
+            })
+            .collect()
+    }
+
+    /// Formats this record as a single 80 column card laid out by `policy`.
+    fn card_with(&self, policy: &FormatPolicy) -> String {
+        let mut card = format!("{:8}", self.keyword.to_string());
+        if self.keyword.is_commentary() {
+            let text = self.comment().unwrap_or("");
+            if policy.uppercase_comments {
+                card.push_str(&text.to_uppercase());
+            } else {
+                card.push_str(text);
+            }
+        } else if self.keyword != Keyword::END {
+            card.push_str("= ");
+            card.push_str(&fixed_format(&self.value, policy));
+            if let Option::Some(comment) = self.comment() {
+                push_comment(&mut card, comment, policy);
+            }
+        }
+        ascii_card(&card)
+    }
+}
+
+/// `card` cut off or padded to 80 columns, with characters other than
+/// printable ASCII replaced by `?`, so that it takes 80 bytes.
+fn ascii_card(card: &str) -> String {
+    let card: String = card.chars()
+        .map(|c| if (' '..='~').contains(&c) { c } else { '?' })
+        .take(80)
+        .collect();
+    format!("{:80}", card)
+}
+
+/// Appends `comment` to `card`, with its `/` in the comment column of
+/// `policy`, or a space after the value when that is further.
+fn push_comment(card: &mut String, comment: &str, policy: &FormatPolicy) {
+    let end = card.trim_end().len();
+    card.truncate(end);
+    let slash = (end + 1).max(policy.comment_column.saturating_sub(1));
+    card.push_str(&" ".repeat(slash - end));
+    card.push_str("/ ");
+    if policy.uppercase_comments {
+        card.push_str(&comment.to_uppercase());
+    } else {
+        card.push_str(comment);
+    }
+}
+
+/// The number of characters of `text` between quotes, with its quotes doubled.
+fn quoted_length(text: &str) -> usize {
+    text.chars().map(|c| if c == '\'' { 2 } else { 1 }).sum()
+}
+
 impl<'a> Display for KeywordRecord<'a> {
+    /// Formats this record as an 80 column card, using the fixed format of the
+    /// standard: the value indicator in columns 9-10, strings starting in column
+    /// 11 and other values right justified to column 30.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.card_with(&FormatPolicy::default()))
+    }
+}
+
+impl<'a> Display for Value<'a> {
+    /// Formats this value as in the fixed format of the standard, in 20
+    /// characters: strings quoted and left justified, other values right
+    /// justified. Longer strings and complex numbers take more characters.
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(f, "{:?}= {:?}/{}", self.keyword, self.value, self.comment.unwrap_or(""))
+        write!(f, "{}", fixed_format(self, &FormatPolicy::default()))
+    }
+}
+
+impl<'a> From<i64> for Value<'a> {
+    fn from(n: i64) -> Value<'a> {
+        Value::Integer(n)
+    }
+}
+
+impl<'a> From<f64> for Value<'a> {
+    fn from(x: f64) -> Value<'a> {
+        Value::Real(x)
+    }
+}
+
+impl<'a> From<bool> for Value<'a> {
+    fn from(b: bool) -> Value<'a> {
+        Value::Logical(b)
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(s: &'a str) -> Value<'a> {
+        Value::CharacterString(Cow::Borrowed(s))
+    }
+}
+
+impl<'a> From<String> for Value<'a> {
+    fn from(s: String) -> Value<'a> {
+        Value::CharacterString(Cow::Owned(s))
+    }
+}
+
+impl<'a> From<(f64, f64)> for Value<'a> {
+    fn from(c: (f64, f64)) -> Value<'a> {
+        Value::Complex(c)
+    }
+}
+
+fn fixed_format(value: &Value, policy: &FormatPolicy) -> String {
+    let real_format = |x: f64| real_format(x, policy);
+    match *value {
+        Value::CharacterString(ref s) if s.is_empty() => format!("{:20}", "''"),
+        Value::CharacterString(ref s) => format!("{:20}", format!("'{:8}'", s.replace('\'', "''"))),
+        Value::Logical(b) => format!("{:>20}", if b { "T" } else { "F" }),
+        Value::Integer(n) => format!("{:>20}", n),
+        Value::Real(x) => format!("{:>20}", real_format(x)),
+        Value::Complex((re, im)) => format!("{:>20}", format!("({}, {})", real_format(re), real_format(im))),
+        Value::Undefined => format!("{:20}", ""),
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    match *value {
+        Value::CharacterString(ref s) => json_string(s.trim_end()),
+        Value::Logical(b) => b.to_string(),
+        Value::Integer(n) => n.to_string(),
+        Value::Real(x) => json_number(x),
+        Value::Complex((re, im)) => format!("[{},{}]", json_number(re), json_number(im)),
+        Value::Undefined => "null".to_string(),
+    }
+}
+
+fn json_number(x: f64) -> String {
+    if x.is_finite() { format!("{:?}", x) } else { "null".to_string() }
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Formats a real with a decimal point and an uppercase exponent, as the
+/// standard requires, in at most 20 characters.
+fn real_format(x: f64, policy: &FormatPolicy) -> String {
+    let mut representation = match policy.precision {
+        Option::Some(digits) => format!("{:.*E}", digits.max(1) - 1, x),
+        Option::None => format!("{:?}", x).to_uppercase(),
+    };
+    if representation.len() > 20 {
+        representation = format!("{:.13E}", x);
+    }
+    if !representation.contains('.') {
+        match representation.find('E') {
+            Option::Some(position) => representation.insert_str(position, ".0"),
+            Option::None => representation.push_str(".0"),
+        }
+    }
+    if policy.exponent == Exponent::D {
+        representation = representation.replace('E', "D");
     }
+    representation
 }
 
 /// The possible values of a KeywordRecord.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value<'a> {
-    /// A string enclosed in single quotes `'`.
-    CharacterString(&'a str),
+    /// A string enclosed in single quotes `'`, with the quotes it contains
+    /// unescaped. It is borrowed from the input unless it had to be unescaped.
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    CharacterString(Cow<'a, str>),
     /// A logical constant signified by either an uppercase `F` or an uppercase `T`.
     Logical(bool),
     /// An optionally signed decimal integer.
@@ -176,28 +1346,62 @@ pub enum Value<'a> {
     Undefined,
 }
 
+impl<'a> Value<'a> {
+    /// Copy the character string this value borrows.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::CharacterString(s) => Value::CharacterString(Cow::Owned(s.into_owned())),
+            Value::Logical(b) => Value::Logical(b),
+            Value::Integer(n) => Value::Integer(n),
+            Value::Real(x) => Value::Real(x),
+            Value::Complex(c) => Value::Complex(c),
+            Value::Undefined => Value::Undefined,
+        }
+    }
+}
+
 /// A unit struct that will act as a placeholder for blank records.
 #[derive(Debug, PartialEq)]
 pub struct BlankRecord;
 
 /// The various keywords that can be found in headers.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 #[allow(non_camel_case_types, missing_docs)]
 pub enum Keyword {
     AV,
+    AUTHOR,
     BITPIX,
+    BLANK,
+    BSCALE,
+    BUNIT,
+    BZERO,
     CAMPAIGN,
+    CDELTia(u16, Option<char>),
+    CDi_ja(u16, u16, Option<char>),
     CHANNEL,
     CHECKSUM,
+    COMMENT,
+    CONTINUE,
     CREATOR,
+    CROTAi(u16),
+    CRPIXja(u16, Option<char>),
+    CRVALia(u16, Option<char>),
+    CTYPEia(u16, Option<char>),
+    CUNITia(u16, Option<char>),
     DATASUM,
+    DATAMAX,
+    DATAMIN,
     DATA_REL,
     DATE,
+    DATE_OBS,
     DEC_OBJ,
     EBMINUSV,
     END,
     EQUINOX,
+    EXPOSURE,
+    EXPTIME,
     EXTEND,
+    EXTLEVEL,
     EXTNAME,
     EXTVER,
     FEH,
@@ -208,32 +1412,45 @@ pub enum Keyword {
     GLON,
     GMAG,
     GRCOLOR,
+    GROUPS,
+    HISTORY,
     HMAG,
     IMAG,
+    INHERIT,
     INSTRUME,
     JKCOLOR,
     JMAG,
     KEPLERID,
     KEPMAG,
     KMAG,
+    LATPOLEa(Option<char>),
     LOGG,
+    LONGSTRN,
+    LONPOLEa(Option<char>),
     MISSION,
     MODULE,
     NAXIS,
     NAXISn(u16),
     NEXTEND,
     OBJECT,
+    OBSERVER,
     OBSMODE,
     ORIGIN,
     OUTPUT,
     PARALLAX,
     PCOUNT,
+    PCi_ja(u16, u16, Option<char>),
     PMDEC,
     PMRA,
     PMTOTAL,
     PROCVER,
+    PSi_ma(u16, u16, Option<char>),
+    PVi_ma(u16, u16, Option<char>),
     RADESYS,
     RADIUS,
+    REFERENC,
+    RESTFRQa(Option<char>),
+    RESTWAVa(Option<char>),
     RA_OBJ,
     RMAG,
     SIMPLE,
@@ -242,6 +1459,7 @@ pub enum Keyword {
     TEFF,
     TELESCOP,
     TFIELDS,
+    TIMESYS,
     TFORMn(u16),
     TIMVERSN,
     THEAP,
@@ -250,11 +1468,209 @@ pub enum Keyword {
     TSCALn(u16),
     TTABLEID,
     TTYPEn(u16),
+    TUCDn(u16),
     TUNITn(u16),
     TZEROn(u16),
+    WCSAXESa(Option<char>),
+    WCSNAMEa(Option<char>),
     XTENSION,
     ZMAG,
-    Unprocessed, // TODO Remove the unprocessed keyword
+    /// A keyword that is not known to this crate, carrying its original text.
+    Unrecognized(KeywordText),
+}
+
+/// The text of a keyword that is not otherwise recognized.
+///
+/// Keywords occupy at most 8 bytes of a keyword record, so the text is stored
+/// inline without allocating.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct KeywordText {
+    bytes: [u8; 8],
+    length: u8,
+}
+
+impl KeywordText {
+    /// Create a `KeywordText` from a `str`.
+    ///
+    /// Returns `ParseKeywordError::TooLong` when the input does not fit in the
+    /// 8 bytes reserved for a keyword.
+    pub fn new(text: &str) -> Result<KeywordText, ParseKeywordError> {
+        if text.len() > 8 {
+            return Err(ParseKeywordError::TooLong)
+        }
+        let mut bytes = [b' '; 8];
+        bytes[..text.len()].copy_from_slice(text.as_bytes());
+        Ok(KeywordText { bytes, length: text.len() as u8 })
+    }
+
+    /// The text of this keyword.
+    pub fn as_str(&self) -> &str {
+        str::from_utf8(&self.bytes[..self.length as usize])
+            .expect("KeywordText is only constructed from a str")
+    }
+}
+
+impl Debug for KeywordText {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "KeywordText({:?})", self.as_str())
+    }
+}
+
+impl Keyword {
+    /// Create the keyword named `name`, which must be 1 to 8 of the characters
+    /// the standard allows in keywords: uppercase letters, digits, `-` and `_`.
+    ///
+    /// Names of keywords known to this crate give those keywords, like
+    /// `Keyword::from_str`, so `Keyword::custom("NAXIS1")` is `NAXISn(1)`.
+    pub fn custom(name: &str) -> Result<Keyword, ParseKeywordError> {
+        if name.is_empty() {
+            return Err(ParseKeywordError::Empty)
+        }
+        if name.len() > 8 {
+            return Err(ParseKeywordError::TooLong)
+        }
+        if !name.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'-' || b == b'_') {
+            return Err(ParseKeywordError::InvalidCharacter)
+        }
+        Keyword::from_str(name)
+    }
+
+    /// Determines if this keyword introduces commentary, i.e. `COMMENT`, `HISTORY`
+    /// or a blank keyword. Commentary records have no value, only text.
+    pub fn is_commentary(&self) -> bool {
+        match *self {
+            Keyword::COMMENT | Keyword::HISTORY => true,
+            Keyword::Unrecognized(ref text) => text.as_str().is_empty(),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Keyword::AV => write!(f, "AV"),
+            Keyword::AUTHOR => write!(f, "AUTHOR"),
+            Keyword::BITPIX => write!(f, "BITPIX"),
+            Keyword::BLANK => write!(f, "BLANK"),
+            Keyword::BSCALE => write!(f, "BSCALE"),
+            Keyword::BUNIT => write!(f, "BUNIT"),
+            Keyword::BZERO => write!(f, "BZERO"),
+            Keyword::CAMPAIGN => write!(f, "CAMPAIGN"),
+            Keyword::CDELTia(i, a) => write!(f, "CDELT{}{}", i, Alternate(a)),
+            Keyword::CDi_ja(i, j, a) => write!(f, "CD{}_{}{}", i, j, Alternate(a)),
+            Keyword::CHANNEL => write!(f, "CHANNEL"),
+            Keyword::CHECKSUM => write!(f, "CHECKSUM"),
+            Keyword::COMMENT => write!(f, "COMMENT"),
+            Keyword::CONTINUE => write!(f, "CONTINUE"),
+            Keyword::CREATOR => write!(f, "CREATOR"),
+            Keyword::CROTAi(i) => write!(f, "CROTA{}", i),
+            Keyword::CRPIXja(j, a) => write!(f, "CRPIX{}{}", j, Alternate(a)),
+            Keyword::CRVALia(i, a) => write!(f, "CRVAL{}{}", i, Alternate(a)),
+            Keyword::CTYPEia(i, a) => write!(f, "CTYPE{}{}", i, Alternate(a)),
+            Keyword::CUNITia(i, a) => write!(f, "CUNIT{}{}", i, Alternate(a)),
+            Keyword::DATASUM => write!(f, "DATASUM"),
+            Keyword::DATAMAX => write!(f, "DATAMAX"),
+            Keyword::DATAMIN => write!(f, "DATAMIN"),
+            Keyword::DATA_REL => write!(f, "DATA_REL"),
+            Keyword::DATE => write!(f, "DATE"),
+            Keyword::DATE_OBS => write!(f, "DATE-OBS"),
+            Keyword::DEC_OBJ => write!(f, "DEC_OBJ"),
+            Keyword::EBMINUSV => write!(f, "EBMINUSV"),
+            Keyword::END => write!(f, "END"),
+            Keyword::EQUINOX => write!(f, "EQUINOX"),
+            Keyword::EXPOSURE => write!(f, "EXPOSURE"),
+            Keyword::EXPTIME => write!(f, "EXPTIME"),
+            Keyword::EXTEND => write!(f, "EXTEND"),
+            Keyword::EXTLEVEL => write!(f, "EXTLEVEL"),
+            Keyword::EXTNAME => write!(f, "EXTNAME"),
+            Keyword::EXTVER => write!(f, "EXTVER"),
+            Keyword::FEH => write!(f, "FEH"),
+            Keyword::FILEVER => write!(f, "FILEVER"),
+            Keyword::GCOUNT => write!(f, "GCOUNT"),
+            Keyword::GKCOLOR => write!(f, "GKCOLOR"),
+            Keyword::GLAT => write!(f, "GLAT"),
+            Keyword::GLON => write!(f, "GLON"),
+            Keyword::GMAG => write!(f, "GMAG"),
+            Keyword::GRCOLOR => write!(f, "GRCOLOR"),
+            Keyword::GROUPS => write!(f, "GROUPS"),
+            Keyword::HISTORY => write!(f, "HISTORY"),
+            Keyword::HMAG => write!(f, "HMAG"),
+            Keyword::IMAG => write!(f, "IMAG"),
+            Keyword::INHERIT => write!(f, "INHERIT"),
+            Keyword::INSTRUME => write!(f, "INSTRUME"),
+            Keyword::JKCOLOR => write!(f, "JKCOLOR"),
+            Keyword::JMAG => write!(f, "JMAG"),
+            Keyword::KEPLERID => write!(f, "KEPLERID"),
+            Keyword::KEPMAG => write!(f, "KEPMAG"),
+            Keyword::KMAG => write!(f, "KMAG"),
+            Keyword::LATPOLEa(a) => write!(f, "LATPOLE{}", Alternate(a)),
+            Keyword::LOGG => write!(f, "LOGG"),
+            Keyword::LONGSTRN => write!(f, "LONGSTRN"),
+            Keyword::LONPOLEa(a) => write!(f, "LONPOLE{}", Alternate(a)),
+            Keyword::MISSION => write!(f, "MISSION"),
+            Keyword::MODULE => write!(f, "MODULE"),
+            Keyword::NAXIS => write!(f, "NAXIS"),
+            Keyword::NAXISn(n) => write!(f, "NAXIS{}", n),
+            Keyword::NEXTEND => write!(f, "NEXTEND"),
+            Keyword::OBJECT => write!(f, "OBJECT"),
+            Keyword::OBSERVER => write!(f, "OBSERVER"),
+            Keyword::OBSMODE => write!(f, "OBSMODE"),
+            Keyword::ORIGIN => write!(f, "ORIGIN"),
+            Keyword::OUTPUT => write!(f, "OUTPUT"),
+            Keyword::PARALLAX => write!(f, "PARALLAX"),
+            Keyword::PCOUNT => write!(f, "PCOUNT"),
+            Keyword::PCi_ja(i, j, a) => write!(f, "PC{}_{}{}", i, j, Alternate(a)),
+            Keyword::PMDEC => write!(f, "PMDEC"),
+            Keyword::PMRA => write!(f, "PMRA"),
+            Keyword::PMTOTAL => write!(f, "PMTOTAL"),
+            Keyword::PROCVER => write!(f, "PROCVER"),
+            Keyword::PSi_ma(i, m, a) => write!(f, "PS{}_{}{}", i, m, Alternate(a)),
+            Keyword::PVi_ma(i, m, a) => write!(f, "PV{}_{}{}", i, m, Alternate(a)),
+            Keyword::RADESYS => write!(f, "RADESYS"),
+            Keyword::RADIUS => write!(f, "RADIUS"),
+            Keyword::REFERENC => write!(f, "REFERENC"),
+            Keyword::RESTFRQa(a) => write!(f, "RESTFRQ{}", Alternate(a)),
+            Keyword::RESTWAVa(a) => write!(f, "RESTWAV{}", Alternate(a)),
+            Keyword::RA_OBJ => write!(f, "RA_OBJ"),
+            Keyword::RMAG => write!(f, "RMAG"),
+            Keyword::SIMPLE => write!(f, "SIMPLE"),
+            Keyword::TDIMn(n) => write!(f, "TDIM{}", n),
+            Keyword::TDISPn(n) => write!(f, "TDISP{}", n),
+            Keyword::TEFF => write!(f, "TEFF"),
+            Keyword::TELESCOP => write!(f, "TELESCOP"),
+            Keyword::TFIELDS => write!(f, "TFIELDS"),
+            Keyword::TIMESYS => write!(f, "TIMESYS"),
+            Keyword::TFORMn(n) => write!(f, "TFORM{}", n),
+            Keyword::TIMVERSN => write!(f, "TIMVERSN"),
+            Keyword::THEAP => write!(f, "THEAP"),
+            Keyword::TMINDEX => write!(f, "TMINDEX"),
+            Keyword::TNULLn(n) => write!(f, "TNULL{}", n),
+            Keyword::TSCALn(n) => write!(f, "TSCAL{}", n),
+            Keyword::TTABLEID => write!(f, "TTABLEID"),
+            Keyword::TTYPEn(n) => write!(f, "TTYPE{}", n),
+            Keyword::TUCDn(n) => write!(f, "TUCD{}", n),
+            Keyword::TUNITn(n) => write!(f, "TUNIT{}", n),
+            Keyword::TZEROn(n) => write!(f, "TZERO{}", n),
+            Keyword::WCSAXESa(a) => write!(f, "WCSAXES{}", Alternate(a)),
+            Keyword::WCSNAMEa(a) => write!(f, "WCSNAME{}", Alternate(a)),
+            Keyword::XTENSION => write!(f, "XTENSION"),
+            Keyword::ZMAG => write!(f, "ZMAG"),
+            Keyword::Unrecognized(ref text) => write!(f, "{}", text.as_str()),
+        }
+    }
+}
+
+/// Displays the letter of an alternate world coordinate system, if any.
+struct Alternate(Option<char>);
+
+impl Display for Alternate {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self.0 {
+            Option::Some(letter) => write!(f, "{}", letter),
+            Option::None => Ok(()),
+        }
+    }
 }
 
 /// Problems that could occur when parsing a `str` for a Keyword are enumerated here.
@@ -262,142 +1678,208 @@ pub enum Keyword {
 pub enum ParseKeywordError {
     /// When a str can not be recognized as a keyword, this error will be returned.
     UnknownKeyword,
-    /// When `NAXIS<number>` et. al. are parsed where `<number>` is not an actual number.
+    /// When `NAXIS<number>` et. al. are parsed where `<number>` is too large for an index.
     NotANumber,
+    /// When a str is longer than the 8 bytes available for a keyword.
+    TooLong,
+    /// When a str contains a character that is not allowed in a keyword.
+    InvalidCharacter,
+    /// When a str is empty where a keyword name is required.
+    Empty,
 }
 
+/// The keywords without an index, by name.
+static NAMED_KEYWORDS: phf::Map<&'static str, Keyword> = phf_map! {
+    "AUTHOR" => Keyword::AUTHOR,
+    "AV" => Keyword::AV,
+    "BITPIX" => Keyword::BITPIX,
+    "BLANK" => Keyword::BLANK,
+    "BSCALE" => Keyword::BSCALE,
+    "BUNIT" => Keyword::BUNIT,
+    "BZERO" => Keyword::BZERO,
+    "CAMPAIGN" => Keyword::CAMPAIGN,
+    "CHANNEL" => Keyword::CHANNEL,
+    "CHECKSUM" => Keyword::CHECKSUM,
+    "COMMENT" => Keyword::COMMENT,
+    "CONTINUE" => Keyword::CONTINUE,
+    "CREATOR" => Keyword::CREATOR,
+    "DATAMAX" => Keyword::DATAMAX,
+    "DATAMIN" => Keyword::DATAMIN,
+    "DATASUM" => Keyword::DATASUM,
+    "DATA_REL" => Keyword::DATA_REL,
+    "DATE" => Keyword::DATE,
+    "DATE-OBS" => Keyword::DATE_OBS,
+    "DEC_OBJ" => Keyword::DEC_OBJ,
+    "EBMINUSV" => Keyword::EBMINUSV,
+    "END" => Keyword::END,
+    "EQUINOX" => Keyword::EQUINOX,
+    "EXPOSURE" => Keyword::EXPOSURE,
+    "EXPTIME" => Keyword::EXPTIME,
+    "EXTEND" => Keyword::EXTEND,
+    "EXTLEVEL" => Keyword::EXTLEVEL,
+    "EXTNAME" => Keyword::EXTNAME,
+    "EXTVER" => Keyword::EXTVER,
+    "FEH" => Keyword::FEH,
+    "FILEVER" => Keyword::FILEVER,
+    "GCOUNT" => Keyword::GCOUNT,
+    "GKCOLOR" => Keyword::GKCOLOR,
+    "GLAT" => Keyword::GLAT,
+    "GLON" => Keyword::GLON,
+    "GMAG" => Keyword::GMAG,
+    "GRCOLOR" => Keyword::GRCOLOR,
+    "GROUPS" => Keyword::GROUPS,
+    "HISTORY" => Keyword::HISTORY,
+    "HMAG" => Keyword::HMAG,
+    "IMAG" => Keyword::IMAG,
+    "INHERIT" => Keyword::INHERIT,
+    "INSTRUME" => Keyword::INSTRUME,
+    "JKCOLOR" => Keyword::JKCOLOR,
+    "JMAG" => Keyword::JMAG,
+    "KEPLERID" => Keyword::KEPLERID,
+    "KEPMAG" => Keyword::KEPMAG,
+    "KMAG" => Keyword::KMAG,
+    "LOGG" => Keyword::LOGG,
+    "LONGSTRN" => Keyword::LONGSTRN,
+    "MISSION" => Keyword::MISSION,
+    "MODULE" => Keyword::MODULE,
+    "NAXIS" => Keyword::NAXIS,
+    "NEXTEND" => Keyword::NEXTEND,
+    "OBJECT" => Keyword::OBJECT,
+    "OBSERVER" => Keyword::OBSERVER,
+    "OBSMODE" => Keyword::OBSMODE,
+    "ORIGIN" => Keyword::ORIGIN,
+    "OUTPUT" => Keyword::OUTPUT,
+    "PARALLAX" => Keyword::PARALLAX,
+    "PCOUNT" => Keyword::PCOUNT,
+    "PMDEC" => Keyword::PMDEC,
+    "PMRA" => Keyword::PMRA,
+    "PMTOTAL" => Keyword::PMTOTAL,
+    "PROCVER" => Keyword::PROCVER,
+    "RADESYS" => Keyword::RADESYS,
+    "RADIUS" => Keyword::RADIUS,
+    "RA_OBJ" => Keyword::RA_OBJ,
+    "REFERENC" => Keyword::REFERENC,
+    "RMAG" => Keyword::RMAG,
+    "SIMPLE" => Keyword::SIMPLE,
+    "TEFF" => Keyword::TEFF,
+    "TELESCOP" => Keyword::TELESCOP,
+    "TFIELDS" => Keyword::TFIELDS,
+    "THEAP" => Keyword::THEAP,
+    "TIMESYS" => Keyword::TIMESYS,
+    "TIMVERSN" => Keyword::TIMVERSN,
+    "TMINDEX" => Keyword::TMINDEX,
+    "TTABLEID" => Keyword::TTABLEID,
+    "XTENSION" => Keyword::XTENSION,
+    "ZMAG" => Keyword::ZMAG,
+};
+
+/// The constructor of a keyword with an index.
+type NumberedKeyword = fn(u16) -> Keyword;
+
+/// The prefixes of the keywords that end in an index. None is a prefix of
+/// another.
+static NUMBERED_KEYWORDS: [(&str, NumberedKeyword); 10] = [
+    ("NAXIS", Keyword::NAXISn),
+    ("TDIM", Keyword::TDIMn),
+    ("TDISP", Keyword::TDISPn),
+    ("TFORM", Keyword::TFORMn),
+    ("TNULL", Keyword::TNULLn),
+    ("TSCAL", Keyword::TSCALn),
+    ("TTYPE", Keyword::TTYPEn),
+    ("TUCD", Keyword::TUCDn),
+    ("TUNIT", Keyword::TUNITn),
+    ("TZERO", Keyword::TZEROn),
+];
+
 impl FromStr for Keyword {
     type Err = ParseKeywordError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim_right() {
-            "AV" => Ok(Keyword::AV),
-            "BITPIX" => Ok(Keyword::BITPIX),
-            "CAMPAIGN" => Ok(Keyword::CAMPAIGN),
-            "CHANNEL" => Ok(Keyword::CHANNEL),
-            "CHECKSUM" => Ok(Keyword::CHECKSUM),
-            "CREATOR" => Ok(Keyword::CREATOR),
-            "DATASUM" => Ok(Keyword::DATASUM),
-            "DATA_REL" => Ok(Keyword::DATA_REL),
-            "DATE" => Ok(Keyword::DATE),
-            "DEC_OBJ" => Ok(Keyword::DEC_OBJ),
-            "EBMINUSV" => Ok(Keyword::EBMINUSV),
-            "END" => Ok(Keyword::END),
-            "EQUINOX" => Ok(Keyword::EQUINOX),
-            "EXTEND" => Ok(Keyword::EXTEND),
-            "EXTNAME" => Ok(Keyword::EXTNAME),
-            "EXTVER" => Ok(Keyword::EXTVER),
-            "FEH" => Ok(Keyword::FEH),
-            "FILEVER" => Ok(Keyword::FILEVER),
-            "GCOUNT" => Ok(Keyword::GCOUNT),
-            "GKCOLOR" => Ok(Keyword::GKCOLOR),
-            "GLAT" => Ok(Keyword::GLAT),
-            "GLON" => Ok(Keyword::GLON),
-            "GMAG" => Ok(Keyword::GMAG),
-            "GRCOLOR" => Ok(Keyword::GRCOLOR),
-            "HMAG" => Ok(Keyword::HMAG),
-            "IMAG" => Ok(Keyword::IMAG),
-            "INSTRUME" => Ok(Keyword::INSTRUME),
-            "JKCOLOR" => Ok(Keyword::JKCOLOR),
-            "JMAG" => Ok(Keyword::JMAG),
-            "KEPLERID" => Ok(Keyword::KEPLERID),
-            "KEPMAG" => Ok(Keyword::KEPMAG),
-            "KMAG" => Ok(Keyword::KMAG),
-            "LOGG" => Ok(Keyword::LOGG),
-            "MISSION" => Ok(Keyword::MISSION),
-            "MODULE" => Ok(Keyword::MODULE),
-            "NAXIS" => Ok(Keyword::NAXIS),
-            "NEXTEND" => Ok(Keyword::NEXTEND),
-            "OBJECT" => Ok(Keyword::OBJECT),
-            "OBSMODE" => Ok(Keyword::OBSMODE),
-            "ORIGIN" => Ok(Keyword::ORIGIN),
-            "OUTPUT" => Ok(Keyword::OUTPUT),
-            "PARALLAX" => Ok(Keyword::PARALLAX),
-            "PCOUNT" => Ok(Keyword::PCOUNT),
-            "PMDEC" => Ok(Keyword::PMDEC),
-            "PMRA" => Ok(Keyword::PMRA),
-            "PMTOTAL" => Ok(Keyword::PMTOTAL),
-            "PROCVER" => Ok(Keyword::PROCVER),
-            "RADESYS" => Ok(Keyword::RADESYS),
-            "RADIUS" => Ok(Keyword::RADIUS),
-            "RA_OBJ" => Ok(Keyword::RA_OBJ),
-            "RMAG" => Ok(Keyword::RMAG),
-            "SIMPLE" => Ok(Keyword::SIMPLE),
-            "TEFF" => Ok(Keyword::TEFF),
-            "TELESCOP" => Ok(Keyword::TELESCOP),
-            "TFIELDS" => Ok(Keyword::TFIELDS),
-            "THEAP" => Ok(Keyword::THEAP),
-            "TIMVERSN" => Ok(Keyword::TIMVERSN),
-            "TMINDEX" => Ok(Keyword::TMINDEX),
-            "TTABLEID" => Ok(Keyword::TTABLEID),
-            "XTENSION" => Ok(Keyword::XTENSION),
-            "ZMAG" => Ok(Keyword::ZMAG),
-            input @ _ => {
-                let t_dim_constructor = Keyword::TDIMn;
-                let t_disp_constructor = Keyword::TDISPn;
-                let t_form_constructor = Keyword::TFORMn;
-                let naxis_constructor = Keyword::NAXISn;
-                let t_null_constructor = Keyword::TNULLn;
-                let t_scal_constructor = Keyword::TSCALn;
-                let t_type_constructor = Keyword::TTYPEn;
-                let t_unit_constructor = Keyword::TUNITn;
-                let t_zero_constructor = Keyword::TZEROn;
-                let tuples: Vec<(&str, &(Fn(u16) -> Keyword))> = vec!(
-                    ("TDIM", &t_dim_constructor),
-                    ("TDISP", &t_disp_constructor),
-                    ("TFORM", &t_form_constructor),
-                    ("NAXIS", &naxis_constructor),
-                    ("TNULL", &t_null_constructor),
-                    ("TSCAL", &t_scal_constructor),
-                    ("TTYPE", &t_type_constructor),
-                    ("TUNIT", &t_unit_constructor),
-                    ("TZERO", &t_zero_constructor),
-                );
-                let special_cases: Vec<PrefixedKeyword> =
-                    tuples
-                    .into_iter()
-                    .map(|(prefix, constructor)|{ PrefixedKeyword::new(prefix, constructor)})
-                    .collect();
-                for special_case in special_cases {
-                    if special_case.handles(input) {
-                        return special_case.transform(input)
-                    }
+        let input = s.trim_end();
+        if let Option::Some(keyword) = NAMED_KEYWORDS.get(input) {
+            return Ok(keyword.clone())
+        }
+        for &(prefix, constructor) in NUMBERED_KEYWORDS.iter() {
+            if let Option::Some(representation) = input.strip_prefix(prefix).filter(|rest| !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit())) {
+                return match u16::from_str(representation) {
+                    Ok(n) => Ok(constructor(n)),
+                    Err(_) => Err(ParseKeywordError::NotANumber)
                 }
-                Ok(Keyword::Unprocessed)
-                //Err(ParseKeywordError::UnknownKeyword)
             }
         }
+        if let Option::Some(keyword) = wcs_keyword(input) {
+            return Ok(keyword)
+        }
+        KeywordText::new(input).map(Keyword::Unrecognized)
     }
 }
 
-trait KeywordSpecialCase {
-    fn handles(&self, input: &str) -> bool;
-    fn transform(&self, input: &str) -> Result<Keyword, ParseKeywordError>;
+/// Recognizes the keywords of the world coordinate system, which may end in
+/// the letter `A` to `Z` of an alternate description. Keywords that only
+/// resemble them, like `CTYPE0` or `PC1`, are not recognized.
+fn wcs_keyword(input: &str) -> Option<Keyword> {
+    wcs_keyword_of(input, Option::None).or_else(|| {
+        match input.chars().last() {
+            Option::Some(letter) if letter.is_ascii_uppercase() => wcs_keyword_of(&input[..input.len() - 1], Option::Some(letter)),
+            _ => Option::None,
+        }
+    })
 }
 
-struct PrefixedKeyword<'a> {
-    prefix: &'a str,
-    constructor: &'a (Fn(u16) -> Keyword),
-}
+/// The constructor of a WCS keyword with an axis and an alternate.
+type IndexedKeyword = fn(u16, Option<char>) -> Keyword;
+
+/// The constructor of a WCS keyword with two indices and an alternate.
+type PairedKeyword = fn(u16, u16, Option<char>) -> Keyword;
 
-impl<'a> PrefixedKeyword<'a> {
-    fn new(prefix: &'a str, constructor: &'a (Fn(u16) -> Keyword)) -> PrefixedKeyword<'a> {
-        PrefixedKeyword { prefix: prefix, constructor: constructor }
+fn wcs_keyword_of(stem: &str, alternate: Option<char>) -> Option<Keyword> {
+    match stem {
+        "LATPOLE" => return Option::Some(Keyword::LATPOLEa(alternate)),
+        "LONPOLE" => return Option::Some(Keyword::LONPOLEa(alternate)),
+        "RESTFRQ" => return Option::Some(Keyword::RESTFRQa(alternate)),
+        "RESTWAV" => return Option::Some(Keyword::RESTWAVa(alternate)),
+        "WCSAXES" => return Option::Some(Keyword::WCSAXESa(alternate)),
+        "WCSNAME" => return Option::Some(Keyword::WCSNAMEa(alternate)),
+        _ => (),
+    }
+    let indexed: [(&str, IndexedKeyword); 5] = [
+        ("CDELT", Keyword::CDELTia),
+        ("CRPIX", Keyword::CRPIXja),
+        ("CRVAL", Keyword::CRVALia),
+        ("CTYPE", Keyword::CTYPEia),
+        ("CUNIT", Keyword::CUNITia),
+    ];
+    for &(prefix, constructor) in indexed.iter() {
+        if let Option::Some(index) = stem.strip_prefix(prefix) {
+            return wcs_index(index, 1).map(|i| constructor(i, alternate))
+        }
+    }
+    if let Option::Some(index) = stem.strip_prefix("CROTA") {
+        return wcs_index(index, 1).filter(|_| alternate.is_none()).map(Keyword::CROTAi)
+    }
+    let paired: [(&str, PairedKeyword, u16); 4] = [
+        ("CD", Keyword::CDi_ja, 1),
+        ("PC", Keyword::PCi_ja, 1),
+        ("PS", Keyword::PSi_ma, 0),
+        ("PV", Keyword::PVi_ma, 0),
+    ];
+    for &(prefix, constructor, least) in paired.iter() {
+        if let Option::Some(rest) = stem.strip_prefix(prefix) {
+            let mut indices = rest.splitn(2, '_');
+            let i = indices.next().and_then(|i| wcs_index(i, 1))?;
+            let j = indices.next().and_then(|j| wcs_index(j, least))?;
+            return Option::Some(constructor(i, j, alternate))
+        }
     }
+    Option::None
 }
 
-impl<'a> KeywordSpecialCase for PrefixedKeyword<'a> {
-    fn handles(&self, input: &str) -> bool {
-        input.starts_with(self.prefix)
-    }
-
-    fn transform(&self, input: &str) -> Result<Keyword, ParseKeywordError> {
-        let (_, representation) = input.split_at(self.prefix.len());
-        match u16::from_str(representation) {
-            Ok(n) => Ok((self.constructor)(n)),
-            Err(_) => Err(ParseKeywordError::NotANumber)
-        }
+/// An index of a WCS keyword, between `least` and 99 without leading zeros.
+fn wcs_index(text: &str, least: u16) -> Option<u16> {
+    if text.is_empty() || text.len() > 2 || !text.bytes().all(|b| b.is_ascii_digit()) || (text.len() > 1 && text.starts_with('0')) {
+        return Option::None
     }
+    u16::from_str(text).ok().filter(|&index| index >= least)
 }
 
 /// For input n and k, finds the least multiple of k such that n <= q*k and
@@ -414,8 +1896,27 @@ fn lmle(n: usize, k: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
+    use nom::IResult;
+    use super::super::parser::fits;
     use super::*;
 
+    #[test]
+    fn remove_and_move_hdu_should_keep_the_primary_hdu_first() {
+        let named = |name: &str| HDU::new(Header::new(vec!(
+            KeywordRecord::new(Keyword::EXTNAME, Value::CharacterString(Cow::Owned(name.to_string())), Option::None),
+        )));
+        let primary = Header::new(vec!(KeywordRecord::new(Keyword::NEXTEND, Value::Integer(3), Option::None)));
+        let mut fits = Fits::new(HDU::new(primary), vec!(named("A"), named("B"), named("C")));
+
+        assert_eq!(fits.move_hdu(3, 1), Ok(()));
+        assert!(fits.get(1).unwrap().has_name("C"));
+        assert!(fits.remove_hdu(2).unwrap().has_name("A"));
+        assert!(fits.get(2).unwrap().has_name("B"));
+        assert_eq!(fits.primary_hdu.header.integer_value_of(&Keyword::NEXTEND), Ok(2));
+        assert_eq!(fits.remove_hdu(0), Err(FitsEditError::PrimaryHdu));
+        assert_eq!(fits.move_hdu(1, 3), Err(FitsEditError::OutOfBounds(3)));
+    }
+
     #[test]
     fn fits_constructed_from_the_new_function_should_eq_hand_construction() {
         assert_eq!(
@@ -427,16 +1928,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fits_should_find_hdus_by_name_and_version() {
+        let named = |name: &'static str, version| {
+            HDU::new(Header::new(vec!(
+                KeywordRecord::new(Keyword::EXTNAME, Value::CharacterString(name.into()), Option::None),
+                KeywordRecord::new(Keyword::EXTVER, Value::Integer(version), Option::None),
+            )))
+        };
+        let fits = Fits::new(
+            HDU::new(Header::new(vec!())),
+            vec!(named("SCI     ", 1i64), named("ERR", 1i64), named("sci", 2i64)));
+
+        assert_eq!(fits.by_name("sci"), Option::Some(&fits.extensions[0]));
+        assert_eq!(fits.by_name("ERR  "), Option::Some(&fits.extensions[1]));
+        assert_eq!(fits.by_name_ver("SCI", 2i64), Option::Some(&fits.extensions[2]));
+        assert_eq!(fits.by_name_ver("ERR", 2i64), Option::None);
+        assert_eq!(fits.by_name("EVENTS"), Option::None);
+    }
+
+    #[test]
+    fn fits_should_behave_like_a_collection_of_hdus() {
+        let hdu = |name: &'static str| {
+            HDU::new(Header::new(vec!(
+                KeywordRecord::new(Keyword::EXTNAME, Value::CharacterString(name.into()), Option::None),
+            )))
+        };
+        let fits = Fits::new(hdu("PRIMARY"), vec!(hdu("FIRST"), hdu("SECOND")));
+
+        assert_eq!(fits.len(), 3);
+        assert!(!fits.is_empty());
+        assert_eq!(fits[0], hdu("PRIMARY"));
+        assert_eq!(fits[2], hdu("SECOND"));
+        assert_eq!(fits.get(3), Option::None);
+        assert_eq!((&fits).into_iter().count(), 3);
+        let names: Vec<String> = fits.into_iter()
+            .map(|hdu| hdu.header.character_string_value_of(&Keyword::EXTNAME).unwrap().to_string())
+            .collect();
+        assert_eq!(names, vec!("PRIMARY", "FIRST", "SECOND"));
+    }
+
     #[test]
     fn header_constructed_from_the_new_function_should_eq_hand_construction() {
+        let mut index = KeywordIndex::default();
+        index.insert(Keyword::SIMPLE, Positions::new(0));
+        index.insert(Keyword::NEXTEND, Positions::new(1));
         assert_eq!(
-            Header { keyword_records: vec!(
+            Header { records: vec!(
                 KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
-                KeywordRecord::new(Keyword::NEXTEND, Value::Integer(0i64), Option::Some("no extensions")),
-            )},
+                KeywordRecord::new(Keyword::NEXTEND, Value::Integer(0i64), Option::Some("no extensions".into())),
+            ), index, shape: Shape {
+                bitpix: Option::None,
+                naxes: Err(NaxesError::NAXIS(ValueRetrievalError::KeywordNotPresent)),
+                bits: Option::Some(0),
+            } },
             Header::new(vec!(
                 KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
-                KeywordRecord::new(Keyword::NEXTEND, Value::Integer(0i64), Option::Some("no extensions")),
+                KeywordRecord::new(Keyword::NEXTEND, Value::Integer(0i64), Option::Some("no extensions".into())),
             ))
         );
     }
@@ -452,19 +2000,32 @@ mod tests {
     fn keywords_could_be_constructed_from_str() {
         let data = vec!(
             ("AV", Keyword::AV),
+            ("AUTHOR", Keyword::AUTHOR),
             ("BITPIX", Keyword::BITPIX),
+            ("BLANK", Keyword::BLANK),
+            ("BSCALE", Keyword::BSCALE),
+            ("BUNIT", Keyword::BUNIT),
+            ("BZERO", Keyword::BZERO),
             ("CAMPAIGN", Keyword::CAMPAIGN),
             ("CHANNEL", Keyword::CHANNEL),
             ("CHECKSUM", Keyword::CHECKSUM),
+            ("COMMENT", Keyword::COMMENT),
+            ("CONTINUE", Keyword::CONTINUE),
             ("CREATOR", Keyword::CREATOR),
             ("DATASUM", Keyword::DATASUM),
+            ("DATAMAX", Keyword::DATAMAX),
+            ("DATAMIN", Keyword::DATAMIN),
             ("DATA_REL", Keyword::DATA_REL),
             ("DATE", Keyword::DATE),
+            ("DATE-OBS", Keyword::DATE_OBS),
             ("DEC_OBJ", Keyword::DEC_OBJ),
             ("EBMINUSV", Keyword::EBMINUSV),
             ("END", Keyword::END),
             ("EQUINOX", Keyword::EQUINOX),
+            ("EXPOSURE", Keyword::EXPOSURE),
+            ("EXPTIME", Keyword::EXPTIME),
             ("EXTEND", Keyword::EXTEND),
+            ("EXTLEVEL", Keyword::EXTLEVEL),
             ("EXTVER", Keyword::EXTVER),
             ("FEH", Keyword::FEH),
             ("FILEVER", Keyword::FILEVER),
@@ -474,8 +2035,11 @@ mod tests {
             ("GLON", Keyword::GLON),
             ("GMAG", Keyword::GMAG),
             ("GRCOLOR", Keyword::GRCOLOR),
+            ("GROUPS", Keyword::GROUPS),
+            ("HISTORY", Keyword::HISTORY),
             ("HMAG", Keyword::HMAG),
             ("IMAG", Keyword::IMAG),
+            ("INHERIT", Keyword::INHERIT),
             ("INSTRUME", Keyword::INSTRUME),
             ("JKCOLOR", Keyword::JKCOLOR),
             ("JMAG", Keyword::JMAG),
@@ -483,11 +2047,13 @@ mod tests {
             ("KEPMAG", Keyword::KEPMAG),
             ("KMAG", Keyword::KMAG),
             ("LOGG", Keyword::LOGG),
+            ("LONGSTRN", Keyword::LONGSTRN),
             ("MISSION", Keyword::MISSION),
             ("MODULE", Keyword::MODULE),
             ("NAXIS", Keyword::NAXIS),
             ("NEXTEND", Keyword::NEXTEND),
             ("OBJECT", Keyword::OBJECT),
+            ("OBSERVER", Keyword::OBSERVER),
             ("OBSMODE", Keyword::OBSMODE),
             ("ORIGIN", Keyword::ORIGIN),
             ("OUTPUT", Keyword::OUTPUT),
@@ -499,12 +2065,14 @@ mod tests {
             ("PROCVER", Keyword::PROCVER),
             ("RADESYS", Keyword::RADESYS),
             ("RADIUS", Keyword::RADIUS),
+            ("REFERENC", Keyword::REFERENC),
             ("RA_OBJ", Keyword::RA_OBJ),
             ("RMAG", Keyword::RMAG),
             ("SIMPLE", Keyword::SIMPLE),
             ("TEFF", Keyword::TEFF),
             ("TELESCOP", Keyword::TELESCOP),
             ("TFIELDS", Keyword::TFIELDS),
+            ("TIMESYS", Keyword::TIMESYS),
             ("TIMVERSN", Keyword::TIMVERSN),
             ("THEAP", Keyword::THEAP),
             ("TMINDEX", Keyword::TMINDEX),
@@ -607,6 +2175,17 @@ mod tests {
         }
     }
 
+    #[allow(non_snake_case)]
+    #[test]
+    fn TUCD_should_be_parsed_from_str() {
+        for n in 1u16..1000u16 {
+            let keyword = Keyword::TUCDn(n);
+            let representation = format!("TUCD{}", n);
+
+            assert_eq!(Keyword::from_str(&representation).unwrap(), keyword);
+        }
+    }
+
     #[allow(non_snake_case)]
     #[test]
     fn TUNIT_should_be_parsed_from_str() {
@@ -618,11 +2197,491 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unknown_keywords_should_be_parsed_as_unrecognized() {
+        assert_eq!(Keyword::from_str("MYKEY   ").unwrap(),
+                   Keyword::Unrecognized(KeywordText::new("MYKEY").unwrap()));
+    }
+
+    #[test]
+    fn named_keywords_should_parse() {
+        for (name, keyword) in NAMED_KEYWORDS.entries() {
+            assert_eq!(&Keyword::from_str(&format!("{:<8}", name)).unwrap(), keyword);
+        }
+        assert_eq!(Keyword::from_str("TFORM12").unwrap(), Keyword::TFORMn(12));
+        match Keyword::from_str("NAXIS123456") {
+            Err(ParseKeywordError::NotANumber) => (),
+            result => panic!("Did not expect {:?}", result),
+        }
+    }
+
+    #[test]
+    fn wcs_keywords_should_parse_with_their_alternates() {
+        let cases = vec!(
+            ("CTYPE1", Keyword::CTYPEia(1, Option::None)),
+            ("CRVAL2B", Keyword::CRVALia(2, Option::Some('B'))),
+            ("CROTA2", Keyword::CROTAi(2)),
+            ("PC1_2", Keyword::PCi_ja(1, 2, Option::None)),
+            ("CD2_1A", Keyword::CDi_ja(2, 1, Option::Some('A'))),
+            ("PV2_0", Keyword::PVi_ma(2, 0, Option::None)),
+            ("WCSAXES", Keyword::WCSAXESa(Option::None)),
+            ("WCSNAMEZ", Keyword::WCSNAMEa(Option::Some('Z'))),
+        );
+        for (text, keyword) in cases {
+            assert_eq!(Keyword::from_str(text).unwrap(), keyword);
+            assert_eq!(keyword.to_string(), text);
+        }
+        for text in ["CTYPE0", "CTYPE01", "PC1", "CDS", "CROTA2A", "CRPIX1a"].iter() {
+            assert_eq!(Keyword::from_str(text).unwrap(), Keyword::Unrecognized(KeywordText::new(text).unwrap()));
+        }
+    }
+
+    #[test]
+    fn over_long_unknown_keywords_should_be_an_error() {
+        match Keyword::from_str("NOT A KEYWORD") {
+            Err(ParseKeywordError::TooLong) => (),
+            result => panic!("Expected TooLong, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn custom_keywords_should_be_checked() {
+        assert_eq!(Keyword::custom("MYKEY01").unwrap(), Keyword::Unrecognized(KeywordText::new("MYKEY01").unwrap()));
+        assert_eq!(Keyword::custom("DATE-OBS").unwrap(), Keyword::DATE_OBS);
+        assert_eq!(Keyword::custom("TFORM3").unwrap(), Keyword::TFORMn(3));
+        for name in ["TUNITS", "NAXISLEN", "TZEROPT", "TFORMAT"].iter() {
+            assert_eq!(Keyword::custom(name).unwrap(), Keyword::Unrecognized(KeywordText::new(name).unwrap()));
+        }
+        for &(name, expected) in [("mykey", "InvalidCharacter"), ("MY KEY", "InvalidCharacter"), ("", "Empty"), ("MYKEY0123", "TooLong")].iter() {
+            match Keyword::custom(name) {
+                Err(error) => assert_eq!(format!("{:?}", error), expected),
+                result => panic!("Expected {}, got {:?}", expected, result),
+            }
+        }
+    }
+
     #[test]
     fn should_also_parse_whitespace_keywords() {
         assert_eq!(Keyword::from_str("SIMPLE  ").unwrap(), Keyword::SIMPLE);
     }
 
+    #[test]
+    fn typed_accessors_should_retrieve_values_of_their_type() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::EQUINOX, Value::Real(2000.0f64), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC 200164267".into()), Option::None),
+            KeywordRecord::new(Keyword::PMRA, Value::Complex((1.0f64, -1.0f64)), Option::None),
+        ));
+
+        assert!(header.logical_value_of(&Keyword::SIMPLE).unwrap());
+        assert_eq!(header.integer_value_of(&Keyword::NAXIS).unwrap(), 0i64);
+        assert_eq!(header.real_value_of(&Keyword::EQUINOX).unwrap(), 2000.0f64);
+        assert_eq!(header.character_string_value_of(&Keyword::OBJECT).unwrap(), "EPIC 200164267");
+        assert_eq!(header.complex_value_of(&Keyword::PMRA).unwrap(), (1.0f64, -1.0f64));
+    }
+
+    #[test]
+    fn real_value_of_should_promote_integers() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::EQUINOX, Value::Integer(2000i64), Option::None),
+        ));
+
+        assert_eq!(header.real_value_of(&Keyword::EQUINOX).unwrap(), 2000.0f64);
+    }
+
+    #[test]
+    fn typed_accessors_should_report_the_kind_of_failure() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::PMRA, Value::Undefined, Option::None),
+        ));
+
+        match header.real_value_of(&Keyword::SIMPLE) {
+            Err(ValueRetrievalError::NotAReal) => (),
+            result => panic!("Expected NotAReal, got {:?}", result),
+        }
+        match header.logical_value_of(&Keyword::PMRA) {
+            Err(ValueRetrievalError::ValueUndefined) => (),
+            result => panic!("Expected ValueUndefined, got {:?}", result),
+        }
+        match header.complex_value_of(&Keyword::EQUINOX) {
+            Err(ValueRetrievalError::KeywordNotPresent) => (),
+            result => panic!("Expected KeywordNotPresent, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn values_of_should_return_all_occurrences_of_a_keyword() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("first".into()), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("second".into()), Option::None),
+        ));
+
+        let values: Vec<&Value> = header.values_of(&Keyword::OBJECT).collect();
+
+        assert_eq!(values, vec!(&Value::CharacterString("first".into()), &Value::CharacterString("second".into())));
+        assert_eq!(header.value_of(&Keyword::OBJECT).unwrap(), Value::CharacterString("first".into()));
+        assert_eq!(header.last_value_of(&Keyword::OBJECT).unwrap(), Value::CharacterString("second".into()));
+        assert_eq!(header.values_of(&Keyword::EXTNAME).count(), 0);
+    }
+
+    #[test]
+    fn history_and_comments_should_yield_commentary_in_order() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+            KeywordRecord::new(Keyword::COMMENT, Value::Undefined, Option::Some("a remark".into())),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("calibrated".into())),
+        ));
+
+        assert_eq!(header.history().collect::<Vec<&str>>(), vec!("created", "calibrated"));
+        assert_eq!(header.comments().collect::<Vec<&str>>(), vec!("a remark"));
+    }
+
+    #[test]
+    fn commentary_blocks_should_join_adjacent_records_with_the_same_keyword() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::COMMENT, Value::Undefined, Option::Some("a remark".into())),
+            KeywordRecord::new(Keyword::COMMENT, Value::Undefined, Option::Some("over two cards".into())),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("calibrated".into())),
+        ));
+
+        assert_eq!(header.commentary_blocks(), vec!(
+            CommentaryBlock { keyword: Keyword::COMMENT, text: "a remark over two cards".to_string(), cards: 1..3 },
+            CommentaryBlock { keyword: Keyword::HISTORY, text: "created".to_string(), cards: 3..4 },
+            CommentaryBlock { keyword: Keyword::HISTORY, text: "calibrated".to_string(), cards: 5..6 },
+        ));
+    }
+
+    #[test]
+    fn commentary_keywords_should_be_recognized() {
+        assert!(Keyword::COMMENT.is_commentary());
+        assert!(Keyword::HISTORY.is_commentary());
+        assert!(Keyword::from_str("        ").unwrap().is_commentary());
+        assert!(!Keyword::OBJECT.is_commentary());
+    }
+
+    #[test]
+    fn inherited_header_should_fall_back_to_the_primary_header() {
+        let primary = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::TELESCOP, Value::CharacterString("Kepler".into()), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("primary".into()), Option::None),
+            KeywordRecord::new(Keyword::EXTNAME, Value::CharacterString("PRIMARY".into()), Option::None),
+        ));
+        let extension = Header::new(vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
+            KeywordRecord::new(Keyword::INHERIT, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("extension".into()), Option::None),
+        ));
+
+        let view = extension.with_inherited(&primary);
+
+        assert_eq!(view.character_string_value_of(&Keyword::TELESCOP).unwrap(), "Kepler");
+        assert_eq!(view.character_string_value_of(&Keyword::OBJECT).unwrap(), "extension");
+        assert!(view.value_of(&Keyword::SIMPLE).is_err());
+        assert!(view.value_of(&Keyword::EXTNAME).is_err());
+    }
+
+    #[test]
+    fn inherited_header_should_not_fall_back_without_inherit() {
+        let primary = Header::new(vec!(
+            KeywordRecord::new(Keyword::TELESCOP, Value::CharacterString("Kepler".into()), Option::None),
+        ));
+        let extension = Header::new(vec!(
+            KeywordRecord::new(Keyword::INHERIT, Value::Logical(false), Option::None),
+        ));
+
+        assert!(extension.with_inherited(&primary).value_of(&Keyword::TELESCOP).is_err());
+    }
+
+    #[test]
+    fn header_should_expose_its_records() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::COMMENT, Value::Undefined, Option::Some("a remark".into())),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::Some("no data".into())),
+        ));
+
+        assert_eq!(header.len(), 3);
+        assert!(!header.is_empty());
+        assert_eq!(header.records()[1].comment(), Option::Some("a remark"));
+        let keywords: Vec<&Keyword> = header.keyword_records().map(|record| record.keyword()).collect();
+        assert_eq!(keywords, vec!(&Keyword::SIMPLE, &Keyword::NAXIS));
+    }
+
+    #[test]
+    fn set_should_update_an_existing_record_keeping_its_comment() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("old".into()), Option::Some("target".into())),
+            KeywordRecord::new(Keyword::END, Value::Undefined, Option::None),
+        ));
+
+        header.set(Keyword::OBJECT, Value::CharacterString("new".into()), Option::None).unwrap();
+
+        assert_eq!(header.records(), &[
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("new".into()), Option::Some("target".into())),
+            KeywordRecord::new(Keyword::END, Value::Undefined, Option::None),
+        ]);
+    }
+
+    #[test]
+    fn set_should_append_new_records_before_end() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::END, Value::Undefined, Option::None),
+        ));
+
+        header.set(Keyword::EXPTIME, Value::Real(1.5f64), Option::Some("seconds".into())).unwrap();
+
+        assert_eq!(header.records()[1], KeywordRecord::new(Keyword::EXPTIME, Value::Real(1.5f64), Option::Some("seconds".into())));
+        assert_eq!(header.records()[2].keyword(), &Keyword::END);
+        assert_eq!(header.real_value_of(&Keyword::EXPTIME).unwrap(), 1.5f64);
+        assert_eq!(header.set(Keyword::END, Value::Undefined, Option::None), Err(HeaderEditError::EndKeyword));
+    }
+
+    #[test]
+    fn value_opt_should_tell_undefined_values_from_missing_keywords() {
+        let file = match fits(include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits")) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let header = &file.primary_hdu.header;
+
+        assert_eq!(header.value_opt(&Keyword::PMRA), Option::Some(Option::None));
+        assert_eq!(header.value_opt(&Keyword::OBJECT), Option::Some(Option::Some(Value::CharacterString("EPIC 200164267".into()))));
+        assert_eq!(header.value_opt(&Keyword::AUTHOR), Option::None);
+        assert_eq!(file[1].header.with_inherited(header).value_opt(&Keyword::PMRA), Option::Some(Option::None));
+    }
+
+    #[test]
+    fn into_owned_should_outlive_the_input() {
+        let expected = match fits(include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits")) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let owned: Fits<'static> = {
+            let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits").to_vec();
+            match fits(&data) {
+                IResult::Done(_, file) => file.into_owned(),
+                _ => panic!("Did not expect the parse to fail"),
+            }
+        };
+
+        assert_eq!(owned, expected);
+        assert_eq!(owned[1].header.naxes(), expected[1].header.naxes());
+    }
+
+    #[test]
+    fn set_should_accept_owned_strings() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+        ));
+
+        for version in 1..3 {
+            let text = format!("version {}", version);
+            header.set(Keyword::OBJECT, Value::CharacterString(text.clone().into()), Option::Some(text.into())).unwrap();
+        }
+
+        assert_eq!(header.character_string_value_of(&Keyword::OBJECT), Ok("version 2"));
+        assert_eq!(header.records()[1].comment(), Option::Some("version 2"));
+    }
+
+    #[test]
+    fn remove_should_remove_the_first_occurrence() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("first".into()), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("second".into()), Option::None),
+        ));
+
+        let removed = header.remove(&Keyword::OBJECT).unwrap();
+
+        assert_eq!(removed.value(), &Value::CharacterString("first".into()));
+        assert_eq!(header.value_of(&Keyword::OBJECT).unwrap(), Value::CharacterString("second".into()));
+        assert_eq!(header.remove(&Keyword::EXTNAME), Err(HeaderEditError::KeywordNotPresent));
+    }
+
+    #[test]
+    fn insert_after_should_place_the_record_after_the_keyword() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+        ));
+
+        header.insert_after(&Keyword::SIMPLE,
+                            KeywordRecord::new(Keyword::COMMENT, Value::Undefined, Option::Some("inserted".into())))
+            .unwrap();
+
+        assert_eq!(header.records()[1].keyword(), &Keyword::COMMENT);
+        assert_eq!(header.integer_value_of(&Keyword::BITPIX).unwrap(), 8i64);
+    }
+
+    #[test]
+    fn rename_should_keep_value_and_position() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("M31".into()), Option::Some("target".into())),
+            KeywordRecord::new(Keyword::END, Value::Undefined, Option::None),
+        ));
+
+        header.rename(&Keyword::OBJECT, Keyword::from_str("TARGET").unwrap()).unwrap();
+
+        assert_eq!(header.records()[1], KeywordRecord::new(Keyword::from_str("TARGET").unwrap(), Value::CharacterString("M31".into()), Option::Some("target".into())));
+        assert!(header.value_of(&Keyword::OBJECT).is_err());
+        assert_eq!(header.rename(&Keyword::OBJECT, Keyword::EXTNAME), Err(HeaderEditError::KeywordNotPresent));
+        assert_eq!(header.rename(&Keyword::SIMPLE, Keyword::END), Err(HeaderEditError::EndKeyword));
+    }
+
+    #[test]
+    fn to_fits_string_should_write_non_ascii_text_in_80_bytes_per_card() {
+        let mut header = Header::new(vec!());
+        for n in 1u16..36u16 {
+            header.set(Keyword::NAXISn(n), Value::Integer(1i64), Option::Some("é".into())).unwrap();
+        }
+        header.set(Keyword::OBSERVER, Value::from("Müller"), Option::None).unwrap();
+
+        let text = header.to_fits_string();
+
+        assert_eq!(text.len(), 2 * 2880);
+        assert_eq!(text.len() * 8, header.header_size());
+        assert!(text.starts_with(&format!("{:80}", "NAXIS1  =                    1 / ?")));
+        assert_eq!(&text[35 * 80..36 * 80], format!("{:80}", "OBSERVER= 'M?ller  '"));
+    }
+
+    #[test]
+    fn header_size_should_include_end_and_padding() {
+        let mut header = Header::new(vec!());
+        for n in 1u16..36u16 {
+            header.set(Keyword::NAXISn(n), Value::Integer(1i64), Option::None).unwrap();
+        }
+
+        assert_eq!(header.header_size(), 2880*8);
+
+        header.set(Keyword::NAXISn(36u16), Value::Integer(1i64), Option::None).unwrap();
+
+        assert_eq!(header.header_size(), 2*2880*8);
+    }
+
+    #[test]
+    fn naxes_should_return_the_axis_lengths_in_order() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(3u16), Value::Integer(7i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(5i64), Option::None),
+        ));
+
+        assert_eq!(header.naxes(), Ok(vec!(3u64, 5u64, 7u64)));
+    }
+
+    #[test]
+    fn naxes_should_report_missing_and_invalid_axes() {
+        let missing = Header::new(vec!(
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(3i64), Option::None),
+        ));
+        let negative = Header::new(vec!(
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(-3i64), Option::None),
+        ));
+
+        assert_eq!(missing.naxes(), Err(NaxesError::NAXISn(2u16, ValueRetrievalError::KeywordNotPresent)));
+        assert_eq!(negative.naxes(), Err(NaxesError::OutOfRange(Keyword::NAXISn(1u16), -3i64)));
+        assert_eq!(Header::new(vec!()).naxes(), Err(NaxesError::NAXIS(ValueRetrievalError::KeywordNotPresent)));
+    }
+
+    #[test]
+    fn keywords_should_display_as_their_name() {
+        assert_eq!(Keyword::SIMPLE.to_string(), "SIMPLE");
+        assert_eq!(Keyword::DATE_OBS.to_string(), "DATE-OBS");
+        assert_eq!(Keyword::NAXISn(12u16).to_string(), "NAXIS12");
+        assert_eq!(Keyword::from_str("MYKEY").unwrap().to_string(), "MYKEY");
+    }
+
+    #[test]
+    fn keyword_records_should_display_as_fixed_format_cards() {
+        let data = vec!(
+            (KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::Some("conforms to FITS standards".into())),
+             "SIMPLE  =                    T / conforms to FITS standards"),
+            (KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC 200164267".into()), Option::Some("string version of target id".into())),
+             "OBJECT  = 'EPIC 200164267'     / string version of target id"),
+            (KeywordRecord::new(Keyword::EXTNAME, Value::CharacterString("O'HARA".into()), Option::None),
+             "EXTNAME = 'O''HARA '"),
+            (KeywordRecord::new(Keyword::TIMVERSN, Value::CharacterString("".into()), Option::None),
+             "TIMVERSN= ''"),
+            (KeywordRecord::new(Keyword::EQUINOX, Value::Real(2000.0f64), Option::None),
+             "EQUINOX =               2000.0"),
+            (KeywordRecord::new(Keyword::EXPTIME, Value::Real(1e-20f64), Option::None),
+             "EXPTIME =              1.0E-20"),
+            (KeywordRecord::new(Keyword::PMRA, Value::Undefined, Option::Some("[arcsec/yr] RA proper motion".into())),
+             "PMRA    =                      / [arcsec/yr] RA proper motion"),
+            (KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+             "HISTORY created"),
+        );
+
+        for (record, expected) in data {
+            assert_eq!(record.to_string(), format!("{:80}", expected));
+        }
+    }
+
+    #[test]
+    fn values_should_convert_from_primitives_and_display_in_fixed_format() {
+        let data: Vec<(Value, Value, &str)> = vec!(
+            (Value::from(42i64), Value::Integer(42), "                  42"),
+            (Value::from(-2.5f64), Value::Real(-2.5), "                -2.5"),
+            (Value::from(true), Value::Logical(true), "                   T"),
+            (Value::from("M31"), Value::CharacterString("M31".into()), "'M31     '          "),
+            (Value::from("O'HARA".to_string()), Value::CharacterString("O'HARA".into()), "'O''HARA '          "),
+            (Value::from((1.0f64, -1.0f64)), Value::Complex((1.0, -1.0)), "         (1.0, -1.0)"),
+        );
+
+        for (value, expected, display) in data {
+            assert_eq!(value, expected);
+            assert_eq!(value.to_string(), display);
+        }
+        assert_eq!(Value::Undefined.to_string(), format!("{:20}", ""));
+    }
+
+    #[test]
+    fn header_to_fits_string_should_be_padded_to_whole_blocks() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::None),
+        ));
+
+        let representation = header.to_fits_string();
+
+        assert_eq!(representation.len(), 2880);
+        assert_eq!(&representation[160..240], format!("{:80}", "END"));
+        assert_eq!(header.to_string().lines().count(), 2);
+    }
+
+    #[test]
+    fn header_to_json_should_keep_types_and_group_commentary() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::Some("conforms".into())),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(0i64), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("M \"31\"  ".into()), Option::None),
+            KeywordRecord::new(Keyword::EQUINOX, Value::Real(2000f64), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("calibrated".into())),
+            KeywordRecord::new(Keyword::END, Value::Undefined, Option::None),
+        ));
+
+        assert_eq!(header.to_json(), concat!(
+            r#"{"SIMPLE":{"value":true,"comment":"conforms"},"#,
+            r#""NAXIS":{"value":0,"comment":null},"#,
+            r#""HISTORY":["created","calibrated"],"#,
+            r#""OBJECT":{"value":"M \"31\"","comment":null},"#,
+            r#""EQUINOX":{"value":2000.0,"comment":null}}"#));
+    }
+
     #[test]
     fn primary_header_should_determine_correct_data_array_size() {
         let header = Header::new(vec!(
@@ -637,10 +2696,64 @@ mod tests {
         assert_eq!(header.data_array_size(), 1*(2880*8) as usize);
     }
 
+    #[test]
+    fn edits_should_update_the_shape_of_a_header() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(16i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(3i64), Option::None),
+        ));
+        assert_eq!((header.bitpix(), header.data_size()), (Option::Some(16i64), 6));
+
+        header.set(Keyword::NAXISn(1u16), Value::Integer(10i64), Option::None).unwrap();
+        assert_eq!(header.data_size(), 20);
+        header.set(Keyword::NAXIS, Value::Integer(2i64), Option::None).unwrap();
+        header.insert_after(&Keyword::NAXISn(1u16), KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(4i64), Option::None)).unwrap();
+        assert_eq!(header.naxes(), Ok(vec!(10u64, 4u64)));
+        assert_eq!(header.data_size(), 80);
+        header.remove(&Keyword::BITPIX).unwrap();
+        assert_eq!((header.bitpix(), header.data_size()), (Option::None, 0));
+    }
+
+    #[test]
+    fn sizes_past_4_gib_should_be_exact() {
+        let mut header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(65536i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(81920i64), Option::None),
+        ));
+        assert_eq!(header.data_length(), Option::Some(5368709120u64));
+        assert_eq!(header.padded_data_length(), Option::Some(5368711680u64));
+
+        header.set(Keyword::BITPIX, Value::Integer(-64i64), Option::None).unwrap();
+        header.set(Keyword::NAXISn(2u16), Value::Integer(i64::MAX), Option::None).unwrap();
+        assert_eq!((header.data_length(), header.padded_data_length()), (Option::None, Option::None));
+        assert_eq!((header.data_size(), header.data_array_size()), (usize::MAX, usize::MAX));
+    }
+
+    #[test]
+    fn hdu_with_data_should_drop_the_padding() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(16i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(3i64), Option::None),
+        ));
+        let data = [1u8; 2880];
+
+        let hdu = HDU::with_data(header, &data);
+
+        assert_eq!(hdu.header.data_size(), 6);
+        assert_eq!(hdu.data(), &[1u8; 6]);
+    }
+
     #[test]
     fn extension_header_should_determine_correct_data_array_size() {
         let header = Header::new(vec!(
-            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE"), Option::None),
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::None),
             KeywordRecord::new(Keyword::BITPIX, Value::Integer(128i64), Option::None),
             KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
             KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(3i64), Option::None),
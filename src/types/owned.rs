@@ -0,0 +1,154 @@
+//! Owned variants of the data model.
+//!
+//! `Fits`, `HDU` and `Header` borrow their strings and data arrays from the
+//! input they were parsed from. The owned variants copy them, so they have no
+//! lifetime: they can be returned from a function that read the file into a
+//! local buffer, or sent to another thread. The borrowed types are recovered
+//! with `fits`, `hdu` and `header`, which borrow from the owned variant.
+//!
+//! `Fits::into_owned` and its counterparts on `HDU` and `Header` copy into the
+//! borrowed types themselves, with a `'static` lifetime.
+
+use std::borrow::Cow;
+use super::{Fits, HDU, Header, KeywordRecord, Value};
+
+/// A FITS file that owns its headers and data arrays.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedFits {
+    /// The primary HDU
+    pub primary_hdu: OwnedHDU,
+    /// The extention HDUs
+    pub extensions: Vec<OwnedHDU>,
+}
+
+impl OwnedFits {
+    /// The file, borrowing from this owned file.
+    pub fn fits(&self) -> Fits<'_> {
+        Fits::new(self.primary_hdu.hdu(), self.extensions.iter().map(OwnedHDU::hdu).collect())
+    }
+
+    /// The HDU at `index`, where the primary HDU has index 0.
+    pub fn get(&self, index: usize) -> Option<&OwnedHDU> {
+        if index == 0 {
+            Option::Some(&self.primary_hdu)
+        } else {
+            self.extensions.get(index - 1)
+        }
+    }
+
+    /// The number of HDUs, including the primary HDU.
+    pub fn len(&self) -> usize {
+        1 + self.extensions.len()
+    }
+
+    /// Determines if there are no HDUs. Because a FITS file always has a
+    /// primary HDU this is never the case.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl<'a, 'b> From<&'b Fits<'a>> for OwnedFits {
+    fn from(fits: &'b Fits<'a>) -> OwnedFits {
+        OwnedFits {
+            primary_hdu: OwnedHDU::from(&fits.primary_hdu),
+            extensions: fits.extensions.iter().map(OwnedHDU::from).collect(),
+        }
+    }
+}
+
+/// A Header Data Unit that owns its header and data array.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedHDU {
+    /// The header of this HDU.
+    pub header: OwnedHeader,
+    data: Vec<u8>,
+}
+
+impl OwnedHDU {
+    /// The HDU, borrowing from this owned HDU.
+    pub fn hdu(&self) -> HDU<'_> {
+        HDU::with_data(self.header.header(), &self.data)
+    }
+
+    /// The bytes of the data array of this HDU, empty when there is no data array.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<'a, 'b> From<&'b HDU<'a>> for OwnedHDU {
+    fn from(hdu: &'b HDU<'a>) -> OwnedHDU {
+        OwnedHDU { header: OwnedHeader::from(&hdu.header), data: hdu.data().to_vec() }
+    }
+}
+
+/// A header that owns the strings of its records.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedHeader {
+    records: Vec<KeywordRecord<'static>>,
+}
+
+impl OwnedHeader {
+    /// The header, borrowing from this owned header.
+    pub fn header(&self) -> Header<'_> {
+        Header::new(self.records.iter().map(|record| {
+            let value = match record.value {
+                Value::CharacterString(ref s) => Value::CharacterString(Cow::Borrowed(s)),
+                ref value => value.clone(),
+            };
+            KeywordRecord::new(record.keyword.clone(), value, record.comment().map(Cow::Borrowed))
+        }).collect())
+    }
+
+    /// The number of records in this header.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Determines if this header has no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl<'a, 'b> From<&'b Header<'a>> for OwnedHeader {
+    fn from(header: &'b Header<'a>) -> OwnedHeader {
+        OwnedHeader {
+            records: header.records.iter().cloned().map(KeywordRecord::into_owned).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use nom::IResult;
+    use super::super::super::parser::{fits, parse_owned};
+    use super::super::Keyword;
+    use super::OwnedFits;
+
+    fn read_locally() -> OwnedFits {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits").to_vec();
+        match parse_owned(&data) {
+            IResult::Done(_, owned) => owned,
+            _ => panic!("Did not expect the parse to fail"),
+        }
+    }
+
+    #[test]
+    fn owned_fits_should_outlive_its_input_and_move_between_threads() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let expected = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let owned = thread::spawn(read_locally).join().unwrap();
+
+        assert_eq!(owned.len(), 3);
+        assert_eq!(owned.fits(), expected);
+        assert_eq!(owned.get(1).unwrap().data(), expected[1].data());
+        assert_eq!(owned.extensions[0].header.header().character_string_value_of(&Keyword::EXTNAME), Ok("TARGETTABLES"));
+    }
+}
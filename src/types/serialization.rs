@@ -0,0 +1,79 @@
+//! Serialization of the types with serde.
+//!
+//! Headers are a sequence of their records, keywords are their text as it
+//! appears in a record and data arrays are bytes. Character strings and
+//! comments are borrowed when the format can lend them out and owned
+//! otherwise, e.g. when they contain escapes.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Error;
+use super::{DataArray, Header, Keyword, KeywordRecord};
+
+impl<'a> Serialize for Header<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.records)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Header<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<KeywordRecord<'a>>::deserialize(deserializer).map(Header::new)
+    }
+}
+
+impl<'a> Serialize for DataArray<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.bytes)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for DataArray<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <&'a [u8]>::deserialize(deserializer).map(|bytes| DataArray { bytes: Cow::Borrowed(bytes), edited: false })
+    }
+}
+
+impl Serialize for Keyword {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keyword {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Keyword::from_str(&text).map_err(|_| D::Error::custom(format!("invalid keyword {:?}", text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::super::{Header, KeywordRecord, Keyword, Value};
+
+    #[test]
+    fn header_should_round_trip_through_json() {
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::Some("conforms".into())),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(3i64), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::CharacterString("EPIC".into()), Option::None),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("created".into())),
+            KeywordRecord::new(Keyword::HISTORY, Value::Undefined, Option::Some("by \"fits-rs\"".into())),
+        ));
+
+        let json = serde_json::to_string(&header).unwrap();
+        let result: Header = serde_json::from_str(&json).unwrap();
+
+        assert!(json.starts_with(r#"[{"keyword":"SIMPLE","value":{"Logical":true},"comment":"conforms"},{"keyword":"NAXIS1""#));
+        assert_eq!(result, header);
+    }
+
+    #[test]
+    fn unknown_keywords_should_not_deserialize() {
+        let result: Result<Keyword, serde_json::Error> = serde_json::from_str(r#""NOT A KEYWORD""#);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! A variant of the data model backed by `bytes::Bytes`.
+//!
+//! The data arrays of a `SharedFits` are slices of the reference counted
+//! buffer the file was parsed from and the headers are owned and reference
+//! counted, so cloning an HDU or a file copies neither. Unlike `Fits` it has no
+//! lifetime, so it can be shared between threads and tasks.
+
+use std::sync::Arc;
+use bytes::Bytes;
+use super::{Fits, HDU, OwnedHeader};
+
+/// A FITS file of which the data arrays share the buffer it was parsed from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SharedFits {
+    /// The primary HDU
+    pub primary_hdu: SharedHDU,
+    /// The extention HDUs
+    pub extensions: Vec<SharedHDU>,
+}
+
+impl SharedFits {
+    /// The HDUs of `fits`, with their data arrays sliced from `buffer`, which
+    /// `fits` was parsed from.
+    pub(crate) fn from_fits(buffer: &Bytes, fits: &Fits) -> SharedFits {
+        SharedFits {
+            primary_hdu: SharedHDU::from_hdu(buffer, &fits.primary_hdu),
+            extensions: fits.extensions.iter().map(|hdu| SharedHDU::from_hdu(buffer, hdu)).collect(),
+        }
+    }
+
+    /// The file, borrowing from this shared file.
+    pub fn fits(&self) -> Fits<'_> {
+        Fits::new(self.primary_hdu.hdu(), self.extensions.iter().map(SharedHDU::hdu).collect())
+    }
+
+    /// The HDU at `index`, where the primary HDU has index 0.
+    pub fn get(&self, index: usize) -> Option<&SharedHDU> {
+        if index == 0 {
+            Option::Some(&self.primary_hdu)
+        } else {
+            self.extensions.get(index - 1)
+        }
+    }
+
+    /// The number of HDUs, including the primary HDU.
+    pub fn len(&self) -> usize {
+        1 + self.extensions.len()
+    }
+
+    /// Determines if there are no HDUs. Because a FITS file always has a
+    /// primary HDU this is never the case.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// A Header Data Unit with a reference counted header and a data array that
+/// shares the buffer of its file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SharedHDU {
+    header: Arc<OwnedHeader>,
+    data: Bytes,
+}
+
+impl SharedHDU {
+    fn from_hdu(buffer: &Bytes, hdu: &HDU) -> SharedHDU {
+        SharedHDU { header: Arc::new(OwnedHeader::from(&hdu.header)), data: buffer.slice_ref(hdu.data()) }
+    }
+
+    /// The HDU, borrowing from this shared HDU.
+    pub fn hdu(&self) -> HDU<'_> {
+        HDU::with_data(self.header.header(), &self.data)
+    }
+
+    /// The header of this HDU.
+    pub fn header(&self) -> &OwnedHeader {
+        &self.header
+    }
+
+    /// The bytes of the data array of this HDU, empty when there is no data
+    /// array. Cloning them does not copy them.
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use bytes::Bytes;
+    use nom::IResult;
+    use super::super::super::parser::{fits, layout, parse_shared};
+    use super::super::Keyword;
+
+    #[test]
+    fn shared_fits_should_share_its_buffer_between_threads() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let buffer = Bytes::from_static(data);
+        let expected = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let shared = match parse_shared(&buffer) {
+            IResult::Done(_, shared) => shared,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let hdu = shared.extensions[0].clone();
+                thread::spawn(move || (hdu.data().as_ptr() as usize, hdu.header().header().integer_value_of(&Keyword::NAXISn(2))))
+            })
+            .collect();
+
+        assert_eq!(shared.fits(), expected);
+        let start = layout(data)[1].1.start;
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), (data[start..].as_ptr() as usize, expected[1].header.integer_value_of(&Keyword::NAXISn(2))));
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! The wasm module exposes header parsing to JavaScript with `wasm-bindgen`,
+//! behind the `wasm` feature, for in-browser FITS viewers.
+//!
+//! The parsers only need the bytes of a file, which a viewer reads with the
+//! `File` API of the browser, so the crate builds for
+//! `wasm32-unknown-unknown` without features that need the file system or
+//! threads, like `parquet` and `rayon`.
+
+use wasm_bindgen::prelude::*;
+use super::parser::{layout, parse_header};
+use super::types::Header;
+
+/// The headers of the HDUs of `bytes`, a FITS file, as a JSON array with an
+/// object per header in the format of `Header::to_json`.
+///
+/// Headers are read up to the first HDU that could not be parsed, so the
+/// headers of a file with a data array cut short are still returned. Throws
+/// when there is not a single header.
+#[wasm_bindgen(js_name = parseHeaders)]
+pub fn parse_headers(bytes: &[u8]) -> Result<String, JsValue> {
+    headers_json(bytes).map_err(JsValue::from_str)
+}
+
+/// The number of HDUs of `bytes`, a FITS file.
+#[wasm_bindgen(js_name = hduCount)]
+pub fn hdu_count(bytes: &[u8]) -> usize {
+    layout(bytes).len()
+}
+
+/// The header of the HDU at `index` of `bytes`, a FITS file, as 80 column
+/// cards, one per line. `undefined` when there is no such HDU.
+#[wasm_bindgen(js_name = headerCards)]
+pub fn header_cards(bytes: &[u8], index: usize) -> Option<String> {
+    headers(bytes).into_iter().nth(index).map(|header| header.to_string())
+}
+
+fn headers_json(bytes: &[u8]) -> Result<String, &'static str> {
+    let headers = headers(bytes);
+    if headers.is_empty() {
+        return Err("not a FITS file")
+    }
+    let objects: Vec<String> = headers.iter().map(Header::to_json).collect();
+    Ok(format!("[{}]", objects.join(",")))
+}
+
+fn headers(bytes: &[u8]) -> Vec<Header<'_>> {
+    layout(bytes).into_iter()
+        .filter_map(|(header_range, _)| parse_header(&bytes[header_range]).to_result().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hdu_count, header_cards, headers_json};
+
+    #[test]
+    fn headers_should_be_read_from_bytes() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+
+        let json = headers_json(data).unwrap();
+
+        assert_eq!(hdu_count(data), 3);
+        assert!(json.starts_with("[{\"SIMPLE\":{\"value\":true"));
+        assert_eq!(json.matches("\"XTENSION\"").count(), 2);
+        assert!(header_cards(data, 2).unwrap().contains("EXTNAME = 'APERTURE'"));
+        assert_eq!(header_cards(data, 3), Option::None);
+        assert_eq!(headers_json(&data[..100]), Err("not a FITS file"));
+    }
+}
@@ -0,0 +1,820 @@
+//! The wcs module converts between pixel and world coordinates, following the
+//! World Coordinate System papers of the standard.
+//!
+//! Pixel coordinates follow the standard as well: the center of the first pixel
+//! along an axis is at 1. Celestial coordinates are in degrees.
+
+use std::str::FromStr;
+use super::types::{Header, Keyword, ValueRetrievalError};
+
+mod projection;
+mod sip;
+mod spectral;
+mod time;
+mod tpv;
+
+pub use self::projection::Projection;
+pub use self::sip::Sip;
+pub use self::spectral::{BasicType, Spectral, SpectralAlgorithm, SpectralType};
+pub use self::time::TimeReference;
+pub use self::tpv::Tpv;
+
+/// A world coordinate system, read from the WCS keywords of a header.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Wcs {
+    /// The letter of an alternate description, `A` to `Z`, which ends its
+    /// keywords. `None` for the primary description.
+    pub alternate: Option<char>,
+    /// The type of each world axis, `CTYPEi`.
+    pub ctype: Vec<String>,
+    /// The unit of each world axis, `CUNITi`.
+    pub cunit: Vec<String>,
+    /// The pixel coordinates of the reference point, `CRPIXj`.
+    pub crpix: Vec<f64>,
+    /// The world coordinates of the reference point, `CRVALi`.
+    pub crval: Vec<f64>,
+    /// The linear transformation from pixel offsets to intermediate world
+    /// coordinates, row by row: `CDi_j`, or `PCi_j` scaled by `CDELTi`.
+    pub cd: Vec<Vec<f64>>,
+    /// The SIP distortion of the first two pixel axes, for `-SIP` projections.
+    pub sip: Option<Sip>,
+    /// The polynomial distortion of the celestial intermediate world
+    /// coordinates, for the `TPV` projection.
+    pub tpv: Option<Tpv>,
+    celestial: Option<Celestial>,
+    spectral: Option<Spectral>,
+}
+
+/// The celestial axes of a `Wcs` and how they are projected.
+#[derive(Debug, PartialEq, Clone)]
+struct Celestial {
+    longitude: usize,
+    latitude: usize,
+    projection: Projection,
+    /// The celestial coordinates of the native pole, `(αp, δp)`.
+    pole: (f64, f64),
+    /// The native longitude of the celestial pole, `φp` or `LONPOLE`.
+    lonpole: f64,
+}
+
+impl Wcs {
+    /// Read the world coordinate system described by `header`.
+    ///
+    /// The number of axes is `WCSAXES`, or `NAXIS` when it is absent. Absent
+    /// keywords take the defaults of the standard. `CDi_j` takes precedence
+    /// over `PCi_j` and `CDELTi`, which take precedence over `CROTA2`. When
+    /// the celestial `CTYPEi` end in `-SIP` the SIP coefficients are read, a
+    /// `TPV` projection is a `TAN` projection with the `PVi_m` polynomials.
+    /// The first spectral axis, `FREQ` or `WAVE-F2W` style, follows WCS Paper
+    /// III.
+    pub fn new(header: &Header) -> Result<Wcs, WcsError> {
+        Wcs::with_alternate(header, Option::None)
+    }
+
+    /// Read the alternate world coordinate system with letter `alternate` of
+    /// `header`, whose keywords end in that letter, `CRVAL1A` style. `None`
+    /// reads the primary description, like `new`. `SIP` distortions and
+    /// `CROTA2` belong to the primary description only.
+    pub fn with_alternate(header: &Header, alternate: Option<char>) -> Result<Wcs, WcsError> {
+        if let Option::Some(letter) = alternate {
+            if !letter.is_ascii_uppercase() {
+                return Err(WcsError::InvalidAlternate(letter))
+            }
+        }
+        let suffix = suffix(alternate);
+        let axes = match integer(header, &format!("WCSAXES{}", suffix))? {
+            Option::Some(axes) => axes,
+            Option::None => header.integer_value_of(&Keyword::NAXIS)
+                .map_err(|error| WcsError::InvalidKeyword(Keyword::NAXIS, error))?,
+        } as usize;
+        let numbered = |name: &str| (1..axes + 1).map(|i| format!("{}{}{}", name, i, suffix)).collect::<Vec<String>>();
+
+        let ctype = numbered("CTYPE").iter()
+            .map(|name| string(header, name).map(|value| value.unwrap_or_default()))
+            .collect::<Result<Vec<String>, WcsError>>()?;
+        let cunit = numbered("CUNIT").iter()
+            .map(|name| string(header, name).map(|value| value.unwrap_or_default()))
+            .collect::<Result<Vec<String>, WcsError>>()?;
+        let crpix = reals(header, &numbered("CRPIX"), 0f64)?;
+        let crval = reals(header, &numbered("CRVAL"), 0f64)?;
+        let cd = matrix(header, axes, alternate)?;
+
+        let sip = if alternate.is_none() && ctype.iter().any(|ctype| ctype.get(8..) == Option::Some("-SIP")) {
+            Option::Some(Sip::new(header)?)
+        } else {
+            Option::None
+        };
+
+        let mut wcs = Wcs { alternate, ctype, cunit, crpix, crval, cd, sip, tpv: Option::None, celestial: Option::None, spectral: Option::None };
+        wcs.celestial = wcs.celestial_axes(header)?;
+        for axis in 0..axes {
+            wcs.spectral = Spectral::new(header, axis, &wcs.ctype[axis], &wcs.cunit[axis], wcs.crval[axis], alternate)?;
+            if wcs.spectral.is_some() {
+                break
+            }
+        }
+        if let Option::Some((longitude, latitude)) = wcs.celestial() {
+            if wcs.ctype[longitude].get(5..8) == Option::Some("TPV") {
+                wcs.tpv = Option::Some(Tpv::new(header, longitude, latitude, alternate)?);
+            }
+        }
+        Ok(wcs)
+    }
+
+    /// The number of axes.
+    pub fn axes(&self) -> usize {
+        self.ctype.len()
+    }
+
+    /// The indices, starting at 0, of the celestial longitude and latitude
+    /// axes, when there are any.
+    pub fn celestial(&self) -> Option<(usize, usize)> {
+        self.celestial.as_ref().map(|celestial| (celestial.longitude, celestial.latitude))
+    }
+
+    /// The projection of the celestial axes.
+    pub fn projection(&self) -> Option<Projection> {
+        self.celestial.as_ref().map(|celestial| celestial.projection)
+    }
+
+    /// The spectral axis, when there is one.
+    pub fn spectral(&self) -> Option<&Spectral> {
+        self.spectral.as_ref()
+    }
+
+    /// The world coordinates of `pixel`, which has a coordinate per axis.
+    ///
+    /// Celestial coordinates are in degrees, with the longitude between 0 and
+    /// 360. Spectral coordinates are in the unit of their axis. Other axes are
+    /// linear.
+    pub fn pixel_to_world(&self, pixel: &[f64]) -> Result<Vec<f64>, WcsError> {
+        if pixel.len() != self.axes() {
+            return Err(WcsError::Dimensions(pixel.len()))
+        }
+        let mut offsets: Vec<f64> = pixel.iter().zip(&self.crpix).map(|(p, crpix)| p - crpix).collect();
+        if let Option::Some(ref sip) = self.sip {
+            let (u, v) = sip.distort(offsets[0], offsets[1]);
+            offsets[0] = u;
+            offsets[1] = v;
+        }
+        let intermediate: Vec<f64> = self.cd.iter()
+            .map(|row| row.iter().zip(&offsets).map(|(m, d)| m * d).sum())
+            .collect();
+        let mut world: Vec<f64> = intermediate.iter().zip(&self.crval).map(|(x, crval)| crval + x).collect();
+        if let Option::Some(ref celestial) = self.celestial {
+            let (mut x, mut y) = (intermediate[celestial.longitude], intermediate[celestial.latitude]);
+            if let Option::Some(ref tpv) = self.tpv {
+                let (xi, eta) = tpv.distort(x, y);
+                x = xi;
+                y = eta;
+            }
+            let (lng, lat) = celestial.deproject(x, y).ok_or(WcsError::OutOfBounds)?;
+            world[celestial.longitude] = lng;
+            world[celestial.latitude] = lat;
+        }
+        if let Option::Some(ref spectral) = self.spectral {
+            world[spectral.axis] = spectral.world(intermediate[spectral.axis])?;
+        }
+        Ok(world)
+    }
+
+    /// The celestial coordinates of pixel `(x, y)` of an image whose first two
+    /// axes are celestial, as `(longitude, latitude)`. Further axes are at
+    /// their first pixel.
+    pub fn pixel_to_sky(&self, x: f64, y: f64) -> Result<(f64, f64), WcsError> {
+        let (longitude, latitude) = self.celestial().ok_or(WcsError::NotCelestial)?;
+        let mut pixel = vec!(1f64; self.axes());
+        pixel[0] = x;
+        if pixel.len() > 1 {
+            pixel[1] = y;
+        }
+        let world = self.pixel_to_world(&pixel)?;
+        Ok((world[longitude], world[latitude]))
+    }
+
+    /// The pixel coordinates of `world`, which has a coordinate per axis. The
+    /// inverse of `pixel_to_world`.
+    pub fn world_to_pixel(&self, world: &[f64]) -> Result<Vec<f64>, WcsError> {
+        if world.len() != self.axes() {
+            return Err(WcsError::Dimensions(world.len()))
+        }
+        let mut intermediate: Vec<f64> = world.iter().zip(&self.crval).map(|(w, crval)| w - crval).collect();
+        if let Option::Some(ref celestial) = self.celestial {
+            let (mut x, mut y) = celestial.project(world[celestial.longitude], world[celestial.latitude])
+                .ok_or(WcsError::OutOfBounds)?;
+            if let Option::Some(ref tpv) = self.tpv {
+                let (u, v) = tpv.undistort(x, y);
+                x = u;
+                y = v;
+            }
+            intermediate[celestial.longitude] = x;
+            intermediate[celestial.latitude] = y;
+        }
+        if let Option::Some(ref spectral) = self.spectral {
+            intermediate[spectral.axis] = spectral.intermediate(world[spectral.axis])?;
+        }
+        let inverse = invert(&self.cd).ok_or(WcsError::SingularMatrix)?;
+        let mut offsets: Vec<f64> = inverse.iter()
+            .map(|row| row.iter().zip(&intermediate).map(|(m, x)| m * x).sum())
+            .collect();
+        if let Option::Some(ref sip) = self.sip {
+            let (u, v) = sip.undistort(offsets[0], offsets[1]);
+            offsets[0] = u;
+            offsets[1] = v;
+        }
+        Ok(offsets.iter().zip(&self.crpix).map(|(d, crpix)| crpix + d).collect())
+    }
+
+    /// The pixel `(x, y)` of the celestial coordinates `(longitude, latitude)`
+    /// in an image whose first two axes are celestial. The inverse of
+    /// `pixel_to_sky`.
+    pub fn sky_to_pixel(&self, longitude: f64, latitude: f64) -> Result<(f64, f64), WcsError> {
+        let (longitude_axis, latitude_axis) = self.celestial().ok_or(WcsError::NotCelestial)?;
+        let mut world = self.pixel_to_world(&vec!(1f64; self.axes()))?;
+        world[longitude_axis] = longitude;
+        world[latitude_axis] = latitude;
+        let pixel = self.world_to_pixel(&world)?;
+        Ok((pixel[0], pixel.get(1).cloned().unwrap_or(1f64)))
+    }
+
+    /// The frequency in Hz of `channel`, the pixel coordinate along the
+    /// spectral axis. Other axes are at their first pixel.
+    pub fn channel_to_frequency(&self, channel: f64) -> Result<f64, WcsError> {
+        let spectral = self.spectral.as_ref().ok_or(WcsError::NotSpectral)?;
+        let mut pixel = vec!(1f64; self.axes());
+        pixel[spectral.axis] = channel;
+        spectral.frequency(self.pixel_to_world(&pixel)?[spectral.axis])
+    }
+
+    /// The vacuum wavelength in m of `channel`, the pixel coordinate along the
+    /// spectral axis. Other axes are at their first pixel.
+    pub fn channel_to_wavelength(&self, channel: f64) -> Result<f64, WcsError> {
+        let spectral = self.spectral.as_ref().ok_or(WcsError::NotSpectral)?;
+        let mut pixel = vec!(1f64; self.axes());
+        pixel[spectral.axis] = channel;
+        spectral.wavelength(self.pixel_to_world(&pixel)?[spectral.axis])
+    }
+
+    fn celestial_axes(&self, header: &Header) -> Result<Option<Celestial>, WcsError> {
+        let longitude = self.ctype.iter().position(|ctype| is_celestial(ctype, true));
+        let latitude = self.ctype.iter().position(|ctype| is_celestial(ctype, false));
+        let (longitude, latitude) = match (longitude, latitude) {
+            (Option::Some(longitude), Option::Some(latitude)) => (longitude, latitude),
+            (Option::None, Option::None) => return Ok(Option::None),
+            _ => return Err(WcsError::UnpairedCelestialAxis),
+        };
+        let code = match self.ctype[longitude].get(5..8).unwrap_or("") {
+            "TPV" => "TAN",
+            code => code,
+        };
+        let projection = Projection::from_str(code).map_err(WcsError::UnsupportedProjection)?;
+
+        let (alpha0, delta0) = (self.crval[longitude], self.crval[latitude]);
+        let theta0 = projection.theta0();
+        let suffix = suffix(self.alternate);
+        let lonpole = real(header, &format!("LONPOLE{}", suffix))?
+            .unwrap_or(if delta0 >= theta0 { 0f64 } else { 180f64 });
+        let latpole = real(header, &format!("LATPOLE{}", suffix))?.unwrap_or(90f64);
+        let pole = native_pole((alpha0, delta0), theta0, lonpole, latpole).ok_or(WcsError::OutOfBounds)?;
+        Ok(Option::Some(Celestial { longitude, latitude, projection, pole, lonpole }))
+    }
+}
+
+impl Celestial {
+    /// Projects intermediate world coordinates onto the native sphere and
+    /// rotates them to celestial coordinates.
+    fn deproject(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let (phi, theta) = self.projection.deproject(x, y)?;
+        let (phi, theta) = (phi.to_radians(), theta.to_radians());
+        let (alpha_p, delta_p) = (self.pole.0.to_radians(), self.pole.1.to_radians());
+        let dphi = phi - self.lonpole.to_radians();
+        let alpha = alpha_p + (-theta.cos() * dphi.sin())
+            .atan2(theta.sin() * delta_p.cos() - theta.cos() * delta_p.sin() * dphi.cos());
+        let delta = (theta.sin() * delta_p.sin() + theta.cos() * delta_p.cos() * dphi.cos()).clamp(-1f64, 1f64).asin();
+        Option::Some((alpha.to_degrees().rem_euclid(360f64), delta.to_degrees()))
+    }
+
+    /// Rotates celestial coordinates to the native sphere and projects them to
+    /// intermediate world coordinates.
+    fn project(&self, longitude: f64, latitude: f64) -> Option<(f64, f64)> {
+        let (alpha, delta) = (longitude.to_radians(), latitude.to_radians());
+        let (alpha_p, delta_p) = (self.pole.0.to_radians(), self.pole.1.to_radians());
+        let dalpha = alpha - alpha_p;
+        let phi = self.lonpole.to_radians() + (-delta.cos() * dalpha.sin())
+            .atan2(delta.sin() * delta_p.cos() - delta.cos() * delta_p.sin() * dalpha.cos());
+        let theta = (delta.sin() * delta_p.sin() + delta.cos() * delta_p.cos() * dalpha.cos()).clamp(-1f64, 1f64).asin();
+        self.projection.project(phi.to_degrees(), theta.to_degrees())
+    }
+}
+
+/// Problems that could occur when reading or applying a world coordinate system.
+#[derive(Debug, PartialEq)]
+pub enum WcsError {
+    /// A WCS keyword is present with a value of the wrong type.
+    InvalidKeyword(Keyword, ValueRetrievalError),
+    /// Only one of a celestial longitude and latitude axis is present.
+    UnpairedCelestialAxis,
+    /// The projection code of the celestial axes is not supported.
+    UnsupportedProjection(String),
+    /// The number of coordinates does not match the number of axes.
+    Dimensions(usize),
+    /// The coordinates lie outside of the projection.
+    OutOfBounds,
+    /// The world coordinate system has no celestial axes.
+    NotCelestial,
+    /// The letter of an alternate description is not `A` to `Z`.
+    InvalidAlternate(char),
+    /// The linear transformation can not be inverted.
+    SingularMatrix,
+    /// The world coordinate system has no spectral axis.
+    NotSpectral,
+    /// The spectral algorithm code of `CTYPEi` is not supported.
+    UnsupportedSpectralAlgorithm(String),
+    /// The unit of the spectral axis, `CUNITi`, is not known.
+    UnsupportedUnit(String),
+    /// A date is not an ISO-8601 date.
+    InvalidDate(String),
+    /// A conversion to or from velocity needs `RESTFRQ` or `RESTWAV`, which
+    /// are absent.
+    RestFrequency,
+}
+
+impl<'a> Header<'a> {
+    /// The world coordinate system of this header, see `Wcs::new`.
+    pub fn wcs(&self) -> Result<Wcs, WcsError> {
+        Wcs::new(self)
+    }
+
+    /// The alternate world coordinate system with letter `alternate` of this
+    /// header, see `Wcs::with_alternate`.
+    pub fn wcs_alternate(&self, alternate: char) -> Result<Wcs, WcsError> {
+        Wcs::with_alternate(self, Option::Some(alternate))
+    }
+
+    /// The letters of the alternate world coordinate systems of this header,
+    /// those with a `WCSAXESa` or `CTYPEia` keyword, in alphabetical order.
+    pub fn wcs_alternates(&self) -> Vec<char> {
+        let mut alternates: Vec<char> = self.keyword_records()
+            .filter_map(|record| match *record.keyword() {
+                Keyword::WCSAXESa(alternate) | Keyword::CTYPEia(_, alternate) => alternate,
+                _ => Option::None,
+            })
+            .collect();
+        alternates.sort();
+        alternates.dedup();
+        alternates
+    }
+}
+
+/// The celestial coordinates `(αp, δp)`, in degrees, of the native pole when
+/// the fiducial point, at native latitude `theta0`, is at `(α0, δ0)` and the
+/// celestial pole at native longitude `lonpole`. Of the two solutions for
+/// `δp` the one closest to `latpole` is taken, following WCS Paper II. `None`
+/// when there is no solution.
+fn native_pole(reference: (f64, f64), theta0: f64, lonpole: f64, latpole: f64) -> Option<(f64, f64)> {
+    let (alpha0, delta0) = reference;
+    if theta0 == 90f64 {
+        return Option::Some(reference)
+    }
+    let (sin_theta0, cos_theta0) = theta0.to_radians().sin_cos();
+    let (sin_delta0, cos_delta0) = delta0.to_radians().sin_cos();
+    let (sin_phi, cos_phi) = lonpole.to_radians().sin_cos();
+    let base = sin_theta0.atan2(cos_theta0 * cos_phi).to_degrees();
+    let ratio = sin_delta0 / (1f64 - (cos_theta0 * sin_phi).powi(2)).sqrt();
+    if ratio.abs() > 1f64 + 1e-12 {
+        return Option::None
+    }
+    let spread = ratio.clamp(-1f64, 1f64).acos().to_degrees();
+    let delta_p = [base + spread, base - spread].iter()
+        .cloned()
+        .filter(|delta| delta.abs() <= 90f64 + 1e-10)
+        .min_by(|a, b| (a - latpole).abs().partial_cmp(&(b - latpole).abs()).unwrap())?
+        .clamp(-90f64, 90f64);
+
+    let alpha_p = if cos_delta0.abs() < 1e-12 {
+        alpha0
+    } else if (delta_p - 90f64).abs() < 1e-10 {
+        alpha0 + lonpole - 180f64
+    } else if (delta_p + 90f64).abs() < 1e-10 {
+        alpha0 - lonpole
+    } else {
+        let (sin_delta_p, cos_delta_p) = delta_p.to_radians().sin_cos();
+        alpha0 - (sin_phi * cos_theta0 / cos_delta0)
+            .atan2((sin_theta0 - sin_delta_p * sin_delta0) / (cos_delta0 * cos_delta_p))
+            .to_degrees()
+    };
+    Option::Some((alpha_p, delta_p))
+}
+
+/// Whether `ctype` is a projected celestial longitude, `RA---TAN`, `GLON-TAN`
+/// or `HPLN-TAN` style, or latitude.
+fn is_celestial(ctype: &str, longitude: bool) -> bool {
+    if ctype.len() < 8 || ctype.get(4..5) != Option::Some("-") {
+        return false
+    }
+    let kind = &ctype[..4];
+    if longitude {
+        kind == "RA--" || &kind[1..] == "LON" || &kind[2..] == "LN"
+    } else {
+        kind == "DEC-" || &kind[1..] == "LAT" || &kind[2..] == "LT"
+    }
+}
+
+/// The inverse of the square `matrix`, by Gauss-Jordan elimination with
+/// partial pivoting. `None` when it is singular.
+fn invert(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut left: Vec<Vec<f64>> = matrix.to_vec();
+    let mut right: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1f64 } else { 0f64 }).collect()).collect();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|&a, &b| left[a][column].abs().partial_cmp(&left[b][column].abs()).unwrap())?;
+        if left[pivot][column].abs() < 1e-300 {
+            return Option::None
+        }
+        left.swap(column, pivot);
+        right.swap(column, pivot);
+        let scale = left[column][column];
+        for j in 0..n {
+            left[column][j] /= scale;
+            right[column][j] /= scale;
+        }
+        for row in 0..n {
+            if row != column {
+                let factor = left[row][column];
+                for j in 0..n {
+                    left[row][j] -= factor * left[column][j];
+                    right[row][j] -= factor * right[column][j];
+                }
+            }
+        }
+    }
+    Option::Some(right)
+}
+
+/// The text that ends the keywords of `alternate`.
+fn suffix(alternate: Option<char>) -> String {
+    alternate.map(String::from).unwrap_or_default()
+}
+
+fn keyword(name: &str) -> Keyword {
+    Keyword::from_str(name).expect("WCS keywords fit in a keyword record")
+}
+
+fn lookup<T, F>(header: &Header, name: &str, retrieve: F) -> Result<Option<T>, WcsError>
+    where F: Fn(&Header, &Keyword) -> Result<T, ValueRetrievalError> {
+    let keyword = keyword(name);
+    match retrieve(header, &keyword) {
+        Ok(value) => Ok(Option::Some(value)),
+        Err(ValueRetrievalError::KeywordNotPresent) => Ok(Option::None),
+        Err(error) => Err(WcsError::InvalidKeyword(keyword, error)),
+    }
+}
+
+fn real(header: &Header, name: &str) -> Result<Option<f64>, WcsError> {
+    lookup(header, name, |header, keyword| header.real_value_of(keyword))
+}
+
+fn integer(header: &Header, name: &str) -> Result<Option<i64>, WcsError> {
+    lookup(header, name, |header, keyword| header.integer_value_of(keyword))
+}
+
+fn string(header: &Header, name: &str) -> Result<Option<String>, WcsError> {
+    lookup(header, name, |header, keyword| header.character_string_value_of(keyword).map(|value| value.trim_end().to_string()))
+}
+
+fn reals(header: &Header, names: &[String], default: f64) -> Result<Vec<f64>, WcsError> {
+    names.iter().map(|name| real(header, name).map(|value| value.unwrap_or(default))).collect()
+}
+
+/// The linear transformation: `CDi_j` when any is present, otherwise `PCi_j`
+/// scaled by `CDELTi`, or the rotation of `CROTA2` when no `PCi_j` is present
+/// in the primary description.
+fn matrix(header: &Header, axes: usize, alternate: Option<char>) -> Result<Vec<Vec<f64>>, WcsError> {
+    let suffix = suffix(alternate);
+    let element = |name: &str, i: usize, j: usize| real(header, &format!("{}{}_{}{}", name, i + 1, j + 1, suffix));
+    let mut cd = vec!(vec!(0f64; axes); axes);
+    let mut pc = vec!(vec!(0f64; axes); axes);
+    let (mut has_cd, mut has_pc) = (false, false);
+    for i in 0..axes {
+        for j in 0..axes {
+            if let Option::Some(value) = element("CD", i, j)? {
+                cd[i][j] = value;
+                has_cd = true;
+            }
+            pc[i][j] = match element("PC", i, j)? {
+                Option::Some(value) => {
+                    has_pc = true;
+                    value
+                }
+                Option::None => if i == j { 1f64 } else { 0f64 },
+            };
+        }
+    }
+    if has_cd {
+        return Ok(cd)
+    }
+    let cdelt = reals(header, &(1..axes + 1).map(|i| format!("CDELT{}{}", i, suffix)).collect::<Vec<String>>(), 1f64)?;
+    if !has_pc && axes >= 2 && alternate.is_none() {
+        if let Option::Some(rotation) = real(header, "CROTA2")? {
+            let (sin, cos) = rotation.to_radians().sin_cos();
+            pc[0][0] = cos;
+            pc[0][1] = -sin * cdelt[1] / cdelt[0];
+            pc[1][0] = sin * cdelt[0] / cdelt[1];
+            pc[1][1] = cos;
+        }
+    }
+    Ok(pc.into_iter().zip(cdelt).map(|(row, scale)| row.into_iter().map(|m| m * scale).collect()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::parser::{fits, template};
+    use super::super::types::ValueRetrievalError;
+    use super::{keyword, BasicType, Projection, SpectralAlgorithm, SpectralType, WcsError};
+
+    const TAN: &str = "NAXIS = 2
+CTYPE1 = 'RA---TAN'
+CTYPE2 = 'DEC--TAN'
+CRPIX1 = 50
+CRPIX2 = 50
+CRVAL1 = 0.0
+CRVAL2 = 0.0
+CDELT1 = -2.777777777777778E-4
+CDELT2 = 2.777777777777778E-4";
+
+    #[test]
+    fn reference_pixel_should_map_to_the_reference_value() {
+        let header = template(TAN).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.projection(), Option::Some(Projection::Tan));
+        assert_eq!(wcs.celestial(), Option::Some((0, 1)));
+        let (ra, dec) = wcs.pixel_to_sky(50f64, 50f64).unwrap();
+        assert!(ra.abs() < 1e-12 || (ra - 360f64).abs() < 1e-12);
+        assert!(dec.abs() < 1e-12);
+    }
+
+    #[test]
+    fn tan_should_be_gnomonic() {
+        let header = template(TAN).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        let (ra, dec) = wcs.pixel_to_sky(50f64 - 3600f64, 50f64).unwrap();
+        assert!((ra - 1f64.to_radians().atan().to_degrees()).abs() < 1e-9);
+        assert!(dec.abs() < 1e-12);
+
+        let (ra, dec) = wcs.pixel_to_sky(50f64, 50f64 + 3600f64).unwrap();
+        assert!(ra.abs() < 1e-9 || (ra - 360f64).abs() < 1e-9);
+        assert!((dec - 1f64.to_radians().atan().to_degrees()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cd_should_take_precedence_over_cdelt() {
+        let header = template("NAXIS = 2\nCRPIX1 = 1\nCRPIX2 = 1\nCRVAL1 = 10\nCD1_2 = 2.0\nCD2_1 = 3.0\nCDELT1 = 100.0").unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.pixel_to_world(&[2f64, 3f64]).unwrap(), vec!(14f64, 3f64));
+        assert_eq!(wcs.pixel_to_world(&[2f64]), Err(WcsError::Dimensions(1)));
+    }
+
+    #[test]
+    fn sky_to_pixel_should_invert_pixel_to_sky() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let wcs = fits[2].header.wcs().unwrap();
+
+        for &(x, y) in &[(1f64, 1f64), (6f64, 6f64), (11.5f64, -3f64), (1083.384f64, 1112.5f64)] {
+            let (ra, dec) = wcs.pixel_to_sky(x, y).unwrap();
+            let (u, v) = wcs.sky_to_pixel(ra, dec).unwrap();
+            assert!((u - x).abs() < 1e-8);
+            assert!((v - y).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn world_to_pixel_should_reject_the_far_hemisphere_and_singular_matrices() {
+        let header = template(TAN).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.sky_to_pixel(180f64, 0f64), Err(WcsError::OutOfBounds));
+
+        let header = template("NAXIS = 2\nCD1_1 = 1.0\nCD1_2 = 2.0\nCD2_1 = 2.0\nCD2_2 = 4.0").unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.world_to_pixel(&[0f64, 0f64]), Err(WcsError::SingularMatrix));
+    }
+
+    #[test]
+    fn sip_should_distort_pixel_offsets() {
+        let sip = format!("{}\nA_ORDER = 2\nA_2_0 = 1.0E-4\nA_1_1 = 2.0E-5\nB_ORDER = 2\nB_0_2 = -3.0E-5", TAN.replace("TAN", "TAN-SIP"));
+        let header = template(&sip).unwrap();
+        let wcs = header.wcs().unwrap();
+        let linear = template(TAN).unwrap().wcs().unwrap();
+
+        let (ra, dec) = wcs.pixel_to_sky(150f64, 250f64).unwrap();
+        let (u, v) = (100f64 + 1e-4 * 100f64 * 100f64 + 2e-5 * 100f64 * 200f64, 200f64 - 3e-5 * 200f64 * 200f64);
+        let (expected_ra, expected_dec) = linear.pixel_to_sky(50f64 + u, 50f64 + v).unwrap();
+        assert!((ra - expected_ra).abs() < 1e-12);
+        assert!((dec - expected_dec).abs() < 1e-12);
+
+        let (x, y) = wcs.sky_to_pixel(ra, dec).unwrap();
+        assert!((x - 150f64).abs() < 1e-6);
+        assert!((y - 250f64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sip_should_need_its_orders() {
+        let text = TAN.replace("TAN", "TAN-SIP");
+        let header = template(&text).unwrap();
+
+        assert_eq!(header.wcs(), Err(WcsError::InvalidKeyword(keyword("A_ORDER"), ValueRetrievalError::KeywordNotPresent)));
+    }
+
+    #[test]
+    fn every_projection_should_round_trip_away_from_the_origin() {
+        for code in ["TAN", "SIN", "ARC", "STG", "ZEA", "CAR", "MER", "SFL", "AIT", "MOL"].iter() {
+            for &(ra, dec) in [(120f64, 35f64), (200f64, -60f64), (10f64, 0f64)].iter() {
+                let text = TAN.replace("TAN", code)
+                    .replace("CRVAL1 = 0.0", &format!("CRVAL1 = {}", ra))
+                    .replace("CRVAL2 = 0.0", &format!("CRVAL2 = {}", dec))
+                    .replace("E-4", "E-2");
+                let header = template(&text).unwrap();
+                let wcs = header.wcs().unwrap();
+
+                let (alpha, delta) = wcs.pixel_to_sky(50f64, 50f64).unwrap();
+                assert!((alpha - ra).abs() < 1e-9 && (delta - dec).abs() < 1e-9, "{} at {:?}: {:?}", code, (ra, dec), (alpha, delta));
+
+                let (alpha, delta) = wcs.pixel_to_sky(20f64, 90f64).unwrap();
+                let (x, y) = wcs.sky_to_pixel(alpha, delta).unwrap();
+                assert!((x - 20f64).abs() < 1e-6 && (y - 90f64).abs() < 1e-6, "{} at {:?}: {:?}", code, (ra, dec), (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn car_should_be_linear_at_the_equator() {
+        let text = TAN.replace("TAN", "CAR").replace("E-4", "E+0");
+        let header = template(&text).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.projection(), Option::Some(Projection::Car));
+        let (ra, dec) = wcs.pixel_to_sky(40f64, 60f64).unwrap();
+        assert!((ra - 27.77777777777778f64).abs() < 1e-9);
+        assert!((dec - 27.77777777777778f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tpv_should_distort_intermediate_coordinates() {
+        let tpv = format!("{}\nPV1_1 = 1.0\nPV1_4 = 0.01\nPV2_1 = 1.0\nPV2_2 = 0.02", TAN.replace("TAN", "TPV"));
+        let header = template(&tpv).unwrap();
+        let wcs = header.wcs().unwrap();
+        let linear = template(TAN).unwrap().wcs().unwrap();
+
+        assert_eq!(wcs.projection(), Option::Some(Projection::Tan));
+        let (ra, dec) = wcs.pixel_to_sky(50f64 - 360f64, 50f64 + 720f64).unwrap();
+        let (x, y) = (0.1f64, 0.2f64);
+        let (xi, eta) = (x + 0.01f64 * x * x, y + 0.02f64 * x);
+        let (expected_ra, expected_dec) = linear.pixel_to_sky(50f64 - xi * 3600f64, 50f64 + eta * 3600f64).unwrap();
+        assert!((ra - expected_ra).abs() < 1e-12);
+        assert!((dec - expected_dec).abs() < 1e-12);
+
+        let (x, y) = wcs.sky_to_pixel(ra, dec).unwrap();
+        assert!((x - (50f64 - 360f64)).abs() < 1e-6);
+        assert!((y - (50f64 + 720f64)).abs() < 1e-6);
+    }
+
+    const CUBE: &str = "NAXIS = 3
+CTYPE1 = 'RA---TAN'
+CTYPE2 = 'DEC--TAN'
+CTYPE3 = 'FREQ'
+CRPIX1 = 50
+CRPIX2 = 50
+CRPIX3 = 1
+CRVAL1 = 0.0
+CRVAL2 = 0.0
+CRVAL3 = 1.4E9
+CDELT1 = -2.777777777777778E-4
+CDELT2 = 2.777777777777778E-4
+CDELT3 = 1.0E5";
+
+    const C: f64 = 299_792_458f64;
+
+    #[test]
+    fn linear_spectral_axes_should_convert_channels() {
+        let header = template(CUBE).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.spectral().map(|spectral| (spectral.axis, spectral.kind)), Option::Some((2, SpectralType::Frequency)));
+        assert!((wcs.channel_to_frequency(11f64).unwrap() - 1.401e9f64).abs() < 1e-3);
+        assert!((wcs.channel_to_wavelength(11f64).unwrap() - C / 1.401e9f64).abs() < 1e-15);
+        assert_eq!(wcs.celestial(), Option::Some((0, 1)));
+    }
+
+    #[test]
+    fn wavelength_axes_linear_in_frequency_should_follow_the_frequency() {
+        let text = CUBE.replace("'FREQ'", "'WAVE-F2W'\nCUNIT3 = 'nm'").replace("1.4E9", "500.0").replace("1.0E5", "0.01");
+        let header = template(&text).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        assert_eq!(wcs.spectral().unwrap().algorithm, SpectralAlgorithm::NonLinear(BasicType::Frequency));
+        let world = wcs.pixel_to_world(&[50f64, 50f64, 101f64]).unwrap();
+        let frequency = C / 500e-9f64 - 1e-9f64 * C / (500e-9f64 * 500e-9f64);
+        assert!((world[2] - C / frequency * 1e9f64).abs() < 1e-9);
+        assert!((wcs.channel_to_frequency(101f64).unwrap() / frequency - 1f64).abs() < 1e-12);
+
+        let pixel = wcs.world_to_pixel(&world).unwrap();
+        assert!((pixel[2] - 101f64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn velocity_axes_should_need_a_rest_frequency() {
+        let text = CUBE.replace("'FREQ'", "'VRAD'\nCUNIT3 = 'km/s'").replace("1.4E9", "0.0").replace("1.0E5", "1.0");
+        let header = template(&text).unwrap();
+        assert_eq!(header.wcs().unwrap().channel_to_frequency(11f64), Err(WcsError::RestFrequency));
+
+        let text = format!("{}\nRESTFRQ = 1.4204057518E9", text);
+        let header = template(&text).unwrap();
+        let frequency = header.wcs().unwrap().channel_to_frequency(11f64).unwrap();
+        assert!((frequency - 1.4204057518e9f64 * (1f64 - 1e4f64 / C)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn logarithmic_spectral_axes_should_be_exponential() {
+        let text = CUBE.replace("'FREQ'", "'WAVE-LOG'").replace("1.4E9", "5.0E-7").replace("1.0E5", "1.0E-9");
+        let header = template(&text).unwrap();
+        let wcs = header.wcs().unwrap();
+
+        let world = wcs.pixel_to_world(&[50f64, 50f64, 11f64]).unwrap();
+        assert!((world[2] - 5e-7f64 * (1e-8f64 / 5e-7f64).exp()).abs() < 1e-18);
+        assert!((wcs.world_to_pixel(&world).unwrap()[2] - 11f64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unknown_spectral_algorithms_should_be_rejected() {
+        let text = CUBE.replace("'FREQ'", "'FREQ-TAB'");
+        let header = template(&text).unwrap();
+
+        assert_eq!(header.wcs(), Err(WcsError::UnsupportedSpectralAlgorithm("TAB".to_string())));
+        assert_eq!(template(TAN).unwrap().wcs().unwrap().channel_to_frequency(1f64), Err(WcsError::NotSpectral));
+    }
+
+    #[test]
+    fn alternate_descriptions_should_be_selectable() {
+        let text = format!("{}
+WCSNAMEA = 'PHYSICAL'
+CTYPE1A = 'X'
+CTYPE2A = 'Y'
+CRPIX1A = 1
+CRPIX2A = 1
+CRVAL1A = 100.0
+CRVAL2A = 200.0
+CTYPE1B = 'RA---CAR'
+CTYPE2B = 'DEC--CAR'
+CRPIX1B = 10
+CRPIX2B = 10
+CRVAL1B = 30.0
+CRVAL2B = 0.0
+PC1_2B = 0.0", TAN);
+        let header = template(&text).unwrap();
+
+        assert_eq!(header.wcs_alternates(), vec!('A', 'B'));
+        let physical = header.wcs_alternate('A').unwrap();
+        assert_eq!(physical.alternate, Option::Some('A'));
+        assert_eq!(physical.celestial(), Option::None);
+        assert_eq!(physical.pixel_to_world(&[3f64, 4f64]).unwrap(), vec!(102f64, 203f64));
+
+        let sky = header.wcs_alternate('B').unwrap();
+        assert_eq!(sky.projection(), Option::Some(Projection::Car));
+        let (ra, dec) = sky.pixel_to_sky(12f64, 10f64).unwrap();
+        assert!((ra - 32f64).abs() < 1e-9 && dec.abs() < 1e-9);
+        let (ra, dec) = header.wcs().unwrap().pixel_to_sky(50f64, 50f64).unwrap();
+        assert!((ra - 360f64 * (ra / 360f64).round()).abs() < 1e-9 && dec.abs() < 1e-9);
+        assert_eq!(header.wcs_alternate('c'), Err(WcsError::InvalidAlternate('c')));
+    }
+
+    #[test]
+    fn celestial_axes_should_come_in_pairs() {
+        let text = TAN.replace("DEC--TAN", "DEC");
+        let header = template(&text).unwrap();
+
+        assert_eq!(header.wcs(), Err(WcsError::UnpairedCelestialAxis));
+    }
+
+    #[test]
+    fn aperture_should_match_the_gnomonic_formulas() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let (ra, dec) = fits[2].header.wcs().unwrap().pixel_to_sky(6f64, 6f64).unwrap();
+
+        assert!((ra - 349.491480246753f64).abs() < 1e-9);
+        assert!((dec - -7.193808222297832f64).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,303 @@
+//! Celestial projections of WCS Paper II, between intermediate world
+//! coordinates and native spherical coordinates.
+
+use std::f64::consts::PI;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+/// A projection of the sphere onto the plane of intermediate world coordinates,
+/// named by the last three characters of the celestial `CTYPEi`. Projections
+/// take the defaults of their `PVi_m` parameters.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Projection {
+    /// Gnomonic, `TAN`.
+    Tan,
+    /// Orthographic, `SIN`.
+    Sin,
+    /// Zenithal equidistant, `ARC`.
+    Arc,
+    /// Stereographic, `STG`.
+    Stg,
+    /// Zenithal equal area, `ZEA`.
+    Zea,
+    /// Plate carrée, `CAR`.
+    Car,
+    /// Mercator, `MER`.
+    Mer,
+    /// Sanson-Flamsteed, `SFL`.
+    Sfl,
+    /// Hammer-Aitoff, `AIT`.
+    Ait,
+    /// Mollweide, `MOL`.
+    Mol,
+}
+
+/// The degrees in a radian, the radius of the sphere of the projections.
+const R: f64 = 180f64 / PI;
+
+impl Projection {
+    /// The native latitude of the fiducial point, `θ0`, in degrees: 90 for the
+    /// zenithal projections and 0 for the others.
+    pub fn theta0(&self) -> f64 {
+        match *self {
+            Projection::Tan | Projection::Sin | Projection::Arc | Projection::Stg | Projection::Zea => 90f64,
+            Projection::Car | Projection::Mer | Projection::Sfl | Projection::Ait | Projection::Mol => 0f64,
+        }
+    }
+
+    /// The native longitude and latitude `(φ, θ)`, in degrees, of the
+    /// intermediate world coordinates `(x, y)`, in degrees. `None` when the
+    /// point lies outside of the projection.
+    pub fn deproject(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let r = x.hypot(y);
+        let phi = if r == 0f64 { 0f64 } else { x.atan2(-y).to_degrees() };
+        match *self {
+            Projection::Tan => Option::Some((phi, R.atan2(r).to_degrees())),
+            Projection::Sin => {
+                if r > R {
+                    return Option::None
+                }
+                Option::Some((phi, (r / R).acos().to_degrees()))
+            }
+            Projection::Arc => {
+                if r > 180f64 {
+                    return Option::None
+                }
+                Option::Some((phi, 90f64 - r))
+            }
+            Projection::Stg => Option::Some((phi, 90f64 - 2f64 * (r / (2f64 * R)).atan().to_degrees())),
+            Projection::Zea => {
+                if r > 2f64 * R {
+                    return Option::None
+                }
+                Option::Some((phi, 90f64 - 2f64 * (r / (2f64 * R)).asin().to_degrees()))
+            }
+            Projection::Car => within(x, y),
+            Projection::Mer => within(x, (2f64 * (y / R).exp().atan()).to_degrees() - 90f64),
+            Projection::Sfl => {
+                let cos_theta = y.to_radians().cos();
+                if cos_theta <= 0f64 {
+                    return if x == 0f64 && y.abs() == 90f64 { Option::Some((0f64, y)) } else { Option::None }
+                }
+                within(x / cos_theta, y)
+            }
+            Projection::Ait => {
+                let zz = 1f64 - (x / (4f64 * R)).powi(2) - (y / (2f64 * R)).powi(2);
+                if zz < 0.5f64 {
+                    return Option::None
+                }
+                let z = zz.sqrt();
+                let phi = 2f64 * (x * z / (2f64 * R)).atan2(2f64 * zz - 1f64).to_degrees();
+                Option::Some((phi, (y * z / R).clamp(-1f64, 1f64).asin().to_degrees()))
+            }
+            Projection::Mol => {
+                let s = y / (2f64.sqrt() * R);
+                if s.abs() > 1f64 {
+                    return Option::None
+                }
+                let gamma = s.asin();
+                let theta = ((2f64 * gamma + (2f64 * gamma).sin()) / PI).clamp(-1f64, 1f64).asin().to_degrees();
+                if s.abs() == 1f64 {
+                    return if x == 0f64 { Option::Some((0f64, theta)) } else { Option::None }
+                }
+                within(PI * x / (2f64 * 2f64.sqrt() * gamma.cos()), theta)
+            }
+        }
+    }
+
+    /// The intermediate world coordinates `(x, y)`, in degrees, of the native
+    /// longitude and latitude `(φ, θ)`, in degrees. `None` when the point can
+    /// not be projected.
+    pub fn project(&self, phi: f64, theta: f64) -> Option<(f64, f64)> {
+        let (sin_phi, cos_phi) = phi.to_radians().sin_cos();
+        let zenithal = |r: f64| Option::Some((r * sin_phi, -r * cos_phi));
+        // The cylindrical and pseudo-cylindrical projections cut the sphere at
+        // φ = 180.
+        let phi = 180f64 - (180f64 - phi).rem_euclid(360f64);
+        match *self {
+            Projection::Tan => {
+                if theta <= 0f64 {
+                    return Option::None
+                }
+                zenithal(R / theta.to_radians().tan())
+            }
+            Projection::Sin => {
+                if theta < 0f64 {
+                    return Option::None
+                }
+                zenithal(R * theta.to_radians().cos())
+            }
+            Projection::Arc => zenithal(90f64 - theta),
+            Projection::Stg => {
+                if theta <= -90f64 {
+                    return Option::None
+                }
+                zenithal(2f64 * R * ((90f64 - theta) / 2f64).to_radians().tan())
+            }
+            Projection::Zea => zenithal(2f64 * R * ((90f64 - theta) / 2f64).to_radians().sin()),
+            Projection::Car => Option::Some((phi, theta)),
+            Projection::Mer => {
+                if theta.abs() >= 90f64 {
+                    return Option::None
+                }
+                Option::Some((phi, R * ((90f64 + theta) / 2f64).to_radians().tan().ln()))
+            }
+            Projection::Sfl => Option::Some((phi * theta.to_radians().cos(), theta)),
+            Projection::Ait => {
+                let (sin_theta, cos_theta) = theta.to_radians().sin_cos();
+                let (sin_half, cos_half) = (phi / 2f64).to_radians().sin_cos();
+                let gamma = R * (2f64 / (1f64 + cos_theta * cos_half)).sqrt();
+                Option::Some((2f64 * gamma * cos_theta * sin_half, gamma * sin_theta))
+            }
+            Projection::Mol => {
+                let gamma = mollweide(theta.to_radians());
+                Option::Some((2f64 * 2f64.sqrt() / PI * phi * gamma.cos(), 2f64.sqrt() * R * gamma.sin()))
+            }
+        }
+    }
+}
+
+/// `(φ, θ)` when both lie within the bounds of the native sphere.
+fn within(phi: f64, theta: f64) -> Option<(f64, f64)> {
+    if phi.abs() > 180f64 + 1e-10 || theta.abs() > 90f64 + 1e-10 {
+        return Option::None
+    }
+    Option::Some((phi.clamp(-180f64, 180f64), theta.clamp(-90f64, 90f64)))
+}
+
+/// The auxiliary angle `γ` of the Mollweide projection, which solves
+/// `2γ + sin 2γ = π sin θ`, by Newton iteration.
+fn mollweide(theta: f64) -> f64 {
+    let target = PI * theta.sin();
+    let mut gamma = theta;
+    for _ in 0..50 {
+        let derivative = 2f64 + 2f64 * (2f64 * gamma).cos();
+        if derivative < 1e-12 {
+            break
+        }
+        let step = (2f64 * gamma + (2f64 * gamma).sin() - target) / derivative;
+        gamma -= step;
+        if step.abs() < 1e-15 {
+            break
+        }
+    }
+    gamma
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code {
+            "TAN" => Ok(Projection::Tan),
+            "SIN" => Ok(Projection::Sin),
+            "ARC" => Ok(Projection::Arc),
+            "STG" => Ok(Projection::Stg),
+            "ZEA" => Ok(Projection::Zea),
+            "CAR" => Ok(Projection::Car),
+            "MER" => Ok(Projection::Mer),
+            "SFL" => Ok(Projection::Sfl),
+            "AIT" => Ok(Projection::Ait),
+            "MOL" => Ok(Projection::Mol),
+            _ => Err(code.to_string()),
+        }
+    }
+}
+
+impl Display for Projection {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let code = match *self {
+            Projection::Tan => "TAN",
+            Projection::Sin => "SIN",
+            Projection::Arc => "ARC",
+            Projection::Stg => "STG",
+            Projection::Zea => "ZEA",
+            Projection::Car => "CAR",
+            Projection::Mer => "MER",
+            Projection::Sfl => "SFL",
+            Projection::Ait => "AIT",
+            Projection::Mol => "MOL",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::{Projection, R};
+
+    const ALL: [Projection; 10] = [
+        Projection::Tan, Projection::Sin, Projection::Arc, Projection::Stg, Projection::Zea,
+        Projection::Car, Projection::Mer, Projection::Sfl, Projection::Ait, Projection::Mol,
+    ];
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!((actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn tan_should_put_the_reference_point_at_the_pole() {
+        assert_eq!(Projection::Tan.deproject(0f64, 0f64), Option::Some((0f64, 90f64)));
+
+        let (phi, theta) = Projection::Tan.deproject(1f64, 0f64).unwrap();
+
+        assert!((phi - 90f64).abs() < 1e-12);
+        assert!((theta - (90f64 - (1f64.to_radians()).atan().to_degrees())).abs() < 1e-12);
+        assert_eq!(Projection::from_str("TAN"), Ok(Projection::Tan));
+    }
+
+    #[test]
+    fn tan_should_invert() {
+        let (phi, theta) = Projection::Tan.deproject(0.3f64, -1.2f64).unwrap();
+        let (x, y) = Projection::Tan.project(phi, theta).unwrap();
+
+        assert!((x - 0.3f64).abs() < 1e-12);
+        assert!((y - -1.2f64).abs() < 1e-12);
+        assert_eq!(Projection::Tan.project(0f64, -10f64), Option::None);
+    }
+
+    #[test]
+    fn projections_should_match_reference_values() {
+        let sqrt2 = 2f64.sqrt();
+        let equator = (90f64, 0f64);
+
+        assert_close(Projection::Sin.project(equator.0, equator.1).unwrap(), (R, 0f64));
+        assert_close(Projection::Arc.project(equator.0, equator.1).unwrap(), (90f64, 0f64));
+        assert_close(Projection::Stg.project(equator.0, equator.1).unwrap(), (2f64 * R, 0f64));
+        assert_close(Projection::Zea.project(equator.0, equator.1).unwrap(), (sqrt2 * R, 0f64));
+        assert_close(Projection::Car.project(30f64, 40f64).unwrap(), (30f64, 40f64));
+        assert_close(Projection::Mer.project(0f64, 45f64).unwrap(), (0f64, R * (1f64 + sqrt2).ln()));
+        assert_close(Projection::Sfl.project(90f64, 60f64).unwrap(), (45f64, 60f64));
+        assert_close(Projection::Ait.project(180f64, 0f64).unwrap(), (2f64 * sqrt2 * R, 0f64));
+        assert_close(Projection::Ait.project(0f64, 90f64).unwrap(), (0f64, sqrt2 * R));
+        assert_close(Projection::Mol.project(180f64, 0f64).unwrap(), (2f64 * sqrt2 * R, 0f64));
+        assert_close(Projection::Mol.project(0f64, 90f64).unwrap(), (0f64, sqrt2 * R));
+    }
+
+    #[test]
+    fn projections_should_round_trip() {
+        let points = [(0f64, 60f64), (45f64, 30f64), (-120f64, 75f64), (170f64, 10f64), (-30f64, 85f64)];
+        for projection in ALL.iter() {
+            for &(phi, theta) in points.iter() {
+                let theta = if projection.theta0() == 0f64 { theta - 50f64 } else { theta };
+                let (x, y) = projection.project(phi, theta).unwrap();
+                let (phi2, theta2) = projection.deproject(x, y).unwrap();
+
+                assert_close((phi2, theta2), (phi, theta));
+            }
+            assert_eq!(Projection::from_str(&projection.to_string()), Ok(*projection));
+        }
+    }
+
+    #[test]
+    fn points_beyond_the_projection_should_not_deproject() {
+        assert_eq!(Projection::Sin.deproject(60f64, 0f64), Option::None);
+        assert_eq!(Projection::Zea.deproject(120f64, 0f64), Option::None);
+        assert_eq!(Projection::Car.deproject(0f64, 91f64), Option::None);
+        assert_eq!(Projection::Ait.deproject(200f64, 0f64), Option::None);
+        assert_eq!(Projection::Mol.deproject(170f64, 0f64), Option::None);
+        assert_eq!(Projection::Sin.project(0f64, -10f64), Option::None);
+    }
+}
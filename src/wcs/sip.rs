@@ -0,0 +1,77 @@
+//! Simple Imaging Polynomial distortion, the `-SIP` convention of Shupe et al.
+//! (2005).
+
+use super::super::types::{Header, ValueRetrievalError};
+use super::{integer, keyword, real, WcsError};
+
+/// The polynomials that distort pixel offsets from `CRPIX` before the linear
+/// transformation, and optionally their inverses.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sip {
+    /// The terms `(p, q, A_p_q)` of the distortion of the first axis.
+    pub a: Vec<(i32, i32, f64)>,
+    /// The terms `(p, q, B_p_q)` of the distortion of the second axis.
+    pub b: Vec<(i32, i32, f64)>,
+    /// The terms `(p, q, AP_p_q)` of the inverse distortion of the first axis.
+    pub ap: Vec<(i32, i32, f64)>,
+    /// The terms `(p, q, BP_p_q)` of the inverse distortion of the second axis.
+    pub bp: Vec<(i32, i32, f64)>,
+}
+
+impl Sip {
+    /// Read the SIP coefficients of `header`. `A_ORDER` and `B_ORDER` are
+    /// required, `AP_ORDER` and `BP_ORDER` are optional.
+    pub fn new(header: &Header) -> Result<Sip, WcsError> {
+        Ok(Sip {
+            a: terms(header, "A", true)?,
+            b: terms(header, "B", true)?,
+            ap: terms(header, "AP", false)?,
+            bp: terms(header, "BP", false)?,
+        })
+    }
+
+    /// The distorted offsets `(u + f(u, v), v + g(u, v))` of the pixel offsets
+    /// `(u, v)`.
+    pub fn distort(&self, u: f64, v: f64) -> (f64, f64) {
+        (u + evaluate(&self.a, u, v), v + evaluate(&self.b, u, v))
+    }
+
+    /// The pixel offsets whose distortion is `(u, v)`.
+    ///
+    /// The inverse polynomials give a first estimate when present, which is
+    /// refined by fixed point iteration of `distort`.
+    pub fn undistort(&self, u: f64, v: f64) -> (f64, f64) {
+        let (mut x, mut y) = (u + evaluate(&self.ap, u, v), v + evaluate(&self.bp, u, v));
+        for _ in 0..100 {
+            let (next_x, next_y) = (u - evaluate(&self.a, x, y), v - evaluate(&self.b, x, y));
+            let change = (next_x - x).abs().max((next_y - y).abs());
+            x = next_x;
+            y = next_y;
+            if change < 1e-12 {
+                break
+            }
+        }
+        (x, y)
+    }
+}
+
+fn evaluate(terms: &[(i32, i32, f64)], u: f64, v: f64) -> f64 {
+    terms.iter().map(|&(p, q, coefficient)| coefficient * u.powi(p) * v.powi(q)).sum()
+}
+
+fn terms(header: &Header, name: &str, required: bool) -> Result<Vec<(i32, i32, f64)>, WcsError> {
+    let order = match integer(header, &format!("{}_ORDER", name))? {
+        Option::Some(order) => order as i32,
+        Option::None if required => return Err(WcsError::InvalidKeyword(keyword(&format!("{}_ORDER", name)), ValueRetrievalError::KeywordNotPresent)),
+        Option::None => return Ok(vec!()),
+    };
+    let mut terms = vec!();
+    for p in 0..order + 1 {
+        for q in 0..order + 1 - p {
+            if let Option::Some(coefficient) = real(header, &format!("{}_{}_{}", name, p, q))? {
+                terms.push((p, q, coefficient));
+            }
+        }
+    }
+    Ok(terms)
+}
@@ -0,0 +1,336 @@
+//! Spectral axes of WCS Paper III, between intermediate world coordinates and
+//! frequency, wavelength, velocity and the other spectral coordinates.
+//!
+//! Conversions work in SI units, spectral coordinates are in the unit of the
+//! axis, `CUNITi`.
+
+use std::str::FromStr;
+use super::super::types::Header;
+use super::{real, suffix, WcsError};
+
+/// The speed of light, in m/s.
+const C: f64 = 299_792_458f64;
+/// The Planck constant, in J s.
+const H: f64 = 6.626_070_15e-34;
+
+/// A spectral coordinate, named by the first four characters of `CTYPEi`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpectralType {
+    /// Frequency, `FREQ`.
+    Frequency,
+    /// Energy, `ENER`.
+    Energy,
+    /// Wavenumber, `WAVN`.
+    Wavenumber,
+    /// Radio velocity, `VRAD`.
+    RadioVelocity,
+    /// Vacuum wavelength, `WAVE`.
+    Wavelength,
+    /// Optical velocity, `VOPT`.
+    OpticalVelocity,
+    /// Redshift, `ZOPT`.
+    Redshift,
+    /// Air wavelength, `AWAV`.
+    AirWavelength,
+    /// Apparent radial velocity, `VELO`.
+    ApparentVelocity,
+    /// Velocity as a fraction of the speed of light, `BETA`.
+    Beta,
+}
+
+impl SpectralType {
+    /// The basic type this coordinate is derived from.
+    pub fn basic(&self) -> BasicType {
+        match *self {
+            SpectralType::Frequency | SpectralType::Energy | SpectralType::Wavenumber | SpectralType::RadioVelocity => BasicType::Frequency,
+            SpectralType::Wavelength | SpectralType::OpticalVelocity | SpectralType::Redshift => BasicType::Wavelength,
+            SpectralType::AirWavelength => BasicType::AirWavelength,
+            SpectralType::ApparentVelocity | SpectralType::Beta => BasicType::Velocity,
+        }
+    }
+
+    /// The basic coordinate of `value`, in SI units.
+    fn basic_value(self, value: f64, rest_frequency: Option<f64>) -> Result<f64, WcsError> {
+        Ok(match self {
+            SpectralType::Frequency | SpectralType::Wavelength | SpectralType::AirWavelength | SpectralType::ApparentVelocity => value,
+            SpectralType::Energy => value / H,
+            SpectralType::Wavenumber => value * C,
+            SpectralType::RadioVelocity => rest(rest_frequency)? * (1f64 - value / C),
+            SpectralType::OpticalVelocity => C / rest(rest_frequency)? * (1f64 + value / C),
+            SpectralType::Redshift => C / rest(rest_frequency)? * (1f64 + value),
+            SpectralType::Beta => value * C,
+        })
+    }
+
+    /// The coordinate of the basic coordinate `value`, in SI units.
+    fn value_of_basic(self, value: f64, rest_frequency: Option<f64>) -> Result<f64, WcsError> {
+        Ok(match self {
+            SpectralType::Frequency | SpectralType::Wavelength | SpectralType::AirWavelength | SpectralType::ApparentVelocity => value,
+            SpectralType::Energy => value * H,
+            SpectralType::Wavenumber => value / C,
+            SpectralType::RadioVelocity => C * (1f64 - value / rest(rest_frequency)?),
+            SpectralType::OpticalVelocity => C * (value * rest(rest_frequency)? / C - 1f64),
+            SpectralType::Redshift => value * rest(rest_frequency)? / C - 1f64,
+            SpectralType::Beta => value / C,
+        })
+    }
+}
+
+impl FromStr for SpectralType {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code {
+            "FREQ" => Ok(SpectralType::Frequency),
+            "ENER" => Ok(SpectralType::Energy),
+            "WAVN" => Ok(SpectralType::Wavenumber),
+            "VRAD" => Ok(SpectralType::RadioVelocity),
+            "WAVE" => Ok(SpectralType::Wavelength),
+            "VOPT" => Ok(SpectralType::OpticalVelocity),
+            "ZOPT" => Ok(SpectralType::Redshift),
+            "AWAV" => Ok(SpectralType::AirWavelength),
+            "VELO" => Ok(SpectralType::ApparentVelocity),
+            "BETA" => Ok(SpectralType::Beta),
+            _ => Err(code.to_string()),
+        }
+    }
+}
+
+/// The basic spectral coordinates, which the others are derived from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BasicType {
+    /// Frequency, `F`.
+    Frequency,
+    /// Vacuum wavelength, `W`.
+    Wavelength,
+    /// Air wavelength, `A`.
+    AirWavelength,
+    /// Apparent radial velocity, `V`.
+    Velocity,
+}
+
+impl BasicType {
+    fn from_letter(letter: char) -> Option<BasicType> {
+        match letter {
+            'F' => Option::Some(BasicType::Frequency),
+            'W' => Option::Some(BasicType::Wavelength),
+            'A' => Option::Some(BasicType::AirWavelength),
+            'V' => Option::Some(BasicType::Velocity),
+            _ => Option::None,
+        }
+    }
+
+    /// The frequency of the basic coordinate `value`, in SI units.
+    fn frequency_of(self, value: f64, rest_frequency: Option<f64>) -> Result<f64, WcsError> {
+        Ok(match self {
+            BasicType::Frequency => value,
+            BasicType::Wavelength => C / value,
+            BasicType::AirWavelength => C / (value * refractive_index(value)),
+            BasicType::Velocity => rest(rest_frequency)? * ((C - value) / (C + value)).sqrt(),
+        })
+    }
+
+    /// The basic coordinate of the frequency `value`, in SI units.
+    fn value_of_frequency(self, value: f64, rest_frequency: Option<f64>) -> Result<f64, WcsError> {
+        Ok(match self {
+            BasicType::Frequency => value,
+            BasicType::Wavelength => C / value,
+            BasicType::AirWavelength => {
+                let vacuum = C / value;
+                let mut air = vacuum;
+                for _ in 0..10 {
+                    air = vacuum / refractive_index(air);
+                }
+                air
+            }
+            BasicType::Velocity => {
+                let rest = rest(rest_frequency)?;
+                C * (rest * rest - value * value) / (rest * rest + value * value)
+            }
+        })
+    }
+}
+
+/// How the spectral coordinate follows from the intermediate world coordinate,
+/// the algorithm code of `CTYPEi`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpectralAlgorithm {
+    /// Linear in the spectral coordinate, no algorithm code.
+    Linear,
+    /// Logarithmic, `LOG`.
+    Logarithmic,
+    /// Linear in the given basic coordinate, the `X` of an `X2P` code.
+    NonLinear(BasicType),
+}
+
+/// A spectral axis of a `Wcs`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spectral {
+    /// The index of the axis, starting at 0.
+    pub axis: usize,
+    /// The spectral coordinate of the axis.
+    pub kind: SpectralType,
+    /// How the axis is sampled.
+    pub algorithm: SpectralAlgorithm,
+    /// The rest frequency in Hz, `RESTFRQ` or the rest wavelength `RESTWAV`
+    /// converted to frequency.
+    pub rest_frequency: Option<f64>,
+    /// The SI value of a unit of the axis.
+    scale: f64,
+    /// The spectral coordinate of the reference point, `CRVALi`.
+    reference: f64,
+    /// The coordinate along which the axis is linear at the reference point,
+    /// and its derivative to the spectral coordinate there.
+    linear: (f64, f64),
+}
+
+impl Spectral {
+    /// Read the spectral axis with index `axis`, whose type is `ctype`, in
+    /// `cunit`, of the description with letter `alternate`. `None` when
+    /// `ctype` is not spectral.
+    pub fn new(header: &Header, axis: usize, ctype: &str, cunit: &str, crval: f64, alternate: Option<char>) -> Result<Option<Spectral>, WcsError> {
+        let kind = match ctype.get(..4).map(SpectralType::from_str) {
+            Option::Some(Ok(kind)) if ctype.len() == 4 || ctype.get(4..5) == Option::Some("-") => kind,
+            _ => return Ok(Option::None),
+        };
+        let code = ctype.get(5..).unwrap_or("");
+        let algorithm = match code {
+            "" => SpectralAlgorithm::Linear,
+            "LOG" => SpectralAlgorithm::Logarithmic,
+            _ => {
+                let letters: Vec<char> = code.chars().collect();
+                match (letters.len(), letters.get(1)) {
+                    (3, Option::Some(&'2')) if BasicType::from_letter(letters[2]) == Option::Some(kind.basic()) => {
+                        let basic = BasicType::from_letter(letters[0])
+                            .ok_or_else(|| WcsError::UnsupportedSpectralAlgorithm(code.to_string()))?;
+                        SpectralAlgorithm::NonLinear(basic)
+                    }
+                    _ => return Err(WcsError::UnsupportedSpectralAlgorithm(code.to_string())),
+                }
+            }
+        };
+        let scale = unit(cunit).ok_or_else(|| WcsError::UnsupportedUnit(cunit.to_string()))?;
+        let suffix = suffix(alternate);
+        let rest_frequency = match real(header, &format!("RESTFRQ{}", suffix))? {
+            Option::Some(frequency) => Option::Some(frequency),
+            Option::None => match real(header, "RESTFREQ")? {
+                Option::Some(frequency) if alternate.is_none() => Option::Some(frequency),
+                _ => real(header, &format!("RESTWAV{}", suffix))?.map(|wavelength| C / wavelength),
+            },
+        };
+
+        let mut spectral = Spectral { axis, kind, algorithm, rest_frequency, scale, reference: crval, linear: (0f64, 1f64) };
+        if let SpectralAlgorithm::NonLinear(basic) = algorithm {
+            let step = if crval == 0f64 { 1e-6 } else { crval.abs() * 1e-6 };
+            let derivative = (spectral.to_linear(basic, crval + step)? - spectral.to_linear(basic, crval - step)?) / (2f64 * step);
+            spectral.linear = (spectral.to_linear(basic, crval)?, derivative);
+        }
+        Ok(Option::Some(spectral))
+    }
+
+    /// The spectral coordinate of the intermediate world coordinate `w`.
+    pub fn world(&self, w: f64) -> Result<f64, WcsError> {
+        match self.algorithm {
+            SpectralAlgorithm::Linear => Ok(self.reference + w),
+            SpectralAlgorithm::Logarithmic => Ok(self.reference * (w / self.reference).exp()),
+            SpectralAlgorithm::NonLinear(basic) => {
+                let frequency = basic.frequency_of(self.linear.0 + w * self.linear.1, self.rest_frequency)?;
+                self.value_of_frequency(frequency)
+            }
+        }
+    }
+
+    /// The intermediate world coordinate of the spectral coordinate `value`,
+    /// the inverse of `world`.
+    pub fn intermediate(&self, value: f64) -> Result<f64, WcsError> {
+        match self.algorithm {
+            SpectralAlgorithm::Linear => Ok(value - self.reference),
+            SpectralAlgorithm::Logarithmic => {
+                if value / self.reference <= 0f64 {
+                    return Err(WcsError::OutOfBounds)
+                }
+                Ok(self.reference * (value / self.reference).ln())
+            }
+            SpectralAlgorithm::NonLinear(basic) => Ok((self.to_linear(basic, value)? - self.linear.0) / self.linear.1),
+        }
+    }
+
+    /// The frequency in Hz of the spectral coordinate `value`.
+    pub fn frequency(&self, value: f64) -> Result<f64, WcsError> {
+        let basic = self.kind.basic_value(value * self.scale, self.rest_frequency)?;
+        self.kind.basic().frequency_of(basic, self.rest_frequency)
+    }
+
+    /// The vacuum wavelength in m of the spectral coordinate `value`.
+    pub fn wavelength(&self, value: f64) -> Result<f64, WcsError> {
+        Ok(C / self.frequency(value)?)
+    }
+
+    fn value_of_frequency(&self, frequency: f64) -> Result<f64, WcsError> {
+        let basic = self.kind.basic().value_of_frequency(frequency, self.rest_frequency)?;
+        Ok(self.kind.value_of_basic(basic, self.rest_frequency)? / self.scale)
+    }
+
+    /// The coordinate of `basic` type, in SI units, of the spectral coordinate
+    /// `value`.
+    fn to_linear(&self, basic: BasicType, value: f64) -> Result<f64, WcsError> {
+        basic.value_of_frequency(self.frequency(value)?, self.rest_frequency)
+    }
+}
+
+fn rest(rest_frequency: Option<f64>) -> Result<f64, WcsError> {
+    rest_frequency.ok_or(WcsError::RestFrequency)
+}
+
+/// The refractive index of air at the air wavelength `wavelength`, in m, from
+/// WCS Paper III.
+fn refractive_index(wavelength: f64) -> f64 {
+    let inverse_square = (1e-6 / wavelength).powi(2);
+    1f64 + 1e-6 * (287.6155f64 + 1.62887f64 * inverse_square + 0.01360f64 * inverse_square * inverse_square)
+}
+
+/// The SI value of the spectral unit `name`, `None` when it is not known. An
+/// empty unit is SI.
+fn unit(name: &str) -> Option<f64> {
+    let scale = match name.trim() {
+        "" | "Hz" | "m" | "m/s" | "J" | "/m" | "m-1" | "m^-1" => 1f64,
+        "kHz" => 1e3,
+        "MHz" => 1e6,
+        "GHz" => 1e9,
+        "km" => 1e3,
+        "cm" => 1e-2,
+        "mm" => 1e-3,
+        "um" => 1e-6,
+        "nm" => 1e-9,
+        "Angstrom" => 1e-10,
+        "km/s" => 1e3,
+        "eV" => 1.602_176_634e-19,
+        "keV" => 1.602_176_634e-16,
+        "/cm" | "cm-1" | "cm^-1" => 1e2,
+        _ => return Option::None,
+    };
+    Option::Some(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{refractive_index, BasicType, C};
+
+    #[test]
+    fn air_wavelengths_should_round_trip_through_frequency() {
+        let frequency = BasicType::AirWavelength.frequency_of(500e-9, Option::None).unwrap();
+        let air = BasicType::AirWavelength.value_of_frequency(frequency, Option::None).unwrap();
+
+        assert!((air - 500e-9).abs() < 1e-18);
+        assert!((C / frequency / 500e-9 - refractive_index(500e-9)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn velocities_should_be_relativistic() {
+        let rest = Option::Some(1.420_405_751_8e9);
+        let frequency = BasicType::Velocity.frequency_of(0.6f64 * C, rest).unwrap();
+
+        assert!((frequency - 1.420_405_751_8e9 * 0.5f64).abs() < 1e-3);
+        assert!((BasicType::Velocity.value_of_frequency(frequency, rest).unwrap() - 0.6f64 * C).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,210 @@
+//! The time reference of WCS Paper IV: the time scale, reference epoch and
+//! unit that time values of a header are relative to.
+
+use super::super::types::Header;
+use super::{real, string, WcsError};
+
+/// The Modified Julian Date of Julian Date 0.
+const JD_TO_MJD: f64 = 2_400_000.5f64;
+
+/// The frame of the time values of a header.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimeReference {
+    /// The time scale, `TIMESYS`, `UTC` by default.
+    pub timesys: String,
+    /// The reference epoch as a Modified Julian Date, split in integer and
+    /// fractional days to keep precision.
+    pub mjdref: (f64, f64),
+    /// The unit of time values, `TIMEUNIT`, `s` by default.
+    pub unit: String,
+    /// Where the times were measured, `TREFPOS` or the older `TIMEREF`,
+    /// `TOPOCENTER` by default.
+    pub trefpos: String,
+    /// The offset added to time values, `TIMEZERO`.
+    pub timezero: f64,
+    /// The start of the observation, `TSTART`.
+    pub tstart: Option<f64>,
+    /// The end of the observation, `TSTOP`.
+    pub tstop: Option<f64>,
+    /// The resolution of the time values, `TIMEDEL`.
+    pub timedel: Option<f64>,
+    /// The position of a time stamp within its bin, from 0 at the start to 1
+    /// at the end, `TIMEPIXR`, 0.5 by default.
+    pub timepixr: f64,
+    /// The seconds in a unit.
+    seconds: f64,
+}
+
+impl TimeReference {
+    /// Read the time reference of `header`.
+    ///
+    /// The reference epoch is the first of `MJDREFI` and `MJDREFF`, `MJDREF`,
+    /// `JDREFI` and `JDREFF`, `JDREF`, `DATEREF` and the `BJDREFI` and
+    /// `BJDREFF` of Kepler and TESS that is present, or MJD 0.
+    pub fn new(header: &Header) -> Result<TimeReference, WcsError> {
+        let timesys = string(header, "TIMESYS")?.unwrap_or_else(|| "UTC".to_string());
+        let unit = string(header, "TIMEUNIT")?.unwrap_or_else(|| "s".to_string());
+        let seconds = seconds(&unit).ok_or_else(|| WcsError::UnsupportedUnit(unit.clone()))?;
+        let trefpos = match string(header, "TREFPOS")? {
+            Option::Some(trefpos) => trefpos,
+            Option::None => string(header, "TIMEREF")?.unwrap_or_else(|| "TOPOCENTER".to_string()),
+        };
+
+        let split = |integer: &str, fraction: &str| -> Result<Option<(f64, f64)>, WcsError> {
+            match (real(header, integer)?, real(header, fraction)?) {
+                (Option::None, Option::None) => Ok(Option::None),
+                (integer, fraction) => Ok(Option::Some((integer.unwrap_or(0f64), fraction.unwrap_or(0f64)))),
+            }
+        };
+        let julian = |(integer, fraction): (f64, f64)| (integer - JD_TO_MJD.floor(), fraction - 0.5f64);
+        let mjdref = if let Option::Some(mjdref) = split("MJDREFI", "MJDREFF")? {
+            mjdref
+        } else if let Option::Some(mjdref) = real(header, "MJDREF")? {
+            (mjdref.floor(), mjdref - mjdref.floor())
+        } else if let Option::Some(jdref) = split("JDREFI", "JDREFF")? {
+            julian(jdref)
+        } else if let Option::Some(jdref) = real(header, "JDREF")? {
+            julian((jdref.floor(), jdref - jdref.floor()))
+        } else if let Option::Some(dateref) = string(header, "DATEREF")? {
+            modified_julian_date(&dateref).ok_or(WcsError::InvalidDate(dateref))?
+        } else if let Option::Some(bjdref) = split("BJDREFI", "BJDREFF")? {
+            julian(bjdref)
+        } else {
+            (0f64, 0f64)
+        };
+
+        Ok(TimeReference {
+            timesys,
+            mjdref,
+            unit,
+            trefpos,
+            timezero: real(header, "TIMEZERO")?.unwrap_or(0f64),
+            tstart: real(header, "TSTART")?,
+            tstop: real(header, "TSTOP")?,
+            timedel: real(header, "TIMEDEL")?,
+            timepixr: real(header, "TIMEPIXR")?.unwrap_or(0.5f64),
+            seconds,
+        })
+    }
+
+    /// The seconds in a unit of time values.
+    pub fn seconds_per_unit(&self) -> f64 {
+        self.seconds
+    }
+
+    /// The Modified Julian Date, in the time scale of the reference, of the
+    /// time value `time`.
+    pub fn mjd(&self, time: f64) -> f64 {
+        self.mjdref.0 + (self.mjdref.1 + (time + self.timezero) * self.seconds / 86_400f64)
+    }
+
+    /// The Modified Julian Date of `TSTART`.
+    pub fn start_mjd(&self) -> Option<f64> {
+        self.tstart.map(|tstart| self.mjd(tstart))
+    }
+
+    /// The Modified Julian Date of `TSTOP`.
+    pub fn stop_mjd(&self) -> Option<f64> {
+        self.tstop.map(|tstop| self.mjd(tstop))
+    }
+}
+
+impl<'a> Header<'a> {
+    /// The time reference of this header, see `TimeReference::new`.
+    pub fn time_reference(&self) -> Result<TimeReference, WcsError> {
+        TimeReference::new(self)
+    }
+}
+
+/// The seconds in the time unit `unit` of WCS Paper IV, `None` when it is not
+/// known.
+fn seconds(unit: &str) -> Option<f64> {
+    let seconds = match unit.trim() {
+        "s" => 1f64,
+        "min" => 60f64,
+        "h" => 3_600f64,
+        "d" => 86_400f64,
+        "a" | "yr" => 365.25f64 * 86_400f64,
+        "cy" => 36_525f64 * 86_400f64,
+        _ => return Option::None,
+    };
+    Option::Some(seconds)
+}
+
+/// The Modified Julian Date of an ISO-8601 date, `YYYY-MM-DD` optionally
+/// followed by `Thh:mm:ss.s`, split in integer and fractional days.
+fn modified_julian_date(date: &str) -> Option<(f64, f64)> {
+    let (day, time) = match date.find('T') {
+        Option::Some(position) => (&date[..position], &date[position + 1..]),
+        Option::None => (date, ""),
+    };
+    let mut parts = day.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (parts.next()?.ok()?, parts.next()?.ok()?, parts.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Option::None
+    }
+    let mut fraction = 0f64;
+    if !time.is_empty() {
+        let mut parts = time.trim_end_matches('Z').splitn(3, ':');
+        let hours: f64 = parts.next()?.parse().ok()?;
+        let minutes: f64 = parts.next().unwrap_or("0").parse().ok()?;
+        let seconds: f64 = parts.next().unwrap_or("0").parse().ok()?;
+        fraction = (hours * 3_600f64 + minutes * 60f64 + seconds) / 86_400f64;
+    }
+
+    // Days since 1970-01-01 of the proleptic Gregorian calendar.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Option::Some(((days + 40_587) as f64, fraction))
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::{fits, template};
+    use super::super::WcsError;
+    use super::modified_julian_date;
+
+    #[test]
+    fn dates_should_convert_to_modified_julian_dates() {
+        assert_eq!(modified_julian_date("1858-11-17"), Option::Some((0f64, 0f64)));
+        assert_eq!(modified_julian_date("2000-01-01T12:00:00"), Option::Some((51_544f64, 0.5f64)));
+        assert_eq!(modified_julian_date("1970-01-01T06:00:00.0Z"), Option::Some((40_587f64, 0.25f64)));
+        assert_eq!(modified_julian_date("2017-13-01"), Option::None);
+    }
+
+    #[test]
+    fn target_pixel_file_should_reference_barycentric_julian_dates() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let fits = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let reference = fits[1].header.time_reference().unwrap();
+
+        assert_eq!(reference.timesys, "TDB");
+        assert_eq!(reference.trefpos, "SOLARSYSTEM");
+        assert_eq!(reference.mjdref, (54_833f64, -0.5f64));
+        assert_eq!(reference.seconds_per_unit(), 86_400f64);
+        assert!((reference.start_mjd().unwrap() - (2_454_833f64 + 2905.371898020152f64 - 2_400_000.5f64)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn references_should_default_and_reject_unknown_units() {
+        let header = template("MJDREF = 50000.25\nTIMEZERO = 60.0").unwrap();
+        let reference = header.time_reference().unwrap();
+
+        assert_eq!((reference.timesys.as_str(), reference.unit.as_str()), ("UTC", "s"));
+        assert_eq!(reference.mjd(21_540f64), 50_000.5f64);
+
+        let header = template("TIMEUNIT = 'fortnight'").unwrap();
+        assert_eq!(header.time_reference(), Err(WcsError::UnsupportedUnit("fortnight".to_string())));
+        let header = template("DATEREF = '2000-01-01T12:00:00'").unwrap();
+        assert_eq!(header.time_reference().unwrap().mjdref, (51_544f64, 0.5f64));
+    }
+}
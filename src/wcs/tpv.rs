@@ -0,0 +1,124 @@
+//! The `TPV` convention of SCAMP: a polynomial distortion of the intermediate
+//! world coordinates of a `TAN` projection, with coefficients `PVi_m`.
+
+use super::super::types::Header;
+use super::{real, suffix, WcsError};
+
+/// The number of `PVi_m` coefficients of a polynomial, up to order 7.
+const TERMS: usize = 40;
+
+/// The polynomials of the longitude and the latitude axis.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tpv {
+    /// The coefficients `PVi_0` to `PVi_39` of the longitude axis `i`.
+    pub longitude: Vec<f64>,
+    /// The coefficients `PVj_0` to `PVj_39` of the latitude axis `j`.
+    pub latitude: Vec<f64>,
+}
+
+impl Tpv {
+    /// Read the coefficients of the axes with indices `longitude` and
+    /// `latitude`, starting at 0, of the description with letter `alternate`.
+    /// Absent coefficients are 0, except for `PVi_1` which is 1, so that
+    /// without coefficients there is no distortion.
+    pub fn new(header: &Header, longitude: usize, latitude: usize, alternate: Option<char>) -> Result<Tpv, WcsError> {
+        let suffix = suffix(alternate);
+        Ok(Tpv {
+            longitude: coefficients(header, longitude + 1, &suffix)?,
+            latitude: coefficients(header, latitude + 1, &suffix)?,
+        })
+    }
+
+    /// The distorted intermediate world coordinates of `(x, y)`, in degrees.
+    /// The polynomial of the latitude axis has its arguments swapped.
+    pub fn distort(&self, x: f64, y: f64) -> (f64, f64) {
+        (polynomial(&self.longitude, x, y), polynomial(&self.latitude, y, x))
+    }
+
+    /// The intermediate world coordinates whose distortion is `(xi, eta)`, by
+    /// Newton iteration from `(xi, eta)`.
+    pub fn undistort(&self, xi: f64, eta: f64) -> (f64, f64) {
+        let (mut x, mut y) = (xi, eta);
+        let step = 1e-7;
+        for _ in 0..50 {
+            let (f, g) = self.distort(x, y);
+            let (residual_f, residual_g) = (f - xi, g - eta);
+            if residual_f.abs().max(residual_g.abs()) < 1e-15 {
+                break
+            }
+            let (fx, gx) = self.distort(x + step, y);
+            let (fy, gy) = self.distort(x, y + step);
+            let (dfdx, dgdx, dfdy, dgdy) = ((fx - f) / step, (gx - g) / step, (fy - f) / step, (gy - g) / step);
+            let determinant = dfdx * dgdy - dfdy * dgdx;
+            if determinant == 0f64 {
+                break
+            }
+            x -= (residual_f * dgdy - residual_g * dfdy) / determinant;
+            y -= (residual_g * dfdx - residual_f * dgdx) / determinant;
+        }
+        (x, y)
+    }
+}
+
+fn coefficients(header: &Header, axis: usize, suffix: &str) -> Result<Vec<f64>, WcsError> {
+    (0..TERMS)
+        .map(|m| real(header, &format!("PV{}_{}{}", axis, m, suffix)).map(|value| value.unwrap_or(if m == 1 { 1f64 } else { 0f64 })))
+        .collect()
+}
+
+/// Evaluates the terms 1, x, y, r, x², xy, y², x³, x²y, xy², y³, r³, ... in
+/// which every odd order is followed by a power of `r = √(x² + y²)`.
+fn polynomial(coefficients: &[f64], x: f64, y: f64) -> f64 {
+    let r = x.hypot(y);
+    let mut terms = Vec::with_capacity(TERMS);
+    for order in 0..8 {
+        for power in 0..order + 1 {
+            terms.push(x.powi(order - power) * y.powi(power));
+        }
+        if order % 2 == 1 {
+            terms.push(r.powi(order));
+        }
+    }
+    coefficients.iter().zip(terms).map(|(c, term)| c * term).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{polynomial, Tpv};
+
+    #[test]
+    fn terms_should_be_in_the_order_of_the_convention() {
+        let term = |k: usize| {
+            let mut coefficients = vec!(0f64; 40);
+            coefficients[k] = 1f64;
+            polynomial(&coefficients, 2f64, 3f64)
+        };
+
+        assert_eq!(term(0), 1f64);
+        assert_eq!(term(2), 3f64);
+        assert_eq!(term(3), 13f64.sqrt());
+        assert_eq!(term(5), 6f64);
+        assert_eq!(term(8), 12f64);
+        assert_eq!(term(16), 81f64);
+        assert_eq!(term(38), 2187f64);
+        assert!((term(39) - 13f64.sqrt().powi(7)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn undistort_should_invert_distort() {
+        let mut longitude = vec!(0f64; 40);
+        let mut latitude = vec!(0f64; 40);
+        longitude[1] = 1.0001f64;
+        longitude[4] = 0.002f64;
+        longitude[7] = -0.01f64;
+        latitude[1] = 0.9998f64;
+        latitude[5] = 0.003f64;
+        let tpv = Tpv { longitude, latitude };
+
+        let (xi, eta) = tpv.distort(0.2f64, -0.15f64);
+        let (x, y) = tpv.undistort(xi, eta);
+
+        assert!((x - 0.2f64).abs() < 1e-12);
+        assert!((y - -0.15f64).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,125 @@
+//! The layout of the cards the writer writes, so that headers can follow the
+//! house style of an institution and stay the same from one write to the next.
+
+/// The letter that introduces the exponent of real values.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Exponent {
+    /// `E`, e.g. `1.5E-7`.
+    #[default]
+    E,
+    /// `D`, e.g. `1.5D-7`, as written for double precision by some software.
+    D,
+}
+
+/// How values and comments are laid out in their cards.
+///
+/// The default policy writes the fixed format of the standard: values in
+/// columns 11 to 30, the `/` of comments in column 32 and reals with as many
+/// digits as it takes to read them back exactly.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FormatPolicy {
+    /// The column, counting from 1, of the `/` that starts a comment, 32 by
+    /// default. A comment follows a longer value after a space.
+    pub comment_column: usize,
+    /// The number of significant digits of reals, written with an exponent.
+    /// All the digits it takes by default.
+    pub precision: Option<usize>,
+    /// The letter of the exponent of reals, `E` by default.
+    pub exponent: Exponent,
+    /// Write comments and commentary in upper case, as older software did.
+    /// `false` by default.
+    pub uppercase_comments: bool,
+}
+
+impl Default for FormatPolicy {
+    fn default() -> FormatPolicy {
+        FormatPolicy {
+            comment_column: 32,
+            precision: Option::None,
+            exponent: Exponent::E,
+            uppercase_comments: false,
+        }
+    }
+}
+
+impl FormatPolicy {
+    /// The default policy, the fixed format of the standard.
+    pub fn new() -> FormatPolicy {
+        FormatPolicy::default()
+    }
+
+    /// Start comments with a `/` in `column`, counting from 1.
+    pub fn with_comment_column(mut self, column: usize) -> FormatPolicy {
+        self.comment_column = column;
+        self
+    }
+
+    /// Write reals with `digits` significant digits.
+    pub fn with_precision(mut self, digits: usize) -> FormatPolicy {
+        self.precision = Option::Some(digits);
+        self
+    }
+
+    /// Introduce the exponent of reals with `exponent`.
+    pub fn with_exponent(mut self, exponent: Exponent) -> FormatPolicy {
+        self.exponent = exponent;
+        self
+    }
+
+    /// Write comments in upper case when `uppercase` is `true`.
+    pub fn with_uppercase_comments(mut self, uppercase: bool) -> FormatPolicy {
+        self.uppercase_comments = uppercase;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::checksum::{verify, Status};
+    use super::super::super::parser::template;
+    use super::super::super::types::HDU;
+    use super::super::write_hdu_with;
+    use super::{Exponent, FormatPolicy};
+
+    const HEADER: &str = "SIMPLE = T / conforms\nBITPIX = -64\nNAXIS = 0\nEXPTIME = 1234.56789 / seconds\n\
+        OBJECT = 'a rather long name for a target' / name\nHISTORY written by fits-rs\nCHECKSUM = '0000000000000000'";
+
+    fn cards(policy: &FormatPolicy) -> Vec<String> {
+        let header = template(HEADER).unwrap();
+        let text = header.to_fits_string_with(policy);
+        text.as_bytes().chunks(80).map(|card| String::from_utf8(card.to_vec()).unwrap().trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn default_policy_should_write_the_fixed_format() {
+        let cards = cards(&FormatPolicy::new());
+
+        assert_eq!(cards[0], "SIMPLE  =                    T / conforms");
+        assert_eq!(cards[3], "EXPTIME =           1234.56789 / seconds");
+        assert_eq!(template(HEADER).unwrap().to_fits_string_with(&FormatPolicy::new()), template(HEADER).unwrap().to_fits_string());
+    }
+
+    #[test]
+    fn policy_should_align_comments_and_format_reals() {
+        let policy = FormatPolicy::new()
+            .with_comment_column(40)
+            .with_precision(4)
+            .with_exponent(Exponent::D)
+            .with_uppercase_comments(true);
+        let cards = cards(&policy);
+
+        assert_eq!(cards[0], "SIMPLE  =                    T         / CONFORMS");
+        assert_eq!(cards[3], "EXPTIME =              1.235D3         / SECONDS");
+        assert_eq!(cards[4], "OBJECT  = 'a rather long name for a target' / NAME");
+        assert_eq!(cards[5], "HISTORY WRITTEN BY FITS-RS");
+    }
+
+    #[test]
+    fn checksum_should_match_the_header_as_written() {
+        let policy = FormatPolicy::new().with_comment_column(50);
+        let mut written = vec!();
+        write_hdu_with(&mut written, &HDU::new(template(HEADER).unwrap()), &policy).unwrap();
+
+        assert_eq!(verify(&written[..2880], &written[2880..]).checksum, Status::Ok);
+    }
+}
@@ -0,0 +1,169 @@
+//! Writing random groups, the primary data array of legacy interferometry
+//! formats like UVFITS.
+//!
+//! Each group holds `PCOUNT` parameters, e.g. the baseline and time of a
+//! visibility, followed by an array with the axes `NAXIS2` to `NAXISn`.
+//! `NAXIS1` is 0 and `GROUPS = T` marks the layout.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::str::FromStr;
+use super::super::types::{HDU, Header, Keyword, KeywordRecord, Value};
+use super::write_hdu;
+
+/// A primary HDU of random groups that is written group by group.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GroupsWriter {
+    bitpix: i64,
+    parameters: Vec<String>,
+    axes: Vec<usize>,
+    groups: usize,
+    data: Vec<u8>,
+}
+
+impl GroupsWriter {
+    /// Random groups without groups yet, with values of `bitpix`, a parameter
+    /// per `PTYPEn` of `parameters` and arrays with the lengths of `axes`,
+    /// which become `NAXIS2` onwards.
+    pub fn new(bitpix: i64, parameters: &[&str], axes: &[usize]) -> Result<GroupsWriter, GroupsWriteError> {
+        match bitpix {
+            8 | 16 | 32 | 64 | -32 | -64 => (),
+            _ => return Err(GroupsWriteError::InvalidBitpix(bitpix)),
+        }
+        if axes.is_empty() || axes.len() > 998 {
+            return Err(GroupsWriteError::InvalidAxes)
+        }
+        Ok(GroupsWriter {
+            bitpix,
+            parameters: parameters.iter().map(|name| name.to_string()).collect(),
+            axes: axes.to_vec(),
+            groups: 0,
+            data: vec!(),
+        })
+    }
+
+    /// Append a group of `parameters`, one per `PTYPEn`, and its `array`, in
+    /// the order of the axes with the first axis varying fastest.
+    ///
+    /// Values are written in the encoding of `BITPIX`, for integers rounded
+    /// and saturated to their range. Scaling with `PSCALn` and `PZEROn` is up
+    /// to the caller.
+    pub fn push_group(&mut self, parameters: &[f64], array: &[f64]) -> Result<(), GroupsWriteError> {
+        if parameters.len() != self.parameters.len() {
+            return Err(GroupsWriteError::ParameterCount(parameters.len()))
+        }
+        let elements = self.axes.iter().product();
+        if array.len() != elements {
+            return Err(GroupsWriteError::ArrayLength(array.len(), elements))
+        }
+        for &value in parameters.iter().chain(array.iter()) {
+            encode(self.bitpix, value, &mut self.data);
+        }
+        self.groups += 1;
+        Ok(())
+    }
+
+    /// The number of groups written so far.
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    /// The primary header of these random groups.
+    pub fn header(&self) -> Header<'static> {
+        let mut records = vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(self.bitpix), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(self.axes.len() as i64 + 1), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(0i64), Option::Some("random groups".into())),
+        );
+        for (index, &length) in self.axes.iter().enumerate() {
+            records.push(KeywordRecord::new(Keyword::NAXISn(index as u16 + 2), Value::Integer(length as i64), Option::None));
+        }
+        records.push(KeywordRecord::new(Keyword::EXTEND, Value::Logical(true), Option::None));
+        records.push(KeywordRecord::new(Keyword::GROUPS, Value::Logical(true), Option::None));
+        records.push(KeywordRecord::new(Keyword::PCOUNT, Value::Integer(self.parameters.len() as i64), Option::Some("parameters per group".into())));
+        records.push(KeywordRecord::new(Keyword::GCOUNT, Value::Integer(self.groups as i64), Option::Some("number of groups".into())));
+        for (index, name) in self.parameters.iter().enumerate() {
+            let keyword = Keyword::from_str(&format!("PTYPE{}", index + 1)).expect("PTYPEn is a valid keyword");
+            records.push(KeywordRecord::new(keyword, Value::CharacterString(Cow::Owned(name.clone())), Option::None));
+        }
+        Header::new(records)
+    }
+
+    /// The data array of these random groups without padding.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Write these random groups as the primary HDU to `out`, padded to whole
+    /// blocks. Extensions, e.g. antenna tables, can follow.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write_hdu(out, &HDU::with_data(self.header(), &self.data))
+    }
+}
+
+/// Problems that could occur when writing random groups.
+#[derive(Debug, PartialEq)]
+pub enum GroupsWriteError {
+    /// The `BITPIX` is not one of the standard values.
+    InvalidBitpix(i64),
+    /// There are no axes for the arrays, or more than 998.
+    InvalidAxes,
+    /// A group has this number of parameters, which is not `PCOUNT`.
+    ParameterCount(usize),
+    /// The array of a group has the first number of elements instead of the
+    /// second, the product of the axes.
+    ArrayLength(usize, usize),
+}
+
+/// Appends the big-endian encoding of `value` for `bitpix` to `out`.
+fn encode(bitpix: i64, value: f64, out: &mut Vec<u8>) {
+    match bitpix {
+        8 => out.push(value.round() as u8),
+        16 => out.extend_from_slice(&(value.round() as i16).to_be_bytes()),
+        32 => out.extend_from_slice(&(value.round() as i32).to_be_bytes()),
+        64 => out.extend_from_slice(&(value.round() as i64).to_be_bytes()),
+        -32 => out.extend_from_slice(&(value as f32).to_be_bytes()),
+        _ => out.extend_from_slice(&value.to_be_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::fits;
+    use super::super::super::types::Keyword;
+    use super::{GroupsWriteError, GroupsWriter};
+
+    #[test]
+    fn written_groups_should_parse_with_their_data_array() {
+        let mut writer = GroupsWriter::new(-32, &["UU", "VV", "BASELINE"], &[3, 2]).unwrap();
+        for group in 0..10 {
+            let visibility: Vec<f64> = (0..6).map(|i| f64::from(group * 6 + i)).collect();
+            writer.push_group(&[0.5, -0.5, 258.0], &visibility).unwrap();
+        }
+        let mut out = vec!();
+        writer.write(&mut out).unwrap();
+
+        let parsed = match fits(&out) {
+            IResult::Done(rest, parsed) => { assert!(rest.is_empty()); parsed },
+            _ => panic!("the written groups should parse"),
+        };
+        let primary = &parsed.primary_hdu;
+        assert!(primary.header.is_random_groups());
+        assert_eq!(primary.header.data_length(), Option::Some(10 * 9 * 4));
+        assert_eq!(primary.header.integer_value_of(&Keyword::GCOUNT), Ok(10));
+        assert_eq!(&primary.data()[36 + 12..36 + 16], &6f32.to_be_bytes());
+    }
+
+    #[test]
+    fn groups_should_match_the_parameters_and_axes() {
+        let mut writer = GroupsWriter::new(16, &["UU"], &[2]).unwrap();
+
+        assert_eq!(writer.push_group(&[], &[1.0, 2.0]), Err(GroupsWriteError::ParameterCount(0)));
+        assert_eq!(writer.push_group(&[1.0], &[1.0]), Err(GroupsWriteError::ArrayLength(1, 2)));
+        assert_eq!(writer.push_group(&[1.0], &[1.4, -2.6]), Ok(()));
+        assert_eq!(writer.data(), &[0, 1, 0, 1, 0xff, 0xfd]);
+        assert_eq!(GroupsWriter::new(12, &[], &[2]), Err(GroupsWriteError::InvalidBitpix(12)));
+    }
+}
@@ -0,0 +1,340 @@
+//! The writer module writes HDUs in the layout of a FITS file: headers and data
+//! arrays padded to whole blocks.
+
+use std::borrow::Cow;
+use std::fmt::{Display, Error, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use super::checksum::{self, ZEROS};
+use super::types::{Fits, HDU, Header, Keyword, KeywordRecord, Value};
+
+mod format;
+mod groups;
+mod stream;
+mod table;
+
+pub use self::format::{Exponent, FormatPolicy};
+pub use self::groups::{GroupsWriteError, GroupsWriter};
+pub use self::stream::HduWriter;
+pub use self::table::{TableWriteError, TableWriter};
+
+/// The size in bytes of a FITS block.
+pub const BLOCK_SIZE: usize = 2880;
+
+/// Write `fits` to `out`, HDU by HDU.
+pub fn write_fits<W: Write>(out: &mut W, fits: &Fits) -> io::Result<()> {
+    write_fits_with(out, fits, &FormatPolicy::default())
+}
+
+/// Write `fits` to `out` like `write_fits`, with the cards of the headers laid
+/// out by `policy`.
+pub fn write_fits_with<W: Write>(out: &mut W, fits: &Fits, policy: &FormatPolicy) -> io::Result<()> {
+    for hdu in fits.iter() {
+        write_hdu_with(out, hdu, policy)?;
+    }
+    Ok(())
+}
+
+impl<'a> Fits<'a> {
+    /// Write this file to `path`, e.g. after removing or moving extensions.
+    ///
+    /// The file at `path` is replaced. The HDUs are written in full, so they
+    /// should not borrow from a memory map of the same file.
+    pub fn save_as<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write_fits(&mut out, self)?;
+        out.flush()
+    }
+}
+
+/// Write the header and data array of `hdu` to `out`.
+///
+/// The data array is padded with zeros, or with spaces for ASCII tables, to a
+/// whole number of blocks. Data arrays of foreign extensions are copied as is.
+///
+/// A parsed HDU whose header and data array are unchanged is written as the
+/// bytes it was parsed from. Otherwise `DATASUM` and `CHECKSUM` are recomputed
+/// if the header has them, so that the written HDU verifies.
+///
+/// Fails with `InvalidData`, before writing anything, when the header can not
+/// be written as is, see `check_header`.
+pub fn write_hdu<W: Write>(out: &mut W, hdu: &HDU) -> io::Result<()> {
+    write_hdu_with(out, hdu, &FormatPolicy::default())
+}
+
+/// Write `hdu` to `out` like `write_hdu`, with the cards of the header laid out
+/// by `policy`. Other policies than the default change the bytes of parsed
+/// headers too, so these are not copied.
+pub fn write_hdu_with<W: Write>(out: &mut W, hdu: &HDU, policy: &FormatPolicy) -> io::Result<()> {
+    let data = hdu.data();
+    if *policy == FormatPolicy::default() {
+        if let Option::Some((header, padding)) = hdu.unchanged_source() {
+            out.write_all(header)?;
+            out.write_all(data)?;
+            return out.write_all(padding)
+        }
+    }
+    check_header(&hdu.header).map_err(invalid_data)?;
+    out.write_all(&checksummed(&hdu.header, data, policy))?;
+    out.write_all(data)?;
+    out.write_all(&vec!(fill(&hdu.header); padding(data.len())))
+}
+
+/// The byte that pads the data array of an HDU with `header`: a space for
+/// ASCII tables, zero otherwise.
+pub(crate) fn fill(header: &Header) -> u8 {
+    match header.character_string_value_of(&Keyword::XTENSION).map(str::trim_end) {
+        Ok("TABLE") => b' ',
+        _ => 0u8,
+    }
+}
+
+/// Check that every record of `header` fits in its cards as the standard
+/// requires: values and comments in printable ASCII, other values than
+/// strings in one card and commentary of at most 72 characters.
+///
+/// Strings over 68 characters are continued over `CONTINUE` cards, see
+/// `KeywordRecord::cards`. Longer comments are cut off at the end of a card.
+pub fn check_header(header: &Header) -> Result<(), HeaderWriteError> {
+    for record in header.records().iter().filter(|record| *record.keyword() != Keyword::END) {
+        let keyword = record.keyword().clone();
+        let text = match *record.value() {
+            Value::CharacterString(ref s) => s.as_ref(),
+            _ => "",
+        };
+        let printable = |text: &str| text.bytes().all(|b| (32..=126).contains(&b));
+        if !printable(text) || !printable(record.comment().unwrap_or("")) {
+            return Err(HeaderWriteError::NonAscii(keyword))
+        }
+        let length = match *record.value() {
+            _ if keyword.is_commentary() => record.comment().map_or(0, str::len),
+            Value::CharacterString(_) => 0,
+            ref value => value.to_string().trim_end().len() + 2,
+        };
+        if length > 72 {
+            return Err(HeaderWriteError::ValueTooLong(keyword))
+        }
+    }
+    Ok(())
+}
+
+/// Problems that keep a header from being written.
+#[derive(Debug, PartialEq)]
+pub enum HeaderWriteError {
+    /// A value or comment of a record with this keyword has characters other
+    /// than printable ASCII.
+    NonAscii(Keyword),
+    /// The value of a record with this keyword does not fit in its card, or
+    /// the text of a commentary record is longer than 72 characters.
+    ValueTooLong(Keyword),
+}
+
+impl Display for HeaderWriteError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            HeaderWriteError::NonAscii(ref keyword) => write!(f, "{}: only printable ASCII can be written", keyword),
+            HeaderWriteError::ValueTooLong(ref keyword) => write!(f, "{}: the value does not fit in a card", keyword),
+        }
+    }
+}
+
+fn invalid_data(error: HeaderWriteError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+/// The bytes of `header` with `DATASUM` and `CHECKSUM`, where present, set for
+/// the data array `data` and its padding.
+fn checksummed(header: &Header, data: &[u8], policy: &FormatPolicy) -> Vec<u8> {
+    let has = |header: &Header, keyword: &Keyword| header.value_opt(keyword).is_some();
+    if !has(header, &Keyword::DATASUM) && !has(header, &Keyword::CHECKSUM) {
+        return header.to_fits_string_with(policy).into_bytes()
+    }
+    let datasum = checksum::sum_of(&[data, &vec!(fill(header); padding(data.len()))]);
+    let mut header = Header::new(header.records().iter().cloned().map(KeywordRecord::into_owned).collect());
+    if has(&header, &Keyword::DATASUM) {
+        let _ = header.set(Keyword::DATASUM, Value::CharacterString(Cow::Owned(datasum.to_string())), Option::None);
+    }
+    let has_checksum = has(&header, &Keyword::CHECKSUM);
+    if has_checksum {
+        let _ = header.set(Keyword::CHECKSUM, Value::CharacterString(Cow::Borrowed(ZEROS)), Option::None);
+    }
+    let mut bytes = header.to_fits_string_with(policy).into_bytes();
+    if has_checksum {
+        let _ = checksum::update(&mut bytes, datasum);
+    }
+    bytes
+}
+
+/// The number of bytes needed to pad `size` bytes to a whole number of blocks.
+pub fn padding(size: usize) -> usize {
+    (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use nom::IResult;
+    use super::super::checksum::{verify, Status};
+    use super::super::parser::fits;
+    use super::super::types::Keyword;
+    use super::super::parser::{parse_header, template};
+    use super::super::types::{HDU, Header, KeywordRecord, Value};
+    use super::{check_header, padding, write_fits, write_hdu, HeaderWriteError};
+
+    #[test]
+    fn written_file_should_parse_to_the_same_hdus() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let original = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+
+        let mut written = vec!();
+        write_fits(&mut written, &original).unwrap();
+
+        assert_eq!(written.len() % 2880, 0);
+        match fits(&written) {
+            IResult::Done(rest, copy) => {
+                assert!(rest.is_empty());
+                assert_eq!(copy.len(), original.len());
+                for (a, b) in copy.iter().zip(original.iter()) {
+                    assert_eq!(a.header.records(), b.header.records());
+                    assert_eq!(a.data(), b.data());
+                }
+            },
+            _ => panic!("Did not expect the parse of the written file to fail"),
+        }
+    }
+
+    #[test]
+    fn edited_data_should_be_written_with_new_checksums() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let mut hdu = match fits(data) {
+            IResult::Done(_, fits) => fits.into_iter().nth(1).unwrap(),
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        assert!(!hdu.is_data_edited());
+
+        hdu.data_mut()[0] ^= 0xff;
+        let mut written = vec!();
+        write_hdu(&mut written, &hdu).unwrap();
+
+        assert!(hdu.is_data_edited());
+        let (header, data) = written.split_at(hdu.header.to_fits_string().len());
+        let verification = verify(header, data);
+        assert_eq!(verification.checksum, Status::Ok);
+        assert_eq!(verification.datasum, Status::Ok);
+    }
+
+    #[test]
+    fn save_as_should_rewrite_the_file_without_removed_extensions() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let mut original = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let count = original.len();
+        let removed = original.remove_hdu(1).unwrap();
+        let path = env::temp_dir().join(format!("fits-rs-save-as-{}.fits", process::id()));
+
+        original.save_as(&path).unwrap();
+        let written = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        match fits(&written) {
+            IResult::Done(_, copy) => {
+                assert_eq!(copy.len(), count - 1);
+                assert!(copy.iter().all(|hdu| hdu.header.value_of(&Keyword::EXTNAME) != removed.header.value_of(&Keyword::EXTNAME)));
+            },
+            _ => panic!("Did not expect the parse of the saved file to fail"),
+        }
+    }
+
+    #[test]
+    fn saved_file_should_keep_the_checksums_of_unchanged_hdus() {
+        let data = include_bytes!("../../assets/images/k2-trappist1-unofficial-tpf-long-cadence.fits");
+        let original = match fits(data) {
+            IResult::Done(_, fits) => fits,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let path = env::temp_dir().join(format!("fits-rs-save-as-checksums-{}.fits", process::id()));
+
+        original.save_as(&path).unwrap();
+        let written = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(written[..] == data[..]);
+        match fits(&written) {
+            IResult::Done(_, copy) => for verification in copy.verify_all() {
+                let verification = verification.unwrap();
+                assert_eq!((verification.checksum, verification.datasum), (Status::Ok, Status::Ok));
+            },
+            _ => panic!("Did not expect the parse of the saved file to fail"),
+        }
+    }
+
+    #[test]
+    fn edited_ascii_tables_should_be_summed_with_their_space_padding() {
+        let header = template("XTENSION = 'TABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 10\nNAXIS2 = 3\nPCOUNT = 0\nGCOUNT = 1\n\
+            TFIELDS = 1\nTFORM1 = 'A10'\nTBCOL1 = 1\nDATASUM = '0'\nCHECKSUM = '0000000000000000'").unwrap();
+        let mut hdu = HDU::new(header);
+        hdu.set_data(b"TRAPPIST-1K2-246    LHS 1140  ".to_vec());
+        let mut written = vec!();
+        write_hdu(&mut written, &hdu).unwrap();
+
+        assert_eq!(written[2880 + 30..], [b' '; 2850][..]);
+        let verification = verify(&written[..2880], &written[2880..]);
+        assert_eq!(verification.checksum, Status::Ok);
+        assert_eq!(verification.datasum, Status::Ok);
+    }
+
+    #[test]
+    fn headers_that_do_not_fit_their_cards_should_not_be_written() {
+        let fits_exactly = format!("SIMPLE = T\nOBJECT = '{}'\nHISTORY {}", "x".repeat(66) + "''", "y".repeat(72));
+        assert_eq!(check_header(&template(&fits_exactly).unwrap()), Ok(()));
+
+        let too_long = format!("SIMPLE = T\nHISTORY {}", "y".repeat(73));
+        let long = template(&too_long).unwrap();
+        assert_eq!(check_header(&long), Err(HeaderWriteError::ValueTooLong(Keyword::HISTORY)));
+        let non_ascii = Header::new(vec!(KeywordRecord::new(Keyword::OBJECT, Value::from("M31"), Option::Some("Andromedanebel".into())),
+            KeywordRecord::new(Keyword::OBSERVER, Value::from("Müller"), Option::None)));
+        assert_eq!(check_header(&non_ascii), Err(HeaderWriteError::NonAscii(Keyword::OBSERVER)));
+
+        let mut written = vec!();
+        assert!(write_hdu(&mut written, &HDU::new(long)).is_err());
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn long_strings_should_be_continued_and_read_back() {
+        let text = "It's a long way to the top if you wanna rock 'n' roll, ".repeat(3);
+        let header = Header::new(vec!(
+            KeywordRecord::new(Keyword::SIMPLE, Value::Logical(true), Option::None),
+            KeywordRecord::new(Keyword::OBJECT, Value::from(text.as_str()), Option::Some("lyrics".into())),
+        ));
+        let mut written = vec!();
+        write_hdu(&mut written, &HDU::new(header)).unwrap();
+
+        let cards: Vec<&[u8]> = written.chunks(80).collect();
+        assert!(cards[1].starts_with(b"LONGSTRN= 'OGIP 1.0'"));
+        assert!(cards[2].starts_with(b"OBJECT  = 'It''s a long way"));
+        assert!(cards[3].starts_with(b"CONTINUE  '"));
+        assert!(cards[4].starts_with(b"CONTINUE  '"));
+        assert!(cards[4].windows(10).any(|w| w == b"' / lyrics"));
+        match parse_header(&written) {
+            IResult::Done(_, parsed) => {
+                assert_eq!(parsed.character_string_value_of(&Keyword::OBJECT), Ok(text.as_str()));
+                assert_eq!(parsed.records()[2].comment(), Option::Some("lyrics"));
+            },
+            _ => panic!("Did not expect the parse of the written header to fail"),
+        }
+    }
+
+    #[test]
+    fn padding_should_complete_the_block() {
+        assert_eq!(padding(0), 0);
+        assert_eq!(padding(1), 2879);
+        assert_eq!(padding(2880), 0);
+    }
+}
@@ -0,0 +1,257 @@
+//! Writing an HDU whose data array is produced piece by piece, so that it
+//! never has to be in memory as a whole.
+
+use std::borrow::Cow;
+use std::io::{self, Seek, SeekFrom, Write};
+use super::super::checksum::{self, ZEROS};
+use super::super::types::{Header, HeaderEditError, Keyword, KeywordRecord, Value};
+use super::{check_header, fill, invalid_data, padding, BLOCK_SIZE};
+
+/// Writes the header of an HDU, then its data array in chunks.
+///
+/// The rows of a table, or the pixels of an image, are written with
+/// `write_data`, the heap of a binary table after them with `write_heap`. The
+/// writer keeps count of the bytes and their `DATASUM`. `finish` pads the
+/// data array to whole blocks. When the size of the data array is not known
+/// up front, `finish_patched` also rewrites the header with the size of the
+/// last axis, `PCOUNT` and the checksums, as well as any value set with
+/// `patch` in the meantime.
+///
+/// Like cfitsio, the header is written with placeholder values, e.g.
+/// `NAXIS2 = 0` and `DATASUM = '0'`, which are replaced in their cards, so
+/// every record that is patched has to be in the header from the start.
+#[derive(Debug)]
+pub struct HduWriter<W: Write> {
+    out: W,
+    header: Header<'static>,
+    header_size: u64,
+    data: u64,
+    heap: u64,
+    sum: u32,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> HduWriter<W> {
+    /// Write `header` to `out` and start the data array.
+    ///
+    /// Fails with `InvalidData` when the header can not be written as is, see
+    /// `check_header`.
+    pub fn new(mut out: W, header: &Header) -> io::Result<HduWriter<W>> {
+        check_header(header).map_err(invalid_data)?;
+        let text = header.to_fits_string();
+        out.write_all(text.as_bytes())?;
+        Ok(HduWriter {
+            out,
+            header: Header::new(header.records().iter().cloned().map(KeywordRecord::into_owned).collect()),
+            header_size: text.len() as u64,
+            data: 0,
+            heap: 0,
+            sum: 0,
+            pending: vec!(),
+        })
+    }
+
+    /// Write the next `chunk` of the rows or pixels of the data array.
+    ///
+    /// Fails with `InvalidInput` once the heap has been started.
+    pub fn write_data(&mut self, chunk: &[u8]) -> io::Result<()> {
+        if self.heap > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the heap follows the rows of a table"))
+        }
+        self.write_chunk(chunk)?;
+        self.data += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Write the next `chunk` of the heap of a binary table, after its rows.
+    pub fn write_heap(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.write_chunk(chunk)?;
+        self.heap += chunk.len() as u64;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.out.write_all(chunk)?;
+        self.add_to_sum(chunk);
+        Ok(())
+    }
+
+    fn add_to_sum(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        let whole = self.pending.len() - self.pending.len() % 4;
+        self.sum = checksum::add(self.sum, checksum::sum(&self.pending[..whole]));
+        self.pending.drain(..whole);
+    }
+
+    /// Replace the placeholder value of `keyword` with `value`, to be written
+    /// by `finish_patched`, e.g. `EXPOSURE` once the last frame is in. The
+    /// comment of the record is kept.
+    ///
+    /// Fails with `KeywordNotPresent` when the header has no such record, or
+    /// the keyword is commentary, as records can not be added afterwards.
+    pub fn patch(&mut self, keyword: Keyword, value: Value<'static>) -> Result<(), HeaderEditError> {
+        if keyword.is_commentary() || self.header.value_opt(&keyword).is_none() {
+            return Err(HeaderEditError::KeywordNotPresent)
+        }
+        self.header.set(keyword, value, Option::None)
+    }
+
+    /// The number of bytes of the data array written so far, including the heap.
+    pub fn written(&self) -> u64 {
+        self.data + self.heap
+    }
+
+    /// The `DATASUM` of the bytes written so far.
+    pub fn datasum(&self) -> u32 {
+        checksum::add(self.sum, checksum::sum(&self.pending))
+    }
+
+    /// Pad the data array to whole blocks and hand back the output.
+    ///
+    /// Fails with `InvalidData` when the number of bytes written differs from
+    /// the size of the data array the header describes.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.header.data_length() != Option::Some(self.written()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the data array does not have the size of the header"))
+        }
+        self.pad()?;
+        Ok(self.out)
+    }
+
+    /// Pad the data array, adding the padding to `datasum`.
+    fn pad(&mut self) -> io::Result<()> {
+        let size = padding((self.written() % BLOCK_SIZE as u64) as usize);
+        let bytes = vec!(fill(&self.header); size);
+        self.out.write_all(&bytes)?;
+        self.add_to_sum(&bytes);
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> HduWriter<W> {
+    /// Pad the data array to whole blocks, then go back and rewrite the header
+    /// for the data that was written: the length of the last axis, which is
+    /// `NAXIS2` of a table, `PCOUNT` of an extension and, when the header has
+    /// them, `DATASUM` and `CHECKSUM`.
+    ///
+    /// The header has to keep its size, so include the records to patch when
+    /// creating the writer, with placeholder values. Fails with `InvalidData`
+    /// when the header has no valid axes, or a patched value does not fit
+    /// its card.
+    pub fn finish_patched(mut self) -> io::Result<W> {
+        self.pad()?;
+        let end = self.out.stream_position()?;
+        let padded = self.written() + padding((self.written() % BLOCK_SIZE as u64) as usize) as u64;
+        let start = end - padded - self.header_size;
+
+        let datasum = self.datasum();
+        let mut header = self.header;
+        patch(&mut header, self.data, self.heap, datasum)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "the header has no valid axes"))?;
+        check_header(&header).map_err(invalid_data)?;
+        let mut text = header.to_fits_string().into_bytes();
+        if text.len() as u64 != self.header_size {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the patched header has grown"))
+        }
+        if header.value_opt(&Keyword::CHECKSUM).is_some() {
+            checksum::update(&mut text, datasum)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "CHECKSUM is not in the fixed format"))?;
+        }
+
+        self.out.seek(SeekFrom::Start(start))?;
+        self.out.write_all(&text)?;
+        self.out.seek(SeekFrom::Start(end))?;
+        Ok(self.out)
+    }
+}
+
+/// Set the length of the last axis of `header` for `data` bytes of rows or
+/// pixels, `PCOUNT` of an extension to `heap`, and `DATASUM` and `CHECKSUM`
+/// when present.
+fn patch(header: &mut Header, data: u64, heap: u64, datasum: u32) -> Result<(), ()> {
+    let naxes = header.naxes().map_err(|_| ())?;
+    if let Option::Some((_, others)) = naxes.split_last() {
+        let element = header.bitpix().ok_or(())?.unsigned_abs() / 8;
+        let size = others.iter().fold(element, |size, &n| size * n).max(1);
+        header.set(Keyword::NAXISn(naxes.len() as u16), Value::Integer((data / size) as i64), Option::None)
+            .map_err(|_| ())?;
+    }
+    if header.value_opt(&Keyword::XTENSION).is_some() {
+        header.set(Keyword::PCOUNT, Value::Integer(heap as i64), Option::None).map_err(|_| ())?;
+    }
+    if header.value_opt(&Keyword::DATASUM).is_some() {
+        header.set(Keyword::DATASUM, Value::CharacterString(Cow::Owned(datasum.to_string())), Option::None)
+            .map_err(|_| ())?;
+    }
+    if header.value_opt(&Keyword::CHECKSUM).is_some() {
+        header.set(Keyword::CHECKSUM, Value::CharacterString(Cow::Borrowed(ZEROS)), Option::None)
+            .map_err(|_| ())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use nom::IResult;
+    use super::super::super::checksum::{verify, Status};
+    use super::super::super::parser::{fits, template};
+    use super::super::super::types::{HDU, HeaderEditError, Keyword, Value};
+    use super::super::write_hdu;
+    use super::HduWriter;
+
+    #[test]
+    fn finish_should_pad_the_data_array() {
+        let header = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 1\nNAXIS1 = 5").unwrap();
+        let mut writer = HduWriter::new(vec!(), &header).unwrap();
+        writer.write_data(&[1, 2]).unwrap();
+        writer.write_data(&[3, 4, 5]).unwrap();
+        assert_eq!(writer.written(), 5);
+        let out = writer.finish().unwrap();
+
+        assert_eq!(out.len(), 2 * 2880);
+        assert_eq!(&out[2880..2886], &[1, 2, 3, 4, 5, 0]);
+    }
+
+    #[test]
+    fn finish_should_refuse_a_data_array_of_the_wrong_size() {
+        let header = template("SIMPLE = T\nBITPIX = 8\nNAXIS = 1\nNAXIS1 = 5").unwrap();
+        let mut writer = HduWriter::new(vec!(), &header).unwrap();
+        writer.write_data(&[1, 2]).unwrap();
+
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn finish_patched_should_fill_in_the_rows_heap_and_checksums() {
+        let header = template("XTENSION = 'BINTABLE'\nBITPIX = 8\nNAXIS = 2\nNAXIS1 = 4\nNAXIS2 = 0\n\
+            PCOUNT = 0\nGCOUNT = 1\nTFIELDS = 1\nTFORM1 = '1J'\n\
+            EXPOSURE = 0.0 / seconds\nDATASUM = '0'\nCHECKSUM = '0000000000000000'").unwrap();
+        let mut out = Cursor::new(vec!());
+        write_hdu(&mut out, &HDU::new(template("SIMPLE = T\nBITPIX = 8\nNAXIS = 0").unwrap())).unwrap();
+        let mut writer = HduWriter::new(out, &header).unwrap();
+        for row in 0..1000u32 {
+            writer.write_data(&row.to_be_bytes()).unwrap();
+        }
+        writer.write_heap(&[7; 3]).unwrap();
+        assert_eq!(writer.patch(Keyword::EXPOSURE, Value::Real(999.5)), Ok(()));
+        assert_eq!(writer.patch(Keyword::OBJECT, Value::from("TRAPPIST-1")), Err(HeaderEditError::KeywordNotPresent));
+        assert!(writer.write_data(&[0; 4]).is_err());
+        let datasum = writer.datasum();
+        let out = writer.finish_patched().unwrap().into_inner();
+
+        let parsed = match fits(&out) {
+            IResult::Done(_, parsed) => parsed,
+            _ => panic!("the patched file should parse"),
+        };
+        let header = &parsed.extensions[0].header;
+        assert_eq!(header.integer_value_of(&Keyword::NAXISn(2)), Ok(1000));
+        assert_eq!(header.integer_value_of(&Keyword::PCOUNT), Ok(3));
+        assert_eq!(header.real_value_of(&Keyword::EXPOSURE), Ok(999.5));
+        assert_eq!(header.records()[9].comment(), Option::Some("seconds"));
+        assert_eq!(header.character_string_value_of(&Keyword::DATASUM), Ok(datasum.to_string().as_str()));
+        let verification = verify(&out[2880..2 * 2880], &out[2 * 2880..]);
+        assert_eq!(verification.checksum, Status::Ok);
+        assert_eq!(verification.datasum, Status::Ok);
+    }
+}
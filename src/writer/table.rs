@@ -0,0 +1,274 @@
+//! Writing binary tables row by row, including variable length arrays.
+//!
+//! The fields of fixed size columns go into the rows of the main table. The
+//! elements of variable length arrays, `P` and `Q` columns, are appended to
+//! the heap, which follows the main table directly, and their rows hold the
+//! descriptors: the number of elements and their offset in the heap.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::str::FromStr;
+use super::super::table::{BinType, ColumnData, Descriptor, TForm};
+use super::super::types::{HDU, Header, Keyword, KeywordRecord, Value};
+use super::write_hdu;
+
+/// A binary table extension that is written row by row.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableWriter {
+    names: Vec<String>,
+    forms: Vec<TForm>,
+    row_width: usize,
+    rows: usize,
+    main: Vec<u8>,
+    heap: Vec<u8>,
+}
+
+impl TableWriter {
+    /// A table without rows with a column per `(TTYPEn, TFORMn)` pair of
+    /// `columns`, e.g. `("FLUX", "1PE")` for a variable length array of floats.
+    pub fn new(columns: &[(&str, &str)]) -> Result<TableWriter, TableWriteError> {
+        let forms = columns.iter().enumerate()
+            .map(|(index, &(_, form))| match TForm::from_str(form) {
+                Ok(form) if form.descriptor.is_none() || form.repeat <= 1 => Ok(form),
+                _ => Err(TableWriteError::InvalidTForm(index)),
+            })
+            .collect::<Result<Vec<TForm>, TableWriteError>>()?;
+        let mut row_width = 0usize;
+        for (index, form) in forms.iter().enumerate() {
+            row_width = form.width()
+                .and_then(|width| row_width.checked_add(width))
+                .ok_or(TableWriteError::InvalidTForm(index))?;
+        }
+        Ok(TableWriter {
+            names: columns.iter().map(|&(name, _)| name.to_string()).collect(),
+            forms,
+            row_width,
+            rows: 0,
+            main: vec!(),
+            heap: vec!(),
+        })
+    }
+
+    /// Append a row with a field per column. Fields of fixed size columns
+    /// have as many elements as the repeat count of their format, fields of
+    /// variable length arrays any number.
+    pub fn push_row(&mut self, fields: &[ColumnData]) -> Result<(), TableWriteError> {
+        if fields.len() != self.forms.len() {
+            return Err(TableWriteError::FieldCount(fields.len()))
+        }
+        let mut row = Vec::with_capacity(self.row_width);
+        let mut heap = vec!();
+        for (index, (form, field)) in self.forms.iter().zip(fields.iter()).enumerate() {
+            if kind_of(field) != form.kind {
+                return Err(TableWriteError::WrongType(index))
+            }
+            match form.descriptor {
+                Option::None => {
+                    if count(field) != form.repeat {
+                        return Err(TableWriteError::WrongLength(index, form.repeat))
+                    }
+                    encode(field, &mut row);
+                }
+                Option::Some(descriptor) => {
+                    let offset = self.heap.len() + heap.len();
+                    let elements = count(field);
+                    encode(field, &mut heap);
+                    match descriptor {
+                        Descriptor::P => {
+                            let end = form.kind.width(elements).and_then(|width| offset.checked_add(width));
+                            if !matches!(end, Option::Some(end) if end <= i32::MAX as usize) {
+                                return Err(TableWriteError::HeapOverflow(index))
+                            }
+                            row.extend_from_slice(&(elements as i32).to_be_bytes());
+                            row.extend_from_slice(&(offset as i32).to_be_bytes());
+                        }
+                        Descriptor::Q => {
+                            row.extend_from_slice(&(elements as i64).to_be_bytes());
+                            row.extend_from_slice(&(offset as i64).to_be_bytes());
+                        }
+                    }
+                }
+            }
+        }
+        for (form, field) in self.forms.iter_mut().zip(fields.iter()) {
+            if form.descriptor.is_some() {
+                form.max = Option::Some(form.max.unwrap_or(0).max(count(field)));
+            }
+        }
+        self.main.extend(row);
+        self.heap.extend(heap);
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// The number of rows written so far.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The header of this table. `PCOUNT` is the size of the heap and the
+    /// formats of variable length arrays hold their longest array, e.g.
+    /// `1PE(12)`.
+    pub fn header(&self) -> Header<'static> {
+        let mut records = vec!(
+            KeywordRecord::new(Keyword::XTENSION, Value::CharacterString("BINTABLE".into()), Option::Some("binary table extension".into())),
+            KeywordRecord::new(Keyword::BITPIX, Value::Integer(8i64), Option::None),
+            KeywordRecord::new(Keyword::NAXIS, Value::Integer(2i64), Option::None),
+            KeywordRecord::new(Keyword::NAXISn(1u16), Value::Integer(self.row_width as i64), Option::Some("width of a row in bytes".into())),
+            KeywordRecord::new(Keyword::NAXISn(2u16), Value::Integer(self.rows as i64), Option::Some("number of rows".into())),
+            KeywordRecord::new(Keyword::PCOUNT, Value::Integer(self.heap.len() as i64), Option::Some("size of the heap".into())),
+            KeywordRecord::new(Keyword::GCOUNT, Value::Integer(1i64), Option::None),
+            KeywordRecord::new(Keyword::TFIELDS, Value::Integer(self.forms.len() as i64), Option::None),
+        );
+        for (index, (name, form)) in self.names.iter().zip(self.forms.iter()).enumerate() {
+            let n = index as u16 + 1;
+            records.push(KeywordRecord::new(Keyword::TTYPEn(n), Value::CharacterString(Cow::Owned(name.clone())), Option::None));
+            records.push(KeywordRecord::new(Keyword::TFORMn(n), Value::CharacterString(Cow::Owned(form.to_string())), Option::None));
+        }
+        if self.forms.iter().any(|form| form.descriptor.is_some()) {
+            records.push(KeywordRecord::new(Keyword::THEAP, Value::Integer(self.main.len() as i64), Option::Some("offset of the heap".into())));
+        }
+        Header::new(records)
+    }
+
+    /// The data array of this table without padding: the main table followed
+    /// by the heap.
+    pub fn data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.main.len() + self.heap.len());
+        data.extend_from_slice(&self.main);
+        data.extend_from_slice(&self.heap);
+        data
+    }
+
+    /// Write this table as an HDU to `out`, padded to whole blocks.
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let data = self.data();
+        write_hdu(out, &HDU::with_data(self.header(), &data))
+    }
+}
+
+/// Problems that could occur when writing a binary table. Each variant holds
+/// the index of the column, except `FieldCount`.
+#[derive(Debug, PartialEq)]
+pub enum TableWriteError {
+    /// The `TFORMn` could not be parsed, has a repeat count over 1 for a
+    /// variable length array or makes the rows too wide for a `usize`.
+    InvalidTForm(usize),
+    /// A row has this number of fields, which is not the number of columns.
+    FieldCount(usize),
+    /// A field has a different type than its column.
+    WrongType(usize),
+    /// A field of a fixed size column does not have the repeat count, the
+    /// second value, of elements.
+    WrongLength(usize, usize),
+    /// The heap grows beyond the offsets a `P` descriptor can hold.
+    HeapOverflow(usize),
+}
+
+fn kind_of(field: &ColumnData) -> BinType {
+    match *field {
+        ColumnData::Logical(_) => BinType::Logical,
+        ColumnData::Bit(_) => BinType::Bit,
+        ColumnData::UnsignedByte(_) => BinType::UnsignedByte,
+        ColumnData::Short(_) => BinType::Short,
+        ColumnData::Int(_) => BinType::Int,
+        ColumnData::Long(_) => BinType::Long,
+        ColumnData::Character(_) => BinType::Character,
+        ColumnData::Float(_) => BinType::Float,
+        ColumnData::Double(_) => BinType::Double,
+        ColumnData::ComplexFloat(_) => BinType::ComplexFloat,
+        ColumnData::ComplexDouble(_) => BinType::ComplexDouble,
+    }
+}
+
+fn count(field: &ColumnData) -> usize {
+    match *field {
+        ColumnData::Logical(ref values) => values.len(),
+        ColumnData::Bit(ref values) => values.len(),
+        ColumnData::UnsignedByte(ref values) | ColumnData::Character(ref values) => values.len(),
+        ColumnData::Short(ref values) => values.len(),
+        ColumnData::Int(ref values) => values.len(),
+        ColumnData::Long(ref values) => values.len(),
+        ColumnData::Float(ref values) => values.len(),
+        ColumnData::Double(ref values) => values.len(),
+        ColumnData::ComplexFloat(ref values) => values.len(),
+        ColumnData::ComplexDouble(ref values) => values.len(),
+    }
+}
+
+/// Appends the big-endian encoding of the elements of `field` to `out`.
+fn encode(field: &ColumnData, out: &mut Vec<u8>) {
+    match *field {
+        ColumnData::Logical(ref values) => out.extend(values.iter().map(|value| match *value {
+            Option::Some(true) => b'T',
+            Option::Some(false) => b'F',
+            Option::None => 0u8,
+        })),
+        ColumnData::Bit(ref values) => out.extend(values.chunks(8).map(|bits| {
+            bits.iter().enumerate().fold(0u8, |byte, (i, &bit)| if bit { byte | (0x80 >> i) } else { byte })
+        })),
+        ColumnData::UnsignedByte(ref values) | ColumnData::Character(ref values) => out.extend_from_slice(values),
+        ColumnData::Short(ref values) => out.extend(values.iter().flat_map(|value| value.to_be_bytes())),
+        ColumnData::Int(ref values) => out.extend(values.iter().flat_map(|value| value.to_be_bytes())),
+        ColumnData::Long(ref values) => out.extend(values.iter().flat_map(|value| value.to_be_bytes())),
+        ColumnData::Float(ref values) => out.extend(values.iter().flat_map(|value| value.to_be_bytes())),
+        ColumnData::Double(ref values) => out.extend(values.iter().flat_map(|value| value.to_be_bytes())),
+        ColumnData::ComplexFloat(ref values) => for &(re, im) in values {
+            out.extend_from_slice(&re.to_be_bytes());
+            out.extend_from_slice(&im.to_be_bytes());
+        },
+        ColumnData::ComplexDouble(ref values) => for &(re, im) in values {
+            out.extend_from_slice(&re.to_be_bytes());
+            out.extend_from_slice(&im.to_be_bytes());
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::IResult;
+    use super::super::super::parser::fits;
+    use super::super::super::table::{BinTable, ColumnData};
+    use super::super::super::types::Keyword;
+    use super::{TableWriteError, TableWriter};
+
+    #[test]
+    fn variable_length_arrays_should_be_written_to_the_heap() {
+        let mut writer = TableWriter::new(&[("ID", "1J"), ("FLUX", "1PE"), ("FLAGS", "1QB"), ("MASK", "3X")]).unwrap();
+        writer.push_row(&[ColumnData::Int(vec!(1)), ColumnData::Float(vec!(1.5, 2.5, 3.5)), ColumnData::UnsignedByte(vec!()),
+                          ColumnData::Bit(vec!(true, false, true))]).unwrap();
+        writer.push_row(&[ColumnData::Int(vec!(2)), ColumnData::Float(vec!(4.5)), ColumnData::UnsignedByte(vec!(7, 8)),
+                          ColumnData::Bit(vec!(false, true, false))]).unwrap();
+
+        let mut written = vec!();
+        writer.write(&mut written).unwrap();
+
+        assert_eq!(written.len(), 2 * 2880);
+        let file = match fits(&written) {
+            IResult::Done(_, file) => file,
+            _ => panic!("Did not expect the parse to fail"),
+        };
+        let header = &file[0].header;
+        assert_eq!(header.integer_value_of(&Keyword::PCOUNT), Ok(18));
+        assert_eq!(header.integer_value_of(&Keyword::THEAP), Ok(58));
+        assert_eq!(header.character_string_value_of(&Keyword::TFORMn(2)).map(str::trim_end), Ok("1PE(3)"));
+        let table = BinTable::new(header).unwrap();
+        let flux = table.read_column(file[0].data(), 1).unwrap();
+        assert_eq!(flux.data, ColumnData::Float(vec!(1.5, 2.5, 3.5, 4.5)));
+        assert_eq!(flux.offsets, vec!(0, 3, 4));
+        let flags = table.read_column(file[0].data(), 2).unwrap();
+        assert_eq!(flags.data, ColumnData::UnsignedByte(vec!(7, 8)));
+        assert_eq!(table.read_column(file[0].data(), 3).unwrap().data, ColumnData::Bit(vec!(true, false, true, false, true, false)));
+    }
+
+    #[test]
+    fn rows_should_match_the_columns() {
+        let mut writer = TableWriter::new(&[("ID", "2J")]).unwrap();
+
+        assert_eq!(writer.push_row(&[]), Err(TableWriteError::FieldCount(0)));
+        assert_eq!(writer.push_row(&[ColumnData::Long(vec!(1, 2))]), Err(TableWriteError::WrongType(0)));
+        assert_eq!(writer.push_row(&[ColumnData::Int(vec!(1))]), Err(TableWriteError::WrongLength(0, 2)));
+        assert_eq!(writer.rows(), 0);
+        assert_eq!(TableWriter::new(&[("A", "2PE")]), Err(TableWriteError::InvalidTForm(0)));
+    }
+}